@@ -0,0 +1,335 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    config,
+    webhook::{AlertContext, EventKind, PacketEvent, PacketSnapshot},
+};
+
+/// Rule, source chain, source channel and destination channel of an alert, or the chain
+/// and subject of alerts about something else than packets
+type AlertKey = (EventKind, String, String, String);
+
+/// Firing state of the alert of a rule on a channel
+#[derive(Default)]
+struct AlertState {
+    /// When packets of the channel started meeting the condition of the rule, if they do
+    firing_since: Option<Instant>,
+    /// Sequences notified since the alert fired
+    notified: HashSet<i64>,
+    /// Whether the alert was notified since it fired, for alerts without packets
+    announced: bool,
+    /// When the alert was last notified, kept once it resolved to apply the cooldown
+    notified_at: Option<Instant>,
+    /// Oldest packet of the alert, reported when it resolves
+    oldest: Option<PacketSnapshot>,
+}
+
+/// Change of an alert without packets to notify
+#[derive(Debug, PartialEq, Eq)]
+pub enum Transition {
+    Fired,
+    Resolved { firing_seconds: i64 },
+}
+
+/// Turns the packets meeting the condition of an alert rule into deduplicated notifications
+pub struct Alerts {
+    config: config::Alerts,
+    states: HashMap<AlertKey, AlertState>,
+}
+
+impl Alerts {
+    pub fn new(config: config::Alerts) -> Self {
+        Self {
+            config,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Apply reloaded settings, keeping the state of the alerts
+    pub fn configure(&mut self, config: config::Alerts) {
+        self.config = config;
+    }
+
+    /// Update the alerts with the packets currently meeting the condition of a rule,
+    /// returning the events to notify
+    ///
+    /// An alert is notified when it fires, and then again only once its number of packets grew
+    /// by the escalation factor, listing the packets not notified yet. Neither happens within
+    /// the cooldown of the rule since the last notification of the alert. When every packet of
+    /// a notified `packet_stuck` alert is gone, a `stuck_resolved` event is returned.
+    pub fn evaluate(&mut self, active: Vec<(EventKind, PacketSnapshot)>) -> Vec<PacketEvent> {
+        let now = Instant::now();
+        let mut events = Vec::new();
+
+        let mut alerts: HashMap<AlertKey, Vec<PacketSnapshot>> = HashMap::new();
+        for (rule, packet) in active {
+            let key = (
+                rule,
+                packet.chain_id.clone(),
+                packet.src_channel.clone(),
+                packet.dst_channel.clone(),
+            );
+            alerts.entry(key).or_default().push(packet);
+        }
+
+        for (key, packets) in &alerts {
+            let rule = key.0;
+            let cooldown = self.config.cooldown(rule);
+            let state = self.states.entry(key.clone()).or_default();
+
+            let firing_since = *state.firing_since.get_or_insert(now);
+            state.oldest = packets
+                .iter()
+                .max_by_key(|packet| packet.age_seconds)
+                .cloned();
+
+            let cooled_down = state
+                .notified_at
+                .is_none_or(|notified_at| now.duration_since(notified_at) >= cooldown);
+            let escalated = state.notified.is_empty()
+                || packets.len() as f64
+                    >= state.notified.len() as f64 * self.config.escalation_factor;
+
+            if !cooled_down || !escalated {
+                continue;
+            }
+
+            let context = AlertContext {
+                packets: packets.len(),
+                firing_seconds: now.duration_since(firing_since).as_secs() as i64,
+            };
+
+            for packet in packets {
+                if state.notified.insert(packet.sequence) {
+                    events.push(PacketEvent::new(rule, packet.clone()).with_alert(context.clone()));
+                }
+            }
+
+            state.notified_at = Some(now);
+        }
+
+        for (key, state) in &mut self.states {
+            if alerts.contains_key(key) {
+                continue;
+            }
+
+            let Some(firing_since) = state.firing_since.take() else {
+                continue;
+            };
+
+            let notified = !std::mem::take(&mut state.notified).is_empty();
+
+            if let (EventKind::PacketStuck, true, Some(oldest)) =
+                (key.0, notified, state.oldest.take())
+            {
+                let context = AlertContext {
+                    packets: 0,
+                    firing_seconds: now.duration_since(firing_since).as_secs() as i64,
+                };
+
+                events.push(PacketEvent::new(EventKind::StuckResolved, oldest).with_alert(context));
+            }
+        }
+
+        self.forget_resolved(now);
+
+        events
+    }
+
+    /// Update the alert of a rule on a chain, or on a subject of it such as a channel or
+    /// a client, with whether its condition is met, returning whether to notify that it fired or resolved
+    ///
+    /// Firing is not notified within the cooldown of the rule since the alert was last
+    /// notified, but once it elapsed if the alert still fires, nor is the resolution of an
+    /// alert whose firing was not notified.
+    pub fn condition(
+        &mut self,
+        rule: EventKind,
+        chain_id: &str,
+        subject: Option<&str>,
+        met: bool,
+    ) -> Option<Transition> {
+        self.condition_at(rule, chain_id, subject, met, Instant::now())
+    }
+
+    fn condition_at(
+        &mut self,
+        rule: EventKind,
+        chain_id: &str,
+        subject: Option<&str>,
+        met: bool,
+        now: Instant,
+    ) -> Option<Transition> {
+        let key = (
+            rule,
+            chain_id.to_string(),
+            subject.unwrap_or_default().to_string(),
+            String::new(),
+        );
+        let cooldown = self.config.cooldown(rule);
+
+        let transition = if met {
+            let state = self.states.entry(key).or_default();
+            let cooled_down = state
+                .notified_at
+                .is_none_or(|notified_at| now.duration_since(notified_at) >= cooldown);
+
+            // An alert firing again within the cooldown is notified once it elapsed
+            if state.firing_since.is_some() && (state.announced || !cooled_down) {
+                None
+            } else {
+                state.firing_since.get_or_insert(now);
+                state.announced = cooled_down;
+
+                if state.announced {
+                    state.notified_at = Some(now);
+                }
+
+                state.announced.then_some(Transition::Fired)
+            }
+        } else {
+            self.states.get_mut(&key).and_then(|state| {
+                let firing_since = state.firing_since.take()?;
+
+                std::mem::take(&mut state.announced).then(|| Transition::Resolved {
+                    firing_seconds: now.duration_since(firing_since).as_secs() as i64,
+                })
+            })
+        };
+
+        self.forget_resolved(now);
+
+        transition
+    }
+
+    /// Resolved alerts are only remembered for their cooldown
+    fn forget_resolved(&mut self, now: Instant) {
+        let config = &self.config;
+        self.states.retain(|(rule, ..), state| {
+            state.firing_since.is_some()
+                || state.notified_at.is_some_and(|notified_at| {
+                    now.duration_since(notified_at) < config.cooldown(*rule)
+                })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn stuck(sequences: &[i64]) -> Vec<(EventKind, PacketSnapshot)> {
+        sequences
+            .iter()
+            .map(|&sequence| {
+                let packet = PacketSnapshot {
+                    chain_id: "osmosis-1".to_string(),
+                    src_channel: "channel-0".to_string(),
+                    dst_channel: "channel-141".to_string(),
+                    sequence,
+                    sender: None,
+                    receiver: None,
+                    amount: None,
+                    denom: None,
+                    age_seconds: 1000 + sequence,
+                    seconds_until_timeout: None,
+                };
+
+                (EventKind::PacketStuck, packet)
+            })
+            .collect()
+    }
+
+    fn sequences(events: &[PacketEvent]) -> Vec<(EventKind, i64)> {
+        let mut sequences: Vec<_> = events
+            .iter()
+            .map(|event| (event.event, event.packet.sequence))
+            .collect();
+        sequences.sort();
+        sequences
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut alerts = Alerts::new(config::Alerts {
+            cooldown_minutes: 0,
+            ..Default::default()
+        });
+
+        // Fires, then stays quiet until the number of packets doubles
+        assert_eq!(
+            sequences(&alerts.evaluate(stuck(&[1, 2]))),
+            [(EventKind::PacketStuck, 1), (EventKind::PacketStuck, 2)]
+        );
+        assert!(alerts.evaluate(stuck(&[1, 2, 3])).is_empty());
+        assert_eq!(
+            sequences(&alerts.evaluate(stuck(&[1, 2, 3, 4]))),
+            [(EventKind::PacketStuck, 3), (EventKind::PacketStuck, 4)]
+        );
+        assert!(alerts.evaluate(stuck(&[4])).is_empty());
+
+        let resolved = alerts.evaluate(vec![]);
+        assert_eq!(sequences(&resolved), [(EventKind::StuckResolved, 4)]);
+        assert_eq!(resolved[0].alert.as_ref().unwrap().packets, 0);
+
+        // Fires again without waiting, but not within the cooldown
+        assert_eq!(alerts.evaluate(stuck(&[5])).len(), 1);
+
+        let mut alerts = Alerts::new(config::Alerts::default());
+        assert_eq!(alerts.evaluate(stuck(&[1])).len(), 1);
+        assert_eq!(alerts.evaluate(vec![]).len(), 1);
+        assert!(alerts.evaluate(stuck(&[2, 3, 4])).is_empty());
+        assert!(alerts.evaluate(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_condition() {
+        let mut alerts = Alerts::new(config::Alerts::default());
+        let halted = |alerts: &mut Alerts, met| {
+            alerts.condition(EventKind::ChainHalted, "osmosis-1", None, met)
+        };
+
+        assert_eq!(halted(&mut alerts, false), None);
+        assert_eq!(halted(&mut alerts, true), Some(Transition::Fired));
+        assert_eq!(halted(&mut alerts, true), None);
+        assert_eq!(
+            halted(&mut alerts, false),
+            Some(Transition::Resolved { firing_seconds: 0 })
+        );
+
+        // Flapping within the cooldown is not notified
+        assert_eq!(halted(&mut alerts, true), None);
+        assert_eq!(halted(&mut alerts, false), None);
+    }
+
+    #[test]
+    fn test_condition_after_cooldown() {
+        let mut alerts = Alerts::new(config::Alerts::default());
+        let cooldown = alerts.config.cooldown(EventKind::ChainHalted);
+        let start = Instant::now();
+        let mut halted = |met, elapsed| {
+            let now = start + elapsed;
+            alerts.condition_at(EventKind::ChainHalted, "osmosis-1", None, met, now)
+        };
+
+        assert_eq!(halted(true, Duration::ZERO), Some(Transition::Fired));
+        assert!(halted(false, Duration::from_secs(1)).is_some());
+
+        // Firing again within the cooldown, it is notified once the cooldown elapsed
+        assert_eq!(halted(true, Duration::from_secs(2)), None);
+        assert_eq!(halted(true, cooldown / 2), None);
+        assert_eq!(halted(true, cooldown), Some(Transition::Fired));
+        assert_eq!(halted(true, cooldown * 2), None);
+        assert_eq!(
+            halted(false, cooldown * 2),
+            Some(Transition::Resolved {
+                firing_seconds: (cooldown * 2).as_secs() as i64 - 2
+            })
+        );
+    }
+}
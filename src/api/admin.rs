@@ -0,0 +1,310 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::{Json, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use serde::Serialize;
+use tendermint::chain;
+use tracing::info;
+
+use crate::{
+    config,
+    monitor::{self, ScanSummary},
+    silence::{self, NewSilence, RegisteredSilence},
+    status::Status,
+    webhook::{self, EventKind, RegisteredWebhook},
+};
+
+use super::{
+    error::{ApiError, ApiJson, ApiPath, ApiResult},
+    timestamps::Timestamp,
+    ApiState,
+};
+
+pub(super) fn routes(state: ApiState) -> Router<ApiState> {
+    Router::new()
+        .route("/scan", post(scan))
+        .route("/chains/:chain/reconnect", post(reconnect))
+        .route("/chains/:chain/reset-metrics", post(reset_metrics))
+        .route("/chains/:chain/pause", post(pause))
+        .route("/chains/:chain/resume", post(resume))
+        .route("/webhooks", get(list_webhooks).post(register_webhook))
+        .route("/webhooks/:id", delete(remove_webhook))
+        .route("/silences", get(list_silences).post(create_silence))
+        .route("/silences/:id", delete(remove_silence))
+        .route_layer(middleware::from_fn_with_state(state, require_admin_token))
+}
+
+#[derive(Debug, Serialize)]
+struct ChainActionResponse {
+    chain_id: String,
+    action: &'static str,
+}
+
+impl ChainActionResponse {
+    fn new(chain_id: &chain::Id, action: &'static str) -> Json<Self> {
+        info!(chain = %chain_id, "Admin {action} requested");
+
+        Json(Self {
+            chain_id: chain_id.to_string(),
+            action,
+        })
+    }
+}
+
+/// Reject requests that do not carry the configured admin token as a bearer token
+async fn require_admin_token<B>(
+    State(state): State<ApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> ApiResult<Response> {
+    authorize(&state, request.headers())?;
+
+    Ok(next.run(request).await)
+}
+
+/// Check that the headers carry the configured admin token as a bearer token
+pub(super) fn authorize(state: &ApiState, headers: &HeaderMap) -> ApiResult<()> {
+    let config = state.config.borrow();
+
+    let Some(expected) = config.metrics.admin_token.as_deref() else {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "admin_disabled",
+            "the admin API is disabled, set `metrics.admin_token` to enable it",
+        ));
+    };
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "missing or invalid admin token",
+        )),
+    }
+}
+
+/// Compare without short-circuiting so the token cannot be recovered from response times
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn monitored_chain(status: &Status, chain: &str) -> ApiResult<chain::Id> {
+    let chain_id = chain::Id::from_str(chain)
+        .map_err(|_| ApiError::invalid_parameter(format!("invalid chain id `{chain}`")))?;
+
+    if !status.contains(&chain_id) {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "unknown_chain",
+            format!("chain `{chain_id}` is not monitored"),
+        ));
+    }
+
+    Ok(chain_id)
+}
+
+async fn scan(State(state): State<ApiState>) -> ApiResult<Json<ScanSummary>> {
+    info!("Admin stuck packet scan requested");
+
+    let summary = monitor::scan(&state.db, &state.metrics, &state.stuck_thresholds()).await?;
+
+    Ok(Json(summary))
+}
+
+async fn reconnect(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+) -> ApiResult<Json<ChainActionResponse>> {
+    let chain_id = monitored_chain(&state.status, &chain)?;
+    state.status.control(&chain_id).request_reconnect();
+
+    Ok(ChainActionResponse::new(&chain_id, "reconnect"))
+}
+
+async fn reset_metrics(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+) -> ApiResult<Json<ChainActionResponse>> {
+    let chain_id = monitored_chain(&state.status, &chain)?;
+    state.metrics.reset_chain(&chain_id);
+    state.status.reset_counters(&chain_id);
+
+    Ok(ChainActionResponse::new(&chain_id, "reset-metrics"))
+}
+
+async fn pause(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+) -> ApiResult<Json<ChainActionResponse>> {
+    let chain_id = monitored_chain(&state.status, &chain)?;
+    state.status.control(&chain_id).set_paused(true);
+
+    Ok(ChainActionResponse::new(&chain_id, "pause"))
+}
+
+async fn resume(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+) -> ApiResult<Json<ChainActionResponse>> {
+    let chain_id = monitored_chain(&state.status, &chain)?;
+    state.status.control(&chain_id).set_paused(false);
+
+    Ok(ChainActionResponse::new(&chain_id, "resume"))
+}
+
+/// A registered webhook, without its secret
+#[derive(Debug, Serialize)]
+struct WebhookResponse {
+    id: i64,
+    url: String,
+    events: Vec<EventKind>,
+    chain: Option<String>,
+    channel: Option<String>,
+    template: Option<String>,
+    signed: bool,
+    created_at: Option<Timestamp>,
+}
+
+impl From<RegisteredWebhook> for WebhookResponse {
+    fn from(registered: RegisteredWebhook) -> Self {
+        let webhook = registered.webhook;
+
+        Self {
+            id: registered.id,
+            url: webhook.url,
+            events: webhook.events,
+            chain: webhook.chain,
+            channel: webhook.channel,
+            template: webhook.template,
+            signed: webhook.secret.is_some(),
+            created_at: registered.created_at.parse().ok(),
+        }
+    }
+}
+
+async fn list_webhooks(State(state): State<ApiState>) -> ApiResult<Json<Vec<WebhookResponse>>> {
+    let webhooks = webhook::list(&state.db).await?;
+
+    Ok(Json(webhooks.into_iter().map(Into::into).collect()))
+}
+
+async fn register_webhook(
+    State(state): State<ApiState>,
+    ApiJson(webhook): ApiJson<config::Webhook>,
+) -> ApiResult<(StatusCode, Json<WebhookResponse>)> {
+    if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+        return Err(ApiError::bad_request(
+            "invalid_webhook",
+            "webhook url must use http or https",
+        ));
+    }
+
+    if let Some(template) = &webhook.template {
+        webhook::validate_template(template).map_err(|e| {
+            ApiError::bad_request("invalid_webhook", format!("invalid template: {e}"))
+        })?;
+    }
+
+    let registered = webhook::register(&state.db, webhook).await?;
+    info!(id = %registered.id, url = %registered.webhook.url, "Registered webhook");
+
+    Ok((StatusCode::CREATED, Json(registered.into())))
+}
+
+async fn remove_webhook(
+    State(state): State<ApiState>,
+    ApiPath(id): ApiPath<i64>,
+) -> ApiResult<StatusCode> {
+    if !webhook::remove(&state.db, id).await? {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("webhook {id} does not exist"),
+        ));
+    }
+
+    info!(%id, "Removed webhook");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A silence created through the API
+#[derive(Debug, Serialize)]
+struct SilenceResponse {
+    id: i64,
+    chain: Option<String>,
+    channel: Option<String>,
+    rules: Vec<EventKind>,
+    starts_at: Option<Timestamp>,
+    ends_at: Option<Timestamp>,
+    comment: Option<String>,
+    created_at: Option<Timestamp>,
+}
+
+impl From<RegisteredSilence> for SilenceResponse {
+    fn from(registered: RegisteredSilence) -> Self {
+        Self {
+            id: registered.id,
+            chain: registered.chain,
+            channel: registered.channel,
+            rules: registered.rules,
+            starts_at: registered.starts_at.parse().ok(),
+            ends_at: registered.ends_at.parse().ok(),
+            comment: registered.comment,
+            created_at: registered.created_at.parse().ok(),
+        }
+    }
+}
+
+async fn list_silences(State(state): State<ApiState>) -> ApiResult<Json<Vec<SilenceResponse>>> {
+    let silences = silence::list(&state.db).await?;
+
+    Ok(Json(silences.into_iter().map(Into::into).collect()))
+}
+
+async fn create_silence(
+    State(state): State<ApiState>,
+    ApiJson(silence): ApiJson<NewSilence>,
+) -> ApiResult<(StatusCode, Json<SilenceResponse>)> {
+    let window = silence
+        .window()
+        .map_err(|e| ApiError::bad_request("invalid_silence", e))?;
+
+    let registered = silence::register(&state.db, silence, window).await?;
+    info!(
+        id = %registered.id,
+        until = %registered.ends_at,
+        "Created silence"
+    );
+
+    Ok((StatusCode::CREATED, Json(registered.into())))
+}
+
+async fn remove_silence(
+    State(state): State<ApiState>,
+    ApiPath(id): ApiPath<i64>,
+) -> ApiResult<StatusCode> {
+    if !silence::remove(&state.db, id).await? {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("silence {id} does not exist"),
+        ));
+    }
+
+    info!(%id, "Removed silence");
+
+    Ok(StatusCode::NO_CONTENT)
+}
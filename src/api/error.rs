@@ -0,0 +1,212 @@
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, FromRequestParts, Path, Query},
+    http::{request::Parts, HeaderName, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, error};
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    /// `x-request-id` of the request being handled
+    static REQUEST_ID: String;
+}
+
+/// Error returned by the API handlers, rendered as a JSON error envelope
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    /// Underlying cause, logged but never sent to the client
+    source: Option<(&'static str, String)>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    request_id: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn invalid_parameter(message: impl Into<String>) -> Self {
+        Self::bad_request("invalid_parameter", message)
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        let class = sql_error_class(&e);
+
+        let (status, code, message) = match class {
+            "row_not_found" => (StatusCode::NOT_FOUND, "not_found", "Resource not found"),
+            "pool_timed_out" | "pool_closed" | "io" | "busy" => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "db_unavailable",
+                "Database is unavailable, try again later",
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "db_error",
+                "Database query failed",
+            ),
+        };
+
+        Self {
+            status,
+            code,
+            message: message.to_string(),
+            source: Some((class, e.to_string())),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        // The request span already carries the request id
+        match &self.source {
+            Some((class, cause)) if self.status.is_server_error() => {
+                error!(
+                    code = self.code,
+                    sql_error = class,
+                    "API request failed: {cause}"
+                )
+            }
+            Some((class, cause)) => {
+                debug!(
+                    code = self.code,
+                    sql_error = class,
+                    "API request failed: {cause}"
+                )
+            }
+            None => debug!(code = self.code, "API request rejected: {}", self.message),
+        }
+
+        let request_id = REQUEST_ID.try_with(String::clone).unwrap_or_default();
+
+        let body = ErrorEnvelope {
+            error: ErrorBody {
+                code: self.code,
+                message: self.message,
+                request_id,
+            },
+        };
+
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Coarse classification of a database error, used for logs and error codes
+fn sql_error_class(e: &sqlx::Error) -> &'static str {
+    match e {
+        sqlx::Error::RowNotFound => "row_not_found",
+        sqlx::Error::PoolTimedOut => "pool_timed_out",
+        sqlx::Error::PoolClosed | sqlx::Error::WorkerCrashed => "pool_closed",
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) => "io",
+        // SQLITE_BUSY and SQLITE_LOCKED
+        sqlx::Error::Database(db) if matches!(db.code().as_deref(), Some("5" | "6")) => "busy",
+        sqlx::Error::Database(_) => "database",
+        sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_) => "decode",
+        sqlx::Error::ColumnNotFound(_) | sqlx::Error::ColumnIndexOutOfBounds { .. } => "column",
+        _ => "other",
+    }
+}
+
+/// The `x-request-id` header of a request, or an empty string when missing
+pub fn request_id<B>(request: &Request<B>) -> String {
+    request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Make the request id available to the error responses rendered while handling the request
+pub async fn scope_request_id<B>(request: Request<B>, next: Next<B>) -> Response {
+    let request_id = request_id(&request);
+
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
+
+/// `Query` extractor reporting malformed parameters with the API error envelope
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> ApiResult<Self> {
+        Query::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Query(params)| Self(params))
+            .map_err(|e| ApiError::invalid_parameter(e.body_text()))
+    }
+}
+
+/// `Path` extractor reporting malformed path segments with the API error envelope
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> ApiResult<Self> {
+        Path::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Path(params)| Self(params))
+            .map_err(|e| ApiError::invalid_parameter(e.body_text()))
+    }
+}
+
+/// `Json` extractor reporting malformed request bodies with the API error envelope
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, B, Rejection = JsonRejection>,
+    S: Send + Sync,
+    B: Send + 'static,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request<B>, state: &S) -> ApiResult<Self> {
+        Json::<T>::from_request(request, state)
+            .await
+            .map(|Json(body)| Self(body))
+            .map_err(|e| ApiError::bad_request("invalid_body", e.body_text()))
+    }
+}
@@ -0,0 +1,97 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use super::error::{ApiError, ApiQuery, ApiResult};
+
+/// Representation of a list response, chosen with `?format=` or the `Accept` header
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Format
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> ApiResult<Self> {
+        let ApiQuery(query) = ApiQuery::<FormatQuery>::from_request_parts(parts, state).await?;
+
+        match query.format.as_deref() {
+            Some("json") => Ok(Self::Json),
+            Some("csv") => Ok(Self::Csv),
+            Some(_) => Err(ApiError::invalid_parameter(
+                "format must be `json` or `csv`",
+            )),
+            None => {
+                let accepts_csv = parts
+                    .headers
+                    .get(header::ACCEPT)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|accept| accept.contains("text/csv"));
+
+                Ok(if accepts_csv { Self::Csv } else { Self::Json })
+            }
+        }
+    }
+}
+
+/// Reject CSV output for responses which do not map to a single table
+pub(super) fn ensure_flat(format: Format, nested: bool) -> ApiResult<()> {
+    if format == Format::Csv && nested {
+        return Err(ApiError::invalid_parameter(
+            "CSV output is not available for grouped results",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Render rows as a CSV attachment, with a header line taken from the field names
+/// and the number of rows matching the query, regardless of pagination, in `x-total-count`
+pub(super) fn csv<T: Serialize>(rows: &[T], total: i64, filename: &str) -> ApiResult<Response> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    for row in rows {
+        writer.serialize(row).map_err(csv_error)?;
+    }
+
+    let body = writer.into_inner().map_err(|e| csv_error(e.into_error()))?;
+
+    let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{filename}\""))
+        .unwrap_or_else(|_| HeaderValue::from_static("attachment"));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("text/csv")),
+            (header::CONTENT_DISPOSITION, disposition),
+            (
+                HeaderName::from_static("x-total-count"),
+                HeaderValue::from(total),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn csv_error(e: impl std::fmt::Display) -> ApiError {
+    ApiError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "csv_error",
+        format!("failed to render CSV: {e}"),
+    )
+}
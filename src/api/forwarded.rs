@@ -0,0 +1,141 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::ConnectInfo,
+    http::{header, HeaderName, Request},
+};
+
+use crate::config;
+
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+static X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+static X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+static X_FORWARDED_PREFIX: HeaderName = HeaderName::from_static("x-forwarded-prefix");
+
+/// Base of the links in the responses, as the client reached the API
+///
+/// Links are relative to the host, starting with `metrics.base_path`. With
+/// `metrics.trust_forwarded_headers`, the prefix a proxy strips from the path is added back from
+/// `X-Forwarded-Prefix`, and the links are absolute when the proxy sets `X-Forwarded-Proto`.
+pub(super) fn link_base<B>(metrics: &config::Metrics, request: &Request<B>) -> String {
+    let base_path = metrics.base_path.as_deref().unwrap_or_default();
+
+    if !metrics.trust_forwarded_headers {
+        return base_path.to_string();
+    }
+
+    let prefix = first_value(request, &X_FORWARDED_PREFIX)
+        .map(|prefix| prefix.trim_end_matches('/'))
+        .filter(|prefix| prefix.starts_with('/'))
+        .unwrap_or_default();
+
+    let proto =
+        first_value(request, &X_FORWARDED_PROTO).filter(|proto| matches!(*proto, "http" | "https"));
+    let host =
+        first_value(request, &X_FORWARDED_HOST).or_else(|| first_value(request, &header::HOST));
+
+    match (proto, host) {
+        (Some(proto), Some(host)) => format!("{proto}://{host}{prefix}{base_path}"),
+        _ => format!("{prefix}{base_path}"),
+    }
+}
+
+/// Address of the client, which is the one the last proxy appended to `X-Forwarded-For` when
+/// `metrics.trust_forwarded_headers` is set
+///
+/// Earlier addresses of the header are set by the client itself, so only a single proxy is
+/// trusted.
+pub(super) fn client_ip<B>(metrics: &config::Metrics, request: &Request<B>) -> Option<IpAddr> {
+    let forwarded = metrics
+        .trust_forwarded_headers
+        .then(|| {
+            request
+                .headers()
+                .get_all(&X_FORWARDED_FOR)
+                .iter()
+                .next_back()
+        })
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|addr| addr.trim().parse().ok());
+
+    forwarded.or_else(|| {
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    })
+}
+
+/// First value of a header which may list several, one per proxy
+fn first_value<'a, B>(request: &'a Request<B>, name: &HeaderName) -> Option<&'a str> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(base_path: Option<&str>, trust_forwarded_headers: bool) -> config::Metrics {
+        let mut metrics: config::Metrics = toml::from_str("enabled = true\nport = 3000").unwrap();
+        metrics.base_path = base_path.map(str::to_string);
+        metrics.trust_forwarded_headers = trust_forwarded_headers;
+        metrics
+    }
+
+    fn request(headers: &[(&str, &str)]) -> Request<()> {
+        let mut request = Request::builder().uri("/api/v1/chains");
+
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_link_base() {
+        let proxied = request(&[
+            ("host", "10.0.0.5:3000"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "ingress.example.com, 10.0.0.1"),
+            ("x-forwarded-prefix", "/monitoring/"),
+        ]);
+
+        assert_eq!(link_base(&metrics(None, false), &proxied), "");
+        assert_eq!(
+            link_base(&metrics(Some("/chainpulse"), false), &proxied),
+            "/chainpulse"
+        );
+        assert_eq!(
+            link_base(&metrics(Some("/chainpulse"), true), &proxied),
+            "https://ingress.example.com/monitoring/chainpulse"
+        );
+
+        // Without the scheme, the link stays relative to the host
+        let prefixed = request(&[
+            ("host", "10.0.0.5:3000"),
+            ("x-forwarded-prefix", "/monitoring"),
+        ]);
+        assert_eq!(link_base(&metrics(None, true), &prefixed), "/monitoring");
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let proxied = request(&[("x-forwarded-for", "1.1.1.1, 2.2.2.2")]);
+
+        assert_eq!(client_ip(&metrics(None, false), &proxied), None);
+        assert_eq!(
+            client_ip(&metrics(None, true), &proxied),
+            Some("2.2.2.2".parse().unwrap())
+        );
+        assert_eq!(client_ip(&metrics(None, true), &request(&[])), None);
+    }
+}
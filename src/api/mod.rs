@@ -0,0 +1,262 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::{
+        header::{self, HeaderName},
+        HeaderValue, Request, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router, Server,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use prometheus::{Encoder, Registry, TextEncoder};
+use sqlx::SqlitePool;
+use tokio::sync::{broadcast, watch};
+use tower_http::{
+    compression::CompressionLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{info, info_span, Span};
+
+use crate::{
+    config::{Config, StuckPackets},
+    metrics::Metrics,
+    status::Status,
+    webhook::Event,
+};
+
+use self::{error::ApiError, rate_limit::RateLimiter};
+
+mod admin;
+mod error;
+mod format;
+mod forwarded;
+mod rate_limit;
+mod reports;
+mod timestamps;
+mod v1;
+mod v2;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Date after which the v1 API may be removed, advertised in the `Sunset` header
+const V1_SUNSET: &str = "Thu, 01 Jul 2027 00:00:00 GMT";
+
+pub async fn run(
+    config: watch::Receiver<Config>,
+    registry: Registry,
+    metrics: Metrics,
+    db: SqlitePool,
+    status: Status,
+    events: broadcast::Sender<Event>,
+) -> Result<()> {
+    // Metrics settings are not reloaded
+    let metrics_config = config.borrow().metrics.clone();
+    let addr = SocketAddr::new(metrics_config.bind_address, metrics_config.port);
+    let tls = metrics_config.tls.clone();
+    let state = ApiState {
+        registry,
+        metrics,
+        db,
+        status,
+        events,
+        rate_limiter: metrics_config
+            .rate_limit
+            .map(|rate_limit| Arc::new(RateLimiter::new(rate_limit))),
+        config,
+    };
+
+    let routes = Router::new()
+        .route(
+            "/metrics",
+            get(get_metrics).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_metrics_auth,
+            )),
+        )
+        .nest(
+            "/api/v1",
+            v1::routes(state.clone())
+                .layer(middleware::from_fn_with_state(state.clone(), deprecate_v1)),
+        )
+        .nest("/api/v2", v2::routes(state.clone()))
+        .nest("/api/admin", admin::routes(state.clone()))
+        .merge(reports::routes())
+        .layer(middleware::from_fn(timestamps::scope));
+
+    let base_path = metrics_config.base_path.clone().unwrap_or_default();
+    let app = match base_path.as_str() {
+        "" => routes,
+        base_path => Router::new().nest(base_path, routes),
+    };
+
+    let app = app
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(error::scope_request_id))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_request(())
+                .on_response(log_response)
+                .on_failure(()),
+        )
+        .layer(PropagateRequestIdLayer::new(error::X_REQUEST_ID.clone()))
+        // Keeps the `x-request-id` sent by the client, if any
+        .layer(SetRequestIdLayer::new(
+            error::X_REQUEST_ID.clone(),
+            MakeRequestUuid,
+        ))
+        .with_state(state);
+
+    match tls {
+        Some(tls) => {
+            let rustls = RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "failed to load TLS certificate `{}` and key `{}`: {e}",
+                        tls.cert.display(),
+                        tls.key.display()
+                    )
+                })?;
+            let server = axum_server::bind_rustls(addr, rustls)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+
+            info!("Metrics server listening at https://{addr}{base_path}/metrics");
+            server.await?;
+        }
+        None => {
+            let server =
+                Server::bind(&addr).serve(app.into_make_service_with_connect_info::<SocketAddr>());
+
+            info!("Metrics server listening at http://{addr}{base_path}/metrics");
+            server.await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_metrics(State(state): State<ApiState>) -> String {
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+
+    let metric_families = state.registry.gather();
+    encoder.encode(&metric_families, &mut buffer).unwrap();
+
+    String::from_utf8(buffer).unwrap()
+}
+
+/// Reject scrapes without the configured basic auth credentials, if any
+async fn require_metrics_auth<B>(
+    State(state): State<ApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(auth) = state.config.borrow().metrics.basic_auth.clone() else {
+        return next.run(request).await;
+    };
+
+    let expected = format!("{}:{}", auth.username, auth.password);
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| {
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded.trim()).ok()
+        });
+
+    match provided {
+        Some(credentials) if admin::constant_time_eq(&credentials, expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            let error = ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "missing or invalid metrics credentials",
+            );
+
+            let mut response = error.into_response();
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"chainpulse\""),
+            );
+            response
+        }
+    }
+}
+
+fn request_span<B>(request: &Request<B>) -> Span {
+    info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %error::request_id(request),
+    )
+}
+
+fn log_response<B>(response: &Response<B>, latency: Duration, _span: &Span) {
+    info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "Request completed"
+    );
+}
+
+/// Mark v1 responses as deprecated and point clients at the v2 equivalent
+async fn deprecate_v1<B>(
+    State(state): State<ApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // Nested routers see the path without the base path and the `/api/v1` prefix
+    let successor = format!(
+        "<{}/api/v2{}>; rel=\"successor-version\"",
+        forwarded::link_base(&state.config.borrow().metrics, &request),
+        request.uri().path()
+    );
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    headers.insert(
+        HeaderName::from_static("sunset"),
+        HeaderValue::from_static(V1_SUNSET),
+    );
+
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+
+    response
+}
+
+// API State and types
+#[derive(Clone)]
+struct ApiState {
+    registry: Registry,
+    metrics: Metrics,
+    db: SqlitePool,
+    status: Status,
+    /// Events queue of the subscribers, only read for its depth
+    events: broadcast::Sender<Event>,
+    config: watch::Receiver<Config>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl ApiState {
+    /// Stuck thresholds of the current configuration
+    fn stuck_thresholds(&self) -> StuckPackets {
+        self.config.borrow().stuck_packets.clone()
+    }
+}
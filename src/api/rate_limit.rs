@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::{header, HeaderName, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::debug;
+
+use crate::config::{self, RateLimitKey};
+
+use super::{error::ApiError, forwarded, ApiState};
+
+/// Header identifying the client when rate limiting by API key
+static X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Number of buckets tracked at most, above which the least recently used are evicted
+const MAX_BUCKETS: usize = 10_000;
+
+/// Token buckets per client and route
+pub(super) struct RateLimiter {
+    config: config::RateLimit,
+    max_buckets: usize,
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    pub(super) fn new(config: config::RateLimit) -> Self {
+        Self::with_capacity(config, MAX_BUCKETS)
+    }
+
+    fn with_capacity(config: config::RateLimit, max_buckets: usize) -> Self {
+        Self {
+            config,
+            max_buckets: max_buckets.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn refill_rate(&self) -> f64 {
+        f64::from(self.config.requests_per_minute) / 60.0
+    }
+
+    /// Take a token from the client's bucket for this route, or return how long to wait for one
+    fn acquire(&self, client: String, route: String) -> Result<(), Duration> {
+        let now = Instant::now();
+        let burst = f64::from(self.config.burst.max(1));
+        let rate = self.refill_rate();
+
+        let key = (client, route);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= self.max_buckets && !buckets.contains_key(&key) {
+            // Buckets refilled to capacity hold no state worth keeping
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * rate < burst
+            });
+
+            if buckets.len() >= self.max_buckets {
+                // Evict a tenth of the buckets at once, the least recently used, so that the scan
+                // is not repeated for every new client
+                let evicted = buckets.len() - self.max_buckets + 1 + self.max_buckets / 10;
+                let mut updated_at: Vec<_> =
+                    buckets.values().map(|bucket| bucket.updated_at).collect();
+                let cutoff = *updated_at.select_nth_unstable(evicted - 1).1;
+
+                buckets.retain(|_, bucket| bucket.updated_at > cutoff);
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        } else {
+            Err(Duration::from_secs(60))
+        }
+    }
+}
+
+/// Reject requests exceeding the configured rate for their client and route
+pub(super) async fn limit<B>(
+    State(state): State<ApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(limiter) = state.rate_limiter.as_deref() else {
+        return next.run(request).await;
+    };
+
+    let client = client_key(&limiter.config, &state.config.borrow().metrics, &request);
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    match limiter.acquire(client.clone(), route.clone()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            debug!(%client, %route, "Rate limit exceeded");
+
+            let retry_after = wait.as_secs() + 1;
+            let error = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                format!("too many requests, retry in {retry_after} seconds"),
+            );
+
+            let mut response = error.into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
+
+/// Identify the client by API key, falling back to its IP address for requests without a
+/// configured one, so that made up keys do not each get a fresh allowance
+fn client_key<B>(
+    rate_limit: &config::RateLimit,
+    metrics: &config::Metrics,
+    request: &Request<B>,
+) -> String {
+    if rate_limit.key == RateLimitKey::ApiKey {
+        let index = request
+            .headers()
+            .get(&X_API_KEY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|api_key| rate_limit.api_keys.iter().position(|key| key == api_key));
+
+        // The position of the key stands for it, keeping the key itself out of the logs
+        if let Some(index) = index {
+            return format!("key:{index}");
+        }
+    }
+
+    forwarded::client_ip(metrics, request)
+        .map(|ip| format!("ip:{ip}"))
+        .unwrap_or_else(|| "ip:unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limit() -> config::RateLimit {
+        toml::from_str(
+            "requests_per_minute = 60\nburst = 1\nkey = \"api_key\"\napi_keys = [\"secret\"]",
+        )
+        .unwrap()
+    }
+
+    fn request(api_key: &str) -> Request<()> {
+        Request::builder()
+            .uri("/api/v1/packets")
+            .header("x-api-key", api_key)
+            .header("x-forwarded-for", "1.1.1.1")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rotating_api_keys() {
+        let mut metrics: config::Metrics = toml::from_str("enabled = true\nport = 3000").unwrap();
+        metrics.trust_forwarded_headers = true;
+
+        let rate_limit = rate_limit();
+        assert_eq!(
+            client_key(&rate_limit, &metrics, &request("secret")),
+            "key:0"
+        );
+
+        // Unknown keys share the allowance of the IP address
+        let limiter = RateLimiter::with_capacity(rate_limit, 4);
+        let acquire = |api_key: &str| {
+            let client = client_key(&limiter.config, &metrics, &request(api_key));
+            limiter.acquire(client, "/api/v1/packets".to_string())
+        };
+
+        assert!(acquire("rotating-0").is_ok());
+        for i in 1..100 {
+            assert!(acquire(&format!("rotating-{i}")).is_err());
+        }
+        assert!(acquire("secret").is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 2);
+
+        // The number of buckets stays bounded however many clients are seen
+        for i in 0..100 {
+            assert!(limiter.acquire(format!("ip:{i}"), "/".to_string()).is_ok());
+            assert!(limiter.buckets.lock().unwrap().len() <= 4);
+        }
+
+        // The least recently used buckets are the ones evicted
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.contains_key(&("ip:99".to_string(), "/".to_string())));
+        assert!(!buckets.contains_key(&("ip:0".to_string(), "/".to_string())));
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use super::{
+    error::{ApiError, ApiPath, ApiResult},
+    ApiState,
+};
+
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new()
+        .route("/reports/", get(list_reports))
+        .route("/reports/:file", get(get_report))
+}
+
+/// Directory of the SLA reports, when configured
+fn directory(state: &ApiState) -> ApiResult<PathBuf> {
+    state
+        .config
+        .borrow()
+        .reports
+        .as_ref()
+        .map(|reports| reports.directory.clone())
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "reports_disabled",
+                "SLA reports are not configured",
+            )
+        })
+}
+
+/// Whether a file name is one of a report, which also keeps it within the directory
+fn is_report(file: &str) -> bool {
+    match file.rsplit_once('.') {
+        Some((name, "json" | "html")) => {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+        _ => false,
+    }
+}
+
+/// Index of the reports on disk, the most recent first
+async fn list_reports(State(state): State<ApiState>) -> ApiResult<Html<String>> {
+    let directory = directory(&state)?;
+
+    let mut names = Vec::new();
+
+    if let Ok(mut entries) = tokio::fs::read_dir(&directory).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let file = entry.file_name().to_string_lossy().into_owned();
+
+            if let Some(name) = file.strip_suffix(".json").filter(|_| is_report(&file)) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort_unstable_by(|a, b| b.cmp(a));
+
+    let items: String = names
+        .iter()
+        .map(|name| {
+            format!(
+                "<li>{name} (<a href=\"{name}.html\">HTML</a>, \
+                 <a href=\"{name}.json\">JSON</a>)</li>\n"
+            )
+        })
+        .collect();
+
+    Ok(Html(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Chainpulse SLA reports</title>\n</head>\n<body>\n\
+         <h1>SLA reports</h1>\n<ul>\n{items}</ul>\n</body>\n</html>\n"
+    )))
+}
+
+async fn get_report(
+    State(state): State<ApiState>,
+    ApiPath(file): ApiPath<String>,
+) -> ApiResult<Response> {
+    let directory = directory(&state)?;
+
+    let not_found = || {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("report `{file}` not found"),
+        )
+    };
+
+    if !is_report(&file) {
+        return Err(not_found());
+    }
+
+    let content = tokio::fs::read(directory.join(&file))
+        .await
+        .map_err(|_| not_found())?;
+
+    let content_type = if file.ends_with(".json") {
+        "application/json"
+    } else {
+        "text/html; charset=utf-8"
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], content).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_report() {
+        assert!(is_report("2026-09.json"));
+        assert!(is_report("2026-W41.html"));
+        assert!(!is_report(".json"));
+        assert!(!is_report("2026-09.txt"));
+        assert!(!is_report("../config.json"));
+        assert!(!is_report("..%2Fconfig.html"));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{fmt::Write, str::FromStr};
+
+use axum::{extract::FromRequestParts, http::Request, middleware::Next, response::Response};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::{
+    error::BoxDynError,
+    sqlite::{SqliteTypeInfo, SqliteValueRef},
+    Decode, Sqlite,
+};
+
+use super::error::{ApiError, ApiQuery};
+
+tokio::task_local! {
+    /// Representation of the times of the response being rendered
+    static TIMESTAMPS: Timestamps;
+}
+
+/// Representation of the times of a response, chosen with `?timestamps=`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(super) enum Timestamps {
+    /// Points in time in RFC 3339 UTC, such as `2024-05-01T12:00:00Z`, and ages as ISO 8601
+    /// durations, such as `PT1H30M`
+    #[default]
+    Rfc3339,
+    /// Points in time as UNIX seconds and ages as seconds
+    Epoch,
+}
+
+impl Timestamps {
+    fn current() -> Self {
+        TIMESTAMPS
+            .try_with(|timestamps| *timestamps)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct TimestampsQuery {
+    timestamps: Option<String>,
+}
+
+/// Render the times of the response as requested with `?timestamps=`
+pub(super) async fn scope<B>(request: Request<B>, next: Next<B>) -> Result<Response, ApiError> {
+    let (mut parts, body) = request.into_parts();
+    let ApiQuery(query) = ApiQuery::<TimestampsQuery>::from_request_parts(&mut parts, &()).await?;
+
+    let timestamps = match query.timestamps.as_deref() {
+        None | Some("rfc3339") => Timestamps::Rfc3339,
+        Some("epoch") => Timestamps::Epoch,
+        Some(_) => {
+            return Err(ApiError::invalid_parameter(
+                "timestamps must be `rfc3339` or `epoch`",
+            ))
+        }
+    };
+
+    let request = Request::from_parts(parts, body);
+
+    // Responses are serialized within the handler, and so within the scope
+    Ok(TIMESTAMPS.scope(timestamps, next.run(request)).await)
+}
+
+/// Point in time of a response, in UTC
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    pub(super) fn from_unix(seconds: i64) -> Option<Self> {
+        DateTime::from_timestamp(seconds, 0).map(Self)
+    }
+
+    /// Packet timeouts are recorded in nanoseconds, zero meaning no timeout
+    pub(super) fn from_unix_nanos(nanos: i64) -> Option<Self> {
+        match nanos {
+            0 => None,
+            nanos => Some(Self(DateTime::from_timestamp_nanos(nanos))),
+        }
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self(time)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = chrono::ParseError;
+
+    /// Parse the `datetime()` strings of SQLite, which are in UTC, or RFC 3339
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
+            Ok(time) => Ok(Self(time.and_utc())),
+            Err(_) => DateTime::parse_from_rfc3339(s).map(|time| Self(time.with_timezone(&Utc))),
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match Timestamps::current() {
+            Timestamps::Rfc3339 => {
+                serializer.serialize_str(&self.0.to_rfc3339_opts(SecondsFormat::Secs, true))
+            }
+            Timestamps::Epoch => serializer.serialize_i64(self.0.timestamp()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for Timestamp {
+    fn type_info() -> SqliteTypeInfo {
+        <String as sqlx::Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as sqlx::Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for Timestamp {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        let time = <&str as Decode<Sqlite>>::decode(value)?;
+        Ok(time.parse()?)
+    }
+}
+
+/// Time elapsed since, or left until, an event of a response, negative for past deadlines
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct Seconds(pub(super) i64);
+
+impl Seconds {
+    /// ISO 8601 duration, such as `PT1H30M`, with a leading `-` when negative
+    fn iso8601(self) -> String {
+        let seconds = self.0.unsigned_abs();
+        let (days, hours, minutes, seconds) = (
+            seconds / 86400,
+            seconds / 3600 % 24,
+            seconds / 60 % 60,
+            seconds % 60,
+        );
+
+        let mut duration = String::new();
+
+        if self.0 < 0 {
+            duration.push('-');
+        }
+
+        duration.push('P');
+
+        if days > 0 {
+            let _ = write!(duration, "{days}D");
+        }
+
+        if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+            duration.push('T');
+        }
+
+        for (value, unit) in [(hours, 'H'), (minutes, 'M')] {
+            if value > 0 {
+                let _ = write!(duration, "{value}{unit}");
+            }
+        }
+
+        if seconds > 0 || duration.ends_with('T') {
+            let _ = write!(duration, "{seconds}S");
+        }
+
+        duration
+    }
+}
+
+impl Serialize for Seconds {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match Timestamps::current() {
+            Timestamps::Rfc3339 => serializer.serialize_str(&self.iso8601()),
+            Timestamps::Epoch => serializer.serialize_i64(self.0),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for Seconds {
+    fn type_info() -> SqliteTypeInfo {
+        <i64 as sqlx::Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <i64 as sqlx::Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for Seconds {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        <i64 as Decode<Sqlite>>::decode(value).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso8601_durations() {
+        assert_eq!(Seconds(0).iso8601(), "PT0S");
+        assert_eq!(Seconds(45).iso8601(), "PT45S");
+        assert_eq!(Seconds(5400).iso8601(), "PT1H30M");
+        assert_eq!(Seconds(86400).iso8601(), "P1D");
+        assert_eq!(Seconds(90061).iso8601(), "P1DT1H1M1S");
+        assert_eq!(Seconds(-300).iso8601(), "-PT5M");
+    }
+
+    #[tokio::test]
+    async fn test_timestamps() {
+        let time: Timestamp = "2024-05-01 12:00:00".parse().unwrap();
+        assert_eq!(Timestamp::from_unix(1714564800), Some(time));
+        assert_eq!(
+            Timestamp::from_unix_nanos(1_714_564_800 * 1_000_000_000),
+            Some(time)
+        );
+        assert_eq!(Timestamp::from_unix_nanos(0), None);
+        assert_eq!("2024-05-01T14:00:00+02:00".parse(), Ok(time));
+
+        let json = || serde_json::to_string(&(time, Seconds(930))).unwrap();
+
+        assert_eq!(json(), r#"["2024-05-01T12:00:00Z","PT15M30S"]"#);
+        assert_eq!(
+            TIMESTAMPS.scope(Timestamps::Epoch, async { json() }).await,
+            "[1714564800,930]"
+        );
+    }
+}
@@ -0,0 +1,3663 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+
+use crate::{
+    channels,
+    clear::{self, ClearTarget, TriggerResult},
+    config::{Config, SanitizedConfig, StuckPackets},
+    denoms,
+    monitor::{self, format_age},
+    prices::UsdPrices,
+    rate_limits,
+    rollup::sum_amounts,
+    status::{ConnectionState, Status, TaskState},
+};
+
+use super::{
+    admin,
+    error::{ApiError, ApiJson, ApiPath, ApiQuery, ApiResult},
+    format::{self, Format},
+    rate_limit,
+    timestamps::{Seconds, Timestamp},
+    ApiState,
+};
+
+/// Largest number of packets accepted by a single clearing request
+const MAX_CLEAR_PACKETS: usize = 1000;
+
+/// Largest number of packets accepted by a single status request
+const MAX_STATUS_PACKETS: usize = 1000;
+
+pub(super) fn routes(state: ApiState) -> Router<ApiState> {
+    // Database heavy queries, subject to the configured rate limit
+    let limited = Router::new()
+        .route("/packets/by-user", get(get_packets_by_user))
+        .route("/packets/stuck", get(get_stuck_packets))
+        .route("/packets/expiring", get(get_expiring_packets))
+        .route("/channels/congestion", get(get_channel_congestion))
+        .route("/routes/top-stuck", get(get_top_stuck_routes))
+        .route("/channels/:channel/latency", get(get_channel_latency))
+        .route("/chains/:chain/gas", get(get_gas_prices))
+        .route("/frontruns", get(get_frontruns))
+        .route("/packets/duplicates", get(get_duplicate_packets))
+        .route("/packets/expired", get(get_expired_packets))
+        .route("/packets/failed-acks", get(get_failed_acks))
+        .route("/address/:address/summary", get(get_address_summary))
+        .route("/packets/status", post(get_packet_statuses))
+        .route("/clients", get(get_clients))
+        .route("/msgs/decoded", get(get_decoded_msgs))
+        .route_layer(middleware::from_fn_with_state(state, rate_limit::limit));
+
+    Router::new()
+        .route("/chains", get(get_chains))
+        .route("/config", get(get_config))
+        .route("/msg-types/unknown", get(get_unknown_msg_types))
+        .route("/denoms", get(get_denom_traces))
+        .route("/stats/daily", get(get_daily_stats))
+        .route("/packets/clear", post(clear_packets))
+        .route("/internal/tasks", get(get_internal_tasks))
+        .route(
+            "/packets/:chain/:channel/:sequence",
+            get(get_packet_details),
+        )
+        .merge(limited)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct UserPacketsQuery {
+    address: String,
+    #[serde(default)]
+    pub(super) role: String, // sender, receiver, both (default)
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(flatten)]
+    range: TimeRange,
+    #[serde(flatten)]
+    sort: Sort,
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct StuckPacketsQuery {
+    /// Age after which a packet is listed, its stuck threshold by default
+    min_age_seconds: Option<i64>,
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(alias = "groupBy")]
+    group_by: Option<String>, // relayer
+    #[serde(flatten)]
+    filters: PacketFilters,
+    #[serde(flatten)]
+    range: TimeRange,
+    #[serde(flatten)]
+    sort: Sort,
+}
+
+/// Optional filters shared by the packet list endpoints
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct PacketFilters {
+    chain: Option<String>,
+    channel: Option<String>, // matches source or destination
+    port: Option<String>,    // matches source or destination
+    denom: Option<String>,
+    sender: Option<String>,
+    receiver: Option<String>,
+    min_amount: Option<String>,
+}
+
+impl PacketFilters {
+    /// Append the filters as `AND` conditions on the `packets p JOIN txs t` query
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>) -> ApiResult<()> {
+        if let Some(chain) = &self.chain {
+            query.push(" AND t.chain = ").push_bind(chain.clone());
+        }
+
+        if let Some(channel) = &self.channel {
+            query
+                .push(" AND (p.src_channel = ")
+                .push_bind(channel.clone())
+                .push(" OR p.dst_channel = ")
+                .push_bind(channel.clone())
+                .push(")");
+        }
+
+        if let Some(port) = &self.port {
+            query
+                .push(" AND (p.src_port = ")
+                .push_bind(port.clone())
+                .push(" OR p.dst_port = ")
+                .push_bind(port.clone())
+                .push(")");
+        }
+
+        if let Some(denom) = &self.denom {
+            query.push(" AND p.denom = ").push_bind(denom.clone());
+        }
+
+        if let Some(sender) = &self.sender {
+            query.push(" AND p.sender = ").push_bind(sender.clone());
+        }
+
+        if let Some(receiver) = &self.receiver {
+            query.push(" AND p.receiver = ").push_bind(receiver.clone());
+        }
+
+        if let Some(min_amount) = &self.min_amount {
+            let min_amount = min_amount
+                .parse::<f64>()
+                .map_err(|_| ApiError::invalid_parameter("min_amount must be a number"))?;
+
+            query
+                .push(" AND CAST(p.amount AS REAL) >= ")
+                .push_bind(min_amount);
+        }
+
+        Ok(())
+    }
+}
+
+/// Ordering of the packet list endpoints
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Sort {
+    sort: Option<String>,  // age, amount, timeout, sequence
+    order: Option<String>, // asc, desc
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SortKey {
+    Age,
+    Amount,
+    Timeout,
+    Sequence,
+}
+
+impl SortKey {
+    /// Most urgent first: oldest, largest, closest to timeout, lowest sequence
+    fn descending_by_default(self) -> bool {
+        matches!(self, Self::Age | Self::Amount)
+    }
+}
+
+impl Sort {
+    /// Append the `ORDER BY` clause, using the endpoint's default when `sort` is not given
+    fn push_order_by(
+        &self,
+        query: &mut QueryBuilder<'_, Sqlite>,
+        default: SortKey,
+        default_descending: bool,
+    ) -> ApiResult<()> {
+        let key = match self.sort.as_deref() {
+            None => default,
+            Some("age") => SortKey::Age,
+            Some("amount") => SortKey::Amount,
+            Some("timeout") => SortKey::Timeout,
+            Some("sequence") => SortKey::Sequence,
+            Some(_) => {
+                return Err(ApiError::invalid_parameter(
+                    "sort must be one of `age`, `amount`, `timeout` or `sequence`",
+                ))
+            }
+        };
+
+        let descending = match self.order.as_deref() {
+            None if self.sort.is_none() => default_descending,
+            None => key.descending_by_default(),
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(_) => return Err(ApiError::invalid_parameter("order must be `asc` or `desc`")),
+        };
+
+        // The oldest packets have the smallest creation time
+        let ascending = if key == SortKey::Age {
+            descending
+        } else {
+            !descending
+        };
+        let direction = if ascending { "ASC" } else { "DESC" };
+
+        let column = match key {
+            SortKey::Age => "p.created_at",
+            SortKey::Amount => "CAST(p.amount AS REAL)",
+            SortKey::Timeout => "p.timeout_timestamp",
+            SortKey::Sequence => "p.sequence",
+        };
+
+        // Packets without an amount or a timeout timestamp go last either way
+        query.push(format!(
+            " ORDER BY {column} {direction} NULLS LAST, p.id {direction}"
+        ));
+
+        Ok(())
+    }
+}
+
+impl StuckPacketsQuery {
+    pub(super) fn group_by_relayer(&self) -> ApiResult<bool> {
+        match self.group_by.as_deref() {
+            None => Ok(false),
+            Some("relayer") => Ok(true),
+            Some(_) => Err(ApiError::invalid_parameter("group_by must be `relayer`")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct FrontrunsQuery {
+    signer: Option<String>,
+    channel: Option<String>,
+    from: Option<String>,
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+}
+
+/// Parse a timestamp given either as unix seconds or as RFC3339, returning unix seconds
+fn parse_timestamp(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok().or_else(|| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|dt| dt.timestamp())
+    })
+}
+
+/// Optional `[from, to)` bounds on the time a packet was recorded
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct TimeRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl TimeRange {
+    /// The bounds as unix seconds
+    fn bounds(&self) -> ApiResult<(Option<i64>, Option<i64>)> {
+        let from = match self.from.as_deref().map(parse_timestamp) {
+            Some(None) => return Err(ApiError::invalid_parameter("invalid `from` timestamp")),
+            Some(Some(from)) => Some(from),
+            None => None,
+        };
+        let to = match self.to.as_deref().map(parse_timestamp) {
+            Some(None) => return Err(ApiError::invalid_parameter("invalid `to` timestamp")),
+            Some(Some(to)) => Some(to),
+            None => None,
+        };
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from >= to {
+                return Err(ApiError::invalid_parameter("`from` must be before `to`"));
+            }
+        }
+
+        Ok((from, to))
+    }
+
+    /// Append the bounds as `AND` conditions on `p.created_at`
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>) -> ApiResult<()> {
+        let (from, to) = self.bounds()?;
+
+        if let Some(from) = from {
+            query
+                .push(" AND p.created_at >= datetime(")
+                .push_bind(from)
+                .push(", 'unixepoch')");
+        }
+
+        if let Some(to) = to {
+            query
+                .push(" AND p.created_at < datetime(")
+                .push_bind(to)
+                .push(", 'unixepoch')");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LatencyQuery {
+    #[serde(default = "default_latency_bucket")]
+    bucket: String, // hour (default), day
+    from: Option<String>,
+    to: Option<String>,
+}
+
+fn default_latency_bucket() -> String {
+    "hour".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct GasPricesQuery {
+    #[serde(default = "default_latency_bucket")]
+    bucket: String, // hour (default), day
+    denom: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct StuckPacketsResponse {
+    packets: Vec<StuckPacketInfo>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct StuckPacketInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    age_seconds: Seconds,
+    timeout_timestamp: Option<Timestamp>,
+    seconds_until_timeout: Option<Seconds>,
+    last_attempt_by: Option<String>,
+    expected_relayer: Option<String>,
+    /// Why the packet can no longer be relayed, `channel_closed` or `client_frozen`
+    unrecoverable: Option<String>,
+    /// Direction of the exhausted rate limit holding the packet, `send` on the sending chain or
+    /// `recv` on the receiving one
+    rate_limited: Option<String>,
+    /// Value of the packet in USD, when its base denom is priced
+    value_usd: Option<f64>,
+}
+
+impl StuckPacketInfo {
+    /// The relayer to chase for this packet: whoever last tried to relay it,
+    /// otherwise whoever most recently relayed on the same channel pair
+    fn responsible_relayer(&self) -> &str {
+        self.last_attempt_by
+            .as_deref()
+            .or(self.expected_relayer.as_deref())
+            .unwrap_or("unknown")
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StuckPacketsByRelayerResponse {
+    relayers: Vec<RelayerStuckPackets>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct RelayerStuckPackets {
+    relayer: String,
+    stuck_count: i64,
+    oldest_age_seconds: Seconds,
+    channels: Vec<String>,
+    packets: Vec<StuckPacketInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct UserPacketsResponse {
+    packets: Vec<PacketInfo>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    api_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChannelCongestionResponse {
+    channels: Vec<ChannelCongestion>,
+    total_stuck: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChannelCongestion {
+    chain_id: String,
+    counterparty_chain_id: Option<String>,
+    src_port: String,
+    src_channel: String,
+    dst_port: String,
+    dst_channel: String,
+    stuck_count: i64,
+    oldest_age_seconds: Seconds,
+    total_value: BTreeMap<String, String>,
+    /// Value of the stuck packets in USD, over the denoms with a price
+    total_value_usd: Option<f64>,
+    denoms: Vec<DenomValue>,
+}
+
+/// Value stuck in one denom, with its ICS-20 trace split into path and base denom, an `ibc/`
+/// denom being resolved to its trace first
+#[derive(Debug, Serialize)]
+struct DenomValue {
+    denom: String,
+    base_denom: String,
+    path: String,
+    amount: String,
+    value_usd: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CongestionRow {
+    chain_id: String,
+    counterparty_chain_id: Option<String>,
+    src_port: String,
+    src_channel: String,
+    dst_port: String,
+    dst_channel: String,
+    denom: Option<String>,
+    trace: Option<String>,
+    stuck_count: i64,
+    oldest_age_seconds: i64,
+    amounts: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChainsResponse {
+    chains: Vec<ChainInfo>,
+    total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChainInfo {
+    chain_id: String,
+    endpoint: String,
+    comet_version: String,
+    state: ConnectionState,
+    disconnected_since: Option<Timestamp>,
+    last_height: Option<u64>,
+    last_block_time: Option<Timestamp>,
+    head_lag_seconds: Option<Seconds>,
+    block_time_seconds: Option<f64>,
+    errors: u64,
+    reconnects: u64,
+    timeouts: u64,
+    decode_failures: u64,
+    last_error: Option<String>,
+}
+
+/// Runtime state of the collectors, background tasks, checks and queues of the instance
+#[derive(Debug, Serialize)]
+pub(super) struct InternalTasks {
+    leading: bool,
+    collectors: Vec<CollectorInfo>,
+    tasks: Vec<TaskInfo>,
+    checks: Vec<CheckInfo>,
+    queues: Queues,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectorInfo {
+    chain_id: String,
+    state: ConnectionState,
+    last_height: Option<u64>,
+    head_lag_seconds: Option<Seconds>,
+    reconnects: u64,
+    errors: u64,
+    last_error: Option<String>,
+    last_error_at: Option<Timestamp>,
+    /// When the collector reconnects, while it waits before doing so
+    backoff_until: Option<Timestamp>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskInfo {
+    name: String,
+    state: TaskState,
+    starts: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckInfo {
+    name: String,
+    runs: u64,
+    last_run_at: Option<Timestamp>,
+    last_run_seconds: Option<f64>,
+    last_error: Option<String>,
+    last_error_at: Option<Timestamp>,
+}
+
+#[derive(Debug, Serialize)]
+struct Queues {
+    /// Events waiting for the slowest subscriber of the embedding application
+    events: usize,
+    database: DatabaseQueue,
+}
+
+#[derive(Debug, Serialize)]
+struct DatabaseQueue {
+    connections: u32,
+    idle_connections: usize,
+    max_connections: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct FrontrunsResponse {
+    frontruns: Vec<FrontrunInfo>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct FrontrunInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    src_port: String,
+    dst_channel: String,
+    dst_port: String,
+    msg_type_url: String,
+    signer: String,
+    frontrunned_by: String,
+    tx_hash: String,
+    memo: String,
+    effected_tx_hash: Option<String>,
+    effected_memo: Option<String>,
+    created_at: Timestamp,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChannelLatencyResponse {
+    channel: String,
+    from: Timestamp,
+    to: Timestamp,
+    bucket_seconds: i64,
+    overall: LatencyStats,
+    buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct LatencyBucket {
+    start: Timestamp,
+    #[serde(flatten)]
+    stats: LatencyStats,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub(super) struct LatencyStats {
+    count: usize,
+    p50_seconds: Option<i64>,
+    p90_seconds: Option<i64>,
+    p99_seconds: Option<i64>,
+}
+
+impl LatencyStats {
+    fn from_latencies(mut latencies: Vec<i64>) -> Self {
+        latencies.sort_unstable();
+
+        Self {
+            count: latencies.len(),
+            p50_seconds: percentile(&latencies, 50.0),
+            p90_seconds: percentile(&latencies, 90.0),
+            p99_seconds: percentile(&latencies, 99.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct GasPricesResponse {
+    chain_id: String,
+    from: Timestamp,
+    to: Timestamp,
+    bucket_seconds: i64,
+    denoms: Vec<DenomGasPrices>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct DenomGasPrices {
+    denom: String,
+    /// Trace of an `ibc/` denom, once resolved, split as in the congestion endpoint
+    base_denom: String,
+    path: String,
+    latest: f64,
+    latest_at: Timestamp,
+    /// Median of the last bucket over the median of the whole range, above 1 when fees rise
+    spike_ratio: Option<f64>,
+    overall: GasPriceStats,
+    buckets: Vec<GasPriceBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct GasPriceBucket {
+    start: Timestamp,
+    #[serde(flatten)]
+    stats: GasPriceStats,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub(super) struct GasPriceStats {
+    count: usize,
+    min: Option<f64>,
+    p50: Option<f64>,
+    p90: Option<f64>,
+    max: Option<f64>,
+}
+
+impl GasPriceStats {
+    fn from_prices(mut prices: Vec<f64>) -> Self {
+        prices.sort_unstable_by(f64::total_cmp);
+
+        Self {
+            count: prices.len(),
+            min: prices.first().copied(),
+            p50: percentile(&prices, 50.0),
+            p90: percentile(&prices, 90.0),
+            max: prices.last().copied(),
+        }
+    }
+}
+
+/// Nearest-rank percentile over an already sorted slice
+fn percentile<T: Copy>(sorted: &[T], pct: f64) -> Option<T> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct PacketInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    age_seconds: Seconds,
+    relay_attempts: i64,
+    last_attempt_by: Option<String>,
+    ibc_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ConfigResponse {
+    #[serde(flatten)]
+    config: SanitizedConfig,
+    thresholds: Thresholds,
+}
+
+/// Thresholds of the stuck packet monitor, the stuck one being overridden for the chains and
+/// channels of `stuck_packets.overrides`
+#[derive(Debug, Serialize)]
+struct Thresholds {
+    scan_interval_seconds: u64,
+    stuck_after_seconds: u64,
+    near_timeout_seconds: i64,
+}
+
+// API Handlers
+async fn get_chains(State(state): State<ApiState>) -> Json<ChainsResponse> {
+    let chains = chain_infos(&state.status);
+    let total = chains.len() as i64;
+
+    Json(ChainsResponse { chains, total })
+}
+
+pub(super) fn chain_infos(status: &Status) -> Vec<ChainInfo> {
+    status
+        .chains()
+        .into_iter()
+        .map(|chain| ChainInfo {
+            head_lag_seconds: chain.head_lag_seconds().map(Seconds),
+            last_block_time: chain.last_block_time.map(Timestamp::from),
+            disconnected_since: chain.disconnected_since.map(Timestamp::from),
+            chain_id: chain.chain_id,
+            endpoint: chain.endpoint,
+            comet_version: chain.comet_version,
+            state: chain.state,
+            last_height: chain.last_height,
+            block_time_seconds: chain
+                .block_time_seconds
+                .map(|seconds| (seconds * 1000.0).round() / 1000.0),
+            errors: chain.errors,
+            reconnects: chain.reconnects,
+            timeouts: chain.timeouts,
+            decode_failures: chain.decode_failures,
+            last_error: chain.last_error,
+        })
+        .collect()
+}
+
+async fn get_internal_tasks(State(state): State<ApiState>) -> Json<InternalTasks> {
+    Json(internal_tasks(&state))
+}
+
+pub(super) fn internal_tasks(state: &ApiState) -> InternalTasks {
+    let collectors = state
+        .status
+        .chains()
+        .into_iter()
+        .map(|chain| CollectorInfo {
+            head_lag_seconds: chain.head_lag_seconds().map(Seconds),
+            last_error_at: chain.last_error_at.map(Timestamp::from),
+            backoff_until: chain.backoff_until.map(Timestamp::from),
+            chain_id: chain.chain_id,
+            state: chain.state,
+            last_height: chain.last_height,
+            reconnects: chain.reconnects,
+            errors: chain.errors,
+            last_error: chain.last_error,
+        })
+        .collect();
+
+    let tasks = state
+        .status
+        .tasks()
+        .into_iter()
+        .map(|task| TaskInfo {
+            name: task.name,
+            state: task.state,
+            starts: task.starts,
+        })
+        .collect();
+
+    let checks = state
+        .status
+        .checks()
+        .into_iter()
+        .map(|check| CheckInfo {
+            last_run_at: check.last_run_at.map(Timestamp::from),
+            last_error_at: check.last_error_at.map(Timestamp::from),
+            name: check.name,
+            runs: check.runs,
+            last_run_seconds: check.last_run_seconds,
+            last_error: check.last_error,
+        })
+        .collect();
+
+    InternalTasks {
+        leading: state.status.is_leading(),
+        collectors,
+        tasks,
+        checks,
+        queues: Queues {
+            events: state.events.len(),
+            database: DatabaseQueue {
+                connections: state.db.size(),
+                idle_connections: state.db.num_idle(),
+                max_connections: state.db.options().get_max_connections(),
+            },
+        },
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ClientsQuery {
+    chain: Option<String>,
+    /// Trusting period assumed for every client, since it is not tracked
+    #[serde(default = "default_trusting_period_hours")]
+    trusting_period_hours: i64,
+}
+
+fn default_trusting_period_hours() -> i64 {
+    336 // 14 days, two thirds of the usual 21 day unbonding period
+}
+
+/// Remaining time below which a client is reported as expiring
+const CLIENT_EXPIRING_SECONDS: i64 = 86400;
+
+#[derive(Debug, Serialize)]
+struct ClientsResponse {
+    clients: Vec<ClientInfo>,
+    total: i64,
+}
+
+/// Latest update of a light client hosted on a monitored chain
+#[derive(Debug, Serialize)]
+pub(super) struct ClientInfo {
+    chain_id: String,
+    client_id: String,
+    counterparty_chain_id: Option<String>,
+    last_update_height: Option<i64>,
+    last_header_time: Option<Timestamp>,
+    last_updated_at: Timestamp,
+    last_updated_by: Option<String>,
+    updates: i64,
+    trusting_period_seconds: i64,
+    /// Estimated from the time of the last header, negative once expired
+    expires_in_seconds: Seconds,
+    status: &'static str, // active, expiring, expired
+}
+
+#[derive(sqlx::FromRow)]
+struct ClientRow {
+    chain_id: String,
+    client_id: String,
+    counterparty_chain_id: Option<String>,
+    header_height: Option<i64>,
+    header_time: Option<Timestamp>,
+    updated_at: Timestamp,
+    signer: Option<String>,
+    updates: i64,
+    header_age_seconds: i64,
+}
+
+async fn get_clients(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ClientsQuery>,
+) -> ApiResult<Json<ClientsResponse>> {
+    let clients = fetch_clients(&state.db, &params).await?;
+    let total = clients.len() as i64;
+
+    Ok(Json(ClientsResponse { clients, total }))
+}
+
+/// Light clients updated on the monitored chains, by chain and client ID
+pub(super) async fn fetch_clients(
+    db: &SqlitePool,
+    params: &ClientsQuery,
+) -> ApiResult<Vec<ClientInfo>> {
+    if !(1..=8760).contains(&params.trusting_period_hours) {
+        return Err(ApiError::invalid_parameter(
+            "trusting_period_hours must be between 1 and 8760",
+        ));
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        WITH latest AS (
+            SELECT t.chain, u.client_id, MAX(u.id) as id, COUNT(*) as updates
+            FROM client_updates u
+            JOIN txs t ON u.tx_id = t.id
+        "#,
+    );
+
+    if let Some(chain) = &params.chain {
+        query.push(" WHERE t.chain = ").push_bind(chain.clone());
+    }
+
+    query.push(
+        r#"
+            GROUP BY t.chain, u.client_id
+        )
+        SELECT
+            l.chain as chain_id,
+            l.client_id,
+            u.counterparty_chain_id,
+            u.header_height,
+            u.header_time,
+            u.created_at as updated_at,
+            u.signer,
+            l.updates,
+            CAST((strftime('%s', 'now') - strftime('%s', COALESCE(u.header_time, u.created_at))) AS INTEGER) as header_age_seconds
+        FROM latest l
+        JOIN client_updates u ON u.id = l.id
+        ORDER BY l.chain, l.client_id
+        "#,
+    );
+
+    let rows = query.build_query_as::<ClientRow>().fetch_all(db).await?;
+    let trusting_period_seconds = params.trusting_period_hours * 3600;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let expires_in_seconds = trusting_period_seconds - row.header_age_seconds;
+
+            ClientInfo {
+                chain_id: row.chain_id,
+                client_id: row.client_id,
+                counterparty_chain_id: row.counterparty_chain_id,
+                last_update_height: row.header_height,
+                last_header_time: row.header_time,
+                last_updated_at: row.updated_at,
+                last_updated_by: row.signer,
+                updates: row.updates,
+                trusting_period_seconds,
+                expires_in_seconds: Seconds(expires_in_seconds),
+                status: client_status(expires_in_seconds),
+            }
+        })
+        .collect())
+}
+
+fn client_status(expires_in_seconds: i64) -> &'static str {
+    if expires_in_seconds <= 0 {
+        "expired"
+    } else if expires_in_seconds < CLIENT_EXPIRING_SECONDS {
+        "expiring"
+    } else {
+        "active"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct UnknownMsgTypesQuery {
+    chain: Option<String>,
+    /// Only list messages of IBC modules, whose type URL starts with `/ibc.`
+    #[serde(default)]
+    ibc_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct UnknownMsgTypesResponse {
+    msg_types: Vec<UnknownMsgType>,
+    total: i64,
+}
+
+/// A message type seen on a chain which chainpulse does not decode
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct UnknownMsgType {
+    chain_id: String,
+    type_url: String,
+    count: i64,
+    first_seen: Timestamp,
+    last_seen: Timestamp,
+}
+
+async fn get_unknown_msg_types(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<UnknownMsgTypesQuery>,
+) -> ApiResult<Json<UnknownMsgTypesResponse>> {
+    let msg_types = fetch_unknown_msg_types(&state.db, &params).await?;
+    let total = msg_types.len() as i64;
+
+    Ok(Json(UnknownMsgTypesResponse { msg_types, total }))
+}
+
+/// Message types left undecoded, most frequent first
+pub(super) async fn fetch_unknown_msg_types(
+    db: &SqlitePool,
+    params: &UnknownMsgTypesQuery,
+) -> ApiResult<Vec<UnknownMsgType>> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT chain as chain_id, type_url, count, first_seen, last_seen
+        FROM unknown_msg_types
+        WHERE 1 = 1"#,
+    );
+
+    if let Some(chain) = &params.chain {
+        query.push(" AND chain = ").push_bind(chain.clone());
+    }
+
+    if params.ibc_only {
+        query.push(" AND type_url LIKE '/ibc.%'");
+    }
+
+    query.push(" ORDER BY count DESC, chain, type_url");
+
+    Ok(query
+        .build_query_as::<UnknownMsgType>()
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DecodedMsgsQuery {
+    type_url: String,
+    chain: Option<String>,
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct DecodedMsgsResponse {
+    msgs: Vec<DecodedMsg>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    api_version: String,
+}
+
+/// A message chainpulse has no types for, decoded into its fields by the descriptor sets
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct DecodedMsg {
+    chain_id: String,
+    height: i64,
+    tx_hash: String,
+    type_url: String,
+    fields: sqlx::types::Json<serde_json::Value>,
+    created_at: Timestamp,
+}
+
+async fn get_decoded_msgs(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DecodedMsgsQuery>,
+) -> ApiResult<Json<DecodedMsgsResponse>> {
+    let (msgs, total) = fetch_decoded_msgs(&state.db, &params).await?;
+
+    Ok(Json(DecodedMsgsResponse {
+        msgs,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+        api_version: "1.0".to_string(),
+    }))
+}
+
+/// Messages of a type decoded by the descriptor sets, most recent first, along with the total
+/// number of matches
+pub(super) async fn fetch_decoded_msgs(
+    db: &SqlitePool,
+    params: &DecodedMsgsQuery,
+) -> ApiResult<(Vec<DecodedMsg>, i64)> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            t.height,
+            t.hash as tx_hash,
+            m.type_url,
+            m.fields,
+            m.created_at
+        FROM decoded_msgs m
+        JOIN txs t ON m.tx_id = t.id
+        "#,
+    );
+    push_decoded_msg_conditions(&mut query, params);
+    query.push(" ORDER BY m.id DESC LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    let mut count = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*) FROM decoded_msgs m JOIN txs t ON m.tx_id = t.id",
+    );
+    push_decoded_msg_conditions(&mut count, params);
+
+    let msgs = query.build_query_as::<DecodedMsg>().fetch_all(db).await?;
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok((msgs, total))
+}
+
+/// Conditions selecting decoded messages, shared by the list and count queries
+fn push_decoded_msg_conditions(query: &mut QueryBuilder<'_, Sqlite>, params: &DecodedMsgsQuery) {
+    query
+        .push(" WHERE m.type_url = ")
+        .push_bind(params.type_url.clone());
+
+    if let Some(chain) = &params.chain {
+        query.push(" AND t.chain = ").push_bind(chain.clone());
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DenomTracesQuery {
+    chain: Option<String>,
+    /// Only list the denoms with this base denom, such as `uatom`
+    base_denom: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DenomTracesResponse {
+    denoms: Vec<DenomTraceInfo>,
+    total: i64,
+}
+
+/// An `ibc/` denom of a chain, with the trace it was resolved to
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct DenomTraceInfo {
+    chain_id: String,
+    denom: String,
+    path: String,
+    base_denom: String,
+    resolved_at: Timestamp,
+}
+
+async fn get_denom_traces(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DenomTracesQuery>,
+) -> ApiResult<Json<DenomTracesResponse>> {
+    let denoms = fetch_denom_traces(&state.db, &params).await?;
+    let total = denoms.len() as i64;
+
+    Ok(Json(DenomTracesResponse { denoms, total }))
+}
+
+/// IBC denoms resolved from the chains they were seen on
+pub(super) async fn fetch_denom_traces(
+    db: &SqlitePool,
+    params: &DenomTracesQuery,
+) -> ApiResult<Vec<DenomTraceInfo>> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT chain as chain_id, denom, path, base_denom, resolved_at
+        FROM denom_traces
+        WHERE 1 = 1"#,
+    );
+
+    if let Some(chain) = &params.chain {
+        query.push(" AND chain = ").push_bind(chain.clone());
+    }
+
+    if let Some(base_denom) = &params.base_denom {
+        query
+            .push(" AND base_denom = ")
+            .push_bind(base_denom.clone());
+    }
+
+    query.push(" ORDER BY chain, base_denom, path");
+
+    Ok(query
+        .build_query_as::<DenomTraceInfo>()
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DailyStatsQuery {
+    chain: Option<String>,
+    /// Source channel of the packets
+    channel: Option<String>,
+    #[serde(flatten)]
+    range: TimeRange,
+}
+
+#[derive(Debug, Serialize)]
+struct DailyStatsResponse {
+    days: Vec<DailyStats>,
+    total: i64,
+}
+
+/// Packets sent on a given day, from the rollups refreshed in the background
+#[derive(Debug, Serialize)]
+pub(super) struct DailyStats {
+    date: String,
+    packets: i64,
+    acknowledged: i64,
+    timed_out: i64,
+    /// Share of the relayed packets which were acknowledged rather than timed out
+    success_rate: Option<f64>,
+    avg_latency_seconds: Option<f64>,
+    volume: Vec<DenomAmount>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DailyStatsRow {
+    day: String,
+    packets: i64,
+    acknowledged: i64,
+    timed_out: i64,
+    latency_seconds_sum: i64,
+    latency_count: i64,
+}
+
+async fn get_daily_stats(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DailyStatsQuery>,
+) -> ApiResult<Json<DailyStatsResponse>> {
+    let days = fetch_daily_stats(&state.db, &params).await?;
+    let total = days.len() as i64;
+
+    Ok(Json(DailyStatsResponse { days, total }))
+}
+
+/// Daily packet counts, success rate, latency and volume, oldest day first
+pub(super) async fn fetch_daily_stats(
+    db: &SqlitePool,
+    params: &DailyStatsQuery,
+) -> ApiResult<Vec<DailyStats>> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            day,
+            SUM(packets) as packets,
+            SUM(acknowledged) as acknowledged,
+            SUM(timed_out) as timed_out,
+            SUM(latency_seconds_sum) as latency_seconds_sum,
+            SUM(latency_count) as latency_count
+        FROM daily_stats
+        WHERE 1 = 1"#,
+    );
+    push_daily_conditions(&mut query, params)?;
+    query.push(" GROUP BY day ORDER BY day");
+
+    let rows = query
+        .build_query_as::<DailyStatsRow>()
+        .fetch_all(db)
+        .await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT day, denom, GROUP_CONCAT(amount) as amounts
+        FROM daily_volume
+        WHERE 1 = 1"#,
+    );
+    push_daily_conditions(&mut query, params)?;
+    query.push(" GROUP BY day, denom ORDER BY day, denom");
+
+    let mut volumes: HashMap<String, Vec<DenomAmount>> = HashMap::new();
+
+    for (day, denom, amounts) in query
+        .build_query_as::<(String, String, String)>()
+        .fetch_all(db)
+        .await?
+    {
+        volumes.entry(day).or_default().push(DenomAmount {
+            denom,
+            amount: sum_amounts(&amounts),
+        });
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let relayed = row.acknowledged + row.timed_out;
+
+            DailyStats {
+                volume: volumes.remove(&row.day).unwrap_or_default(),
+                date: row.day,
+                packets: row.packets,
+                acknowledged: row.acknowledged,
+                timed_out: row.timed_out,
+                success_rate: (relayed > 0).then(|| row.acknowledged as f64 / relayed as f64),
+                avg_latency_seconds: (row.latency_count > 0)
+                    .then(|| row.latency_seconds_sum as f64 / row.latency_count as f64),
+            }
+        })
+        .collect())
+}
+
+/// Append the filters of a daily stats query as `AND` conditions on a rollup table
+fn push_daily_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &DailyStatsQuery,
+) -> ApiResult<()> {
+    if let Some(chain) = &params.chain {
+        query.push(" AND chain = ").push_bind(chain.clone());
+    }
+
+    if let Some(channel) = &params.channel {
+        query.push(" AND src_channel = ").push_bind(channel.clone());
+    }
+
+    // Days overlapping the range are included
+    let (from, to) = params.range.bounds()?;
+
+    if let Some(from) = from {
+        query
+            .push(" AND day >= date(")
+            .push_bind(from)
+            .push(", 'unixepoch')");
+    }
+
+    if let Some(to) = to {
+        query
+            .push(" AND datetime(day) < datetime(")
+            .push_bind(to)
+            .push(", 'unixepoch')");
+    }
+
+    Ok(())
+}
+
+async fn get_config(State(state): State<ApiState>) -> Json<ConfigResponse> {
+    Json(effective_config(&state.config.borrow()))
+}
+
+pub(super) fn effective_config(config: &Config) -> ConfigResponse {
+    ConfigResponse {
+        config: config.sanitized(),
+        thresholds: Thresholds {
+            scan_interval_seconds: config.intervals.stuck_scan_seconds,
+            stuck_after_seconds: config.stuck_packets.threshold_seconds,
+            near_timeout_seconds: monitor::NEAR_TIMEOUT_SECONDS,
+        },
+    }
+}
+
+async fn get_packets_by_user(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<UserPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let (packets, total) = fetch_packets_by_user(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "packets.csv");
+    }
+
+    Ok(Json(UserPacketsResponse {
+        packets,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+        api_version: "1.0".to_string(),
+    })
+    .into_response())
+}
+
+/// Packets sent or received by an address, along with the total number of matches
+pub(super) async fn fetch_packets_by_user(
+    db: &SqlitePool,
+    params: &UserPacketsQuery,
+) -> ApiResult<(Vec<PacketInfo>, i64)> {
+    // Validate address format (basic check)
+    if params.address.is_empty() {
+        return Err(ApiError::bad_request(
+            "invalid_address",
+            "address must not be empty",
+        ));
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT 
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            p.ibc_version,
+            p.signer as last_attempt_by,
+            p.effected,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            (SELECT COUNT(*) FROM packets p2 WHERE p2.src_channel = p.src_channel 
+             AND p2.dst_channel = p.dst_channel AND p2.sequence = p.sequence) as relay_attempts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+    );
+    push_user_conditions(&mut query, params)?;
+    params.sort.push_order_by(&mut query, SortKey::Age, false)?;
+    query.push(" LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching packets so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_user_conditions(&mut count, params)?;
+
+    let rows = query
+        .build_query_as::<(
+            String,
+            i64,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            bool,
+            i64,
+            i64,
+        )>()
+        .fetch_all(db)
+        .await?;
+
+    let packets: Vec<PacketInfo> = rows
+        .into_iter()
+        .map(|row| PacketInfo {
+            chain_id: row.0,
+            sequence: row.1,
+            src_channel: row.2,
+            dst_channel: row.3,
+            sender: row.4,
+            receiver: row.5,
+            amount: row.6,
+            denom: row.7,
+            ibc_version: row.8.unwrap_or_else(|| "v1".to_string()),
+            last_attempt_by: Some(row.9),
+            age_seconds: Seconds(row.11),
+            relay_attempts: row.12,
+        })
+        .collect();
+
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok((packets, total))
+}
+
+/// Conditions selecting the packets of an address, shared by the list and count queries
+fn push_user_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &UserPacketsQuery,
+) -> ApiResult<()> {
+    match params.role.as_str() {
+        "sender" => query
+            .push(" WHERE p.sender = ")
+            .push_bind(params.address.clone()),
+        "receiver" => query
+            .push(" WHERE p.receiver = ")
+            .push_bind(params.address.clone()),
+        _ => query
+            .push(" WHERE (p.sender = ")
+            .push_bind(params.address.clone())
+            .push(" OR p.receiver = ")
+            .push_bind(params.address.clone())
+            .push(")"),
+    };
+
+    params.range.push_conditions(query)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct AddressSummaryQuery {
+    #[serde(default = "default_window_hours")]
+    window_hours: i64,
+}
+
+fn default_window_hours() -> i64 {
+    24
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct AddressSummary {
+    address: String,
+    pending: TransferTotals,
+    stuck: TransferTotals,
+    expired: TransferTotals,
+    completed: TransferTotals,
+    window_hours: i64,
+}
+
+/// Number of transfers in a state and their total value per denom
+#[derive(Debug, Default, Serialize)]
+struct TransferTotals {
+    count: i64,
+    amounts: Vec<DenomAmount>,
+}
+
+#[derive(Debug, Serialize)]
+struct DenomAmount {
+    denom: String,
+    amount: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct SummaryRow {
+    state: String,
+    denom: Option<String>,
+    packets: i64,
+    amounts: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AddressSummaryResponse {
+    #[serde(flatten)]
+    summary: AddressSummary,
+    api_version: String,
+}
+
+async fn get_address_summary(
+    State(state): State<ApiState>,
+    ApiPath(address): ApiPath<String>,
+    ApiQuery(params): ApiQuery<AddressSummaryQuery>,
+) -> ApiResult<Json<AddressSummaryResponse>> {
+    let summary =
+        fetch_address_summary(&state.db, address, &params, &state.stuck_thresholds()).await?;
+
+    Ok(Json(AddressSummaryResponse {
+        summary,
+        api_version: "1.0".to_string(),
+    }))
+}
+
+/// Transfers sent or received by an address, bucketed into pending, stuck, expired,
+/// and completed within the last `window_hours`
+pub(super) async fn fetch_address_summary(
+    db: &SqlitePool,
+    address: String,
+    params: &AddressSummaryQuery,
+    thresholds: &StuckPackets,
+) -> ApiResult<AddressSummary> {
+    if address.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "invalid_address",
+            "address must not be empty",
+        ));
+    }
+
+    if !(1..=720).contains(&params.window_hours) {
+        return Err(ApiError::invalid_parameter(
+            "window_hours must be between 1 and 720",
+        ));
+    }
+
+    // Acknowledged packets keep their `send_packet` type and are marked effected
+    let query = format!(
+        r#"
+        SELECT
+            CASE
+                WHEN p.effected = 1 THEN 'completed'
+                WHEN p.timeout_timestamp IS NOT NULL
+                     AND p.timeout_timestamp < strftime('%s', 'now') * 1000000000 THEN 'expired'
+                WHEN CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {} THEN 'stuck'
+                ELSE 'pending'
+            END as state,
+            p.denom,
+            COUNT(*) as packets,
+            GROUP_CONCAT(p.amount) as amounts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        LEFT JOIN txs et ON p.effected_tx = et.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND (p.sender = ? OR p.receiver = ?)
+          AND (
+            p.effected = 0
+            OR COALESCE(et.created_at, p.created_at) >= datetime('now', '-' || ? || ' hours')
+          )
+        GROUP BY state, p.denom
+        ORDER BY state, p.denom
+        "#,
+        thresholds.sql("t.chain", "p.src_channel")
+    );
+
+    let rows = sqlx::query_as::<_, SummaryRow>(&query)
+        .bind(&address)
+        .bind(&address)
+        .bind(params.window_hours)
+        .fetch_all(db)
+        .await?;
+
+    let mut summary = AddressSummary {
+        address,
+        pending: TransferTotals::default(),
+        stuck: TransferTotals::default(),
+        expired: TransferTotals::default(),
+        completed: TransferTotals::default(),
+        window_hours: params.window_hours,
+    };
+
+    for row in rows {
+        let totals = match row.state.as_str() {
+            "completed" => &mut summary.completed,
+            "expired" => &mut summary.expired,
+            "stuck" => &mut summary.stuck,
+            _ => &mut summary.pending,
+        };
+
+        totals.count += row.packets;
+
+        // Packets without transfer data are counted but carry no value
+        if let (Some(denom), Some(amounts)) = (row.denom, row.amounts) {
+            totals.amounts.push(DenomAmount {
+                denom,
+                amount: sum_amounts(&amounts),
+            });
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn get_frontruns(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<FrontrunsQuery>,
+) -> ApiResult<Json<FrontrunsResponse>> {
+    let (frontruns, total) = fetch_frontruns(&state.db, &params).await?;
+
+    Ok(Json(FrontrunsResponse {
+        frontruns,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+    }))
+}
+
+/// Frontrun packets matching the query, along with the total number of matches
+pub(super) async fn fetch_frontruns(
+    db: &SqlitePool,
+    params: &FrontrunsQuery,
+) -> ApiResult<(Vec<FrontrunInfo>, i64)> {
+    let from = match params.from.as_deref().map(parse_timestamp) {
+        Some(None) => return Err(ApiError::invalid_parameter("invalid `from` timestamp")),
+        Some(Some(from)) => Some(from),
+        None => None,
+    };
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.src_port,
+            p.dst_channel,
+            p.dst_port,
+            p.msg_type_url,
+            p.signer,
+            p.effected_signer as frontrunned_by,
+            t.hash as tx_hash,
+            t.memo,
+            et.hash as effected_tx_hash,
+            et.memo as effected_memo,
+            p.created_at
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        LEFT JOIN txs et ON p.effected_tx = et.id
+        "#,
+    );
+    push_frontrun_conditions(&mut query, params, from);
+    query.push(" ORDER BY p.created_at DESC LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching frontruns so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_frontrun_conditions(&mut count, params, from);
+
+    let frontruns = query.build_query_as::<FrontrunInfo>().fetch_all(db).await?;
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok((frontruns, total))
+}
+
+/// Conditions selecting frontrun packets, shared by the list and count queries
+fn push_frontrun_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &FrontrunsQuery,
+    from: Option<i64>,
+) {
+    // A frontrun is recorded as an uneffected packet pointing at the tx that effected it first
+    query.push(" WHERE p.effected = 0 AND p.effected_signer IS NOT NULL");
+
+    if let Some(signer) = &params.signer {
+        query
+            .push(" AND (p.signer = ")
+            .push_bind(signer.clone())
+            .push(" OR p.effected_signer = ")
+            .push_bind(signer.clone())
+            .push(")");
+    }
+
+    if let Some(channel) = &params.channel {
+        query
+            .push(" AND (p.src_channel = ")
+            .push_bind(channel.clone())
+            .push(" OR p.dst_channel = ")
+            .push_bind(channel.clone())
+            .push(")");
+    }
+
+    if let Some(from) = from {
+        query
+            .push(" AND p.created_at >= datetime(")
+            .push_bind(from)
+            .push(", 'unixepoch')");
+    }
+}
+
+async fn get_packet_details(
+    State(state): State<ApiState>,
+    ApiPath((chain, channel, sequence)): ApiPath<(String, String, i64)>,
+) -> ApiResult<Json<PacketInfo>> {
+    fetch_packet(&state.db, chain, channel, sequence)
+        .await
+        .map(Json)
+}
+
+/// A single packet identified by its source chain, channel and sequence
+pub(super) async fn fetch_packet(
+    db: &SqlitePool,
+    chain: String,
+    channel: String,
+    sequence: i64,
+) -> ApiResult<PacketInfo> {
+    let query = r#"
+        SELECT 
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            p.ibc_version,
+            p.signer as last_attempt_by,
+            p.effected,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            (SELECT COUNT(*) FROM packets p2 WHERE p2.src_channel = p.src_channel 
+             AND p2.dst_channel = p.dst_channel AND p2.sequence = p.sequence) as relay_attempts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE t.chain = ? AND p.src_channel = ? AND p.sequence = ?
+        LIMIT 1
+    "#;
+
+    match sqlx::query_as::<
+        _,
+        (
+            String,
+            i64,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            bool,
+            i64,
+            i64,
+        ),
+    >(query)
+    .bind(chain)
+    .bind(channel)
+    .bind(sequence)
+    .fetch_one(db)
+    .await
+    {
+        Ok(row) => Ok(PacketInfo {
+            chain_id: row.0,
+            sequence: row.1,
+            src_channel: row.2,
+            dst_channel: row.3,
+            sender: row.4,
+            receiver: row.5,
+            amount: row.6,
+            denom: row.7,
+            ibc_version: row.8.unwrap_or_else(|| "v1".to_string()),
+            last_attempt_by: Some(row.9),
+            age_seconds: Seconds(row.11),
+            relay_attempts: row.12,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A packet identified by its source chain, channel and sequence
+#[derive(Debug, Deserialize)]
+pub(super) struct PacketKey {
+    chain: String,
+    channel: String,
+    sequence: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct PacketStatusResponse {
+    packets: Vec<PacketStatus>,
+    total: i64,
+    api_version: String,
+}
+
+/// Current state of a requested packet, `unknown` when chainpulse has not seen it
+#[derive(Debug, Serialize)]
+pub(super) struct PacketStatus {
+    chain: String,
+    channel: String,
+    sequence: i64,
+    state: String, // pending, stuck, expired, acknowledged, timed_out, unknown
+    dst_channel: Option<String>,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    age_seconds: Option<Seconds>,
+    ack_error: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PacketStatusRow {
+    idx: i64,
+    state: Option<String>,
+    dst_channel: Option<String>,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    age_seconds: Option<i64>,
+    ack_error: Option<String>,
+}
+
+async fn get_packet_statuses(
+    State(state): State<ApiState>,
+    ApiJson(packets): ApiJson<Vec<PacketKey>>,
+) -> ApiResult<Json<PacketStatusResponse>> {
+    let packets = fetch_packet_statuses(&state.db, packets, &state.stuck_thresholds()).await?;
+    let total = packets.len() as i64;
+
+    Ok(Json(PacketStatusResponse {
+        packets,
+        total,
+        api_version: "1.0".to_string(),
+    }))
+}
+
+/// State of each packet, in the order they were requested
+pub(super) async fn fetch_packet_statuses(
+    db: &SqlitePool,
+    packets: Vec<PacketKey>,
+    thresholds: &StuckPackets,
+) -> ApiResult<Vec<PacketStatus>> {
+    if packets.len() > MAX_STATUS_PACKETS {
+        return Err(ApiError::invalid_parameter(format!(
+            "at most {MAX_STATUS_PACKETS} packets can be queried at once"
+        )));
+    }
+
+    if packets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new("WITH wanted (idx, chain, channel, sequence) AS (");
+    query.push_values(packets.iter().enumerate(), |mut row, (idx, packet)| {
+        row.push_bind(idx as i64)
+            .push_bind(packet.chain.clone())
+            .push_bind(packet.channel.clone())
+            .push_bind(packet.sequence);
+    });
+
+    // Relayed timeouts turn the `send_packet` row into a `timeout_packet` one
+    query.push(
+        r#"
+        )
+        SELECT
+            w.idx,
+            CASE
+                WHEN p.id IS NULL THEN NULL
+                WHEN p.msg_type_url = 'timeout_packet' THEN 'timed_out'
+                WHEN p.effected = 1 THEN 'acknowledged'
+                WHEN p.timeout_timestamp IS NOT NULL
+                     AND p.timeout_timestamp < strftime('%s', 'now') * 1000000000 THEN 'expired'
+                WHEN CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > "#,
+    );
+    query.push(thresholds.sql("t.chain", "p.src_channel"));
+    query.push(
+        r#" THEN 'stuck'
+                ELSE 'pending'
+            END as state,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            (SELECT a.ack_error FROM packets a
+             JOIN txs at ON a.tx_id = at.id
+             WHERE at.chain = w.chain AND a.src_channel = w.channel AND a.sequence = w.sequence
+               AND a.msg_type_url = '/ibc.core.channel.v1.MsgAcknowledgement'
+               AND a.effected = 1
+             LIMIT 1) as ack_error
+        FROM wanted w
+        LEFT JOIN (packets p JOIN txs t ON p.tx_id = t.id)
+          ON t.chain = w.chain
+         AND p.src_channel = w.channel
+         AND p.sequence = w.sequence
+         AND p.msg_type_url IN ('send_packet', 'timeout_packet')
+        ORDER BY w.idx, p.id
+        "#,
+    );
+
+    let rows = query
+        .build_query_as::<PacketStatusRow>()
+        .fetch_all(db)
+        .await?;
+
+    let mut rows = rows.into_iter().peekable();
+    let mut statuses = Vec::with_capacity(packets.len());
+
+    for (idx, packet) in packets.into_iter().enumerate() {
+        // A packet recorded more than once is reported from its first row
+        let mut row = None;
+        while let Some(next) = rows.next_if(|next| next.idx == idx as i64) {
+            row.get_or_insert(next);
+        }
+
+        let row = row.filter(|row| row.state.is_some());
+
+        statuses.push(match row {
+            Some(row) => PacketStatus {
+                chain: packet.chain,
+                channel: packet.channel,
+                sequence: packet.sequence,
+                state: row.state.unwrap_or_default(),
+                dst_channel: row.dst_channel,
+                sender: row.sender,
+                receiver: row.receiver,
+                amount: row.amount,
+                denom: row.denom,
+                age_seconds: row.age_seconds.map(Seconds),
+                ack_error: row.ack_error,
+            },
+            None => PacketStatus {
+                chain: packet.chain,
+                channel: packet.channel,
+                sequence: packet.sequence,
+                state: "unknown".to_string(),
+                dst_channel: None,
+                sender: None,
+                receiver: None,
+                amount: None,
+                denom: None,
+                age_seconds: None,
+                ack_error: None,
+            },
+        });
+    }
+
+    Ok(statuses)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ClearPacketsRequest {
+    channel: Option<ClearChannel>,
+    #[serde(default)]
+    packets: Vec<ClearPacket>,
+    /// Trigger clearing on the configured relayer instead of only returning commands
+    #[serde(default)]
+    trigger: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearChannel {
+    chain: String,
+    channel: String,
+    port: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearPacket {
+    chain: String,
+    channel: String,
+    sequence: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ClearPacketsResponse {
+    id: i64,
+    status: &'static str,
+    targets: Vec<ClearTarget>,
+    commands: Vec<String>,
+    triggered: Vec<TriggerResult>,
+}
+
+async fn clear_packets(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<ClearPacketsRequest>,
+) -> ApiResult<Json<ClearPacketsResponse>> {
+    submit_clear_request(&state, &headers, &request)
+        .await
+        .map(Json)
+}
+
+/// Resolve the packets to clear, trigger the relayer if asked to, and record the request
+pub(super) async fn submit_clear_request(
+    state: &ApiState,
+    headers: &HeaderMap,
+    request: &ClearPacketsRequest,
+) -> ApiResult<ClearPacketsResponse> {
+    // Every request is written to the audit table, and triggering makes the relayer spend
+    // fees, so both are reserved to admins
+    admin::authorize(state, headers)?;
+
+    let hermes_url = state.config.borrow().relayer.hermes_url.clone();
+    let hermes_url = match (request.trigger, hermes_url.as_deref()) {
+        (false, _) => None,
+        (true, None) => {
+            return Err(ApiError::bad_request(
+                "relayer_not_configured",
+                "set `relayer.hermes_url` to trigger clearing",
+            ))
+        }
+        (true, Some(url)) => Some(url),
+    };
+
+    let targets = fetch_clear_targets(&state.db, request).await?;
+    let commands: Vec<String> = targets.iter().map(ClearTarget::hermes_command).collect();
+
+    let triggered = match hermes_url {
+        Some(url) => clear::trigger_hermes(url, &targets).await,
+        None => Vec::new(),
+    };
+
+    let id = clear::record(&state.db, &targets, &commands, &triggered).await?;
+
+    Ok(ClearPacketsResponse {
+        id,
+        status: clear::status(&triggered),
+        targets,
+        commands,
+        triggered,
+    })
+}
+
+/// Turn a clearing request into one target per channel, looking up the ports of the packets
+async fn fetch_clear_targets(
+    db: &SqlitePool,
+    request: &ClearPacketsRequest,
+) -> ApiResult<Vec<ClearTarget>> {
+    let port_query = r#"
+        SELECT p.src_port
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet' AND t.chain = ? AND p.src_channel = ?
+          AND (? IS NULL OR p.sequence = ?)
+        ORDER BY p.id DESC
+        LIMIT 1
+    "#;
+
+    match (&request.channel, request.packets.is_empty()) {
+        (Some(channel), true) => {
+            let port = match &channel.port {
+                Some(port) => port.clone(),
+                None => sqlx::query_scalar::<_, String>(port_query)
+                    .bind(&channel.chain)
+                    .bind(&channel.channel)
+                    .bind(None::<i64>)
+                    .bind(None::<i64>)
+                    .fetch_optional(db)
+                    .await?
+                    .unwrap_or_else(|| "transfer".to_string()),
+            };
+
+            Ok(vec![ClearTarget {
+                chain: channel.chain.clone(),
+                port,
+                channel: channel.channel.clone(),
+                sequences: Vec::new(),
+            }])
+        }
+        (None, false) => {
+            if request.packets.len() > MAX_CLEAR_PACKETS {
+                return Err(ApiError::invalid_parameter(format!(
+                    "at most {MAX_CLEAR_PACKETS} packets can be cleared at once"
+                )));
+            }
+
+            let mut targets: Vec<ClearTarget> = Vec::new();
+
+            for packet in &request.packets {
+                let port = sqlx::query_scalar::<_, String>(port_query)
+                    .bind(&packet.chain)
+                    .bind(&packet.channel)
+                    .bind(packet.sequence)
+                    .bind(packet.sequence)
+                    .fetch_optional(db)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::new(
+                            StatusCode::NOT_FOUND,
+                            "not_found",
+                            format!(
+                                "packet {}/{}/{} not found",
+                                packet.chain, packet.channel, packet.sequence
+                            ),
+                        )
+                    })?;
+
+                let existing = targets.iter_mut().find(|target| {
+                    target.chain == packet.chain
+                        && target.channel == packet.channel
+                        && target.port == port
+                });
+
+                match existing {
+                    Some(target) => target.sequences.push(packet.sequence),
+                    None => targets.push(ClearTarget {
+                        chain: packet.chain.clone(),
+                        port,
+                        channel: packet.channel.clone(),
+                        sequences: vec![packet.sequence],
+                    }),
+                }
+            }
+
+            for target in &mut targets {
+                target.sequences.sort_unstable();
+                target.sequences.dedup();
+            }
+
+            Ok(targets)
+        }
+        _ => Err(ApiError::invalid_parameter(
+            "provide either `channel` or a non-empty `packets` list",
+        )),
+    }
+}
+
+async fn get_stuck_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<StuckPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let group_by_relayer = params.group_by_relayer()?;
+    format::ensure_flat(format, group_by_relayer)?;
+
+    let (packets, total) =
+        fetch_stuck_packets(&state.db, &params, &state.stuck_thresholds()).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "stuck-packets.csv");
+    }
+
+    if group_by_relayer {
+        let relayers = group_stuck_packets_by_relayer(packets);
+
+        return Ok(Json(StuckPacketsByRelayerResponse {
+            relayers,
+            total,
+            limit: params.limit,
+            offset: params.offset,
+        })
+        .into_response());
+    }
+
+    Ok(Json(StuckPacketsResponse {
+        packets,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+    })
+    .into_response())
+}
+
+/// Stuck packets matching the query, along with the total number of matches
+pub(super) async fn fetch_stuck_packets(
+    db: &SqlitePool,
+    params: &StuckPacketsQuery,
+    thresholds: &StuckPackets,
+) -> ApiResult<(Vec<StuckPacketInfo>, i64)> {
+    // Query for send_packet events that haven't been acknowledged or timed out
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        r#"
+        SELECT 
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            p.timeout_timestamp,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            CASE 
+                WHEN p.timeout_timestamp IS NOT NULL 
+                THEN CAST((p.timeout_timestamp / 1000000000 - strftime('%s', 'now')) AS INTEGER)
+                ELSE NULL 
+            END as seconds_until_timeout,
+            (SELECT p2.signer FROM packets p2
+             WHERE p2.src_channel = p.src_channel AND p2.dst_channel = p.dst_channel
+               AND p2.sequence = p.sequence AND p2.msg_type_url != 'send_packet'
+               AND p2.signer IS NOT NULL AND p2.signer != ''
+             ORDER BY p2.created_at DESC LIMIT 1) as last_attempt_by,
+            (SELECT p3.signer FROM packets p3
+             WHERE p3.src_channel = p.src_channel AND p3.dst_channel = p.dst_channel
+               AND p3.effected = 1 AND p3.msg_type_url != 'send_packet'
+               AND p3.signer IS NOT NULL AND p3.signer != ''
+             ORDER BY p3.created_at DESC LIMIT 1) as expected_relayer,
+            {} as unrecoverable,
+            {} as trace,
+            {} as rate_limited
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+        channels::unrecoverable_sql("t.chain", "p.src_port", "p.src_channel"),
+        denoms::trace_sql("t.chain", "p.denom"),
+        rate_limits::exhausted_sql(
+            "t.chain",
+            "p.src_port",
+            "p.src_channel",
+            "p.dst_port",
+            "p.dst_channel",
+            "p.denom"
+        )
+    ));
+    push_stuck_conditions(&mut query, params, thresholds)?;
+    params.sort.push_order_by(&mut query, SortKey::Age, true)?;
+    query.push(" LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching packets so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_stuck_conditions(&mut count, params, thresholds)?;
+
+    match query
+        .build_query_as::<(
+            String,
+            i64,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<i64>,
+            i64,
+            Option<i64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        )>()
+        .fetch_all(db)
+        .await
+    {
+        Ok(rows) => {
+            let prices = UsdPrices::load(db).await?;
+
+            let packets: Vec<StuckPacketInfo> = rows
+                .into_iter()
+                .map(|row| StuckPacketInfo {
+                    value_usd: match (&row.14, &row.6) {
+                        (Some(trace), Some(amount)) => prices.value(trace, amount),
+                        _ => None,
+                    },
+                    chain_id: row.0,
+                    sequence: row.1,
+                    src_channel: row.2,
+                    dst_channel: row.3,
+                    sender: row.4,
+                    receiver: row.5,
+                    amount: row.6,
+                    denom: row.7,
+                    timeout_timestamp: row.8.and_then(Timestamp::from_unix_nanos),
+                    age_seconds: Seconds(row.9),
+                    seconds_until_timeout: row.10.map(Seconds),
+                    last_attempt_by: row.11,
+                    expected_relayer: row.12,
+                    unrecoverable: row.13,
+                    rate_limited: row.15,
+                })
+                .collect();
+
+            let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+            Ok((packets, total))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Conditions selecting stuck packets, shared by the list and count queries
+fn push_stuck_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &StuckPacketsQuery,
+    thresholds: &StuckPackets,
+) -> ApiResult<()> {
+    query.push(
+        r#"
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > "#,
+    );
+    match params.min_age_seconds {
+        Some(min_age_seconds) => query.push_bind(min_age_seconds),
+        None => query.push(thresholds.sql("t.chain", "p.src_channel")),
+    };
+    params.filters.push_conditions(query)?;
+    params.range.push_conditions(query)
+}
+
+pub(super) fn group_stuck_packets_by_relayer(
+    packets: Vec<StuckPacketInfo>,
+) -> Vec<RelayerStuckPackets> {
+    let mut groups: HashMap<String, RelayerStuckPackets> = HashMap::new();
+
+    for packet in packets {
+        let group = groups
+            .entry(packet.responsible_relayer().to_string())
+            .or_insert_with_key(|relayer| RelayerStuckPackets {
+                relayer: relayer.clone(),
+                stuck_count: 0,
+                oldest_age_seconds: Seconds::default(),
+                channels: Vec::new(),
+                packets: Vec::new(),
+            });
+
+        let channel = format!("{} -> {}", packet.src_channel, packet.dst_channel);
+        if !group.channels.contains(&channel) {
+            group.channels.push(channel);
+        }
+
+        group.stuck_count += 1;
+        group.oldest_age_seconds = group.oldest_age_seconds.max(packet.age_seconds);
+        group.packets.push(packet);
+    }
+
+    let mut groups: Vec<_> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.stuck_count
+            .cmp(&a.stuck_count)
+            .then_with(|| a.relayer.cmp(&b.relayer))
+    });
+
+    groups
+}
+
+async fn get_channel_latency(
+    State(state): State<ApiState>,
+    ApiPath(channel): ApiPath<String>,
+    ApiQuery(params): ApiQuery<LatencyQuery>,
+) -> ApiResult<Json<ChannelLatencyResponse>> {
+    fetch_channel_latency(&state.db, channel, &params)
+        .await
+        .map(Json)
+}
+
+/// Relay latency percentiles for a channel, overall and per time bucket
+pub(super) async fn fetch_channel_latency(
+    db: &SqlitePool,
+    channel: String,
+    params: &LatencyQuery,
+) -> ApiResult<ChannelLatencyResponse> {
+    let bucket_seconds = match params.bucket.as_str() {
+        "hour" => 3600,
+        "day" => 86400,
+        _ => {
+            return Err(ApiError::invalid_parameter(
+                "bucket must be `hour` or `day`",
+            ))
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let to = match params.to.as_deref() {
+        Some(to) => parse_timestamp(to)
+            .ok_or_else(|| ApiError::invalid_parameter("invalid `to` timestamp"))?,
+        None => now,
+    };
+    let from = match params.from.as_deref() {
+        Some(from) => parse_timestamp(from)
+            .ok_or_else(|| ApiError::invalid_parameter("invalid `from` timestamp"))?,
+        None => to - 86400,
+    };
+
+    // Latency runs from the send_packet to the first effected MsgRecvPacket on the
+    // destination chain, or to the acknowledgement when the destination is not monitored
+    let query = r#"
+        SELECT
+            CAST(strftime('%s', s.created_at) AS INTEGER) as sent_at,
+            CAST(strftime('%s', COALESCE(
+                (SELECT MIN(r.created_at) FROM packets r
+                 WHERE r.src_channel = s.src_channel AND r.dst_channel = s.dst_channel
+                   AND r.sequence = s.sequence AND r.effected = 1
+                   AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'),
+                a.created_at
+            )) AS INTEGER) - CAST(strftime('%s', s.created_at) AS INTEGER) as latency_seconds
+        FROM packets s
+        LEFT JOIN txs a ON s.effected_tx = a.id
+        WHERE s.msg_type_url = 'send_packet'
+          AND s.effected = 1
+          AND s.src_channel = ?
+          AND s.created_at >= datetime(?, 'unixepoch')
+          AND s.created_at < datetime(?, 'unixepoch')
+        ORDER BY s.created_at ASC
+    "#;
+
+    let rows = sqlx::query_as::<_, (i64, Option<i64>)>(query)
+        .bind(&channel)
+        .bind(from)
+        .bind(to)
+        .fetch_all(db)
+        .await?;
+
+    let mut all = Vec::with_capacity(rows.len());
+    let mut buckets: std::collections::BTreeMap<i64, Vec<i64>> = Default::default();
+
+    for (sent_at, latency) in rows {
+        let Some(latency) = latency.filter(|latency| *latency >= 0) else {
+            continue;
+        };
+
+        all.push(latency);
+        buckets
+            .entry(sent_at - sent_at.rem_euclid(bucket_seconds))
+            .or_default()
+            .push(latency);
+    }
+
+    let timestamp = |ts: i64| {
+        Timestamp::from_unix(ts)
+            .ok_or_else(|| ApiError::invalid_parameter("timestamp out of range"))
+    };
+
+    let buckets = buckets
+        .into_iter()
+        .map(|(start, latencies)| {
+            Ok(LatencyBucket {
+                start: timestamp(start)?,
+                stats: LatencyStats::from_latencies(latencies),
+            })
+        })
+        .collect::<ApiResult<_>>()?;
+
+    Ok(ChannelLatencyResponse {
+        channel,
+        from: timestamp(from)?,
+        to: timestamp(to)?,
+        bucket_seconds,
+        overall: LatencyStats::from_latencies(all),
+        buckets,
+    })
+}
+
+async fn get_gas_prices(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+    ApiQuery(params): ApiQuery<GasPricesQuery>,
+) -> ApiResult<Json<GasPricesResponse>> {
+    fetch_gas_prices(&state.db, chain, &params).await.map(Json)
+}
+
+/// Gas prices paid by the relay txs of a chain, per fee denom, overall and per time bucket
+pub(super) async fn fetch_gas_prices(
+    db: &SqlitePool,
+    chain_id: String,
+    params: &GasPricesQuery,
+) -> ApiResult<GasPricesResponse> {
+    let bucket_seconds = match params.bucket.as_str() {
+        "hour" => 3600,
+        "day" => 86400,
+        _ => {
+            return Err(ApiError::invalid_parameter(
+                "bucket must be `hour` or `day`",
+            ))
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let to = match params.to.as_deref() {
+        Some(to) => parse_timestamp(to)
+            .ok_or_else(|| ApiError::invalid_parameter("invalid `to` timestamp"))?,
+        None => now,
+    };
+    let from = match params.from.as_deref() {
+        Some(from) => parse_timestamp(from)
+            .ok_or_else(|| ApiError::invalid_parameter("invalid `from` timestamp"))?,
+        None => to - 86400,
+    };
+
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        r#"
+        SELECT denom, {} as trace, CAST(strftime('%s', created_at) AS INTEGER) as paid_at, gas_price
+        FROM relay_fees r
+        WHERE chain = "#,
+        denoms::trace_sql("r.chain", "r.denom")
+    ));
+
+    query
+        .push_bind(&chain_id)
+        .push(" AND created_at >= datetime(")
+        .push_bind(from)
+        .push(", 'unixepoch') AND created_at < datetime(")
+        .push_bind(to)
+        .push(", 'unixepoch')");
+
+    if let Some(denom) = &params.denom {
+        query.push(" AND denom = ").push_bind(denom);
+    }
+
+    query.push(" ORDER BY denom, created_at, id");
+
+    let rows = query
+        .build_query_as::<(String, String, i64, f64)>()
+        .fetch_all(db)
+        .await?;
+
+    let timestamp = |ts: i64| {
+        Timestamp::from_unix(ts)
+            .ok_or_else(|| ApiError::invalid_parameter("timestamp out of range"))
+    };
+
+    let mut prices: BTreeMap<String, Vec<(i64, f64)>> = BTreeMap::new();
+    let mut traces: HashMap<String, String> = HashMap::new();
+
+    for (denom, trace, paid_at, gas_price) in rows {
+        prices
+            .entry(denom.clone())
+            .or_default()
+            .push((paid_at, gas_price));
+        traces.insert(denom, trace);
+    }
+
+    let denoms = prices
+        .into_iter()
+        .map(|(denom, prices)| {
+            let (path, base_denom) = denoms::split_trace(&traces[&denom]);
+
+            // Rows are ordered by time, so the last one is the latest relay
+            let (latest_at, latest) = *prices.last().expect("denoms have a price");
+
+            let mut buckets: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+
+            for (paid_at, gas_price) in &prices {
+                buckets
+                    .entry(paid_at - paid_at.rem_euclid(bucket_seconds))
+                    .or_default()
+                    .push(*gas_price);
+            }
+
+            let overall =
+                GasPriceStats::from_prices(prices.iter().map(|(_, price)| *price).collect());
+
+            let buckets = buckets
+                .into_iter()
+                .map(|(start, prices)| {
+                    Ok(GasPriceBucket {
+                        start: timestamp(start)?,
+                        stats: GasPriceStats::from_prices(prices),
+                    })
+                })
+                .collect::<ApiResult<Vec<_>>>()?;
+
+            let spike_ratio = match (
+                buckets.last().and_then(|bucket| bucket.stats.p50),
+                overall.p50,
+            ) {
+                (Some(last), Some(median)) if median > 0.0 => Some(last / median),
+                _ => None,
+            };
+
+            Ok(DenomGasPrices {
+                denom,
+                base_denom,
+                path,
+                latest,
+                latest_at: timestamp(latest_at)?,
+                spike_ratio,
+                overall,
+                buckets,
+            })
+        })
+        .collect::<ApiResult<_>>()?;
+
+    Ok(GasPricesResponse {
+        chain_id,
+        from: timestamp(from)?,
+        to: timestamp(to)?,
+        bucket_seconds,
+        denoms,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct CongestionQuery {
+    #[serde(flatten)]
+    range: TimeRange,
+}
+
+async fn get_channel_congestion(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<CongestionQuery>,
+) -> ApiResult<Json<ChannelCongestionResponse>> {
+    fetch_channel_congestion(&state.db, &params, &state.stuck_thresholds())
+        .await
+        .map(Json)
+}
+
+/// Channels with stuck packets, most congested first
+pub(super) async fn fetch_channel_congestion(
+    db: &SqlitePool,
+    params: &CongestionQuery,
+    thresholds: &StuckPackets,
+) -> ApiResult<ChannelCongestionResponse> {
+    let mut channels = congested_channels(db, &params.range, thresholds).await?;
+    channels.sort_by_key(|channel| std::cmp::Reverse(channel.stuck_count));
+    channels.truncate(100);
+
+    let total_stuck = channels.iter().map(|channel| channel.stuck_count).sum();
+
+    Ok(ChannelCongestionResponse {
+        channels,
+        total_stuck,
+    })
+}
+
+/// Every channel with stuck packets, in no particular order
+async fn congested_channels(
+    db: &SqlitePool,
+    range: &TimeRange,
+    thresholds: &StuckPackets,
+) -> ApiResult<Vec<ChannelCongestion>> {
+    // Stuck packets per channel and denom. The counterparty chain is the one resolved
+    // from the channel, or else the one we have seen a packet being received on.
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            COALESCE(
+                (SELECT c.counterparty_chain_id FROM channel_counterparties c
+                 WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel),
+                (SELECT rt.chain FROM packets r
+                 JOIN txs rt ON r.tx_id = rt.id
+                 WHERE r.src_channel = p.src_channel AND r.dst_channel = p.dst_channel
+                   AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+                 LIMIT 1)
+            ) as counterparty_chain_id,
+            p.src_port,
+            p.src_channel,
+            p.dst_port,
+            p.dst_channel,
+            p.denom,
+            {} as trace,
+            COUNT(*) as stuck_count,
+            MAX(CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER)) as oldest_age_seconds,
+            GROUP_CONCAT(p.amount) as amounts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > "#,
+        denoms::trace_sql("t.chain", "p.denom")
+    ));
+    query.push(thresholds.sql("t.chain", "p.src_channel"));
+    range.push_conditions(&mut query)?;
+    query.push(" GROUP BY t.chain, p.src_port, p.src_channel, p.dst_port, p.dst_channel, p.denom");
+
+    let rows = query
+        .build_query_as::<CongestionRow>()
+        .fetch_all(db)
+        .await?;
+
+    let prices = UsdPrices::load(db).await?;
+
+    let mut channels: BTreeMap<(String, String, String, String, String), ChannelCongestion> =
+        BTreeMap::new();
+
+    for row in rows {
+        let key = (
+            row.chain_id.clone(),
+            row.src_port.clone(),
+            row.src_channel.clone(),
+            row.dst_port.clone(),
+            row.dst_channel.clone(),
+        );
+
+        let channel = channels.entry(key).or_insert_with(|| ChannelCongestion {
+            chain_id: row.chain_id,
+            counterparty_chain_id: row.counterparty_chain_id,
+            src_port: row.src_port,
+            src_channel: row.src_channel,
+            dst_port: row.dst_port,
+            dst_channel: row.dst_channel,
+            stuck_count: 0,
+            oldest_age_seconds: Seconds::default(),
+            total_value: BTreeMap::new(),
+            total_value_usd: None,
+            denoms: Vec::new(),
+        });
+
+        channel.stuck_count += row.stuck_count;
+        channel.oldest_age_seconds = channel
+            .oldest_age_seconds
+            .max(Seconds(row.oldest_age_seconds));
+
+        if let (Some(denom), Some(trace), Some(amounts)) = (row.denom, row.trace, row.amounts) {
+            let amount = sum_amounts(&amounts);
+            let (path, base_denom) = denoms::split_trace(&trace);
+            let value_usd = prices.value(&trace, &amount);
+
+            if let Some(value_usd) = value_usd {
+                *channel.total_value_usd.get_or_insert(0.0) += value_usd;
+            }
+
+            channel.total_value.insert(denom.clone(), amount.clone());
+            channel.denoms.push(DenomValue {
+                denom,
+                base_denom,
+                path,
+                amount,
+                value_usd,
+            });
+        }
+    }
+
+    Ok(channels.into_values().collect())
+}
+
+// Top stuck routes, for alert bodies
+
+#[derive(Debug, Deserialize)]
+pub(super) struct TopStuckRoutesQuery {
+    #[serde(default = "default_top_stuck_limit")]
+    limit: i64,
+    #[serde(flatten)]
+    range: TimeRange,
+}
+
+fn default_top_stuck_limit() -> i64 {
+    5
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct TopStuckRoutesResponse {
+    routes: Vec<StuckRoute>,
+    total_stuck: i64,
+    /// One summary line per route, ready to paste into an alert
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct StuckRoute {
+    rank: usize,
+    /// 0-100, weighing stuck count, oldest age and stuck value equally
+    score: f64,
+    chain_id: String,
+    counterparty_chain_id: Option<String>,
+    src_channel: String,
+    dst_channel: String,
+    stuck_count: i64,
+    oldest_age_seconds: Seconds,
+    total_value: BTreeMap<String, String>,
+    summary: String,
+}
+
+async fn get_top_stuck_routes(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<TopStuckRoutesQuery>,
+) -> ApiResult<Json<TopStuckRoutesResponse>> {
+    fetch_top_stuck_routes(&state.db, &params, &state.stuck_thresholds())
+        .await
+        .map(Json)
+}
+
+/// The worst channel pairs by stuck packets, worst first
+pub(super) async fn fetch_top_stuck_routes(
+    db: &SqlitePool,
+    params: &TopStuckRoutesQuery,
+    thresholds: &StuckPackets,
+) -> ApiResult<TopStuckRoutesResponse> {
+    if !(1..=50).contains(&params.limit) {
+        return Err(ApiError::invalid_parameter(
+            "limit must be between 1 and 50",
+        ));
+    }
+
+    let channels = congested_channels(db, &params.range, thresholds).await?;
+    let total_stuck = channels.iter().map(|channel| channel.stuck_count).sum();
+
+    let routes = rank_stuck_routes(channels, params.limit as usize);
+    let text = routes
+        .iter()
+        .map(|route| route.summary.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(TopStuckRoutesResponse {
+        routes,
+        total_stuck,
+        text,
+    })
+}
+
+/// Score each route relative to the worst one on every axis and keep the `limit` highest.
+///
+/// Amounts of different denoms cannot be compared without prices, so the value of a route
+/// is its largest share of the amount stuck in any single denom across all routes.
+fn rank_stuck_routes(channels: Vec<ChannelCongestion>, limit: usize) -> Vec<StuckRoute> {
+    let amount = |value: &String| value.parse::<u128>().unwrap_or(0) as f64;
+
+    let max_count = channels.iter().map(|c| c.stuck_count).max().unwrap_or(0);
+    let max_age = channels
+        .iter()
+        .map(|c| c.oldest_age_seconds.0)
+        .max()
+        .unwrap_or(0);
+
+    let mut max_amounts: HashMap<&str, f64> = HashMap::new();
+    for channel in &channels {
+        for (denom, value) in &channel.total_value {
+            let max = max_amounts.entry(denom.as_str()).or_default();
+            *max = max.max(amount(value));
+        }
+    }
+
+    let ratio = |value: f64, max: f64| if max > 0.0 { value / max } else { 0.0 };
+
+    let mut scored: Vec<(f64, &ChannelCongestion)> = channels
+        .iter()
+        .map(|channel| {
+            let value_share = channel
+                .total_value
+                .iter()
+                .map(|(denom, value)| ratio(amount(value), max_amounts[denom.as_str()]))
+                .fold(0.0, f64::max);
+
+            let score = (ratio(channel.stuck_count as f64, max_count as f64)
+                + ratio(channel.oldest_age_seconds.0 as f64, max_age as f64)
+                + value_share)
+                / 3.0
+                * 100.0;
+
+            ((score * 10.0).round() / 10.0, channel)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(limit);
+
+    scored
+        .into_iter()
+        .enumerate()
+        .map(|(i, (score, channel))| StuckRoute {
+            rank: i + 1,
+            score,
+            chain_id: channel.chain_id.clone(),
+            counterparty_chain_id: channel.counterparty_chain_id.clone(),
+            src_channel: channel.src_channel.clone(),
+            dst_channel: channel.dst_channel.clone(),
+            stuck_count: channel.stuck_count,
+            oldest_age_seconds: channel.oldest_age_seconds,
+            total_value: channel.total_value.clone(),
+            summary: format!("#{} {}", i + 1, route_summary(channel)),
+        })
+        .collect()
+}
+
+/// e.g. `osmosis-1 channel-0 -> cosmoshub-4 channel-141: 12 stuck, oldest 3h05m, 5000 uosmo`
+fn route_summary(channel: &ChannelCongestion) -> String {
+    let mut summary = format!(
+        "{} {} -> {} {}: {} stuck, oldest {}",
+        channel.chain_id,
+        channel.src_channel,
+        channel.counterparty_chain_id.as_deref().unwrap_or("?"),
+        channel.dst_channel,
+        channel.stuck_count,
+        format_age(channel.oldest_age_seconds.0),
+    );
+
+    let values: Vec<String> = channel
+        .total_value
+        .iter()
+        .take(3)
+        .map(|(denom, value)| format!("{value} {denom}"))
+        .collect();
+
+    if !values.is_empty() {
+        summary.push_str(", ");
+        summary.push_str(&values.join(", "));
+    }
+
+    if channel.total_value.len() > 3 {
+        summary.push_str(&format!(" (+{} denoms)", channel.total_value.len() - 3));
+    }
+
+    summary
+}
+
+// Timeout-based query endpoints
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ExpiringPacketsQuery {
+    #[serde(default = "default_expiring_minutes")]
+    minutes: i64,
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(flatten)]
+    filters: PacketFilters,
+    #[serde(flatten)]
+    sort: Sort,
+}
+
+fn default_expiring_minutes() -> i64 {
+    60 // 1 hour default
+}
+
+#[derive(Debug, Serialize)]
+struct ExpiringPacketsResponse {
+    packets: Vec<ExpiringPacketInfo>,
+    api_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ExpiringPacketInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    seconds_until_timeout: Seconds,
+    timeout_type: String,
+    timeout_value: TimeoutValue,
+}
+
+/// Height of the destination chain, as `revision-height`, or time at which a packet times out
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum TimeoutValue {
+    Height(String),
+    Timestamp(Option<Timestamp>),
+}
+
+async fn get_expiring_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ExpiringPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let (packets, total) = fetch_expiring_packets(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "expiring-packets.csv");
+    }
+
+    Ok(Json(ExpiringPacketsResponse {
+        packets,
+        api_version: "1.0".to_string(),
+    })
+    .into_response())
+}
+
+/// Pending packets timing out within the query window, along with the total number of matches
+pub(super) async fn fetch_expiring_packets(
+    db: &SqlitePool,
+    params: &ExpiringPacketsQuery,
+) -> ApiResult<(Vec<ExpiringPacketInfo>, i64)> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT 
+            t.chain,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            p.timeout_timestamp,
+            p.timeout_height_revision_number,
+            p.timeout_height_revision_height,
+            (p.timeout_timestamp - strftime('%s', 'now') * 1000000000) / 1000000000 as seconds_until_timeout
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+    );
+    push_expiring_conditions(&mut query, params)?;
+    params
+        .sort
+        .push_order_by(&mut query, SortKey::Timeout, false)?;
+    query.push(" LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching packets so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_expiring_conditions(&mut count, params)?;
+
+    match query.build().fetch_all(db).await {
+        Ok(rows) => {
+            let packets = rows
+                .into_iter()
+                .map(|row| {
+                    let timeout_type = if row.get::<Option<i64>, _>(9).is_some() {
+                        "height".to_string()
+                    } else {
+                        "timestamp".to_string()
+                    };
+
+                    let timeout_value = if timeout_type == "height" {
+                        TimeoutValue::Height(format!(
+                            "{}-{}",
+                            row.get::<Option<i64>, _>(9).unwrap_or(0),
+                            row.get::<Option<i64>, _>(10).unwrap_or(0)
+                        ))
+                    } else {
+                        let ts = row.get::<Option<i64>, _>(8).unwrap_or(0);
+                        TimeoutValue::Timestamp(Timestamp::from_unix_nanos(ts))
+                    };
+
+                    ExpiringPacketInfo {
+                        chain_id: row.get(0),
+                        sequence: row.get(1),
+                        src_channel: row.get(2),
+                        dst_channel: row.get(3),
+                        sender: row.get(4),
+                        receiver: row.get(5),
+                        amount: row.get(6),
+                        denom: row.get(7),
+                        seconds_until_timeout: Seconds(row.get(11)),
+                        timeout_type,
+                        timeout_value,
+                    }
+                })
+                .collect();
+
+            let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+            Ok((packets, total))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Conditions selecting expiring packets, shared by the list and count queries
+fn push_expiring_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &ExpiringPacketsQuery,
+) -> ApiResult<()> {
+    query.push(
+        r#"
+        WHERE p.effected = 0
+          AND p.timeout_timestamp IS NOT NULL
+          AND p.timeout_timestamp > strftime('%s', 'now') * 1000000000
+          AND p.timeout_timestamp < (strftime('%s', 'now') + "#,
+    );
+    query.push_bind(params.minutes);
+    query.push(" * 60) * 1000000000");
+    params.filters.push_conditions(query)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ExpiredPacketsQuery {
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(flatten)]
+    filters: PacketFilters,
+}
+
+#[derive(Debug, Serialize)]
+struct ExpiredPacketsResponse {
+    packets: Vec<ExpiredPacketInfo>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    refunds: Vec<RefundSummary>,
+    api_version: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct ExpiredPacketInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    seconds_since_timeout: Seconds,
+    timeout_type: String,
+}
+
+/// Amounts returning to a sender for one denom, split between timeouts still waiting to be
+/// relayed and timeouts already relayed back to the source chain
+#[derive(Debug, Serialize)]
+pub(super) struct RefundSummary {
+    sender: String,
+    chain_id: String,
+    denom: String,
+    pending_packets: i64,
+    pending_amount: String,
+    refunded_packets: i64,
+    refunded_amount: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct RefundRow {
+    sender: String,
+    chain_id: String,
+    denom: String,
+    refunded: bool,
+    packets: i64,
+    amounts: String,
+}
+
+async fn get_expired_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ExpiredPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let (packets, total) = fetch_expired_packets(&state.db, &params).await?;
+
+    // The refund summary has its own shape and is only part of the JSON response
+    if format == Format::Csv {
+        return format::csv(&packets, total, "expired-packets.csv");
+    }
+
+    let refunds = fetch_refund_summary(&state.db, &params).await?;
+
+    Ok(Json(ExpiredPacketsResponse {
+        packets,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+        refunds,
+        api_version: "1.0".to_string(),
+    })
+    .into_response())
+}
+
+/// Packets past their timeout which were neither received nor timed out on the source chain,
+/// along with the total number of matches
+pub(super) async fn fetch_expired_packets(
+    db: &SqlitePool,
+    params: &ExpiredPacketsQuery,
+) -> ApiResult<(Vec<ExpiredPacketInfo>, i64)> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            (strftime('%s', 'now') * 1000000000 - p.timeout_timestamp) / 1000000000 as seconds_since_timeout,
+            'timestamp' as timeout_type
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+    );
+    push_expired_conditions(&mut query, params)?;
+    query.push(" ORDER BY p.timeout_timestamp DESC LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching packets so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_expired_conditions(&mut count, params)?;
+
+    let packets = query
+        .build_query_as::<ExpiredPacketInfo>()
+        .fetch_all(db)
+        .await?;
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok((packets, total))
+}
+
+/// Conditions selecting expired packets, shared by the list and count queries
+fn push_expired_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &ExpiredPacketsQuery,
+) -> ApiResult<()> {
+    query.push(
+        r#"
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND p.timeout_timestamp IS NOT NULL
+          AND p.timeout_timestamp < strftime('%s', 'now') * 1000000000"#,
+    );
+    params.filters.push_conditions(query)
+}
+
+/// Per sender, chain and denom totals of the funds returning to senders of expired packets
+pub(super) async fn fetch_refund_summary(
+    db: &SqlitePool,
+    params: &ExpiredPacketsQuery,
+) -> ApiResult<Vec<RefundSummary>> {
+    // Relaying the timeout marks the send_packet row as `timeout_packet`, refunding the sender
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            p.sender,
+            t.chain as chain_id,
+            p.denom,
+            p.msg_type_url = 'timeout_packet' as refunded,
+            COUNT(*) as packets,
+            GROUP_CONCAT(p.amount) as amounts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.sender IS NOT NULL
+          AND p.denom IS NOT NULL
+          AND p.amount IS NOT NULL
+          AND (
+            p.msg_type_url = 'timeout_packet'
+            OR (p.msg_type_url = 'send_packet'
+                AND p.effected = 0
+                AND p.timeout_timestamp IS NOT NULL
+                AND p.timeout_timestamp < strftime('%s', 'now') * 1000000000)
+          )"#,
+    );
+    params.filters.push_conditions(&mut query)?;
+    query
+        .push(" GROUP BY p.sender, t.chain, p.denom, refunded ORDER BY p.sender, t.chain, p.denom");
+
+    let rows = query.build_query_as::<RefundRow>().fetch_all(db).await?;
+
+    let mut refunds: Vec<RefundSummary> = Vec::new();
+
+    for row in rows {
+        let same_group = refunds.last().is_some_and(|refund| {
+            refund.sender == row.sender
+                && refund.chain_id == row.chain_id
+                && refund.denom == row.denom
+        });
+
+        if !same_group {
+            refunds.push(RefundSummary {
+                sender: row.sender,
+                chain_id: row.chain_id,
+                denom: row.denom,
+                pending_packets: 0,
+                pending_amount: "0".to_string(),
+                refunded_packets: 0,
+                refunded_amount: "0".to_string(),
+            });
+        }
+
+        let refund = refunds.last_mut().expect("a refund group was just pushed");
+
+        if row.refunded {
+            refund.refunded_packets = row.packets;
+            refund.refunded_amount = sum_amounts(&row.amounts);
+        } else {
+            refund.pending_packets = row.packets;
+            refund.pending_amount = sum_amounts(&row.amounts);
+        }
+    }
+
+    Ok(refunds)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct FailedAcksQuery {
+    #[serde(default = "default_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(flatten)]
+    filters: PacketFilters,
+    #[serde(flatten)]
+    range: TimeRange,
+}
+
+#[derive(Debug, Serialize)]
+struct FailedAcksResponse {
+    packets: Vec<FailedAckInfo>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    api_version: String,
+}
+
+/// A packet whose acknowledgement carried an error
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub(super) struct FailedAckInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    receiver: Option<String>,
+    amount: Option<String>,
+    denom: Option<String>,
+    error: String,
+    relayer: Option<String>,
+    tx_hash: String,
+    acknowledged_at: Timestamp,
+}
+
+async fn get_failed_acks(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<FailedAcksQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let (packets, total) = fetch_failed_acks(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "failed-acks.csv");
+    }
+
+    Ok(Json(FailedAcksResponse {
+        packets,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+        api_version: "1.0".to_string(),
+    })
+    .into_response())
+}
+
+/// Packets acknowledged with an error, most recent first, along with the total number of matches
+pub(super) async fn fetch_failed_acks(
+    db: &SqlitePool,
+    params: &FailedAcksQuery,
+) -> ApiResult<(Vec<FailedAckInfo>, i64)> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            p.ack_error as error,
+            p.signer as relayer,
+            t.hash as tx_hash,
+            p.created_at as acknowledged_at
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+    );
+    push_failed_ack_conditions(&mut query, params)?;
+    query.push(" ORDER BY p.created_at DESC, p.id DESC LIMIT ");
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+
+    // Count all matching packets so clients can paginate
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM packets p JOIN txs t ON p.tx_id = t.id");
+    push_failed_ack_conditions(&mut count, params)?;
+
+    let packets = query
+        .build_query_as::<FailedAckInfo>()
+        .fetch_all(db)
+        .await?;
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok((packets, total))
+}
+
+/// Conditions selecting failed acknowledgements, shared by the list and count queries
+fn push_failed_ack_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &FailedAcksQuery,
+) -> ApiResult<()> {
+    // Acknowledgements relayed again after the first one are not failures of their own
+    query.push(
+        r#"
+        WHERE p.msg_type_url = '/ibc.core.channel.v1.MsgAcknowledgement'
+          AND p.effected = 1
+          AND p.ack_error IS NOT NULL"#,
+    );
+    params.filters.push_conditions(query)?;
+    params.range.push_conditions(query)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DuplicatePacketsQuery {
+    channel: Option<String>, // matches source or destination
+    #[serde(default = "default_duplicates_limit")]
+    pub(super) limit: i64,
+    #[serde(default)]
+    pub(super) offset: i64,
+    #[serde(flatten)]
+    range: TimeRange,
+}
+
+fn default_duplicates_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+struct DuplicatePacketsResponse {
+    duplicates: Vec<DuplicateGroup>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    api_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct DuplicateGroup {
+    data_hash: String,
+    count: i64,
+    packets: Vec<DuplicatePacketInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct DuplicatePacketInfo {
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    created_at: Timestamp,
+}
+
+#[derive(sqlx::FromRow)]
+struct DuplicateRow {
+    data_hash: String,
+    count: i64,
+    chain_id: String,
+    sequence: i64,
+    src_channel: String,
+    dst_channel: String,
+    sender: Option<String>,
+    created_at: Timestamp,
+}
+
+async fn get_duplicate_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DuplicatePacketsQuery>,
+) -> ApiResult<Json<DuplicatePacketsResponse>> {
+    let (duplicates, total) = fetch_duplicate_packets(&state.db, &params).await?;
+
+    Ok(Json(DuplicatePacketsResponse {
+        duplicates,
+        total,
+        limit: params.limit,
+        offset: params.offset,
+        api_version: "1.0".to_string(),
+    }))
+}
+
+/// Groups of packets sharing the same data hash, most duplicated first,
+/// along with the total number of groups
+pub(super) async fn fetch_duplicate_packets(
+    db: &SqlitePool,
+    params: &DuplicatePacketsQuery,
+) -> ApiResult<(Vec<DuplicateGroup>, i64)> {
+    // Page through the groups first, then join back to their packets
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        WITH groups AS (
+            SELECT p.data_hash, COUNT(*) as count
+            FROM packets p
+        "#,
+    );
+    push_duplicate_conditions(&mut query, params)?;
+    query.push(
+        r#"
+            GROUP BY p.data_hash
+            HAVING COUNT(*) > 1
+            ORDER BY count DESC, p.data_hash
+            LIMIT "#,
+    );
+    query.push_bind(params.limit);
+    query.push(" OFFSET ");
+    query.push_bind(params.offset);
+    query.push(
+        r#"
+        )
+        SELECT
+            g.data_hash,
+            g.count,
+            t.chain as chain_id,
+            p.sequence,
+            p.src_channel,
+            p.dst_channel,
+            p.sender,
+            p.created_at
+        FROM groups g
+        JOIN packets p ON p.data_hash = g.data_hash
+        JOIN txs t ON p.tx_id = t.id
+        "#,
+    );
+    push_duplicate_conditions(&mut query, params)?;
+    query.push(" ORDER BY g.count DESC, g.data_hash, p.created_at ASC");
+
+    let mut count =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM (SELECT p.data_hash FROM packets p");
+    push_duplicate_conditions(&mut count, params)?;
+    count.push(" GROUP BY p.data_hash HAVING COUNT(*) > 1)");
+
+    let rows = query.build_query_as::<DuplicateRow>().fetch_all(db).await?;
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    let mut duplicates: Vec<DuplicateGroup> = Vec::new();
+
+    for row in rows {
+        let packet = DuplicatePacketInfo {
+            chain_id: row.chain_id,
+            sequence: row.sequence,
+            src_channel: row.src_channel,
+            dst_channel: row.dst_channel,
+            sender: row.sender,
+            created_at: row.created_at,
+        };
+
+        match duplicates.last_mut() {
+            Some(group) if group.data_hash == row.data_hash => group.packets.push(packet),
+            _ => duplicates.push(DuplicateGroup {
+                data_hash: row.data_hash,
+                count: row.count,
+                packets: vec![packet],
+            }),
+        }
+    }
+
+    Ok((duplicates, total))
+}
+
+/// Conditions selecting the packets considered for duplicates, shared by the list and count queries
+fn push_duplicate_conditions(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &DuplicatePacketsQuery,
+) -> ApiResult<()> {
+    query.push(" WHERE p.data_hash IS NOT NULL");
+
+    if let Some(channel) = &params.channel {
+        query
+            .push(" AND (p.src_channel = ")
+            .push_bind(channel.clone())
+            .push(" OR p.dst_channel = ")
+            .push_bind(channel.clone())
+            .push(")");
+    }
+
+    params.range.push_conditions(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+
+    use super::*;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let latencies: Vec<i64> = (1..=100).collect();
+
+        assert_eq!(percentile(&latencies, 50.0), Some(50));
+        assert_eq!(percentile(&latencies, 90.0), Some(90));
+        assert_eq!(percentile(&latencies, 99.0), Some(99));
+        assert_eq!(percentile(&[42], 99.0), Some(42));
+        assert_eq!(percentile::<i64>(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_gas_price_stats() {
+        let stats = GasPriceStats::from_prices(vec![0.025, 0.0025, 0.5, 0.025]);
+
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, Some(0.0025));
+        assert_eq!(stats.p50, Some(0.025));
+        assert_eq!(stats.p90, Some(0.5));
+        assert_eq!(stats.max, Some(0.5));
+        assert_eq!(GasPriceStats::from_prices(vec![]).p50, None);
+    }
+
+    #[test]
+    fn test_sum_amounts() {
+        assert_eq!(sum_amounts("1000,250"), "1250");
+        assert_eq!(
+            sum_amounts("1000000000000000000000,1000000000000000000000"),
+            "2000000000000000000000"
+        );
+        assert_eq!(sum_amounts("5,not-a-number"), "5");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(45), "45s");
+        assert_eq!(format_age(12 * 60 + 30), "12m");
+        assert_eq!(format_age(3 * 3600 + 5 * 60), "3h05m");
+        assert_eq!(format_age(2 * 86400 + 4 * 3600), "2d04h");
+    }
+
+    fn order_by(sort: Option<&str>, order: Option<&str>) -> ApiResult<String> {
+        let sort = Sort {
+            sort: sort.map(str::to_string),
+            order: order.map(str::to_string),
+        };
+        let mut query = QueryBuilder::<Sqlite>::new("");
+        sort.push_order_by(&mut query, SortKey::Age, true)?;
+        Ok(query.sql().to_string())
+    }
+
+    #[test]
+    fn test_sort_order_by() {
+        assert_eq!(
+            order_by(None, None).unwrap(),
+            " ORDER BY p.created_at ASC NULLS LAST, p.id ASC"
+        );
+        assert_eq!(
+            order_by(Some("age"), Some("asc")).unwrap(),
+            " ORDER BY p.created_at DESC NULLS LAST, p.id DESC"
+        );
+        assert_eq!(
+            order_by(Some("amount"), None).unwrap(),
+            " ORDER BY CAST(p.amount AS REAL) DESC NULLS LAST, p.id DESC"
+        );
+        assert_eq!(
+            order_by(Some("timeout"), None).unwrap(),
+            " ORDER BY p.timeout_timestamp ASC NULLS LAST, p.id ASC"
+        );
+        assert!(order_by(Some("fee"), None).is_err());
+        assert!(order_by(Some("sequence"), Some("up")).is_err());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_timestamp("2023-11-14T22:13:20Z"), Some(1_700_000_000));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn test_stuck_packets_group_by() {
+        let query = |uri: &str| {
+            let uri = uri.parse().unwrap();
+            let Query(params) = Query::<StuckPacketsQuery>::try_from_uri(&uri).unwrap();
+            params.group_by_relayer().is_ok_and(|group_by| group_by)
+        };
+
+        assert!(query("/api/v1/packets/stuck?group_by=relayer"));
+        assert!(query("/api/v1/packets/stuck?groupBy=relayer"));
+        assert!(!query("/api/v1/packets/stuck"));
+        assert!(!query("/api/v1/packets/stuck?groupBy=channel"));
+    }
+}
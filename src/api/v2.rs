@@ -0,0 +1,380 @@
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    middleware,
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Serialize;
+
+use super::{
+    error::{ApiError, ApiJson, ApiPath, ApiQuery, ApiResult},
+    format::{self, Format},
+    rate_limit,
+    v1::{
+        chain_infos, effective_config, fetch_address_summary, fetch_channel_congestion,
+        fetch_channel_latency, fetch_clients, fetch_daily_stats, fetch_decoded_msgs,
+        fetch_denom_traces, fetch_duplicate_packets, fetch_expired_packets, fetch_expiring_packets,
+        fetch_failed_acks, fetch_frontruns, fetch_gas_prices, fetch_packet, fetch_packet_statuses,
+        fetch_packets_by_user, fetch_refund_summary, fetch_stuck_packets, fetch_top_stuck_routes,
+        fetch_unknown_msg_types, group_stuck_packets_by_relayer, internal_tasks,
+        submit_clear_request, AddressSummary, AddressSummaryQuery, ChainInfo,
+        ChannelCongestionResponse, ChannelLatencyResponse, ClearPacketsRequest,
+        ClearPacketsResponse, ClientInfo, ClientsQuery, ConfigResponse, CongestionQuery,
+        DailyStats, DailyStatsQuery, DecodedMsg, DecodedMsgsQuery, DenomTraceInfo,
+        DenomTracesQuery, DuplicateGroup, DuplicatePacketsQuery, ExpiredPacketsQuery,
+        ExpiringPacketsQuery, FailedAcksQuery, FrontrunInfo, FrontrunsQuery, GasPricesQuery,
+        GasPricesResponse, InternalTasks, LatencyQuery, PacketInfo, PacketKey, PacketStatus,
+        RefundSummary, StuckPacketsQuery, TopStuckRoutesQuery, TopStuckRoutesResponse,
+        UnknownMsgType, UnknownMsgTypesQuery, UserPacketsQuery,
+    },
+    ApiState,
+};
+
+/// Largest page size accepted by the list endpoints
+const MAX_LIMIT: i64 = 1000;
+
+pub(super) fn routes(state: ApiState) -> Router<ApiState> {
+    // Database heavy queries, subject to the configured rate limit
+    let limited = Router::new()
+        .route("/packets/by-user", get(get_packets_by_user))
+        .route("/packets/stuck", get(get_stuck_packets))
+        .route("/packets/expiring", get(get_expiring_packets))
+        .route("/channels/congestion", get(get_channel_congestion))
+        .route("/routes/top-stuck", get(get_top_stuck_routes))
+        .route("/channels/:channel/latency", get(get_channel_latency))
+        .route("/chains/:chain/gas", get(get_gas_prices))
+        .route("/frontruns", get(get_frontruns))
+        .route("/packets/duplicates", get(get_duplicate_packets))
+        .route("/packets/expired", get(get_expired_packets))
+        .route("/packets/expired/refunds", get(get_refund_summary))
+        .route("/packets/failed-acks", get(get_failed_acks))
+        .route("/address/:address/summary", get(get_address_summary))
+        .route("/packets/status", post(get_packet_statuses))
+        .route("/clients", get(get_clients))
+        .route("/msgs/decoded", get(get_decoded_msgs))
+        .route_layer(middleware::from_fn_with_state(state, rate_limit::limit));
+
+    Router::new()
+        .route("/chains", get(get_chains))
+        .route("/config", get(get_config))
+        .route("/msg-types/unknown", get(get_unknown_msg_types))
+        .route("/denoms", get(get_denom_traces))
+        .route("/stats/daily", get(get_daily_stats))
+        .route("/packets/clear", post(clear_packets))
+        .route("/internal/tasks", get(get_internal_tasks))
+        .route(
+            "/packets/:chain/:channel/:sequence",
+            get(get_packet_details),
+        )
+        .merge(limited)
+}
+
+/// Response envelope for a single resource
+#[derive(Debug, Serialize)]
+struct Item<T> {
+    data: T,
+}
+
+/// Response envelope for a paginated list
+#[derive(Debug, Serialize)]
+struct Page<T> {
+    data: Vec<T>,
+    pagination: Pagination,
+}
+
+#[derive(Debug, Serialize)]
+struct Pagination {
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+impl Pagination {
+    fn new(limit: i64, offset: i64) -> ApiResult<Self> {
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(ApiError::invalid_parameter(format!(
+                "limit must be between 1 and {MAX_LIMIT}"
+            )));
+        }
+
+        if offset < 0 {
+            return Err(ApiError::invalid_parameter("offset must not be negative"));
+        }
+
+        Ok(Self {
+            total: 0,
+            limit,
+            offset,
+        })
+    }
+
+    fn page<T>(self, data: Vec<T>, total: i64) -> Page<T> {
+        Page {
+            data,
+            pagination: Self { total, ..self },
+        }
+    }
+}
+
+async fn get_internal_tasks(State(state): State<ApiState>) -> Json<Item<InternalTasks>> {
+    Json(Item {
+        data: internal_tasks(&state),
+    })
+}
+
+async fn get_chains(State(state): State<ApiState>) -> Json<Item<Vec<ChainInfo>>> {
+    Json(Item {
+        data: chain_infos(&state.status),
+    })
+}
+
+async fn get_clients(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ClientsQuery>,
+) -> ApiResult<Json<Item<Vec<ClientInfo>>>> {
+    let data = fetch_clients(&state.db, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_unknown_msg_types(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<UnknownMsgTypesQuery>,
+) -> ApiResult<Json<Item<Vec<UnknownMsgType>>>> {
+    let data = fetch_unknown_msg_types(&state.db, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_denom_traces(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DenomTracesQuery>,
+) -> ApiResult<Json<Item<Vec<DenomTraceInfo>>>> {
+    let data = fetch_denom_traces(&state.db, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_daily_stats(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DailyStatsQuery>,
+) -> ApiResult<Json<Item<Vec<DailyStats>>>> {
+    let data = fetch_daily_stats(&state.db, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_config(State(state): State<ApiState>) -> Json<Item<ConfigResponse>> {
+    Json(Item {
+        data: effective_config(&state.config.borrow()),
+    })
+}
+
+async fn get_packets_by_user(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<UserPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+
+    if !matches!(params.role.as_str(), "" | "both" | "sender" | "receiver") {
+        return Err(ApiError::invalid_parameter(
+            "role must be `sender`, `receiver` or `both`",
+        ));
+    }
+
+    let (packets, total) = fetch_packets_by_user(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "packets.csv");
+    }
+
+    Ok(Json(pagination.page(packets, total)).into_response())
+}
+
+async fn get_stuck_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<StuckPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let group_by_relayer = params.group_by_relayer()?;
+    format::ensure_flat(format, group_by_relayer)?;
+
+    let (packets, total) =
+        fetch_stuck_packets(&state.db, &params, &state.stuck_thresholds()).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "stuck-packets.csv");
+    }
+
+    if group_by_relayer {
+        let relayers = group_stuck_packets_by_relayer(packets);
+        return Ok(Json(pagination.page(relayers, total)).into_response());
+    }
+
+    Ok(Json(pagination.page(packets, total)).into_response())
+}
+
+async fn get_expiring_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ExpiringPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (packets, total) = fetch_expiring_packets(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "expiring-packets.csv");
+    }
+
+    Ok(Json(pagination.page(packets, total)).into_response())
+}
+
+async fn clear_packets(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<ClearPacketsRequest>,
+) -> ApiResult<Json<Item<ClearPacketsResponse>>> {
+    let data = submit_clear_request(&state, &headers, &request).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_packet_statuses(
+    State(state): State<ApiState>,
+    ApiJson(packets): ApiJson<Vec<PacketKey>>,
+) -> ApiResult<Json<Item<Vec<PacketStatus>>>> {
+    let data = fetch_packet_statuses(&state.db, packets, &state.stuck_thresholds()).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_packet_details(
+    State(state): State<ApiState>,
+    ApiPath((chain, channel, sequence)): ApiPath<(String, String, i64)>,
+) -> ApiResult<Json<Item<PacketInfo>>> {
+    let data = fetch_packet(&state.db, chain, channel, sequence).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_channel_congestion(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<CongestionQuery>,
+) -> ApiResult<Json<Item<ChannelCongestionResponse>>> {
+    let data = fetch_channel_congestion(&state.db, &params, &state.stuck_thresholds()).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_top_stuck_routes(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<TopStuckRoutesQuery>,
+) -> ApiResult<Json<Item<TopStuckRoutesResponse>>> {
+    let data = fetch_top_stuck_routes(&state.db, &params, &state.stuck_thresholds()).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_channel_latency(
+    State(state): State<ApiState>,
+    ApiPath(channel): ApiPath<String>,
+    ApiQuery(params): ApiQuery<LatencyQuery>,
+) -> ApiResult<Json<Item<ChannelLatencyResponse>>> {
+    let data = fetch_channel_latency(&state.db, channel, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_gas_prices(
+    State(state): State<ApiState>,
+    ApiPath(chain): ApiPath<String>,
+    ApiQuery(params): ApiQuery<GasPricesQuery>,
+) -> ApiResult<Json<Item<GasPricesResponse>>> {
+    let data = fetch_gas_prices(&state.db, chain, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_frontruns(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<FrontrunsQuery>,
+) -> ApiResult<Json<Page<FrontrunInfo>>> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (frontruns, total) = fetch_frontruns(&state.db, &params).await?;
+
+    Ok(Json(pagination.page(frontruns, total)))
+}
+
+async fn get_duplicate_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DuplicatePacketsQuery>,
+) -> ApiResult<Json<Page<DuplicateGroup>>> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (duplicates, total) = fetch_duplicate_packets(&state.db, &params).await?;
+
+    Ok(Json(pagination.page(duplicates, total)))
+}
+
+async fn get_expired_packets(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ExpiredPacketsQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (packets, total) = fetch_expired_packets(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "expired-packets.csv");
+    }
+
+    Ok(Json(pagination.page(packets, total)).into_response())
+}
+
+async fn get_failed_acks(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<FailedAcksQuery>,
+    format: Format,
+) -> ApiResult<Response> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (packets, total) = fetch_failed_acks(&state.db, &params).await?;
+
+    if format == Format::Csv {
+        return format::csv(&packets, total, "failed-acks.csv");
+    }
+
+    Ok(Json(pagination.page(packets, total)).into_response())
+}
+
+async fn get_decoded_msgs(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<DecodedMsgsQuery>,
+) -> ApiResult<Json<Page<DecodedMsg>>> {
+    let pagination = Pagination::new(params.limit, params.offset)?;
+    let (msgs, total) = fetch_decoded_msgs(&state.db, &params).await?;
+
+    Ok(Json(pagination.page(msgs, total)))
+}
+
+async fn get_refund_summary(
+    State(state): State<ApiState>,
+    ApiQuery(params): ApiQuery<ExpiredPacketsQuery>,
+) -> ApiResult<Json<Item<Vec<RefundSummary>>>> {
+    let data = fetch_refund_summary(&state.db, &params).await?;
+
+    Ok(Json(Item { data }))
+}
+
+async fn get_address_summary(
+    State(state): State<ApiState>,
+    ApiPath(address): ApiPath<String>,
+    ApiQuery(params): ApiQuery<AddressSummaryQuery>,
+) -> ApiResult<Json<Item<AddressSummary>>> {
+    let data =
+        fetch_address_summary(&state.db, address, &params, &state.stuck_thresholds()).await?;
+
+    Ok(Json(Item { data }))
+}
@@ -0,0 +1,251 @@
+use std::{collections::HashMap, time::Duration};
+
+use ibc_proto::ibc::core::{
+    channel::v1::{QueryChannelRequest, QueryChannelResponse, State},
+    connection::v1::{QueryConnectionRequest, QueryConnectionResponse},
+};
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{Config, Endpoint},
+    expiry, rpc,
+};
+
+/// How often the channels with pending packets are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Reason why the packets of a channel can no longer be relayed, given the columns holding the
+/// chain sending them, their source port and source channel, such as `t.chain`, `p.src_port` and
+/// `p.src_channel`
+///
+/// The expression is `NULL` for the channels which are open, or were not checked yet.
+pub fn unrecoverable_sql(chain: &str, port: &str, channel: &str) -> String {
+    format!(
+        "(SELECT CASE WHEN c.state = 'CLOSED' THEN 'channel_closed' ELSE 'client_frozen' END
+          FROM channel_states c
+          WHERE c.chain = {chain} AND c.port = {port} AND c.channel = {channel}
+            AND (c.state = 'CLOSED' OR c.client_frozen = 1))"
+    )
+}
+
+/// State of a channel and of the light client behind it
+#[derive(Debug)]
+struct ChannelState {
+    state: &'static str,
+    client_id: String,
+    client_frozen: bool,
+}
+
+/// Periodically check the state of the channels with pending packets, recording in the
+/// `channel_states` table those which are closed or whose light client is frozen
+///
+/// Pending packets of such channels cannot be relayed anymore, and are reported as
+/// unrecoverable rather than stuck. The client of a connection is queried once, and the state
+/// of the channels and clients on every check.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    let mut interval = time::interval(CHECK_INTERVAL);
+    let mut connection_clients: HashMap<(String, String), String> = HashMap::new();
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+
+        let chains = config.borrow_and_update().chains.clone();
+
+        let channels = match pending_channels(&pool).await {
+            Ok(channels) => channels,
+            Err(e) => {
+                error!("Failed to load the channels with pending packets: {e}");
+                continue;
+            }
+        };
+
+        // Frozen status of the clients, queried once per check
+        let mut frozen: HashMap<(String, String), bool> = HashMap::new();
+
+        for (chain_id, port_id, channel_id) in channels {
+            let Some(endpoint) = expiry::endpoint(&chains, &chain_id) else {
+                continue;
+            };
+
+            let state = channel_state(
+                &client,
+                endpoint,
+                &chain_id,
+                &port_id,
+                &channel_id,
+                &mut connection_clients,
+                &mut frozen,
+            )
+            .await;
+
+            let state = match state {
+                Ok(state) => state,
+                Err(e) => {
+                    warn!(chain = %chain_id, "Failed to query the state of {port_id}/{channel_id}: {e}");
+                    continue;
+                }
+            };
+
+            match record(&pool, &chain_id, &port_id, &channel_id, &state).await {
+                Ok(true) if state.state == "CLOSED" => {
+                    warn!(chain = %chain_id, "Channel {port_id}/{channel_id} is closed, its pending packets are unrecoverable");
+                }
+                Ok(true) if state.client_frozen => {
+                    warn!(chain = %chain_id, "Client {} of {port_id}/{channel_id} is frozen, its pending packets are unrecoverable", state.client_id);
+                }
+                Ok(true) => {
+                    info!(chain = %chain_id, "Channel {port_id}/{channel_id} is {}", state.state);
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!(chain = %chain_id, "Failed to record the state of {port_id}/{channel_id}: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Chain, port and channel of the channels with packets neither relayed nor timed out
+async fn pending_channels(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String, String)>> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT t.chain, p.src_port, p.src_channel
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+        ORDER BY t.chain, p.src_channel
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+async fn channel_state(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    chain_id: &str,
+    port_id: &str,
+    channel_id: &str,
+    connection_clients: &mut HashMap<(String, String), String>,
+    frozen: &mut HashMap<(String, String), bool>,
+) -> crate::Result<ChannelState> {
+    let response: QueryChannelResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.core.channel.v1.Query/Channel",
+        QueryChannelRequest {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        },
+    )
+    .await?;
+
+    let channel = response.channel.ok_or("channel not found")?;
+
+    let state = match State::from_i32(channel.state) {
+        Some(State::Open) => "OPEN",
+        Some(State::Closed) => "CLOSED",
+        Some(State::Init) => "INIT",
+        Some(State::Tryopen) => "TRYOPEN",
+        Some(State::UninitializedUnspecified) | None => "UNKNOWN",
+    };
+
+    let connection_id = channel
+        .connection_hops
+        .into_iter()
+        .next()
+        .ok_or("no connection in the channel")?;
+
+    let connection_key = (chain_id.to_string(), connection_id.clone());
+
+    if !connection_clients.contains_key(&connection_key) {
+        let response: QueryConnectionResponse = rpc::abci_query(
+            client,
+            endpoint,
+            "/ibc.core.connection.v1.Query/Connection",
+            QueryConnectionRequest {
+                connection_id: connection_id.clone(),
+            },
+        )
+        .await?;
+
+        let connection = response
+            .connection
+            .ok_or_else(|| format!("connection {connection_id} not found"))?;
+
+        connection_clients.insert(connection_key.clone(), connection.client_id);
+    }
+
+    let client_id = connection_clients[&connection_key].clone();
+    let client_key = (chain_id.to_string(), client_id.clone());
+
+    let client_frozen = match frozen.get(&client_key) {
+        Some(client_frozen) => *client_frozen,
+        None => {
+            let client_state = rpc::client_state(client, endpoint, &client_id).await?;
+            let client_frozen = client_state
+                .frozen_height
+                .is_some_and(|height| height.revision_number != 0 || height.revision_height != 0);
+
+            frozen.insert(client_key, client_frozen);
+            client_frozen
+        }
+    };
+
+    debug!("Channel {port_id}/{channel_id} is {state}, client {client_id} frozen: {client_frozen}");
+
+    Ok(ChannelState {
+        state,
+        client_id,
+        client_frozen,
+    })
+}
+
+/// Record the state of a channel, returning whether it changed since the previous check
+async fn record(
+    pool: &SqlitePool,
+    chain_id: &str,
+    port_id: &str,
+    channel_id: &str,
+    state: &ChannelState,
+) -> sqlx::Result<bool> {
+    let previous: Option<(String, bool)> = sqlx::query_as(
+        "SELECT state, client_frozen FROM channel_states WHERE chain = ? AND port = ? AND channel = ?",
+    )
+    .bind(chain_id)
+    .bind(port_id)
+    .bind(channel_id)
+    .fetch_optional(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO channel_states
+            (chain, port, channel, state, client_id, client_frozen, checked_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+        "#,
+    )
+    .bind(chain_id)
+    .bind(port_id)
+    .bind(channel_id)
+    .bind(state.state)
+    .bind(&state.client_id)
+    .bind(state.client_frozen)
+    .execute(pool)
+    .await?;
+
+    let changed = match previous {
+        Some((previous, client_frozen)) => {
+            previous != state.state || client_frozen != state.client_frozen
+        }
+        // Open channels are the norm, and not worth logging
+        None => state.state != "OPEN" || state.client_frozen,
+    };
+
+    Ok(changed)
+}
@@ -0,0 +1,107 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use tendermint::chain;
+use tracing::info;
+
+use crate::config::{Chains, Resume};
+
+/// Record a block processed for a chain, along with its rows, and its time when known
+///
+/// The checkpoint is only written in the transaction of the rows, so that it never moves past
+/// a block which was not recorded, such as those a standby skips.
+pub async fn record(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    height: u64,
+    time: Option<tendermint::Time>,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO checkpoints (chain, height, block_time, updated_at)
+        VALUES (?, ?, ?, datetime('now'))
+        ON CONFLICT (chain) DO UPDATE
+        SET height = excluded.height,
+            block_time = excluded.block_time,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(chain_id.as_str())
+    .bind(height as i64)
+    .bind(time.map(|time| time.to_rfc3339()))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Last block recorded for a chain, if any
+pub async fn load(pool: &SqlitePool, chain_id: &chain::Id) -> sqlx::Result<Option<u64>> {
+    let height: Option<i64> = sqlx::query_scalar("SELECT height FROM checkpoints WHERE chain = ?")
+        .bind(chain_id.as_str())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(height.map(|height| height as u64))
+}
+
+/// Log the last block processed for each chain before the previous shutdown, as the blocks
+/// produced since are only collected up to `resume.max_blocks` unless backfilled
+pub async fn report(pool: &SqlitePool, chains: &Chains, resume: &Resume) -> sqlx::Result<()> {
+    let checkpoints = sqlx::query_as::<_, (String, i64, String)>(
+        "SELECT chain, height, updated_at FROM checkpoints ORDER BY chain",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (chain, height, updated_at) in checkpoints {
+        if !chains
+            .endpoints
+            .keys()
+            .any(|chain_id| chain_id.as_str() == chain)
+        {
+            continue;
+        }
+
+        if resume.max_blocks == 0 {
+            info!(
+                %chain,
+                "Last block processed before the shutdown at {updated_at} was {height}, \
+                 run `chainpulse backfill {chain} --from {}` to collect the blocks missed since",
+                height + 1
+            );
+        } else {
+            info!(
+                %chain,
+                "Last block processed before the shutdown at {updated_at} was {height}, \
+                 catching up the blocks missed since"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    #[tokio::test]
+    async fn test_record_load() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        assert_eq!(load(&pool, &chain_id).await.unwrap(), None);
+
+        let mut conn = pool.acquire().await.unwrap();
+        let time = tendermint::Time::unix_epoch();
+        record(&mut conn, &chain_id, 12, Some(time)).await.unwrap();
+        record(&mut conn, &chain_id, 13, None).await.unwrap();
+        drop(conn);
+
+        assert_eq!(load(&pool, &chain_id).await.unwrap(), Some(13));
+
+        let other = chain::Id::try_from("cosmoshub-4").unwrap();
+        assert_eq!(load(&pool, &other).await.unwrap(), None);
+    }
+}
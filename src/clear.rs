@@ -0,0 +1,518 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use tokio::{process::Command, sync::watch, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    channels,
+    config::{AutoClear, ClearMethod, ClearRelayer, Config, StuckPackets},
+};
+
+/// Timeout for requests made to the relayer
+const RELAYER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the allowed channels are checked for stuck packets to clear
+const AUTO_CLEAR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time given to `hermes clear packets`, which relays every packet it clears
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Sequences cleared by a single action, the lowest ones first
+const MAX_SEQUENCES: usize = 100;
+
+/// A channel, or a set of packets on a channel, to be cleared
+#[derive(Clone, Debug, Serialize)]
+pub struct ClearTarget {
+    pub chain: String,
+    pub port: String,
+    pub channel: String,
+    /// Sequences to clear, or every pending packet on the channel when empty
+    pub sequences: Vec<i64>,
+}
+
+impl ClearTarget {
+    /// The `hermes clear packets` invocation clearing this target
+    pub fn hermes_command(&self) -> String {
+        format!("hermes {}", self.hermes_args().join(" "))
+    }
+
+    /// Arguments of the `hermes` binary clearing this target
+    fn hermes_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "clear".to_string(),
+            "packets".to_string(),
+            "--chain".to_string(),
+            self.chain.clone(),
+            "--port".to_string(),
+            self.port.clone(),
+            "--channel".to_string(),
+            self.channel.clone(),
+        ];
+
+        if !self.sequences.is_empty() {
+            let sequences: Vec<String> = self.sequences.iter().map(i64::to_string).collect();
+            args.push("--packet-sequences".to_string());
+            args.push(sequences.join(","));
+        }
+
+        args
+    }
+}
+
+/// Result of asking the relayer to clear the packets of a chain
+#[derive(Clone, Debug, Serialize)]
+pub struct TriggerResult {
+    pub chain: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// Ask a Hermes REST server to clear pending packets on every chain of the targets
+///
+/// Hermes clears all channels of a chain at once, so targets are deduplicated by chain.
+pub async fn trigger_hermes(hermes_url: &str, targets: &[ClearTarget]) -> Vec<TriggerResult> {
+    let client = reqwest::Client::new();
+    let base = hermes_url.trim_end_matches('/');
+
+    let chains: BTreeSet<&str> = targets.iter().map(|target| target.chain.as_str()).collect();
+    let mut results = Vec::with_capacity(chains.len());
+
+    for chain in chains {
+        let response = client
+            .post(format!("{base}/clear_packets"))
+            .query(&[("chain", chain)])
+            .timeout(RELAYER_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        let error = match response {
+            Ok(_) => {
+                info!(%chain, "Triggered packet clearing on Hermes");
+                None
+            }
+            Err(e) => {
+                warn!(%chain, "Failed to trigger packet clearing on Hermes: {e}");
+                Some(e.to_string())
+            }
+        };
+
+        results.push(TriggerResult {
+            chain: chain.to_string(),
+            ok: error.is_none(),
+            error,
+        });
+    }
+
+    results
+}
+
+/// Status of a clearing request: `generated` when only commands were produced,
+/// `triggered` when the relayer accepted every trigger, `failed` otherwise
+pub fn status(triggered: &[TriggerResult]) -> &'static str {
+    if triggered.is_empty() {
+        "generated"
+    } else if triggered.iter().all(|result| result.ok) {
+        "triggered"
+    } else {
+        "failed"
+    }
+}
+
+/// Record a clearing request in the audit table, returning its id
+pub async fn record(
+    pool: &SqlitePool,
+    targets: &[ClearTarget],
+    commands: &[String],
+    triggered: &[TriggerResult],
+) -> sqlx::Result<i64> {
+    let failures: Vec<String> = triggered
+        .iter()
+        .filter_map(|result| {
+            let error = result.error.as_ref()?;
+            Some(format!("{}: {error}", result.chain))
+        })
+        .collect();
+
+    let query = r#"
+        INSERT INTO clear_requests (targets, commands, triggered, status, error, created_at)
+        VALUES (?, ?, ?, ?, ?, datetime('now'))
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(serde_json::to_string(targets).unwrap_or_default())
+        .bind(commands.join("\n"))
+        .bind(!triggered.is_empty())
+        .bind(status(triggered))
+        .bind((!failures.is_empty()).then(|| failures.join("; ")))
+        .execute(pool)
+        .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Periodically clear the stuck packets of the channels allowed by `relayer.auto_clear`,
+/// recording every action in the `clear_actions` table
+///
+/// A channel is not cleared again within `cooldown_seconds`, and no more than `max_per_hour`
+/// actions run over the last hour, dry runs included, so that a relayer failing to clear a
+/// channel is not asked again every minute.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    let mut interval = time::interval(AUTO_CLEAR_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let config = config.borrow_and_update().clone();
+
+        let Some(auto_clear) = &config.relayer.auto_clear else {
+            continue;
+        };
+
+        if let Err(e) = auto_clear_stuck(&pool, &config, auto_clear).await {
+            error!("Failed to clear the stuck packets: {e}");
+        }
+    }
+}
+
+async fn auto_clear_stuck(
+    pool: &SqlitePool,
+    config: &Config,
+    auto_clear: &AutoClear,
+) -> crate::Result<()> {
+    let targets = stuck_targets(pool, &config.stuck_packets, auto_clear).await?;
+
+    for mut target in targets {
+        if target.sequences.len() < auto_clear.min_packets as usize {
+            continue;
+        }
+
+        if cleared_within(pool, &target, auto_clear.cooldown_seconds).await? {
+            debug!(
+                chain = %target.chain,
+                "Not clearing {} again within {} seconds",
+                target.channel, auto_clear.cooldown_seconds
+            );
+            continue;
+        }
+
+        if cleared_last_hour(pool).await? >= auto_clear.max_per_hour as i64 {
+            warn!(
+                "Not clearing the stuck packets of {} on {}, {} actions already ran over the last hour",
+                target.channel, target.chain, auto_clear.max_per_hour
+            );
+            break;
+        }
+
+        target.sequences.truncate(MAX_SEQUENCES);
+
+        let action = Action::new(config, auto_clear, &target);
+        let command = action.command();
+
+        let result = match auto_clear.dry_run {
+            true => Ok(()),
+            false => action.run(&target).await,
+        };
+
+        let status = match (&result, auto_clear.dry_run) {
+            (_, true) => "dry_run",
+            (Ok(()), false) => "cleared",
+            (Err(_), false) => "failed",
+        };
+
+        match &result {
+            Ok(()) => info!(
+                chain = %target.chain,
+                "{} {} stuck packets of {}: {command}",
+                if auto_clear.dry_run { "Would clear" } else { "Cleared" },
+                target.sequences.len(),
+                target.channel
+            ),
+            Err(e) => warn!(
+                chain = %target.chain,
+                "Failed to clear the stuck packets of {}: {e}",
+                target.channel
+            ),
+        }
+
+        record_action(pool, &target, action.method, &command, status, result.err()).await?;
+    }
+
+    Ok(())
+}
+
+/// Stuck packets of the allowed channels, one target per channel with the sequences of its
+/// stuck packets in ascending order
+async fn stuck_targets(
+    pool: &SqlitePool,
+    thresholds: &StuckPackets,
+    auto_clear: &AutoClear,
+) -> sqlx::Result<Vec<ClearTarget>> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        r#"
+        SELECT t.chain, p.src_port, p.src_channel, p.sequence
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+          AND {} IS NULL
+          AND (
+        "#,
+        thresholds.sql("t.chain", "p.src_channel"),
+        channels::unrecoverable_sql("t.chain", "p.src_port", "p.src_channel")
+    ));
+
+    let mut channels = query.separated(" OR ");
+
+    for channel in &auto_clear.channels {
+        channels.push("(t.chain = ");
+        channels.push_bind_unseparated(&channel.chain);
+        channels.push_unseparated(" AND p.src_channel = ");
+        channels.push_bind_unseparated(&channel.channel);
+        channels.push_unseparated(")");
+    }
+
+    query.push(") ORDER BY p.sequence");
+
+    let rows: Vec<(String, String, String, i64)> = query.build_query_as().fetch_all(pool).await?;
+
+    let mut targets: BTreeMap<(String, String, String), Vec<i64>> = BTreeMap::new();
+
+    for (chain, port, channel, sequence) in rows {
+        targets
+            .entry((chain, port, channel))
+            .or_default()
+            .push(sequence);
+    }
+
+    Ok(targets
+        .into_iter()
+        .map(|((chain, port, channel), mut sequences)| {
+            sequences.dedup();
+
+            ClearTarget {
+                chain,
+                port,
+                channel,
+                sequences,
+            }
+        })
+        .collect())
+}
+
+/// Whether an action ran on the channel of a target within a number of seconds
+async fn cleared_within(
+    pool: &SqlitePool,
+    target: &ClearTarget,
+    seconds: u64,
+) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM clear_actions
+            WHERE chain = ? AND channel = ? AND created_at > datetime('now', ?)
+        )
+        "#,
+    )
+    .bind(&target.chain)
+    .bind(&target.channel)
+    .bind(format!("-{seconds} seconds"))
+    .fetch_one(pool)
+    .await
+}
+
+/// Actions run over the last hour, across all channels
+async fn cleared_last_hour(pool: &SqlitePool) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM clear_actions WHERE created_at > datetime('now', '-1 hour')",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Request to the relayer clearing the stuck packets of a channel
+struct Action {
+    /// Backend, as recorded in the audit table: `rest`, `command` or `rly`
+    method: &'static str,
+    invocation: Invocation,
+}
+
+enum Invocation {
+    /// `POST /clear_packets` on the REST server of Hermes
+    Rest {
+        hermes_url: Option<String>,
+        chain: String,
+    },
+    /// Relayer binary run with each list of arguments in turn
+    Commands {
+        binary: PathBuf,
+        runs: Vec<Vec<String>>,
+    },
+}
+
+impl Action {
+    /// Request clearing a target with the relayer of its channel
+    fn new(config: &Config, auto_clear: &AutoClear, target: &ClearTarget) -> Self {
+        let channel = auto_clear
+            .channels
+            .iter()
+            .find(|channel| channel.chain == target.chain && channel.channel == target.channel);
+
+        match channel.map(|channel| (channel.relayer, channel.path.as_deref())) {
+            Some((ClearRelayer::Rly, Some(path))) => {
+                let home: Vec<String> = match &auto_clear.rly_home {
+                    Some(home) => vec!["--home".to_string(), home.display().to_string()],
+                    None => Vec::new(),
+                };
+
+                // Packets are relayed in both directions of the path, then their acknowledgements
+                let runs = ["relay-pkts", "relay-acks"]
+                    .into_iter()
+                    .map(|command| {
+                        let mut args = vec![
+                            "tx".to_string(),
+                            command.to_string(),
+                            path.to_string(),
+                            target.channel.clone(),
+                        ];
+                        args.extend(home.iter().cloned());
+                        args
+                    })
+                    .collect();
+
+                Self {
+                    method: "rly",
+                    invocation: Invocation::Commands {
+                        binary: auto_clear.rly_binary.clone(),
+                        runs,
+                    },
+                }
+            }
+            _ => match auto_clear.method {
+                ClearMethod::Rest => Self {
+                    method: "rest",
+                    invocation: Invocation::Rest {
+                        hermes_url: config.relayer.hermes_url.clone(),
+                        chain: target.chain.clone(),
+                    },
+                },
+                ClearMethod::Command => {
+                    let mut args = Vec::new();
+
+                    if let Some(hermes_config) = &auto_clear.hermes_config {
+                        args.push("--config".to_string());
+                        args.push(hermes_config.display().to_string());
+                    }
+
+                    args.extend(target.hermes_args());
+
+                    Self {
+                        method: "command",
+                        invocation: Invocation::Commands {
+                            binary: auto_clear.hermes_binary.clone(),
+                            runs: vec![args],
+                        },
+                    }
+                }
+            },
+        }
+    }
+
+    /// The request as recorded in the audit table, without the address of the REST server
+    fn command(&self) -> String {
+        match &self.invocation {
+            Invocation::Rest { chain, .. } => format!("POST /clear_packets?chain={chain}"),
+            Invocation::Commands { binary, runs } => runs
+                .iter()
+                .map(|args| format!("{} {}", binary.display(), args.join(" ")))
+                .collect::<Vec<_>>()
+                .join(" && "),
+        }
+    }
+
+    async fn run(&self, target: &ClearTarget) -> Result<(), String> {
+        match &self.invocation {
+            Invocation::Rest {
+                hermes_url: Some(url),
+                ..
+            } => trigger_hermes(url, std::slice::from_ref(target))
+                .await
+                .into_iter()
+                .find_map(|result| result.error)
+                .map_or(Ok(()), Err),
+            Invocation::Rest { .. } => Err("relayer.hermes_url is not set".to_string()),
+            Invocation::Commands { binary, runs } => {
+                for args in runs {
+                    run_command(binary, args).await?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run a relayer binary, failing with the end of its output when it does not succeed
+async fn run_command(binary: &Path, args: &[String]) -> Result<(), String> {
+    let output = Command::new(binary).args(args).kill_on_drop(true).output();
+
+    let output = match time::timeout(COMMAND_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("failed to run {}: {e}", binary.display())),
+        Err(_) => return Err(format!("timed out after {COMMAND_TIMEOUT:?}")),
+    };
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let last_line = [stderr.trim(), stdout.trim()]
+        .into_iter()
+        .find(|output| !output.is_empty())
+        .and_then(|output| output.lines().last())
+        .unwrap_or_default();
+
+    Err(format!("{}: {last_line}", output.status))
+}
+
+/// Record a clearing action in the audit table
+async fn record_action(
+    pool: &SqlitePool,
+    target: &ClearTarget,
+    method: &str,
+    command: &str,
+    status: &str,
+    error: Option<String>,
+) -> sqlx::Result<()> {
+    let sequences: Vec<String> = target.sequences.iter().map(i64::to_string).collect();
+
+    let query = r#"
+        INSERT INTO clear_actions (chain, port, channel, sequences, method, command, status, error, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+    "#;
+
+    sqlx::query(query)
+        .bind(&target.chain)
+        .bind(&target.port)
+        .bind(&target.channel)
+        .bind(sequences.join(","))
+        .bind(method)
+        .bind(command)
+        .bind(status)
+        .bind(error)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
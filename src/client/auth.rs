@@ -1,15 +1,31 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use tendermint::{block::Height, Block};
-use tendermint_rpc::event::Event;
 
-use super::{BlockResults, BlockSubscription, ChainClient, Result};
-use crate::simple_auth_client::{AuthMethod, SimpleAuthClient};
+use super::{subscription, tls, BlockResults, BlockSubscription, ChainClient, Keepalive, Result};
+use crate::{
+    config::EndpointTls,
+    rpc,
+    simple_auth_client::{AuthMethod, SimpleAuthClient},
+};
+
+#[derive(serde::Deserialize)]
+struct JsonRpcResponse {
+    result: tendermint_rpc::endpoint::block::Response,
+}
 
 /// Client wrapper for authenticated connections
 pub struct AuthClient {
     url: String,
     auth_method: AuthMethod,
     version: String,
+    /// Blocks queued by the subscription
+    buffer: usize,
+    /// TLS configuration of the subscription, for an endpoint with TLS settings of its own
+    tls: Option<Arc<rustls::ClientConfig>>,
+    http: reqwest::Client,
+    keepalive: Keepalive,
 }
 
 impl AuthClient {
@@ -17,42 +33,38 @@ impl AuthClient {
     pub async fn new(
         url: String,
         version: String,
-        username: String,
-        password: String,
+        auth_method: AuthMethod,
+        buffer: usize,
+        tls: Option<&EndpointTls>,
+        keepalive: Keepalive,
     ) -> Result<Self> {
-        let auth_method = AuthMethod::Basic { username, password };
-
         Ok(Self {
             url,
             auth_method,
             version,
+            buffer,
+            tls: tls.map(tls::rustls_config).transpose()?,
+            http: tls::http_client(tls)?.build()?,
+            keepalive,
         })
     }
-}
 
-#[async_trait]
-impl ChainClient for AuthClient {
-    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+    /// Subscribe to the events of the queries
+    async fn subscribe(&self, queries: &[String]) -> Result<BlockSubscription> {
         // Create a new SimpleAuthClient instance for this subscription
-        let client = SimpleAuthClient::new(self.url.clone(), self.auth_method.clone());
-        let mut block_stream = client.subscribe_blocks().await?;
+        let client = SimpleAuthClient::new(
+            self.url.clone(),
+            self.auth_method.clone(),
+            self.tls.clone(),
+            self.keepalive.clone(),
+        );
+        let mut event_stream = client.subscribe(queries, self.version == "0.34").await?;
 
-        // Create a channel to bridge between BlockStream and our Event stream
-        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        // Create a channel to bridge between EventStream and our subscription
+        let (tx, rx) = tokio::sync::mpsc::channel(self.buffer);
 
-        // Spawn a task to convert blocks to events
         tokio::spawn(async move {
-            while let Some(block) = block_stream.next().await {
-                let event = Event {
-                    query: "tm.event='NewBlock'".to_string(),
-                    data: tendermint_rpc::event::EventData::NewBlock {
-                        block: Some(block),
-                        result_begin_block: None,
-                        result_end_block: None,
-                    },
-                    events: None,
-                };
-
+            while let Some(event) = event_stream.next().await {
                 if tx.send(Ok(event)).await.is_err() {
                     break; // Receiver dropped
                 }
@@ -63,11 +75,37 @@ impl ChainClient for AuthClient {
         let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
         Ok(Box::pin(stream))
     }
+}
+
+#[async_trait]
+impl ChainClient for AuthClient {
+    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+        self.subscribe(&[subscription::NEW_BLOCK.to_string()]).await
+    }
+
+    async fn subscribe_txs(&self, query: &str) -> Result<BlockSubscription> {
+        self.subscribe(&subscription::tx_queries(query)).await
+    }
+
+    async fn get_block(&self, height: Height) -> Result<Block> {
+        // SimpleAuthClient only subscribes, fetch the block from the RPC server instead
+        let url = rpc::rpc_url(&self.url).ok_or_else(|| format!("no RPC URL for {}", self.url))?;
+
+        let request = self
+            .http
+            .get(format!("{url}/block"))
+            .query(&[("height", height.to_string())]);
+
+        let response: JsonRpcResponse = rpc::authenticate(request, &self.auth_method)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
 
-    async fn get_block(&self, _height: Height) -> Result<Block> {
-        // SimpleAuthClient doesn't have a get_block method
-        // For now, return an error - this would need to be implemented
-        Err("get_block not implemented for AuthClient".into())
+        Ok(response.result.block)
     }
 
     async fn get_block_results(&self, height: Height) -> Result<BlockResults> {
@@ -1,50 +1,282 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
 use tendermint_rpc::WebSocketClientUrl;
 
-use super::{auth::AuthClient, v034::V034Client, v038::V038Client, ChainClient, Result};
+use super::{
+    auth::AuthClient, grpc::GrpcClient, polling::PollingClient, tls, v034::V034Client,
+    v038::V038Client, v1::V1Client, ChainClient, Keepalive, Result,
+};
+use crate::{
+    config::{EndpointTls, Transport, AUTO_COMET_VERSION, COMET_VERSIONS},
+    rpc,
+    simple_auth_client::AuthMethod,
+};
+
+/// CometBFT versions detected on the nodes, by URL
+static DETECTED: Detected = Detected::new();
+
+/// Versions the nodes reported on their `/status`, queried again only once a connection to the
+/// node failed, so that a mismatch is warned about once rather than on every reconnect
+struct Detected(Mutex<BTreeMap<String, Version>>);
+
+struct Version {
+    version: String,
+    /// Whether the version is queried again on the next connection
+    stale: bool,
+}
+
+impl Detected {
+    const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    /// Version detected on a node, and whether it is still current
+    fn get(&self, node: &str) -> Option<(String, bool)> {
+        let versions = self.0.lock().unwrap();
+        let version = versions.get(node)?;
+        Some((version.version.clone(), !version.stale))
+    }
+
+    fn insert(&self, node: &str, version: String) {
+        let version = Version {
+            version,
+            stale: false,
+        };
+        self.0.lock().unwrap().insert(node.to_string(), version);
+    }
+
+    fn recheck(&self, node: &str) {
+        if let Some(version) = self.0.lock().unwrap().get_mut(node) {
+            version.stale = true;
+        }
+    }
+}
 
-/// Authentication configuration
-#[derive(Clone)]
-pub struct AuthConfig {
-    pub username: String,
-    pub password: String,
+/// Query the version of a node again on the next connection, after the last one failed, such
+/// as when its chain halted for an upgrade
+pub fn recheck_version(ws_url: &WebSocketClientUrl) {
+    DETECTED.recheck(&ws_url.to_string());
 }
 
-/// Create a chain client based on version and authentication requirements
+/// Create a chain client based on version, transport and authentication requirements
+///
+/// The subscriptions of the clients with a buffer of their own queue up to `buffer` blocks, and
+/// those of the HTTP and gRPC transports poll the latest height every `poll_interval`.
+/// Endpoints of CometBFT 0.34 and 0.37 with TLS settings use the authenticated client, the
+/// WebSocket client of tendermint-rpc not taking them. The WebSocket connections are pinged as
+/// `keepalive` sets, except the one of the tendermint-rpc client fetching the blocks of
+/// CometBFT 0.34 and 0.37, which pings every 27 seconds.
+///
+/// The version the node reports on its `/status` is used when `version` is `auto`, and checked
+/// against `version` otherwise, the gRPC transport not using it. It is queried on the first
+/// connection to the node, and on the next one after `recheck_version`.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_client(
     ws_url: &WebSocketClientUrl,
     version: &str,
-    auth: Option<AuthConfig>,
+    auth: AuthMethod,
+    buffer: usize,
+    transport: Transport,
+    poll_interval: Duration,
+    tls: Option<&EndpointTls>,
+    keepalive: Keepalive,
 ) -> Result<Box<dyn ChainClient>> {
+    let version = match transport {
+        Transport::Grpc => version.to_string(),
+        _ => client_version(ws_url, version, &auth, tls).await?,
+    };
+    let version = version.as_str();
+
     tracing::info!("Creating client for version {} at {}", version, ws_url);
 
-    match auth {
-        Some(auth_config) => {
-            // Authenticated connection - use custom auth client
-            tracing::info!("Using authenticated client");
-            let client = AuthClient::new(
-                ws_url.to_string(),
+    match transport {
+        Transport::Http => {
+            tracing::info!("Using PollingClient for version {}", version);
+            let client = PollingClient::new(
+                &ws_url.to_string(),
                 version.to_string(),
-                auth_config.username,
-                auth_config.password,
-            )
-            .await?;
-            Ok(Box::new(client))
+                auth,
+                buffer,
+                poll_interval,
+                tls,
+            )?;
+            return Ok(Box::new(client));
         }
-        None => {
+        Transport::Grpc => {
+            tracing::info!("Using GrpcClient");
+            let client = GrpcClient::new(&ws_url.to_string(), auth, buffer, poll_interval, tls)?;
+            return Ok(Box::new(client));
+        }
+        Transport::WebSocket => {}
+    }
+
+    match auth {
+        AuthMethod::None => {
             // Non-authenticated connection - use version-specific client
             match version {
-                "0.34" | "0.37" => {
+                "0.34" | "0.37" if tls.is_none() => {
                     tracing::info!("Using V034Client for version {}", version);
-                    let client = V034Client::new(ws_url.clone(), version).await?;
+                    let client = V034Client::new(ws_url.clone(), version, keepalive).await?;
                     Ok(Box::new(client))
                 }
                 "0.38" => {
                     tracing::info!("Using V038Client for version 0.38");
-                    let client = V038Client::new(ws_url.to_string()).await?;
+                    let client =
+                        V038Client::new(ws_url.to_string(), buffer, tls, keepalive).await?;
+                    Ok(Box::new(client))
+                }
+                "1.0" => {
+                    tracing::info!("Using V1Client for version 1.0");
+                    let client = V1Client::new(ws_url.to_string(), buffer, tls, keepalive).await?;
                     Ok(Box::new(client))
                 }
+                "0.34" | "0.37" => auth_client(ws_url, version, auth, buffer, tls, keepalive).await,
                 _ => Err(format!("Unsupported CometBFT version: {}", version).into()),
             }
         }
+        auth => auth_client(ws_url, version, auth, buffer, tls, keepalive).await,
+    }
+}
+
+/// Version of the client of a node, as `comet_version` sets it or detected from its `/status`
+async fn client_version(
+    ws_url: &WebSocketClientUrl,
+    configured: &str,
+    auth: &AuthMethod,
+    tls: Option<&EndpointTls>,
+) -> Result<String> {
+    let node = ws_url.to_string();
+    let previous = DETECTED.get(&node);
+
+    if let Some((version, true)) = previous {
+        return select_version(configured, Ok(version.clone()), Some(&version));
+    }
+
+    let detected = match tls::http_client(tls)?.build() {
+        Ok(client) => rpc::comet_version(&client, ws_url, auth).await,
+        Err(e) => Err(e.into()),
+    };
+
+    if let Ok(version) = &detected {
+        DETECTED.insert(&node, version.clone());
+    }
+
+    let previous = previous.map(|(version, _)| version);
+    select_version(configured, detected, previous.as_deref())
+}
+
+/// Version of the client of a node running the `detected` CometBFT version
+///
+/// A configured version is kept, with a warning when the node runs another one or its version
+/// cannot be queried, while `auto` needs a detected version with a client. The version is only
+/// logged when it differs from the one `previous`ly detected on the node.
+fn select_version(
+    configured: &str,
+    detected: Result<String>,
+    previous: Option<&str>,
+) -> Result<String> {
+    match detected {
+        Ok(detected) if configured == AUTO_COMET_VERSION => {
+            let minor = rpc::minor_version(&detected);
+
+            if !COMET_VERSIONS.contains(&minor.as_str()) {
+                return Err(format!(
+                    "the node runs CometBFT {detected}, expected one of {}",
+                    COMET_VERSIONS.join(", ")
+                )
+                .into());
+            }
+
+            if previous != Some(detected.as_str()) {
+                tracing::info!("Detected CometBFT {detected}");
+            }
+
+            Ok(minor)
+        }
+        Ok(detected) => {
+            if rpc::minor_version(&detected) != configured && previous != Some(detected.as_str()) {
+                tracing::warn!(
+                    "The node runs CometBFT {detected}, but comet_version is {configured}, \
+                     its blocks may fail to decode"
+                );
+            }
+
+            Ok(configured.to_string())
+        }
+        Err(e) if configured == AUTO_COMET_VERSION => {
+            Err(format!("failed to detect the CometBFT version of the node: {e}").into())
+        }
+        Err(e) => {
+            tracing::warn!("Failed to check the CometBFT version of the node: {e}");
+            Ok(configured.to_string())
+        }
+    }
+}
+
+/// Authenticated client, subscribing over a WebSocket of its own
+async fn auth_client(
+    ws_url: &WebSocketClientUrl,
+    version: &str,
+    auth: AuthMethod,
+    buffer: usize,
+    tls: Option<&EndpointTls>,
+    keepalive: Keepalive,
+) -> Result<Box<dyn ChainClient>> {
+    tracing::info!("Using authenticated client ({})", auth.kind());
+    let client = AuthClient::new(
+        ws_url.to_string(),
+        version.to_string(),
+        auth,
+        buffer,
+        tls,
+        keepalive,
+    )
+    .await?;
+    Ok(Box::new(client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_version() {
+        let select = |configured, detected: std::result::Result<&str, &str>| -> Result<String> {
+            select_version(
+                configured,
+                detected.map(str::to_string).map_err(|e| e.into()),
+                None,
+            )
+        };
+
+        assert_eq!(select("auto", Ok("v0.38.12")).unwrap(), "0.38");
+        assert_eq!(select("auto", Ok("1.0.0")).unwrap(), "1.0");
+        assert_eq!(select("auto", Ok("v1.1.3")).unwrap(), "1.0");
+        assert!(select("auto", Ok("0.39.0")).is_err());
+        assert!(select("auto", Err("connection refused")).is_err());
+
+        // A configured version is kept, even when the node runs another one
+        assert_eq!(select("0.37", Ok("0.37.4")).unwrap(), "0.37");
+        assert_eq!(select("0.37", Ok("0.38.12")).unwrap(), "0.37");
+        assert_eq!(select("0.34", Err("connection refused")).unwrap(), "0.34");
+    }
+
+    #[test]
+    fn test_detected_versions() {
+        let detected = Detected::new();
+        let node = "wss://rpc.example.com/websocket";
+        assert_eq!(detected.get(node), None);
+
+        detected.insert(node, "0.38.12".to_string());
+        assert_eq!(detected.get(node), Some(("0.38.12".to_string(), true)));
+
+        // The version is kept after a failure, to be compared with the one queried again
+        detected.recheck(node);
+        assert_eq!(detected.get(node), Some(("0.38.12".to_string(), false)));
+
+        detected.insert(node, "1.0.0".to_string());
+        assert_eq!(detected.get(node), Some(("1.0.0".to_string(), true)));
     }
 }
@@ -0,0 +1,356 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use ibc_proto::cosmos::tx::v1beta1::GetTxsEventRequest;
+use prost::Message;
+use tendermint::{block::Height, Block};
+use tendermint_proto::v0_37::types::Block as RawBlock;
+
+use super::{
+    polling::{self, Poll},
+    tls, BlockResults, BlockSubscription, ChainClient, EventAttribute, Result, TxEvent, TxResult,
+};
+use crate::{config::EndpointTls, rpc, simple_auth_client::AuthMethod};
+
+/// Timeout of a call to the gRPC server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Transaction responses of a single page of `GetTxsEvent`
+const TXS_PER_PAGE: u64 = 100;
+
+/// Client of the gRPC services of the Cosmos SDK, for the providers which only expose those
+///
+/// The blocks come from `cosmos.base.tendermint.v1beta1.Service`, polled like the HTTP
+/// transport does, and the results of their transactions from `cosmos.tx.v1beta1.Service`.
+/// Only the transactions are listed by the SDK, the events of the blocks themselves are left
+/// out, which the collector does not read.
+pub struct GrpcClient {
+    grpc: Arc<Grpc>,
+    /// Blocks queued by the subscription
+    buffer: usize,
+    /// Interval between two polls of the latest height
+    poll_interval: Duration,
+}
+
+/// gRPC server of a node, with its credentials
+struct Grpc {
+    client: reqwest::Client,
+    url: String,
+    auth: AuthMethod,
+}
+
+/// `cosmos.base.tendermint.v1beta1.GetBlockByHeightRequest`
+#[derive(Clone, PartialEq, Message)]
+struct GetBlockByHeightRequest {
+    #[prost(int64, tag = "1")]
+    height: i64,
+}
+
+/// `cosmos.base.tendermint.v1beta1.GetBlockByHeightResponse` and `GetLatestBlockResponse`,
+/// leaving out the block ID and the block of the SDK
+#[derive(Clone, PartialEq, Message)]
+struct GetBlockResponse {
+    #[prost(message, optional, tag = "2")]
+    block: Option<RawBlock>,
+}
+
+/// `cosmos.tx.v1beta1.GetTxsEventResponse`, leaving out the transactions and the pagination
+#[derive(Clone, PartialEq, Message)]
+struct GetTxsEventResponse {
+    #[prost(message, repeated, tag = "2")]
+    tx_responses: Vec<TxResponse>,
+    #[prost(uint64, tag = "4")]
+    total: u64,
+}
+
+/// `cosmos.base.abci.v1beta1.TxResponse`, leaving out the logs and the transaction
+#[derive(Clone, PartialEq, Message)]
+struct TxResponse {
+    #[prost(string, tag = "2")]
+    txhash: String,
+    #[prost(uint32, tag = "4")]
+    code: u32,
+    #[prost(message, repeated, tag = "13")]
+    events: Vec<Event>,
+}
+
+/// `tendermint.abci.Event`
+#[derive(Clone, PartialEq, Message)]
+struct Event {
+    #[prost(string, tag = "1")]
+    r#type: String,
+    #[prost(message, repeated, tag = "2")]
+    attributes: Vec<Attribute>,
+}
+
+/// `tendermint.abci.EventAttribute`, whose key and value are bytes up to CometBFT 0.34 and
+/// strings since
+#[derive(Clone, PartialEq, Message)]
+struct Attribute {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+impl Grpc {
+    /// Call a unary method, `path` being `/<service>/<method>`
+    async fn call<Response: Message + Default>(
+        &self,
+        path: &str,
+        request: impl Message,
+    ) -> Result<Response> {
+        let request = self
+            .client
+            .post(format!("{}{path}", self.url))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .timeout(REQUEST_TIMEOUT)
+            .body(encode_frame(&request.encode_to_vec()));
+
+        // The URL may hold credentials
+        let response = rpc::authenticate(request, &self.auth)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.without_url())?;
+
+        // A failed call without a message carries its status in the headers, the status of
+        // the others coming in the trailers
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        if let Some(status) = header("grpc-status").filter(|status| status != "0") {
+            let message = header("grpc-message").unwrap_or_default();
+            return Err(format!("gRPC error {status} calling {path}: {message}").into());
+        }
+
+        let body = response.bytes().await.map_err(|e| e.without_url())?;
+        let message = decode_frame(&body).ok_or_else(|| format!("no response to {path}"))?;
+
+        Ok(Response::decode(message)?)
+    }
+
+    /// Responses of the transactions of a block, over every page of them
+    async fn tx_responses(&self, height: u64) -> Result<Vec<TxResponse>> {
+        let query = format!("tx.height={height}");
+        let mut responses = Vec::new();
+
+        for page in 1.. {
+            // `events` up to SDK 0.47, `query` since 0.50, the other one being ignored
+            #[allow(deprecated)]
+            let response: GetTxsEventResponse = self
+                .call(
+                    "/cosmos.tx.v1beta1.Service/GetTxsEvent",
+                    GetTxsEventRequest {
+                        events: vec![query.clone()],
+                        page,
+                        limit: TXS_PER_PAGE,
+                        query: query.clone(),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+
+            let last = response.tx_responses.is_empty();
+            responses.extend(response.tx_responses);
+
+            if last || responses.len() as u64 >= response.total {
+                break;
+            }
+        }
+
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl Poll for Grpc {
+    async fn latest_height(&self) -> Result<u64> {
+        let response: GetBlockResponse = self
+            .call("/cosmos.base.tendermint.v1beta1.Service/GetLatestBlock", ())
+            .await?;
+
+        let header = response
+            .block
+            .and_then(|block| block.header)
+            .ok_or("missing block header in GetLatestBlock")?;
+
+        Ok(header.height.try_into()?)
+    }
+
+    async fn block(&self, height: u64) -> Result<Block> {
+        let response: GetBlockResponse = self
+            .call(
+                "/cosmos.base.tendermint.v1beta1.Service/GetBlockByHeight",
+                GetBlockByHeightRequest {
+                    height: height.try_into()?,
+                },
+            )
+            .await?;
+
+        let block = response.block.ok_or("missing block in GetBlockByHeight")?;
+
+        Ok(Block::try_from(block)?)
+    }
+}
+
+impl GrpcClient {
+    /// Create a client of the gRPC server behind a WebSocket URL
+    pub fn new(
+        url: &str,
+        auth: AuthMethod,
+        buffer: usize,
+        poll_interval: Duration,
+        tls: Option<&EndpointTls>,
+    ) -> Result<Self> {
+        let url = rpc::rpc_url(url).ok_or_else(|| format!("no gRPC URL for {url}"))?;
+
+        Ok(Self {
+            grpc: Arc::new(Grpc {
+                client: tls::http_client(tls)?.http2_prior_knowledge().build()?,
+                url,
+                auth,
+            }),
+            buffer,
+            poll_interval,
+        })
+    }
+}
+
+#[async_trait]
+impl ChainClient for GrpcClient {
+    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+        polling::subscribe(self.grpc.clone(), self.buffer, self.poll_interval).await
+    }
+
+    async fn get_block(&self, height: Height) -> Result<Block> {
+        self.grpc.block(height.value()).await
+    }
+
+    async fn get_block_results(&self, height: Height) -> Result<BlockResults> {
+        let block = self.grpc.block(height.value()).await?;
+        let responses = self.grpc.tx_responses(height.value()).await?;
+
+        let hashes: Vec<String> = block
+            .data
+            .iter()
+            .map(|tx| crate::tx_format::hash(tx))
+            .collect();
+
+        Ok(BlockResults {
+            height,
+            txs_results: tx_results(&hashes, responses),
+        })
+    }
+
+    fn supports_events(&self) -> bool {
+        true
+    }
+}
+
+/// Results of the transactions of a block in their order in the block, from their responses
+///
+/// The SDK lists the transactions it decodes only, the others having no events.
+fn tx_results(hashes: &[String], responses: Vec<TxResponse>) -> Vec<TxResult> {
+    let mut responses: HashMap<String, TxResponse> = responses
+        .into_iter()
+        .map(|response| (response.txhash.to_uppercase(), response))
+        .collect();
+
+    hashes
+        .iter()
+        .map(|hash| match responses.remove(hash) {
+            Some(response) => TxResult {
+                code: response.code,
+                events: response
+                    .events
+                    .into_iter()
+                    .map(|event| TxEvent {
+                        type_str: event.r#type,
+                        attributes: event
+                            .attributes
+                            .into_iter()
+                            .map(|attribute| EventAttribute {
+                                key: String::from_utf8_lossy(&attribute.key).into_owned(),
+                                value: String::from_utf8_lossy(&attribute.value).into_owned(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            },
+            None => TxResult {
+                code: 0,
+                events: Vec::new(),
+            },
+        })
+        .collect()
+}
+
+/// Length-prefixed message of a gRPC body, never compressed
+fn encode_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(5 + message.len());
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// Message of the first frame of a gRPC body, if complete and not compressed
+fn decode_frame(body: &[u8]) -> Option<&[u8]> {
+    let (&[0, a, b, c, d], message) = body.split_first_chunk::<5>()? else {
+        return None;
+    };
+
+    message.get(..u32::from_be_bytes([a, b, c, d]) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let frame = encode_frame(b"message");
+        assert_eq!(&frame[..5], &[0, 0, 0, 0, 7]);
+        assert_eq!(decode_frame(&frame), Some(&b"message"[..]));
+
+        assert_eq!(decode_frame(&encode_frame(b"")), Some(&b""[..]));
+        assert_eq!(decode_frame(&frame[..8]), None);
+        assert_eq!(decode_frame(&[1, 0, 0, 0, 0]), None);
+        assert_eq!(decode_frame(&[]), None);
+    }
+
+    #[test]
+    fn test_tx_results() {
+        let response = |txhash: &str, code| TxResponse {
+            txhash: txhash.to_string(),
+            code,
+            events: vec![Event {
+                r#type: "send_packet".to_string(),
+                attributes: vec![Attribute {
+                    key: b"packet_sequence".to_vec(),
+                    value: b"7".to_vec(),
+                }],
+            }],
+        };
+
+        let hashes = ["AA".to_string(), "BB".to_string(), "CC".to_string()];
+
+        // Listed out of order, the hashes in lowercase, and BB not decoded by the SDK
+        let results = tx_results(&hashes, vec![response("cc", 5), response("aa", 0)]);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].code, 0);
+        assert_eq!(results[0].events[0].type_str, "send_packet");
+        assert_eq!(results[0].events[0].attributes[0].key, "packet_sequence");
+        assert_eq!(results[0].events[0].attributes[0].value, "7");
+        assert!(results[1].events.is_empty());
+        assert_eq!(results[2].code, 5);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
+
+/// WebSocket pings of the connections to a node
+///
+/// A connection whose last ping is still unanswered when the next one is due is closed as dead,
+/// rather than waiting for the timeout of the blocks.
+#[derive(Clone, Default)]
+pub struct Keepalive {
+    /// Interval between two pings, none disabling them
+    pub interval: Option<Duration>,
+    /// Reports the round-trip time of every pong
+    pub on_rtt: Option<Arc<dyn Fn(Duration) + Send + Sync>>,
+}
+
+impl Keepalive {
+    pub fn new(interval: Duration, on_rtt: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        Self {
+            interval: Some(interval).filter(|interval| !interval.is_zero()),
+            on_rtt: Some(Arc::new(on_rtt)),
+        }
+    }
+}
+
+/// Pings of a single connection
+pub(crate) struct Pinger {
+    ticks: Option<Interval>,
+    on_rtt: Option<Arc<dyn Fn(Duration) + Send + Sync>>,
+    sequence: u64,
+    /// Sequence and time of the ping awaiting its pong
+    awaiting: Option<(u64, Instant)>,
+}
+
+impl Pinger {
+    pub(crate) fn new(keepalive: &Keepalive) -> Self {
+        let ticks = keepalive.interval.map(|interval| {
+            let mut ticks = time::interval_at(Instant::now() + interval, interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticks
+        });
+
+        Self {
+            ticks,
+            on_rtt: keepalive.on_rtt.clone(),
+            sequence: 0,
+            awaiting: None,
+        }
+    }
+
+    /// Wait until the next ping is due, forever when the pings are disabled
+    pub(crate) async fn tick(&mut self) {
+        match &mut self.ticks {
+            Some(ticks) => {
+                ticks.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Payload of the next ping, failing when the last one was not answered
+    pub(crate) fn ping(&mut self) -> super::Result<Vec<u8>> {
+        if let Some((_, sent)) = self.awaiting {
+            return Err(format!("no pong within {:?} of the last ping", sent.elapsed()).into());
+        }
+
+        self.sequence += 1;
+        self.awaiting = Some((self.sequence, Instant::now()));
+
+        Ok(self.sequence.to_be_bytes().to_vec())
+    }
+
+    /// Record a pong, reporting its round-trip time when it answers the last ping
+    pub(crate) fn pong(&mut self, payload: &[u8]) {
+        let Some((sequence, sent)) = self.awaiting else {
+            return;
+        };
+
+        if payload != sequence.to_be_bytes() {
+            return;
+        }
+
+        self.awaiting = None;
+
+        if let Some(on_rtt) = &self.on_rtt {
+            on_rtt(sent.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pinger() {
+        let rtts = Arc::new(Mutex::new(Vec::new()));
+        let keepalive = Keepalive::new(Duration::from_millis(20), {
+            let rtts = rtts.clone();
+            move |rtt| rtts.lock().unwrap().push(rtt)
+        });
+
+        let mut pinger = Pinger::new(&keepalive);
+
+        pinger.tick().await;
+        let first = pinger.ping().unwrap();
+
+        // Pongs of other pings are ignored
+        pinger.pong(b"other");
+        assert!(rtts.lock().unwrap().is_empty());
+
+        pinger.pong(&first);
+        assert_eq!(rtts.lock().unwrap().len(), 1);
+
+        // The next ping is left unanswered
+        pinger.tick().await;
+        pinger.ping().unwrap();
+
+        pinger.tick().await;
+        assert!(pinger.ping().is_err());
+
+        assert!(Keepalive::new(Duration::ZERO, |_| {}).interval.is_none());
+    }
+}
@@ -6,10 +6,17 @@ use tendermint_rpc::{event::Event, Error as RpcError};
 
 pub mod auth;
 pub mod factory;
+pub mod grpc;
+pub mod keepalive;
+pub mod polling;
+pub mod subscription;
+pub mod tls;
 pub mod v034;
 pub mod v038;
+pub mod v1;
 
-pub use factory::{create_client, AuthConfig};
+pub use factory::{create_client, recheck_version};
+pub use keepalive::Keepalive;
 
 /// Result type for client operations
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -24,6 +31,12 @@ pub trait ChainClient: Send + Sync {
     /// Subscribe to new block events
     async fn subscribe_blocks(&self) -> Result<BlockSubscription>;
 
+    /// Subscribe to the headers of new blocks and to the txs matching `query`, in place of the
+    /// whole blocks
+    async fn subscribe_txs(&self, _query: &str) -> Result<BlockSubscription> {
+        Err("tx subscriptions are not supported by this client".into())
+    }
+
     /// Get a specific block by height
     async fn get_block(&self, height: Height) -> Result<Block>;
 
@@ -0,0 +1,229 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::Value;
+use tendermint::{block::Height, Block};
+use tendermint_rpc::event::{Event, EventData};
+use tokio::{sync::mpsc, time};
+
+use super::{tls, v1, BlockResults, BlockSubscription, ChainClient, Result};
+use crate::{config::EndpointTls, rpc, simple_auth_client::AuthMethod};
+
+/// Timeout of a request to the RPC server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client polling the HTTP JSON-RPC of the nodes which do not serve `/websocket`
+///
+/// The subscription polls `/status` every `poll_interval` and fetches the blocks up to the latest
+/// height from `/block`, starting from the latest height like a WebSocket subscription does.
+/// A failed poll is retried on the next one, the collector reconnecting when no block comes.
+pub struct PollingClient {
+    http: Arc<Http>,
+    version: String,
+    /// Blocks queued by the subscription
+    buffer: usize,
+    /// Interval between two polls of the latest height
+    poll_interval: Duration,
+}
+
+/// RPC server of a node, with its credentials
+struct Http {
+    client: reqwest::Client,
+    url: String,
+    auth: AuthMethod,
+}
+
+/// Source of the blocks of a polled subscription
+#[async_trait]
+pub(super) trait Poll: Send + Sync + 'static {
+    async fn latest_height(&self) -> Result<u64>;
+
+    async fn block(&self, height: u64) -> Result<Block>;
+}
+
+/// Subscribe to the blocks after the latest height of a source, polling it every `interval`
+pub(super) async fn subscribe(
+    source: Arc<dyn Poll>,
+    buffer: usize,
+    interval: Duration,
+) -> Result<BlockSubscription> {
+    // The node is reachable when subscribing, as with a WebSocket
+    let latest = source.latest_height().await?;
+
+    let (tx, rx) = mpsc::channel(buffer.max(1));
+    tokio::spawn(poll(source, latest, interval, tx));
+
+    let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+    Ok(Box::pin(stream))
+}
+
+impl Http {
+    /// GET a route, returning the JSON-RPC result
+    async fn get(&self, route: &str, height: Option<u64>) -> Result<Value> {
+        let mut request = self
+            .client
+            .get(format!("{}{route}", self.url))
+            .timeout(REQUEST_TIMEOUT);
+
+        if let Some(height) = height {
+            request = request.query(&[("height", height.to_string())]);
+        }
+
+        // The URL may hold credentials
+        let response: Value = rpc::authenticate(request, &self.auth)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+
+        match response.get("result") {
+            Some(result) if !result.is_null() => Ok(result.clone()),
+            _ => Err(format!("RPC error: {}", response["error"]).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Poll for Http {
+    async fn latest_height(&self) -> Result<u64> {
+        let status = self.get("/status", None).await?;
+
+        let height = status["sync_info"]["latest_block_height"]
+            .as_str()
+            .ok_or("missing latest_block_height in /status")?;
+
+        Ok(height.parse()?)
+    }
+
+    async fn block(&self, height: u64) -> Result<Block> {
+        let result = self.get("/block", Some(height)).await?;
+        let block = result.get("block").ok_or("Missing block in response")?;
+
+        Ok(serde_json::from_value(block.clone())?)
+    }
+}
+
+impl PollingClient {
+    /// Create a client polling the RPC server behind a WebSocket URL
+    pub fn new(
+        url: &str,
+        version: String,
+        auth: AuthMethod,
+        buffer: usize,
+        poll_interval: Duration,
+        tls: Option<&EndpointTls>,
+    ) -> Result<Self> {
+        let url = rpc::rpc_url(url).ok_or_else(|| format!("no RPC URL for {url}"))?;
+
+        Ok(Self {
+            http: Arc::new(Http {
+                client: tls::http_client(tls)?.build()?,
+                url,
+                auth,
+            }),
+            version,
+            buffer,
+            poll_interval,
+        })
+    }
+}
+
+#[async_trait]
+impl ChainClient for PollingClient {
+    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+        subscribe(self.http.clone(), self.buffer, self.poll_interval).await
+    }
+
+    async fn get_block(&self, height: Height) -> Result<Block> {
+        self.http.block(height.value()).await
+    }
+
+    async fn get_block_results(&self, height: Height) -> Result<BlockResults> {
+        let result = self
+            .http
+            .get("/block_results", Some(height.value()))
+            .await?;
+        let mut results = v1::parse_block_results(height, &result);
+
+        // CometBFT 0.34 encodes the attributes of the events in base64
+        if self.version == "0.34" {
+            let engine = base64::engine::general_purpose::STANDARD;
+            let decode = |text: &mut String| {
+                if let Ok(bytes) = engine.decode(text.as_bytes()) {
+                    *text = String::from_utf8_lossy(&bytes).into_owned();
+                }
+            };
+
+            for event in results
+                .txs_results
+                .iter_mut()
+                .flat_map(|result| &mut result.events)
+            {
+                for attribute in &mut event.attributes {
+                    decode(&mut attribute.key);
+                    decode(&mut attribute.value);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn supports_events(&self) -> bool {
+        true
+    }
+}
+
+/// Send the blocks after `latest` as NewBlock events, until the subscription is dropped
+async fn poll(
+    source: Arc<dyn Poll>,
+    latest: u64,
+    interval: Duration,
+    tx: mpsc::Sender<std::result::Result<Event, tendermint_rpc::Error>>,
+) {
+    let mut next = latest + 1;
+    let mut interval = time::interval(interval);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+    while !tx.is_closed() {
+        interval.tick().await;
+
+        let latest = match source.latest_height().await {
+            Ok(latest) => latest,
+            Err(e) => {
+                tracing::warn!("Failed to poll the latest height: {}", e);
+                continue;
+            }
+        };
+
+        while next <= latest {
+            let block = match source.block(next).await {
+                Ok(block) => block,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch block {}: {}", next, e);
+                    break;
+                }
+            };
+
+            let event = Event {
+                query: "tm.event='NewBlock'".to_string(),
+                data: EventData::NewBlock {
+                    block: Some(block),
+                    result_begin_block: None,
+                    result_end_block: None,
+                },
+                events: None,
+            };
+
+            if tx.send(Ok(event)).await.is_err() {
+                return;
+            }
+
+            next += 1;
+        }
+    }
+}
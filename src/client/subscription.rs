@@ -0,0 +1,273 @@
+use base64::Engine;
+use serde_json::Value;
+use tendermint::{abci, block::Header, Block};
+use tendermint_rpc::event::{self, Event, EventData, TxInfo};
+
+use super::{BlockResults, EventAttribute, Result, TxEvent, TxResult};
+
+/// Query of the new blocks
+pub const NEW_BLOCK: &str = "tm.event='NewBlock'";
+
+/// Query of the headers of the new blocks
+pub const NEW_BLOCK_HEADER: &str = "tm.event='NewBlockHeader'";
+
+/// Queries of a tx subscription: the headers of the blocks, which keep track of the chain while
+/// no tx matches, and the txs matching `filter`
+pub fn tx_queries(filter: &str) -> Vec<String> {
+    vec![
+        NEW_BLOCK_HEADER.to_string(),
+        format!("tm.event='Tx' AND {filter}"),
+    ]
+}
+
+/// Event of a message of a subscription, none for the responses and the events which fail to
+/// parse
+///
+/// The `NewBlockHeader` events, which tendermint-rpc does not know, are generic JSON events.
+/// The attributes of the events of the txs are base64-encoded up to CometBFT 0.34.
+pub fn parse_event(text: &str, base64_attributes: bool) -> Option<Event> {
+    let message: Value = serde_json::from_str(text).ok()?;
+    let result = message.get("result")?;
+    let data = result.get("data")?;
+    let value = data.get("value")?;
+
+    let data = match data.get("type")?.as_str()? {
+        "tendermint/event/NewBlock" => EventData::NewBlock {
+            block: Some(serde_json::from_value::<Block>(value.get("block")?.clone()).ok()?),
+            result_begin_block: None,
+            result_end_block: None,
+        },
+        "tendermint/event/NewBlockHeader" => EventData::GenericJsonEvent(value.clone()),
+        "tendermint/event/Tx" => EventData::Tx {
+            tx_result: tx_info(value.get("TxResult")?, base64_attributes)?,
+        },
+        _ => return None,
+    };
+
+    let query = result
+        .get("query")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    Some(Event {
+        query: query.to_string(),
+        data,
+        events: None,
+    })
+}
+
+/// Header of a `NewBlockHeader` event
+pub fn header(event: &Event) -> Option<Header> {
+    let EventData::GenericJsonEvent(value) = &event.data else {
+        return None;
+    };
+
+    serde_json::from_value(value.get("header")?.clone()).ok()
+}
+
+/// Results of the tx of a `Tx` event, as those of a block holding it alone
+///
+/// The events of the subscriptions leave out the code of the tx, which is reported as 0.
+pub fn tx_results(tx: &TxInfo) -> Result<BlockResults> {
+    let events = tx
+        .result
+        .events
+        .iter()
+        .map(|event| TxEvent {
+            type_str: event.kind.clone(),
+            attributes: event
+                .attributes
+                .iter()
+                .map(|attr| EventAttribute {
+                    key: attr.key.clone(),
+                    value: attr.value.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(BlockResults {
+        height: tx.height.try_into()?,
+        txs_results: vec![TxResult { code: 0, events }],
+    })
+}
+
+/// Tx of a `Tx` event, whose events without a type are skipped
+fn tx_info(tx_result: &Value, base64_attributes: bool) -> Option<TxInfo> {
+    let height = match tx_result.get("height")? {
+        Value::String(height) => height.parse().ok()?,
+        height => height.as_i64()?,
+    };
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let tx = engine.decode(tx_result.get("tx")?.as_str()?).ok()?;
+
+    let events = tx_result
+        .pointer("/result/events")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|event| abci_event(event, base64_attributes))
+        .collect();
+
+    Some(TxInfo {
+        height,
+        index: tx_result.get("index").and_then(Value::as_i64),
+        tx,
+        result: event::TxResult {
+            log: None,
+            gas_wanted: None,
+            gas_used: None,
+            events,
+        },
+    })
+}
+
+/// Event of a tx, whose attributes without a key are skipped and without a value are empty
+fn abci_event(event: &Value, base64_attributes: bool) -> Option<abci::Event> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let text = |value: Option<&Value>| {
+        let value = value.and_then(Value::as_str).unwrap_or_default();
+
+        if !base64_attributes {
+            return Some(value.to_string());
+        }
+
+        let bytes = engine.decode(value).ok()?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let attributes = event
+        .get("attributes")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|attr| {
+            Some(abci::EventAttribute {
+                key: text(Some(attr.get("key")?))?,
+                value: text(attr.get("value"))?,
+                index: attr.get("index").and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect();
+
+    Some(abci::Event {
+        kind: event.get("type")?.as_str()?.to_string(),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn message(kind: &str, value: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": "chainpulse-v038-2",
+            "result": {
+                "query": "tm.event='Tx' AND message.module='ibc'",
+                "data": {"type": kind, "value": value},
+                "events": {}
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_tx_event() {
+        let tx = |attributes: Value| {
+            json!({
+                "TxResult": {
+                    "height": "12",
+                    "index": 1,
+                    "tx": "AQID",
+                    "result": {
+                        "events": [
+                            {"type": "send_packet", "attributes": attributes},
+                            {"attributes": []}
+                        ]
+                    }
+                }
+            })
+        };
+
+        // CometBFT 0.34 encodes the attributes in base64
+        let text = message(
+            "tendermint/event/Tx",
+            tx(json!([
+                {"key": "cGFja2V0X3NlcXVlbmNl", "value": "Nw==", "index": true},
+                {"key": "cGFja2V0X2RhdGFfaGV4", "value": null}
+            ])),
+        );
+        let event = parse_event(&text, true).unwrap();
+        assert_eq!(event.query, "tm.event='Tx' AND message.module='ibc'");
+
+        let EventData::Tx { tx_result } = &event.data else {
+            panic!("not a Tx event: {event:?}");
+        };
+        assert_eq!(tx_result.height, 12);
+        assert_eq!(tx_result.index, Some(1));
+        assert_eq!(tx_result.tx, [1, 2, 3]);
+
+        let results = tx_results(tx_result).unwrap();
+        assert_eq!(results.height.value(), 12);
+        assert_eq!(results.txs_results.len(), 1);
+
+        let events = &results.txs_results[0].events;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].type_str, "send_packet");
+
+        let attributes: Vec<_> = events[0]
+            .attributes
+            .iter()
+            .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            [("packet_sequence", "7"), ("packet_data_hex", "")]
+        );
+
+        // Since CometBFT 0.37 they are strings
+        let text = message(
+            "tendermint/event/Tx",
+            tx(json!([{"key": "packet_sequence", "value": "7", "index": true}])),
+        );
+        let event = parse_event(&text, false).unwrap();
+        let EventData::Tx { tx_result } = &event.data else {
+            panic!("not a Tx event: {event:?}");
+        };
+        assert_eq!(
+            tx_result.result.events[0].attributes[0].key,
+            "packet_sequence"
+        );
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let text = message(
+            "tendermint/event/NewBlockHeader",
+            json!({"header": {"height": "12"}, "num_txs": "0"}),
+        );
+        let event = parse_event(&text, false).unwrap();
+        assert!(matches!(event.data, EventData::GenericJsonEvent(_)));
+
+        // Not a valid header
+        assert!(header(&event).is_none());
+
+        // Responses, other events and blocks which fail to parse
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": {}}).to_string();
+        assert!(parse_event(&response, false).is_none());
+        assert!(parse_event(&message("tendermint/event/Vote", json!({})), false).is_none());
+        assert!(parse_event(
+            &message("tendermint/event/NewBlock", json!({"block": {}})),
+            false
+        )
+        .is_none());
+        assert!(parse_event("not json", false).is_none());
+    }
+}
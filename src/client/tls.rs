@@ -0,0 +1,85 @@
+use std::{path::Path, sync::Arc};
+
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+
+use super::Result;
+use crate::config::EndpointTls;
+
+/// rustls configuration of the WebSocket connections to the nodes of an endpoint
+pub fn rustls_config(tls: &EndpointTls) -> Result<Arc<rustls::ClientConfig>> {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let mut roots = rustls::RootCertStore::empty();
+
+    match &tls.ca {
+        Some(ca) => {
+            for cert in certificates(ca)? {
+                roots.add(cert)?;
+            }
+        }
+        None => {
+            for cert in rustls_native_certs::load_native_certs()? {
+                let _ = roots.add(cert);
+            }
+        }
+    }
+
+    let config = rustls::ClientConfig::builder().with_root_certificates(roots);
+
+    let config = match (&tls.cert, &tls.key) {
+        (Some(cert), Some(key)) => {
+            let key = PrivateKeyDer::from_pem_file(key)
+                .map_err(|e| format!("failed to read {}: {e}", key.display()))?;
+
+            config.with_client_auth_cert(certificates(cert)?, key)?
+        }
+        _ => config.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// HTTP client builder of the requests to the nodes of an endpoint, presenting its client
+/// certificate and verifying the nodes with its CAs
+pub fn http_client(tls: Option<&EndpointTls>) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+
+    let Some(tls) = tls else {
+        return Ok(builder);
+    };
+
+    if let Some(ca) = &tls.ca {
+        builder = builder.tls_built_in_root_certs(false);
+
+        for cert in reqwest::Certificate::from_pem_bundle(&read(ca)?)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if let (Some(cert), Some(key)) = (&tls.cert, &tls.key) {
+        let mut pem = read(cert)?;
+        pem.push(b'\n');
+        pem.extend(read(key)?);
+
+        builder = builder.identity(reqwest::Identity::from_pem(&pem)?);
+    }
+
+    Ok(builder)
+}
+
+/// Certificates of a PEM file
+fn certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+
+    if certs.is_empty() {
+        return Err(format!("no certificate in {}", path.display()).into());
+    }
+
+    Ok(certs)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()).into())
+}
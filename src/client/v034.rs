@@ -1,30 +1,39 @@
 use async_trait::async_trait;
 use tendermint::{block::Height, Block};
-use tendermint_rpc::{
-    client::CompatMode,
-    query::{EventType, Query},
-    Client, SubscriptionClient, WebSocketClient, WebSocketClientUrl,
-};
+use tendermint_rpc::{client::CompatMode, Client, WebSocketClient, WebSocketClientUrl};
 
 use super::{
-    BlockResults, BlockSubscription, ChainClient, EventAttribute, Result, TxEvent, TxResult,
+    subscription, BlockResults, BlockSubscription, ChainClient, EventAttribute, Keepalive, Result,
+    TxEvent, TxResult,
 };
+use crate::simple_auth_client::{AuthMethod, SimpleAuthClient};
 
 /// Client for v0.34 and v0.37 protocols using tendermint-rs v0.32
+///
+/// The subscriptions go over a WebSocket of their own, pinged as `keepalive` sets, as
+/// tendermint-rpc neither knows the `NewBlockHeader` events nor reports its pings. Its
+/// connection, which fetches the blocks and their results, pings every 27 seconds.
 pub struct V034Client {
     client: WebSocketClient,
     compat_mode: CompatMode,
+    url: String,
+    keepalive: Keepalive,
 }
 
 impl V034Client {
     /// Create a new v0.34/v0.37 client
-    pub async fn new(ws_url: WebSocketClientUrl, version: &str) -> Result<Self> {
+    pub async fn new(
+        ws_url: WebSocketClientUrl,
+        version: &str,
+        keepalive: Keepalive,
+    ) -> Result<Self> {
         let compat_mode = match version {
             "0.34" => CompatMode::V0_34,
             "0.37" => CompatMode::V0_37,
             _ => return Err(format!("Unsupported version for V034Client: {}", version).into()),
         };
 
+        let url = ws_url.to_string();
         let (client, driver) = WebSocketClient::builder(ws_url)
             .compat_mode(compat_mode)
             .build()
@@ -36,16 +45,40 @@ impl V034Client {
         Ok(Self {
             client,
             compat_mode,
+            url,
+            keepalive,
         })
     }
+
+    /// Subscribe to the events of the queries
+    async fn subscribe(&self, queries: &[String]) -> Result<BlockSubscription> {
+        let client = SimpleAuthClient::new(
+            self.url.clone(),
+            AuthMethod::None,
+            None,
+            self.keepalive.clone(),
+        );
+        let events = client
+            .subscribe(queries, self.compat_mode == CompatMode::V0_34)
+            .await?;
+
+        let stream = futures::stream::unfold(events, |mut events| async move {
+            let event = events.next().await?;
+            Some((Ok(event), events))
+        });
+
+        Ok(Box::pin(stream))
+    }
 }
 
 #[async_trait]
 impl ChainClient for V034Client {
     async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
-        let query = Query::from(EventType::NewBlock);
-        let subscription = self.client.subscribe(query).await?;
-        Ok(Box::pin(subscription))
+        self.subscribe(&[subscription::NEW_BLOCK.to_string()]).await
+    }
+
+    async fn subscribe_txs(&self, query: &str) -> Result<BlockSubscription> {
+        self.subscribe(&subscription::tx_queries(query)).await
     }
 
     async fn get_block(&self, height: Height) -> Result<Block> {
@@ -106,3 +139,67 @@ impl ChainClient for V034Client {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use tokio::{net::TcpListener, time};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscription_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/websocket", listener.local_addr().unwrap());
+
+        // Answers the subscriptions and, as it reads them, the pings, but sends no event
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = accept_async(stream).await.unwrap();
+
+                    while let Some(Ok(message)) = ws.next().await {
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+
+                        let request: Value = serde_json::from_str(&text).unwrap();
+                        let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": {}});
+                        ws.send(Message::Text(response.to_string())).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let rtts = Arc::new(Mutex::new(Vec::new()));
+        let keepalive = Keepalive::new(Duration::from_millis(20), {
+            let rtts = rtts.clone();
+            move |rtt| rtts.lock().unwrap().push(rtt)
+        });
+
+        let client = V034Client::new(
+            WebSocketClientUrl::from_str(&url).unwrap(),
+            "0.37",
+            keepalive,
+        )
+        .await
+        .unwrap();
+        let mut subscription = client.subscribe_blocks().await.unwrap();
+
+        // No block comes, the pings being answered meanwhile
+        assert!(
+            time::timeout(Duration::from_millis(200), subscription.next())
+                .await
+                .is_err()
+        );
+        assert!(!rtts.lock().unwrap().is_empty());
+    }
+}
@@ -2,34 +2,178 @@ use async_trait::async_trait;
 use futures::{SinkExt, StreamExt};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tendermint::{block::Height, Block};
 use tendermint_rpc::event::Event;
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{
+    connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream,
+};
 
 use super::{
-    BlockResults, BlockSubscription, ChainClient, EventAttribute, Result, TxEvent, TxResult,
+    keepalive::{Keepalive, Pinger},
+    subscription, tls, BlockResults, BlockSubscription, ChainClient, EventAttribute, Result,
+    TxEvent, TxResult,
 };
+use crate::config::EndpointTls;
+
+/// Attempts of a request whose connection closes before its response
+const MAX_ATTEMPTS: usize = 3;
+
+/// Time a response is awaited before its request fails
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Requests awaiting their response, by JSON-RPC id
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<Result<Value>>>>>;
 
 /// Client for v0.38 protocol with custom implementation
+///
+/// The RPC calls share a single WebSocket connection, opened again by the next call once it
+/// closes, while the subscription has one of its own.
 pub struct V038Client {
     url: String,
     request_id: Arc<AtomicU64>,
+    /// Blocks queued by the subscription
+    buffer: usize,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+    /// TLS connector of an endpoint with TLS settings of its own
+    connector: Option<Connector>,
+    keepalive: Keepalive,
+    /// Time a response is awaited, `REQUEST_TIMEOUT` but in the tests
+    request_timeout: Duration,
+}
+
+/// WebSocket connection multiplexing the RPC calls, driven by a task of its own which matches
+/// the responses to the requests by JSON-RPC id
+#[derive(Clone)]
+struct Connection {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+}
+
+impl Connection {
+    fn open(ws: WebSocketStream<MaybeTlsStream<TcpStream>>, keepalive: &Keepalive) -> Self {
+        let (outgoing, rx) = mpsc::unbounded_channel();
+        let pending = Pending::default();
+
+        tokio::spawn(drive(ws, rx, pending.clone(), Pinger::new(keepalive)));
+
+        Self { outgoing, pending }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.outgoing.is_closed()
+    }
+}
+
+/// Send the requests queued on a connection and route its responses until it closes, it does
+/// not answer a ping, or the client is dropped
+async fn drive(
+    mut ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    pending: Pending,
+    mut pinger: Pinger,
+) {
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if let Err(e) = ws.send(message).await {
+                        tracing::warn!("RPC connection failed: {}", e);
+                        break;
+                    }
+                }
+                None => {
+                    let _ = ws.close(None).await;
+                    break;
+                }
+            },
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => respond(&pending, &text),
+                Some(Ok(Message::Pong(payload))) => pinger.pong(&payload),
+                Some(Ok(Message::Close(_))) | None => {
+                    tracing::debug!("RPC connection closed by the node");
+                    break;
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    tracing::warn!("RPC connection failed: {}", e);
+                    break;
+                }
+            },
+            _ = pinger.tick() => {
+                let sent = match pinger.ping() {
+                    Ok(payload) => ws.send(Message::Ping(payload)).await.map_err(Into::into),
+                    Err(e) => Err(e),
+                };
+
+                if let Err(e) = sent {
+                    tracing::warn!("RPC connection dead: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    // Closed first, so that the requests which are not pending yet fail to be queued rather
+    // than wait for a response which never comes, the pending ones being sent again
+    outgoing.close();
+    pending.lock().unwrap().clear();
+}
+
+/// Hand a response to the request with its id
+fn respond(pending: &Pending, text: &str) {
+    let Ok(response) = serde_json::from_str::<JsonRpcResponse>(text) else {
+        return;
+    };
+
+    let id = match &response.id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    };
+
+    let Some(request) = pending.lock().unwrap().remove(&id) else {
+        return;
+    };
+
+    let result = match response.error {
+        Some(error) => Err(format!("RPC error: {} - {}", error.code, error.message).into()),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    };
+
+    let _ = request.send(result);
 }
 
 impl V038Client {
     /// Create a new v0.38 client
-    pub async fn new(url: String) -> Result<Self> {
+    pub async fn new(
+        url: String,
+        buffer: usize,
+        tls: Option<&EndpointTls>,
+        keepalive: Keepalive,
+    ) -> Result<Self> {
         // Initialize rustls crypto provider if not already done
         let _ = rustls::crypto::ring::default_provider().install_default();
 
+        let connector = match tls {
+            Some(tls) => Some(Connector::Rustls(tls::rustls_config(tls)?)),
+            None => None,
+        };
+
         Ok(Self {
             url,
             request_id: Arc::new(AtomicU64::new(1)),
+            buffer,
+            connection: Default::default(),
+            connector,
+            keepalive,
+            request_timeout: REQUEST_TIMEOUT,
         })
     }
 
@@ -41,57 +185,126 @@ impl V038Client {
 
     /// Create a new WebSocket connection
     async fn connect(&self) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-        let (ws_stream, _) = connect_async(&self.url).await?;
+        let (ws_stream, _) =
+            connect_async_tls_with_config(&self.url, None, false, self.connector.clone()).await?;
         Ok(ws_stream)
     }
 
-    /// Send JSON-RPC request and get response
-    async fn request(&self, method: &str, params: Value) -> Result<Value> {
-        let mut ws = self.connect().await?;
+    /// Connection of the RPC calls, opened again once its driver ended or it was dropped
+    async fn connection(&self) -> Result<Connection> {
+        let mut connection = self.connection.lock().await;
 
-        let request = json!({
-            "jsonrpc": "2.0",
-            "id": self.next_request_id(),
-            "method": method,
-            "params": params
-        });
+        if let Some(open) = connection.as_ref().filter(|open| !open.is_closed()) {
+            return Ok(open.clone());
+        }
 
-        ws.send(Message::Text(request.to_string())).await?;
+        let open = Connection::open(self.connect().await?, &self.keepalive);
+        *connection = Some(open.clone());
 
-        while let Some(msg) = ws.next().await {
-            match msg? {
-                Message::Text(text) => {
-                    let response: JsonRpcResponse = serde_json::from_str(&text)?;
-                    if let Some(error) = response.error {
-                        return Err(format!("RPC error: {} - {}", error.code, error.message).into());
-                    }
-                    return Ok(response.result.unwrap_or(Value::Null));
+        Ok(open)
+    }
+
+    /// Forget a connection which left a request unanswered, so that the next call opens a new
+    /// one, its driver ending once the calls still using it are done
+    async fn drop_connection(&self, stale: &Connection) {
+        let mut connection = self.connection.lock().await;
+
+        if connection
+            .as_ref()
+            .is_some_and(|open| Arc::ptr_eq(&open.pending, &stale.pending))
+        {
+            *connection = None;
+        }
+    }
+
+    /// Send JSON-RPC request and get response, shared with the CometBFT 1.x client
+    ///
+    /// A request whose connection closes before its response is sent again on a new one, up to
+    /// `MAX_ATTEMPTS` times. One left unanswered drops its connection, which the node may have
+    /// stopped serving without closing it.
+    pub(super) async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let mut attempt = 1;
+
+        loop {
+            let connection = self.connection().await?;
+
+            let id = self.next_request_id();
+            let (tx, rx) = oneshot::channel();
+            connection.pending.lock().unwrap().insert(id.clone(), tx);
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params
+            });
+
+            if connection
+                .outgoing
+                .send(Message::Text(request.to_string()))
+                .is_err()
+            {
+                connection.pending.lock().unwrap().remove(&id);
+            }
+
+            match time::timeout(self.request_timeout, rx).await {
+                Ok(Ok(result)) => return result,
+                Ok(Err(_)) if attempt < MAX_ATTEMPTS => {
+                    tracing::debug!("RPC connection closed during `{}`, retrying", method);
+                    attempt += 1;
+                }
+                Ok(Err(_)) => {
+                    return Err(format!(
+                        "RPC connection closed during `{}` {} times",
+                        method, MAX_ATTEMPTS
+                    )
+                    .into())
+                }
+                Err(_) => {
+                    connection.pending.lock().unwrap().remove(&id);
+                    self.drop_connection(&connection).await;
+                    return Err(format!(
+                        "No response to `{}` within {} seconds",
+                        method,
+                        self.request_timeout.as_secs()
+                    )
+                    .into());
                 }
-                _ => continue,
             }
         }
-
-        Err("No response received".into())
     }
-}
 
-#[async_trait]
-impl ChainClient for V038Client {
-    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
-        let (tx, rx) = mpsc::channel(100);
+    /// Subscribe to the events of the queries over a connection of its own
+    fn subscribe(&self, queries: Vec<String>) -> BlockSubscription {
+        let (tx, rx) = mpsc::channel(self.buffer);
         let url = self.url.clone();
+        let connector = self.connector.clone();
         let request_id = self.request_id.clone();
+        let pinger = Pinger::new(&self.keepalive);
 
         // Spawn subscription handler
         tokio::spawn(async move {
-            if let Err(e) = handle_subscription(url, request_id, tx).await {
+            let subscription = handle_subscription(url, connector, request_id, pinger, queries, tx);
+
+            if let Err(e) = subscription.await {
                 tracing::error!("Subscription error: {}", e);
             }
         });
 
         // Convert receiver to stream
         let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-        Ok(Box::pin(stream))
+        Box::pin(stream)
+    }
+}
+
+#[async_trait]
+impl ChainClient for V038Client {
+    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+        Ok(self.subscribe(vec![subscription::NEW_BLOCK.to_string()]))
+    }
+
+    async fn subscribe_txs(&self, query: &str) -> Result<BlockSubscription> {
+        Ok(self.subscribe(subscription::tx_queries(query)))
     }
 
     async fn get_block(&self, height: Height) -> Result<Block> {
@@ -202,60 +415,56 @@ fn parse_v038_event(event: &Value) -> TxEvent {
     }
 }
 
-/// Handle WebSocket subscription for new blocks
+/// Handle WebSocket subscription to the events of the queries, until the connection closes, it
+/// does not answer a ping, or the subscription is dropped
 async fn handle_subscription(
     url: String,
+    connector: Option<Connector>,
     request_id: Arc<AtomicU64>,
+    mut pinger: Pinger,
+    queries: Vec<String>,
     tx: mpsc::Sender<std::result::Result<Event, tendermint_rpc::Error>>,
 ) -> Result<()> {
-    let (mut ws, _) = connect_async(&url).await?;
-
-    // Subscribe to NewBlock events
-    let id = request_id.fetch_add(1, Ordering::SeqCst);
-    let subscribe_request = json!({
-        "jsonrpc": "2.0",
-        "id": format!("chainpulse-v038-{}", id),
-        "method": "subscribe",
-        "params": {
-            "query": "tm.event='NewBlock'"
-        }
-    });
+    let (mut ws, _) = connect_async_tls_with_config(&url, None, false, connector).await?;
+
+    for query in queries {
+        let id = request_id.fetch_add(1, Ordering::SeqCst);
+        let subscribe_request = json!({
+            "jsonrpc": "2.0",
+            "id": format!("chainpulse-v038-{}", id),
+            "method": "subscribe",
+            "params": {
+                "query": query
+            }
+        });
+
+        ws.send(Message::Text(subscribe_request.to_string()))
+            .await?;
+    }
 
-    ws.send(Message::Text(subscribe_request.to_string()))
-        .await?;
+    loop {
+        let msg = tokio::select! {
+            msg = ws.next() => match msg {
+                Some(msg) => msg?,
+                None => break,
+            },
+            _ = pinger.tick() => {
+                ws.send(Message::Ping(pinger.ping()?)).await?;
+                continue;
+            }
+        };
 
-    while let Some(msg) = ws.next().await {
-        match msg? {
+        match msg {
             Message::Text(text) => {
-                if let Ok(response) = serde_json::from_str::<Value>(&text) {
-                    if let Some(result) = response.get("result") {
-                        if let Some(data) = result.get("data") {
-                            // Extract block data and construct Event manually
-                            if let Some(value) = data.get("value") {
-                                if let Some(block_json) = value.get("block") {
-                                    // Try to parse the block
-                                    if let Ok(block_str) = serde_json::to_string(block_json) {
-                                        if let Ok(block) = serde_json::from_str::<Block>(&block_str)
-                                        {
-                                            // Construct Event manually
-                                            let event = Event {
-                                                query: "tm.event='NewBlock'".to_string(),
-                                                data: tendermint_rpc::event::EventData::NewBlock {
-                                                    block: Some(block),
-                                                    result_begin_block: None,
-                                                    result_end_block: None,
-                                                },
-                                                events: None,
-                                            };
-                                            let _ = tx.send(Ok(event)).await;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+                let Some(event) = subscription::parse_event(&text, false) else {
+                    continue;
+                };
+
+                if tx.send(Ok(event)).await.is_err() {
+                    return Ok(());
                 }
             }
+            Message::Pong(payload) => pinger.pong(&payload),
             Message::Close(_) => break,
             _ => continue,
         }
@@ -268,7 +477,7 @@ async fn handle_subscription(
 #[derive(Debug, Deserialize)]
 struct JsonRpcResponse {
     jsonrpc: String,
-    id: String,
+    id: Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     result: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -283,3 +492,112 @@ struct JsonRpcError {
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    fn answer(text: &str) -> Message {
+        let request: Value = serde_json::from_str(text).unwrap();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": request["method"],
+        });
+        Message::Text(response.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_request_multiplexing() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/websocket", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // The first connection closes before responding, its requests being sent again
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            ws.next().await.unwrap().unwrap();
+            drop(ws);
+
+            // The second one answers the first two requests in reverse order, and every later
+            // one as it comes, no other connection being accepted
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+
+            let mut requests = Vec::new();
+            while requests.len() < 2 {
+                requests.push(ws.next().await.unwrap().unwrap().into_text().unwrap());
+            }
+            for text in requests.iter().rev() {
+                ws.send(answer(text)).await.unwrap();
+            }
+
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                ws.send(answer(&text)).await.unwrap();
+            }
+        });
+
+        let client = V038Client::new(url, 1, None, Keepalive::default())
+            .await
+            .unwrap();
+
+        let requests = async {
+            let (block, block_results) = tokio::join!(
+                client.request("block", json!({})),
+                client.request("block_results", json!({})),
+            );
+
+            assert_eq!(block.unwrap(), json!("block"));
+            assert_eq!(block_results.unwrap(), json!("block_results"));
+            assert_eq!(
+                client.request("status", json!({})).await.unwrap(),
+                json!("status")
+            );
+        };
+
+        time::timeout(Duration::from_secs(5), requests)
+            .await
+            .expect("requests answered on the second connection");
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/websocket", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // The first connection reads the request but never answers, nor closes
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut silent = accept_async(stream).await.unwrap();
+            silent.next().await.unwrap().unwrap();
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                ws.send(answer(&text)).await.unwrap();
+            }
+
+            drop(silent);
+        });
+
+        let mut client = V038Client::new(url, 1, None, Keepalive::default())
+            .await
+            .unwrap();
+        client.request_timeout = Duration::from_millis(200);
+
+        let requests = async {
+            assert!(client.request("block", json!({})).await.is_err());
+            assert_eq!(
+                client.request("status", json!({})).await.unwrap(),
+                json!("status")
+            );
+        };
+
+        time::timeout(Duration::from_secs(5), requests)
+            .await
+            .expect("request answered on a new connection");
+    }
+}
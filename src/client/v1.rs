@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tendermint::{block::Height, Block};
+
+use super::{
+    keepalive::Keepalive, v038::V038Client, BlockResults, BlockSubscription, ChainClient,
+    EventAttribute, Result, TxEvent, TxResult,
+};
+use crate::config::EndpointTls;
+
+/// Client for CometBFT 1.x
+///
+/// CometBFT 1.x serves the blocks and their subscription over the same JSON-RPC as 0.38, which
+/// this client shares with `V038Client`, but the results of its blocks are decoded leniently:
+/// the results may be listed under `tx_results`, their code may be encoded as a string, and
+/// event attributes may come without a value.
+pub struct V1Client {
+    inner: V038Client,
+}
+
+impl V1Client {
+    /// Create a new CometBFT 1.x client
+    pub async fn new(
+        url: String,
+        buffer: usize,
+        tls: Option<&EndpointTls>,
+        keepalive: Keepalive,
+    ) -> Result<Self> {
+        Ok(Self {
+            inner: V038Client::new(url, buffer, tls, keepalive).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl ChainClient for V1Client {
+    async fn subscribe_blocks(&self) -> Result<BlockSubscription> {
+        self.inner.subscribe_blocks().await
+    }
+
+    async fn subscribe_txs(&self, query: &str) -> Result<BlockSubscription> {
+        self.inner.subscribe_txs(query).await
+    }
+
+    async fn get_block(&self, height: Height) -> Result<Block> {
+        self.inner.get_block(height).await
+    }
+
+    async fn get_block_results(&self, height: Height) -> Result<BlockResults> {
+        let params = json!({
+            "height": height.to_string(),
+        });
+
+        let result = self.inner.request("block_results", params).await?;
+
+        Ok(parse_block_results(height, &result))
+    }
+
+    fn supports_events(&self) -> bool {
+        true
+    }
+}
+
+/// Parse the results of a block, skipping the events which cannot be decoded
+pub(super) fn parse_block_results(height: Height, result: &Value) -> BlockResults {
+    let txs_results = result
+        .get("txs_results")
+        .or_else(|| result.get("tx_results"))
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|tx_result| {
+            let code = match tx_result.get("code") {
+                Some(Value::String(code)) => code.parse().unwrap_or(0),
+                Some(code) => code.as_u64().unwrap_or(0) as u32,
+                None => 0,
+            };
+
+            let events = tx_result
+                .get("events")
+                .and_then(|v| v.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(parse_event)
+                .collect();
+
+            TxResult { code, events }
+        })
+        .collect();
+
+    BlockResults {
+        height,
+        txs_results,
+    }
+}
+
+/// Parse an event, whose attributes without a key are skipped and without a value are empty
+fn parse_event(event: &Value) -> Option<TxEvent> {
+    let type_str = event.get("type")?.as_str()?.to_string();
+
+    let attributes = event
+        .get("attributes")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|attr| {
+            let key = attr.get("key")?.as_str()?.to_string();
+            let value = attr
+                .get("value")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+
+            Some(EventAttribute { key, value })
+        })
+        .collect();
+
+    Some(TxEvent {
+        type_str,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_results() {
+        let result = json!({
+            "height": "12",
+            "tx_results": [
+                {
+                    "code": "5",
+                    "events": [
+                        {
+                            "type": "send_packet",
+                            "attributes": [
+                                {"key": "packet_sequence", "value": "7", "index": true},
+                                {"key": "packet_data_hex", "value": null},
+                                {"value": "orphan"}
+                            ]
+                        },
+                        {"attributes": []}
+                    ]
+                },
+                {"code": 0}
+            ],
+            "finalize_block_events": []
+        });
+
+        let results = parse_block_results(Height::from(12u32), &result);
+
+        assert_eq!(results.txs_results.len(), 2);
+        assert_eq!(results.txs_results[0].code, 5);
+        assert_eq!(results.txs_results[1].code, 0);
+
+        let events = &results.txs_results[0].events;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].type_str, "send_packet");
+
+        let attributes: Vec<_> = events[0]
+            .attributes
+            .iter()
+            .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            [("packet_sequence", "7"), ("packet_data_hex", "")]
+        );
+
+        assert!(
+            parse_block_results(Height::from(12u32), &json!({"txs_results": null}))
+                .txs_results
+                .is_empty()
+        );
+    }
+}
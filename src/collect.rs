@@ -1,23 +1,28 @@
-use std::time::Duration;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use futures::StreamExt;
-use ibc_proto::cosmos::tx::v1beta1::Tx;
-use prost::Message as ProstMessage;
-use sqlx::SqlitePool;
+use ibc_proto::{cosmos::tx::v1beta1::AuthInfo, google::protobuf::Any};
+use sqlx::{SqliteConnection, SqlitePool};
 use tendermint::{
     block::Height,
     chain::{self, Id as ChainId},
-    crypto::Sha256,
+    Block,
 };
-use tendermint_rpc::{event::EventData, WebSocketClientUrl};
-use tokio::time;
-use tracing::{error, info, warn};
+use tendermint_rpc::event::{Event, EventData, TxInfo};
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    client::{self, AuthConfig},
+    checkpoint,
+    client::{self, BlockResults, ChainClient, Keepalive},
+    config::{Config, Filters, Mode, Node, TxFormat},
     db::{PacketRow, TxRow},
+    descriptors::Registry,
+    filter,
     metrics::Metrics,
     msg::{self, Msg, UniversalPacketInfo},
+    status::{ConnectionState, Status},
+    tx_format::{self, DecodedTx},
 };
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -33,82 +38,318 @@ pub enum Outcome {
 
     #[error("Disconnecting after {0} blocks")]
     BlockElapsed(usize),
+
+    #[error("Subscription closed by the connection")]
+    Closed,
+
+    #[error("Reconnect requested")]
+    Reconnect,
+
+    #[error("Collector paused")]
+    Paused,
+
+    #[error("Collector stopped")]
+    Stopped,
 }
 
 /// Run unified collector with support for all protocol versions
+///
+/// The collector connects to the nodes in turn when the connection to one of them fails or
+/// times out, going back to the first one after the last. It backs off between two connections
+/// as `[reconnect]` sets, longer after every connection which receives no block, up to the
+/// `max_backoff_seconds` of its endpoint when it has one.
 pub async fn run(
     chain_id: chain::Id,
     version: &str,
-    ws_url: WebSocketClientUrl,
-    username: Option<String>,
-    password: Option<String>,
+    nodes: Vec<Node>,
     db: Pool,
     metrics: Metrics,
+    status: Status,
+    config: watch::Receiver<Config>,
 ) -> Result<()> {
+    let control = status.control(&chain_id);
+    let mut current = 0;
+
+    // Connections in a row which received no block
+    let mut failures: u32 = 0;
+
     loop {
+        if control.is_stopping() {
+            return Ok(());
+        }
+
+        if control.is_paused() {
+            // Chains disabled in the configuration are reported as such until resumed
+            if status.chain(&chain_id).map(|chain| chain.state) != Some(ConnectionState::Disabled) {
+                status.set_state(&chain_id, ConnectionState::Paused);
+            }
+
+            info!("Collector paused, waiting to be resumed...");
+
+            tokio::select! {
+                _ = control.resumed() => info!("Collector resumed"),
+                _ = control.stop_requested() => return Ok(()),
+            }
+        }
+
+        let last_height = status.chain(&chain_id).and_then(|chain| chain.last_height);
+
         let task = collect(
-            &chain_id, version, &ws_url, &username, &password, &db, &metrics,
+            &chain_id,
+            version,
+            &nodes[current],
+            &db,
+            &metrics,
+            &status,
+            &config,
         );
 
-        match task.await {
-            Ok(outcome) => warn!("{outcome}"),
+        let failed = match task.await {
+            Ok(Outcome::Stopped) => {
+                info!("Collector stopped");
+                return Ok(());
+            }
+            Ok(Outcome::Paused) => continue,
+            Ok(Outcome::Reconnect) => {
+                info!("Reconnect requested, reconnecting now...");
+
+                metrics.chainpulse_reconnects(&chain_id);
+                status.reconnect(&chain_id);
+                continue;
+            }
+            Ok(outcome) => {
+                warn!("{outcome}");
+                matches!(outcome, Outcome::Timeout(_) | Outcome::Closed)
+            }
             Err(e) => {
                 metrics.chainpulse_errors(&chain_id);
+                status.error(&chain_id, &e.to_string());
 
-                error!("{e}")
+                error!("{e}");
+                true
             }
+        };
+
+        if failed {
+            client::recheck_version(&nodes[current].url);
+        }
+
+        if failed && nodes.len() > 1 {
+            current = (current + 1) % nodes.len();
+            status.set_endpoint(&chain_id, &nodes[current].url.to_string());
+
+            info!(
+                "Failing over to endpoint {} of {}",
+                current + 1,
+                nodes.len()
+            );
+        }
+
+        if status.chain(&chain_id).and_then(|chain| chain.last_height) != last_height {
+            failures = 0;
         }
 
+        let backoff = {
+            let config = config.borrow();
+            let endpoint = config.chains.endpoints.get(&chain_id);
+            let reconnect = config
+                .reconnect
+                .capped(endpoint.and_then(|e| e.max_backoff_seconds));
+            reconnect.jittered(reconnect.backoff(failures), rand::random())
+        };
+        failures = failures.saturating_add(1);
+
         metrics.chainpulse_reconnects(&chain_id);
+        metrics.chainpulse_reconnect_backoff_seconds(&chain_id, backoff);
+        status.reconnect(&chain_id);
+        status.backoff(&chain_id, backoff);
+
+        info!("Reconnecting in {:.1} seconds...", backoff.as_secs_f64());
 
-        info!("Reconnecting in 5 seconds...");
-        time::sleep(Duration::from_secs(5)).await;
+        tokio::select! {
+            _ = time::sleep(backoff) => {}
+            _ = control.stop_requested() => return Ok(()),
+        }
     }
 }
 
 async fn collect(
     chain_id: &chain::Id,
     version: &str,
-    ws_url: &WebSocketClientUrl,
-    username: &Option<String>,
-    password: &Option<String>,
+    node: &Node,
     db: &Pool,
     metrics: &Metrics,
+    status: &Status,
+    config: &watch::Receiver<Config>,
 ) -> Result<Outcome> {
-    // Create appropriate client based on version and auth
-    let auth_config = match (username, password) {
-        (Some(user), Some(pass)) => Some(AuthConfig {
-            username: user.clone(),
-            password: pass.clone(),
-        }),
-        _ => None,
+    status.set_state(chain_id, ConnectionState::Connecting);
+
+    let control = status.control(chain_id);
+
+    // Nothing is written until a block is received, so connecting can be interrupted
+    let connect = async {
+        // Create appropriate client based on version, transport and auth
+        let (buffer, transport, poll_interval, tls, ping_interval, tx_query) = {
+            let config = config.borrow();
+            let endpoint = config.chains.endpoints.get(chain_id);
+            (
+                config.performance.subscription_buffer,
+                endpoint.map(|e| e.transport).unwrap_or_default(),
+                endpoint.map_or(Duration::from_secs(1), |e| e.poll_interval()),
+                endpoint.and_then(|e| e.tls.clone()),
+                config.performance.ping_interval_seconds,
+                endpoint.and_then(|e| e.tx_query.clone()),
+            )
+        };
+        let keepalive = Keepalive::new(Duration::from_secs(ping_interval), {
+            let (metrics, chain_id) = (metrics.clone(), chain_id.clone());
+            move |rtt| metrics.chainpulse_ws_ping_rtt_seconds(&chain_id, rtt)
+        });
+        let client = client::create_client(
+            &node.url,
+            version,
+            node.auth.clone(),
+            buffer,
+            transport,
+            poll_interval,
+            tls.as_ref(),
+            keepalive,
+        )
+        .await?;
+
+        let subscription = match &tx_query {
+            Some(query) => {
+                info!("Subscribing to NewBlockHeader events and Tx events matching {query}...");
+                client.subscribe_txs(query).await?
+            }
+            None => {
+                info!("Subscribing to NewBlock events...");
+                client.subscribe_blocks().await?
+            }
+        };
+
+        Result::Ok((client, subscription))
     };
 
-    let client = client::create_client(ws_url, version, auth_config).await?;
+    let (client, mut subscription) = tokio::select! {
+        connected = connect => connected?,
+        _ = control.stop_requested() => return Ok(Outcome::Stopped),
+    };
 
-    info!("Subscribing to NewBlock events...");
-    let mut subscription = client.subscribe_blocks().await?;
+    status.set_state(chain_id, ConnectionState::Connected);
+    metrics.chainpulse_reconnect_backoff_seconds(chain_id, Duration::ZERO);
 
     info!("Waiting for new blocks...");
 
     let mut count: usize = 0;
 
+    // The blocks missed since the last one recorded are caught up before the first new one
+    let mut caught_up = false;
+
+    // The txs of a tx subscription received before its first header, which may belong to the
+    // blocks being caught up
+    let mut pending = Vec::new();
+
+    // A block being processed is always recorded, signals are only handled between blocks
     loop {
-        let next_block = time::timeout(NEWBLOCK_TIMEOUT, subscription.next()).await;
+        let next_block = tokio::select! {
+            next_block = time::timeout(NEWBLOCK_TIMEOUT, subscription.next()) => next_block,
+            _ = control.reconnect_requested() => return Ok(Outcome::Reconnect),
+            _ = control.paused() => return Ok(Outcome::Paused),
+            _ = control.stop_requested() => return Ok(Outcome::Stopped),
+        };
+
         let next_block = match next_block {
             Ok(next_block) => next_block,
             Err(_) => {
                 metrics.chainpulse_timeouts(chain_id);
+                status.timeout(chain_id);
                 return Ok(Outcome::Timeout(NEWBLOCK_TIMEOUT));
             }
         };
 
+        // The connection closed, or did not answer a ping
+        let Some(next_block) = next_block else {
+            return Ok(Outcome::Closed);
+        };
+
+        // The txs of a tx subscription are recorded as they come, and do not count as blocks
+        if let Ok(Event {
+            data: EventData::Tx { tx_result },
+            ..
+        }) = &next_block
+        {
+            if status.is_leading() && !caught_up {
+                debug!(
+                    "Matching tx at height {} before the first header",
+                    tx_result.height
+                );
+                pending.push(tx_result.clone());
+            } else if status.is_leading() {
+                debug!("Matching tx at height {}", tx_result.height);
+
+                let processing = Processing::new(&config.borrow(), chain_id);
+
+                let mut tx = db.begin().await?;
+                process_tx(&mut tx, chain_id, tx_result, metrics, status, &processing).await?;
+                tx.commit().await?;
+            }
+
+            continue;
+        }
+
         count += 1;
 
-        let Some(Ok(event)) = next_block else {
+        let Ok(event) = next_block else {
             continue;
         };
 
+        // The headers of a tx subscription keep track of the chain in place of the blocks
+        if let Some(header) = client::subscription::header(&event) {
+            let height = header.height.value();
+
+            if status.is_leading() {
+                if !caught_up {
+                    let settings = CatchUp::new(&config.borrow(), chain_id);
+                    catch_up(
+                        db,
+                        chain_id,
+                        client.as_ref(),
+                        height,
+                        metrics,
+                        status,
+                        &settings,
+                    )
+                    .await;
+                    caught_up = true;
+
+                    let pending = std::mem::take(&mut pending);
+                    record_pending(
+                        db,
+                        chain_id,
+                        &pending,
+                        metrics,
+                        status,
+                        &settings.processing,
+                    )
+                    .await?;
+                }
+
+                let mut conn = db.acquire().await?;
+                record_header(&mut conn, chain_id, height).await?;
+            }
+
+            // The txs of the block are still to come, the block is left to the catch-up of the
+            // next connection
+            if count >= DISCONNECT_AFTER_BLOCKS {
+                return Ok(Outcome::BlockElapsed(count));
+            }
+
+            status.block_processed(chain_id, height, header.time);
+
+            continue;
+        }
+
         let EventData::NewBlock { block, .. } = &event.data else {
             continue;
         };
@@ -118,93 +359,504 @@ async fn collect(
         };
 
         let height = block.header.height;
-        info!("New block at height {}", height);
 
-        // Process transactions in the block
-        for tx_bytes in &block.data {
-            metrics.chainpulse_txs(chain_id);
+        // A standby stays connected to take over right away, but only the leader records
+        if status.is_leading() {
+            if !caught_up {
+                let settings = CatchUp::new(&config.borrow(), chain_id);
+                catch_up(
+                    db,
+                    chain_id,
+                    client.as_ref(),
+                    height.value(),
+                    metrics,
+                    status,
+                    &settings,
+                )
+                .await;
+                caught_up = true;
+            }
 
-            let tx = <Tx as ProstMessage>::decode(tx_bytes.as_slice())?;
-            let tx_row = insert_tx(db, chain_id, height, &tx).await?;
+            info!("New block at height {}", height);
 
-            let msgs = tx.body.ok_or("missing tx body")?.messages;
+            // Settings changed by a reload apply from the next block
+            let processing = Processing::new(&config.borrow(), chain_id);
+            let results = fetch_results(client.as_ref(), height, &processing).await;
 
-            for msg in msgs {
-                let type_url = msg.type_url.clone();
-                let msg = match Msg::decode(msg) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        warn!("Failed to decode message: {e}");
-                        continue;
-                    }
-                };
+            // The rows of a block are written at once, once its results are fetched
+            let mut tx = db.begin().await?;
+            process_block(
+                &mut tx,
+                chain_id,
+                block,
+                results.as_ref(),
+                metrics,
+                status,
+                &processing,
+            )
+            .await?;
+            checkpoint::record(&mut tx, chain_id, height.value(), Some(block.header.time)).await?;
+            tx.commit().await?;
+        } else {
+            debug!("Standing by, skipping the block at height {}", height);
+        }
+
+        status.block_processed(chain_id, height.value(), block.header.time);
+
+        if count >= DISCONNECT_AFTER_BLOCKS {
+            return Ok(Outcome::BlockElapsed(count));
+        }
+    }
+}
+
+/// Record the checkpoint of a tx subscription at the header of the block at `height`
+///
+/// The txs of a block come after its header, so only the previous block is complete, the
+/// block itself being caught up when the connection closes before its txs are recorded.
+async fn record_header(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    height: u64,
+) -> Result<()> {
+    if let Some(previous) = height.checked_sub(1).filter(|&previous| previous > 0) {
+        checkpoint::record(conn, chain_id, previous, None).await?;
+    }
+
+    Ok(())
+}
+
+/// Record the txs of a tx subscription received before its first header, once the blocks
+/// before it are caught up
+///
+/// The txs of the blocks up to the checkpoint were recorded by the catch-up, only the ones of
+/// the later blocks are left to record.
+async fn record_pending(
+    db: &Pool,
+    chain_id: &chain::Id,
+    pending: &[TxInfo],
+    metrics: &Metrics,
+    status: &Status,
+    processing: &Processing,
+) -> Result<()> {
+    let checkpoint = checkpoint::load(db, chain_id).await?.unwrap_or(0);
+
+    let mut tx = db.begin().await?;
+    for tx_result in pending {
+        if u64::try_from(tx_result.height).map_or(true, |height| height <= checkpoint) {
+            debug!("Tx at height {} already caught up", tx_result.height);
+            continue;
+        }
+
+        process_tx(&mut tx, chain_id, tx_result, metrics, status, processing).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Settings of the catch-up of the blocks missed by a collector
+#[derive(Clone, Debug, Default)]
+struct CatchUp {
+    max_blocks: u64,
+    batch_size: u64,
+    processing: Processing,
+}
+
+impl CatchUp {
+    fn new(config: &Config, chain_id: &chain::Id) -> Self {
+        Self {
+            max_blocks: config.resume.max_blocks,
+            batch_size: config.performance.db_batch_size as u64,
+            processing: Processing::new(config, chain_id),
+        }
+    }
+}
+
+/// Record the blocks missed since the last one recorded, before the block at `height`
+///
+/// At most `resume.max_blocks` are fetched, the older ones being left to a backfill. A block
+/// which cannot be fetched, such as one the node pruned, ends the catch-up rather than holding
+/// back the new blocks.
+async fn catch_up(
+    db: &Pool,
+    chain_id: &chain::Id,
+    client: &dyn ChainClient,
+    height: u64,
+    metrics: &Metrics,
+    status: &Status,
+    settings: &CatchUp,
+) {
+    let CatchUp {
+        max_blocks,
+        batch_size,
+        processing,
+    } = settings;
+    let (max_blocks, batch_size) = (*max_blocks, *batch_size);
+
+    if max_blocks == 0 {
+        return;
+    }
+
+    let last = match checkpoint::load(db, chain_id).await {
+        Ok(Some(last)) => last,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to read the checkpoint, not catching up: {e}");
+            return;
+        }
+    };
+
+    if last + 1 >= height {
+        return;
+    }
+
+    let to = height - 1;
+    let from = (last + 1).max(height.saturating_sub(max_blocks));
+
+    if from > last + 1 {
+        warn!(
+            "Catching up the last {max_blocks} blocks only, run `chainpulse backfill {chain_id} \
+             --from {} --to {}` to collect the blocks missed before",
+            last + 1,
+            from - 1
+        );
+    }
+
+    info!(
+        "Catching up {} blocks from height {from} to {to}",
+        to - from + 1
+    );
+
+    let control = status.control(chain_id);
+
+    for first in (from..=to).step_by(batch_size as usize) {
+        // The new blocks are waited for once the signal is handled
+        if control.is_stopping() || control.is_paused() {
+            return;
+        }
+
+        let heights: Vec<u64> = (first..=(first + batch_size - 1).min(to)).collect();
+
+        if let Err(e) = process_batch(
+            db, chain_id, client, &heights, metrics, status, processing, true,
+        )
+        .await
+        {
+            warn!(
+                "Failed to catch up from height {first}: {e}, run `chainpulse backfill \
+                 {chain_id} --from {first} --to {to}` to collect the blocks missed"
+            );
+            return;
+        }
+    }
+
+    info!("Caught up to height {to}");
+}
+
+/// Settings of the configuration the processing of a block depends on
+#[derive(Clone, Debug, Default)]
+pub struct Processing {
+    pub mode: Mode,
+    pub filters: Filters,
+    pub tx_format: TxFormat,
+    pub descriptors: Arc<Registry>,
+}
+
+impl Processing {
+    pub fn new(config: &Config, chain_id: &chain::Id) -> Self {
+        let tx_format = config
+            .chains
+            .endpoints
+            .get(chain_id)
+            .map(|endpoint| endpoint.tx_format)
+            .unwrap_or_default();
+
+        Self {
+            mode: config.mode,
+            filters: config.filters.clone(),
+            tx_format,
+            descriptors: config.descriptors.clone(),
+        }
+    }
+}
+
+/// Results of a block, when the client can fetch them and the mode is not light
+pub async fn fetch_results(
+    client: &dyn ChainClient,
+    height: Height,
+    processing: &Processing,
+) -> Option<BlockResults> {
+    if !client.supports_events() || processing.mode == Mode::Light {
+        return None;
+    }
+
+    match client.get_block_results(height).await {
+        Ok(results) => Some(results),
+        Err(e) => {
+            tracing::debug!("Could not fetch block results: {}", e);
+            None
+        }
+    }
+}
+
+/// Fetch the blocks at the heights along with their results, then record them in a single
+/// transaction
+///
+/// With `checkpoint`, the last block is recorded as the checkpoint of the chain in the same
+/// transaction, as the catch-up does, so that an interrupted catch-up resumes after the
+/// batches written. A backfill of older blocks leaves the checkpoint as it is.
+#[allow(clippy::too_many_arguments)]
+pub async fn process_batch(
+    db: &Pool,
+    chain_id: &chain::Id,
+    client: &dyn ChainClient,
+    heights: &[u64],
+    metrics: &Metrics,
+    status: &Status,
+    processing: &Processing,
+    checkpoint: bool,
+) -> Result<()> {
+    let mut blocks = Vec::with_capacity(heights.len());
+
+    for &height in heights {
+        let block = client
+            .get_block(Height::try_from(height)?)
+            .await
+            .map_err(|e| format!("failed to fetch block {height}: {e}"))?;
+
+        let results = fetch_results(client, block.header.height, processing).await;
+        blocks.push((block, results));
+    }
+
+    let mut tx = db.begin().await?;
+
+    for (block, results) in &blocks {
+        process_block(
+            &mut tx,
+            chain_id,
+            block,
+            results.as_ref(),
+            metrics,
+            status,
+            processing,
+        )
+        .await
+        .map_err(|e| format!("failed to process block {}: {e}", block.header.height))?;
+    }
+
+    if let Some((last, _)) = blocks.last().filter(|_| checkpoint) {
+        let (height, time) = (last.header.height.value(), last.header.time);
+        checkpoint::record(&mut tx, chain_id, height, Some(time)).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Record the txs of a block containing IBC messages, along with the events of its results when
+/// they were fetched, leaving out the packets the filters do not allow
+///
+/// In the light mode, the user data of the packets is not decoded.
+pub async fn process_block(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    block: &Block,
+    results: Option<&BlockResults>,
+    metrics: &Metrics,
+    status: &Status,
+    processing: &Processing,
+) -> Result<()> {
+    let height = block.header.height;
+
+    process_txs(conn, chain_id, height, &block.data, results, metrics, status, processing).await
+}
+
+/// Record a tx of a tx subscription along with the events of its result, as `process_block`
+/// does with the txs of a block
+pub async fn process_tx(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    tx: &TxInfo,
+    metrics: &Metrics,
+    status: &Status,
+    processing: &Processing,
+) -> Result<()> {
+    let results = client::subscription::tx_results(tx)?;
+
+    // As the results of the blocks, the events are left out in the light mode
+    let events = (processing.mode != Mode::Light).then_some(&results);
+
+    let txs = std::slice::from_ref(&tx.tx);
+    process_txs(conn, chain_id, results.height, txs, events, metrics, status, processing).await
+}
+
+/// Record the txs at a height, along with the events of their results in the same order
+#[allow(clippy::too_many_arguments)]
+async fn process_txs(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    height: Height,
+    txs: &[Vec<u8>],
+    results: Option<&BlockResults>,
+    metrics: &Metrics,
+    status: &Status,
+    processing: &Processing,
+) -> Result<()> {
+    // Messages chainpulse does not decode, counted per type URL
+    let mut unknown_msgs: HashMap<String, i64> = HashMap::new();
+
+    // Process transactions in the block
+    for tx_bytes in txs {
+        metrics.chainpulse_txs(chain_id);
+
+        // A tx which fails to decode is skipped rather than the whole block
+        let tx = match tx_format::decode(processing.tx_format, tx_bytes) {
+            Ok(tx) => tx,
+            Err(e) => {
+                warn!("Failed to decode tx at height {height}: {e}");
+                status.decode_failure(chain_id);
+                continue;
+            }
+        };
+
+        let tx_row = insert_tx(conn, chain_id, height, &tx).await?;
+        let mut relays_packets = false;
 
-                if msg.is_ibc() {
-                    tracing::debug!("  {}", type_url);
+        for msg in tx.messages {
+            let type_url = msg.type_url.clone();
+            let msg = match Msg::decode(msg) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    warn!("Failed to decode message: {e}");
+                    status.decode_failure(chain_id);
+                    continue;
+                }
+            };
+
+            if let Msg::Other(other) = &msg {
+                *unknown_msgs.entry(type_url.clone()).or_default() += 1;
 
-                    if msg.is_relevant() {
-                        process_msg(db, chain_id, &tx_row, &type_url, msg, metrics).await?;
-                    }
+                if processing.mode == Mode::Full && processing.descriptors.contains(&type_url) {
+                    record_decoded_msg(conn, &tx_row, other, &processing.descriptors).await?;
                 }
             }
-        }
 
-        // Try to get events if the client supports it
-        if client.supports_events() {
-            match client.get_block_results(height).await {
-                Ok(block_results) => {
-                    // Process events for enhanced data extraction
-                    for (tx_idx, tx_result) in block_results.txs_results.iter().enumerate() {
-                        tracing::debug!("TX {} has {} events", tx_idx, tx_result.events.len());
-                        
-                        // Get the corresponding tx_row if it exists
-                        if let Some(tx_bytes) = block.data().iter().nth(tx_idx) {
-                            // Decode the transaction
-                            let tx = Tx::decode(tx_bytes.as_slice())?;
-                            let tx_row = insert_tx(db, chain_id, height, &tx).await?;
-                            
-                            // Process events for this transaction
-                            process_tx_events(db, chain_id, &tx_row, &tx_result.events, metrics).await?;
-                        }
-                    }
+            if msg.is_ibc() {
+                tracing::debug!("  {}", type_url);
+
+                if let Some(update) = msg.client_update() {
+                    process_client_update(conn, &tx_row, &update, msg.signer()).await?;
                 }
-                Err(e) => {
-                    tracing::debug!("Could not fetch block results: {}", e);
+
+                relays_packets |= msg.packet().is_some();
+
+                if msg.is_relevant() {
+                    process_msg(conn, chain_id, &tx_row, &type_url, msg, metrics, processing).await?;
                 }
             }
         }
 
-        if count >= DISCONNECT_AFTER_BLOCKS {
-            return Ok(Outcome::BlockElapsed(count));
+        if relays_packets {
+            record_relay_fees(conn, chain_id, &tx_row, tx.auth_info.as_ref(), metrics).await?;
         }
     }
+
+    record_unknown_msgs(conn, chain_id, &unknown_msgs).await?;
+
+    if let Some(block_results) = results {
+        // Process events for enhanced data extraction
+        for (tx_idx, tx_result) in block_results.txs_results.iter().enumerate() {
+            tracing::debug!("TX {} has {} events", tx_idx, tx_result.events.len());
+            
+            // Get the corresponding tx_row if it exists
+            if let Some(tx_bytes) = txs.get(tx_idx) {
+                // Decode the transaction, its failure was counted above
+                let Ok(tx) = tx_format::decode(processing.tx_format, tx_bytes) else {
+                    continue;
+                };
+                let tx_row = insert_tx(conn, chain_id, height, &tx).await?;
+                
+                // Process events for this transaction
+                process_tx_events(conn, chain_id, &tx_row, &tx_result.events, metrics, &processing.filters).await?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 async fn process_msg(
-    pool: &Pool,
+    conn: &mut SqliteConnection,
     chain_id: &chain::Id,
     tx_row: &TxRow,
     type_url: &str,
     msg: Msg,
     metrics: &Metrics,
+    processing: &Processing,
 ) -> Result<()> {
+    let filters = &processing.filters;
+
     // Handle MsgTransfer separately since it doesn't have a packet field
     let (packet, packet_info) = if let Some(transfer) = msg.transfer() {
+        let token = transfer.token.as_ref();
+
+        let allowed = filter::allows(
+            filters,
+            &filter::Packet {
+                src_port: &transfer.source_port,
+                src_channel: &transfer.source_channel,
+                denom: token.map(|token| token.denom.as_str()),
+                amount: token.map(|token| token.amount.as_str()),
+                ..Default::default()
+            },
+        );
+
+        if !allowed {
+            debug!(
+                "    Transfer on channel {} filtered out",
+                transfer.source_channel
+            );
+            return Ok(());
+        }
+
         // For MsgTransfer, we need to track it but we don't have the packet details yet
         // The packet will be created when the recv is processed
         // For now, we'll store the transfer info
         metrics.chainpulse_packets(chain_id);
-        
+
         // MsgTransfer doesn't have sequence number or destination channel
         // We'll need to handle this differently
-        return process_transfer(pool, chain_id, tx_row, type_url, transfer, metrics).await;
+        return process_transfer(conn, chain_id, tx_row, type_url, transfer, metrics).await;
     } else if let Some(packet) = msg.packet() {
-        let packet_info = UniversalPacketInfo::from_packet(packet);
+        let packet_info = match processing.mode {
+            Mode::Full => UniversalPacketInfo::from_packet(packet),
+            Mode::Light => UniversalPacketInfo::without_user_data(packet),
+        };
         (packet, packet_info)
     } else {
         return Ok(());
     };
 
+    let fields = filter::Packet {
+        src_port: &packet.source_port,
+        src_channel: &packet.source_channel,
+        dst_port: &packet.destination_port,
+        dst_channel: &packet.destination_channel,
+        denom: packet_info.denom.as_deref(),
+        amount: packet_info.amount.as_deref(),
+    };
+
+    if !filter::allows(filters, &fields) {
+        debug!("    Packet #{} filtered out", packet.sequence);
+        return Ok(());
+    }
+
+    if is_recorded(conn, tx_row, type_url, packet.sequence as i64, &fields).await? {
+        debug!("    Packet #{} already recorded", packet.sequence);
+        return Ok(());
+    }
+
     metrics.chainpulse_packets(chain_id);
 
     tracing::debug!(
@@ -233,13 +885,13 @@ async fn process_msg(
         .bind(&packet.destination_port)
         .bind(packet.sequence as i64)
         .bind(type_url)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
     if let Some(existing) = &existing {
         let effected_tx: TxRow = sqlx::query_as("SELECT * FROM txs WHERE id = ? LIMIT 1")
             .bind(existing.tx_id)
-            .fetch_one(pool)
+            .fetch_one(&mut *conn)
             .await?;
 
         tracing::debug!(
@@ -288,9 +940,9 @@ async fn process_msg(
             msg_type_url, signer, effected, effected_signer, effected_tx, 
             sender, receiver, denom, amount, ibc_version,
             timeout_timestamp, timeout_height_revision_number, timeout_height_revision_height,
-            data_hash, created_at)
+            data_hash, ack_error, created_at)
         VALUES
-            (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
     "#;
 
     sqlx::query(query)
@@ -314,32 +966,71 @@ async fn process_msg(
         .bind(packet_info.timeout_height.as_ref().map(|h| h.revision_number as i64))
         .bind(packet_info.timeout_height.as_ref().map(|h| h.revision_height as i64))
         .bind(&packet_info.data_hash)
-        .execute(pool)
+        .bind(msg.ack_error())
+        .execute(&mut *conn)
         .await?;
 
     Ok(())
 }
 
+/// Whether the packet of a message or event of a tx was recorded already, by a block processed
+/// again, such as one caught up after the subscription delivered it
+///
+/// An existing row of another tx is a frontrun, while one of the same tx is the packet itself,
+/// a `send_packet` being a `timeout_packet` once it timed out.
+async fn is_recorded(
+    conn: &mut SqliteConnection,
+    tx_row: &TxRow,
+    type_url: &str,
+    sequence: i64,
+    packet: &filter::Packet<'_>,
+) -> Result<bool> {
+    let query = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM packets
+            WHERE tx_id = ?1
+              AND (msg_type_url = ?2 OR (?2 = 'send_packet' AND msg_type_url = 'timeout_packet'))
+              AND sequence = ?3
+              AND src_channel = ?4 AND src_port = ?5
+              AND dst_channel = ?6 AND dst_port = ?7
+        )
+    "#;
+
+    let recorded = sqlx::query_scalar(query)
+        .bind(tx_row.id)
+        .bind(type_url)
+        .bind(sequence)
+        .bind(packet.src_channel)
+        .bind(packet.src_port)
+        .bind(packet.dst_channel)
+        .bind(packet.dst_port)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(recorded)
+}
+
 async fn process_tx_events(
-    pool: &Pool,
+    conn: &mut SqliteConnection,
     chain_id: &chain::Id,
     tx_row: &TxRow,
     events: &[client::TxEvent],
     metrics: &Metrics,
+    filters: &Filters,
 ) -> Result<()> {
     for event in events {
         match event.type_str.as_str() {
             "send_packet" => {
-                process_send_packet_event(pool, chain_id, tx_row, event, metrics).await?;
+                process_send_packet_event(conn, chain_id, tx_row, event, metrics, filters).await?;
             }
             "recv_packet" => {
-                process_recv_packet_event(pool, chain_id, tx_row, event, metrics).await?;
+                process_recv_packet_event(conn, chain_id, tx_row, event, metrics).await?;
             }
             "acknowledge_packet" => {
-                process_acknowledge_packet_event(pool, chain_id, tx_row, event, metrics).await?;
+                process_acknowledge_packet_event(conn, chain_id, tx_row, event, metrics).await?;
             }
             "timeout_packet" => {
-                process_timeout_packet_event(pool, chain_id, tx_row, event, metrics).await?;
+                process_timeout_packet_event(conn, chain_id, tx_row, event, metrics).await?;
             }
             _ => {
                 // Skip other events
@@ -350,11 +1041,12 @@ async fn process_tx_events(
 }
 
 async fn process_send_packet_event(
-    pool: &Pool,
+    conn: &mut SqliteConnection,
     chain_id: &chain::Id,
     tx_row: &TxRow,
     event: &client::TxEvent,
     metrics: &Metrics,
+    filters: &Filters,
 ) -> Result<()> {
     // Extract packet info from send_packet event
     let mut packet_data = std::collections::HashMap::new();
@@ -394,6 +1086,25 @@ async fn process_send_packet_event(
         "    SendPacket event: seq {} on channel {} -> {}",
         sequence, src_channel, dst_channel
     );
+
+    let fields = filter::Packet {
+        src_port: &src_port,
+        src_channel: &src_channel,
+        dst_port: &dst_port,
+        dst_channel: &dst_channel,
+        denom: denom.as_deref(),
+        amount: amount.as_deref(),
+    };
+
+    if !filter::allows(filters, &fields) {
+        debug!("    Packet #{sequence} filtered out");
+        return Ok(());
+    }
+
+    if is_recorded(conn, tx_row, "send_packet", sequence, &fields).await? {
+        debug!("    Packet #{sequence} already recorded");
+        return Ok(());
+    }
     
     metrics.chainpulse_packets(chain_id);
     
@@ -422,14 +1133,14 @@ async fn process_send_packet_event(
         .bind(&amount)
         .bind(timeout_timestamp)
         .bind(packet_data_hex)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
     
     Ok(())
 }
 
 async fn process_recv_packet_event(
-    _pool: &Pool,
+    _conn: &mut SqliteConnection,
     _chain_id: &chain::Id,
     _tx_row: &TxRow,
     event: &client::TxEvent,
@@ -454,7 +1165,7 @@ async fn process_recv_packet_event(
 }
 
 async fn process_acknowledge_packet_event(
-    pool: &Pool,
+    conn: &mut SqliteConnection,
     _chain_id: &chain::Id,
     tx_row: &TxRow,
     event: &client::TxEvent,
@@ -492,14 +1203,14 @@ async fn process_acknowledge_packet_event(
         .bind(sequence)
         .bind(&src_channel)
         .bind(&dst_channel)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
     
     Ok(())
 }
 
 async fn process_timeout_packet_event(
-    pool: &Pool,
+    conn: &mut SqliteConnection,
     _chain_id: &chain::Id,
     tx_row: &TxRow,
     event: &client::TxEvent,
@@ -537,14 +1248,14 @@ async fn process_timeout_packet_event(
         .bind(sequence)
         .bind(&src_channel)
         .bind(&dst_channel)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
     
     Ok(())
 }
 
 async fn process_transfer(
-    _pool: &Pool,
+    _conn: &mut SqliteConnection,
     chain_id: &chain::Id,
     tx_row: &TxRow,
     _type_url: &str,
@@ -575,31 +1286,160 @@ async fn process_transfer(
     Ok(())
 }
 
-async fn insert_tx(db: &Pool, chain_id: &ChainId, height: Height, tx: &Tx) -> Result<TxRow> {
+async fn process_client_update(
+    conn: &mut SqliteConnection,
+    tx_row: &TxRow,
+    update: &msg::ClientUpdate,
+    signer: Option<&str>,
+) -> Result<()> {
+    tracing::debug!(
+        "    UpdateClient {} to {:?} height {:?} in tx {} ({})",
+        update.client_id,
+        update.counterparty_chain_id,
+        update.header_height,
+        tx_row.id,
+        tx_row.hash
+    );
+
+    let query = r#"
+        INSERT OR IGNORE INTO client_updates
+            (tx_id, client_id, counterparty_chain_id, header_height, header_time, signer, created_at)
+        VALUES
+            (?, ?, ?, ?, datetime(?, 'unixepoch'), ?, datetime('now'))
+    "#;
+
+    sqlx::query(query)
+        .bind(tx_row.id)
+        .bind(&update.client_id)
+        .bind(&update.counterparty_chain_id)
+        .bind(update.header_height)
+        .bind(update.header_time)
+        .bind(signer)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Record a message chainpulse has no types for, decoded into its fields by the descriptor sets
+async fn record_decoded_msg(
+    conn: &mut SqliteConnection,
+    tx_row: &TxRow,
+    msg: &Any,
+    descriptors: &Registry,
+) -> Result<()> {
+    let fields = match descriptors.decode(&msg.type_url, &msg.value) {
+        Ok(fields) => fields,
+        Err(e) => {
+            warn!("Failed to decode message {} with the descriptor sets: {e}", msg.type_url);
+            return Ok(());
+        }
+    };
+
+    let query = r#"
+        INSERT INTO decoded_msgs (tx_id, type_url, fields, created_at)
+        VALUES (?, ?, ?, datetime('now'))
+    "#;
+
+    sqlx::query(query)
+        .bind(tx_row.id)
+        .bind(&msg.type_url)
+        .bind(fields.to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn record_unknown_msgs(
+    conn: &mut SqliteConnection,
+    chain_id: &chain::Id,
+    unknown_msgs: &HashMap<String, i64>,
+) -> Result<()> {
+    let query = r#"
+        INSERT INTO unknown_msg_types (chain, type_url, count, first_seen, last_seen)
+        VALUES (?, ?, ?, datetime('now'), datetime('now'))
+        ON CONFLICT (chain, type_url) DO UPDATE
+        SET count = count + excluded.count, last_seen = excluded.last_seen
+    "#;
+
+    for (type_url, count) in unknown_msgs {
+        sqlx::query(query)
+            .bind(chain_id.as_str())
+            .bind(type_url)
+            .bind(count)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Record the gas price paid by a relay tx in each denom of its fee, as the fee over the gas
+/// wanted, whether or not the filters allow its packets
+async fn record_relay_fees(
+    conn: &mut SqliteConnection,
+    chain_id: &ChainId,
+    tx_row: &TxRow,
+    auth_info: Option<&AuthInfo>,
+    metrics: &Metrics,
+) -> Result<()> {
+    let Some(fee) = auth_info.and_then(|auth_info| auth_info.fee.as_ref()) else {
+        return Ok(());
+    };
+
+    if fee.gas_limit == 0 {
+        return Ok(());
+    }
+
+    let query = r#"
+        INSERT OR IGNORE INTO relay_fees
+            (tx_id, chain, denom, amount, gas_wanted, gas_price, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+    "#;
+
+    for coin in &fee.amount {
+        let Ok(amount) = coin.amount.parse::<u128>() else {
+            continue;
+        };
+
+        let gas_price = amount as f64 / fee.gas_limit as f64;
+
+        sqlx::query(query)
+            .bind(tx_row.id)
+            .bind(chain_id.as_str())
+            .bind(&coin.denom)
+            .bind(&coin.amount)
+            .bind(fee.gas_limit as i64)
+            .bind(gas_price)
+            .execute(&mut *conn)
+            .await?;
+
+        metrics.chainpulse_gas_price(chain_id, &coin.denom, gas_price);
+    }
+
+    Ok(())
+}
+
+async fn insert_tx(
+    conn: &mut SqliteConnection,
+    chain_id: &ChainId,
+    height: Height,
+    tx: &DecodedTx,
+) -> Result<TxRow> {
     let query = r#"
         INSERT OR IGNORE INTO txs (chain, height, hash, memo, created_at)
         VALUES (?, ?, ?, ?, datetime('now'))
     "#;
 
-    let bytes = tx.encode_to_vec();
-    let hash = tendermint::crypto::default::Sha256::digest(&bytes);
-    let hash = subtle_encoding::hex::encode_upper(hash);
-    let hash = String::from_utf8_lossy(&hash);
-
     let height = height.value() as i64;
 
-    let memo = tx
-        .body
-        .as_ref()
-        .map(|body| body.memo.to_string())
-        .unwrap_or_default();
-
     sqlx::query(query)
         .bind(chain_id.as_str())
         .bind(height)
-        .bind(&hash)
-        .bind(memo)
-        .execute(db)
+        .bind(&tx.hash)
+        .bind(&tx.memo)
+        .execute(&mut *conn)
         .await?;
 
     let query = r#"
@@ -608,9 +1448,269 @@ async fn insert_tx(db: &Pool, chain_id: &ChainId, height: Height, tx: &Tx) -> Re
 
     let tx = sqlx::query_as(query)
         .bind(chain_id.as_str())
-        .bind(&hash)
-        .fetch_one(db)
+        .bind(&tx.hash)
+        .fetch_one(&mut *conn)
         .await?;
 
     Ok(tx)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use async_trait::async_trait;
+    use ibc_proto::{
+        cosmos::tx::v1beta1::{Tx, TxBody},
+        google::protobuf::Any,
+        ibc::core::channel::v1::{MsgRecvPacket, Packet},
+    };
+    use prost::Message;
+    use tendermint::{
+        account,
+        block::{header::Version, Commit, Header},
+        evidence,
+        hash::AppHash,
+        Hash, Time,
+    };
+
+    use super::*;
+    use crate::{client::BlockSubscription, db};
+
+    /// Client of a node which pruned every block, recording the heights asked for
+    #[derive(Default)]
+    struct Pruned(Mutex<Vec<u64>>);
+
+    #[async_trait]
+    impl ChainClient for Pruned {
+        async fn subscribe_blocks(&self) -> client::Result<BlockSubscription> {
+            Err("no subscription".into())
+        }
+
+        async fn get_block(&self, height: Height) -> client::Result<Block> {
+            self.0.lock().unwrap().push(height.value());
+            Err(format!("height {height} is not available").into())
+        }
+
+        async fn get_block_results(&self, height: Height) -> client::Result<BlockResults> {
+            Err(format!("height {height} is not available").into())
+        }
+    }
+
+    /// Client of a node serving the blocks it was given
+    struct Served(BTreeMap<u64, Block>);
+
+    #[async_trait]
+    impl ChainClient for Served {
+        async fn subscribe_blocks(&self) -> client::Result<BlockSubscription> {
+            Err("no subscription".into())
+        }
+
+        async fn get_block(&self, height: Height) -> client::Result<Block> {
+            let block = self.0.get(&height.value());
+            block.cloned().ok_or_else(|| "no block".into())
+        }
+
+        async fn get_block_results(&self, height: Height) -> client::Result<BlockResults> {
+            Err(format!("no results at height {height}").into())
+        }
+    }
+
+    /// Block of `osmosis-1` holding the txs
+    fn block(height: u64, txs: Vec<Vec<u8>>) -> Block {
+        let header = Header {
+            version: Version { block: 11, app: 0 },
+            chain_id: chain::Id::try_from("osmosis-1").unwrap(),
+            height: Height::try_from(height).unwrap(),
+            time: Time::from_unix_timestamp(1_704_067_200 + height as i64 * 6, 0).unwrap(),
+            last_block_id: None,
+            last_commit_hash: None,
+            data_hash: None,
+            validators_hash: Hash::None,
+            next_validators_hash: Hash::None,
+            consensus_hash: Hash::None,
+            app_hash: AppHash::default(),
+            last_results_hash: None,
+            evidence_hash: None,
+            proposer_address: account::Id::new([0; 20]),
+        };
+
+        Block::new(
+            header,
+            txs,
+            evidence::List::default(),
+            Some(Commit::default()),
+        )
+        .unwrap()
+    }
+
+    /// Tx of a relayer receiving the packet of a sequence
+    fn relay(sequence: u64, relayer: &str) -> Vec<u8> {
+        let msg = MsgRecvPacket {
+            packet: Some(Packet {
+                sequence,
+                source_port: "transfer".to_string(),
+                source_channel: "channel-0".to_string(),
+                destination_port: "transfer".to_string(),
+                destination_channel: "channel-1".to_string(),
+                ..Default::default()
+            }),
+            signer: relayer.to_string(),
+            ..Default::default()
+        };
+
+        Tx {
+            body: Some(TxBody {
+                messages: vec![Any {
+                    type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(),
+                    value: msg.encode_to_vec(),
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_again() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        let (metrics, registry) = Metrics::new();
+        let status = Status::new();
+        let settings = CatchUp {
+            max_blocks: 1000,
+            batch_size: 2,
+            processing: Processing::default(),
+        };
+
+        // The relay of packet 1 in block 11 is frontrun by the one in block 10
+        let client = Served(BTreeMap::from([
+            (10, block(10, vec![relay(1, "osmo1relayer0")])),
+            (
+                11,
+                block(
+                    11,
+                    vec![relay(1, "osmo1relayer1"), relay(2, "osmo1relayer0")],
+                ),
+            ),
+            (12, block(12, vec![relay(3, "osmo1relayer0")])),
+        ]));
+
+        let counts = || async {
+            let (packets, frontrun): (i64, i64) = sqlx::query_as(
+                "SELECT COUNT(*), COUNT(*) FILTER (WHERE effected = 0) FROM packets",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+            let frontrun_counter: f64 = registry
+                .gather()
+                .iter()
+                .filter(|family| family.get_name() == "ibc_frontrun_counter")
+                .flat_map(|family| family.get_metric())
+                .map(|metric| metric.get_counter().get_value())
+                .sum();
+
+            (packets, frontrun, frontrun_counter)
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        checkpoint::record(&mut conn, &chain_id, 9, None)
+            .await
+            .unwrap();
+        drop(conn);
+
+        catch_up(&pool, &chain_id, &client, 13, &metrics, &status, &settings).await;
+        assert_eq!(checkpoint::load(&pool, &chain_id).await.unwrap(), Some(12));
+        assert_eq!(counts().await, (4, 1, 1.0));
+
+        // A catch-up interrupted before its checkpoint processes the same blocks again
+        let mut conn = pool.acquire().await.unwrap();
+        checkpoint::record(&mut conn, &chain_id, 9, None)
+            .await
+            .unwrap();
+        drop(conn);
+
+        catch_up(&pool, &chain_id, &client, 13, &metrics, &status, &settings).await;
+        assert_eq!(counts().await, (4, 1, 1.0));
+    }
+
+    #[tokio::test]
+    async fn test_header_disconnect() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        let (metrics, _) = Metrics::new();
+        let status = Status::new();
+        let settings = CatchUp {
+            max_blocks: 1000,
+            batch_size: 100,
+            processing: Processing::default(),
+        };
+
+        // The connection closes at the header of block 11, before its txs come
+        let mut conn = pool.acquire().await.unwrap();
+        record_header(&mut conn, &chain_id, 10).await.unwrap();
+        record_header(&mut conn, &chain_id, 11).await.unwrap();
+        drop(conn);
+
+        assert_eq!(checkpoint::load(&pool, &chain_id).await.unwrap(), Some(10));
+
+        // The next connection catches up from block 11
+        let client = Pruned::default();
+        catch_up(&pool, &chain_id, &client, 14, &metrics, &status, &settings).await;
+        assert_eq!(*client.0.lock().unwrap(), [11]);
+    }
+
+    #[tokio::test]
+    async fn test_record_pending() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        let (metrics, _) = Metrics::new();
+        let status = Status::new();
+
+        let tx_info = |height: i64, sequence: u64| TxInfo {
+            height,
+            index: Some(0),
+            tx: relay(sequence, "osmo1relayer"),
+            result: tendermint_rpc::event::TxResult {
+                log: None,
+                gas_wanted: None,
+                gas_used: None,
+                events: Vec::new(),
+            },
+        };
+
+        // Block 10 was caught up before the first header, block 11 was not
+        let mut conn = pool.acquire().await.unwrap();
+        checkpoint::record(&mut conn, &chain_id, 10, None)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let pending = [tx_info(10, 1), tx_info(11, 2)];
+        record_pending(
+            &pool,
+            &chain_id,
+            &pending,
+            &metrics,
+            &status,
+            &Processing::default(),
+        )
+        .await
+        .unwrap();
+
+        let sequences: Vec<i64> = sqlx::query_scalar("SELECT sequence FROM packets")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(sequences, [2]);
+    }
+}
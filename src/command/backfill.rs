@@ -0,0 +1,118 @@
+use std::time::Instant;
+
+use tendermint::chain;
+use tracing::info;
+
+use crate::{client, collect, config::Config, db, metrics::Metrics, rpc, status::Status};
+
+/// Number of blocks between two progress reports
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// Collect the IBC messages of past blocks of a chain
+#[derive(clap::Args)]
+pub struct Args {
+    /// Chain to backfill, which must be configured in `chains`
+    chain: String,
+
+    /// First height to process
+    #[clap(long)]
+    from: u64,
+
+    /// Last height to process, the latest block of the chain by default
+    #[clap(long)]
+    to: Option<u64>,
+}
+
+/// Fetch the blocks of the range one by one and record them as the collector would
+///
+/// Blocks already collected are skipped by the deduplication of txs and packets, so an
+/// interrupted backfill can be run again from the last height it reported.
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    let chain_id = chain::Id::try_from(args.chain.as_str())?;
+
+    // Archive nodes of a chain reference keep the old blocks
+    let endpoint = &config
+        .chains
+        .endpoints
+        .get(&chain_id)
+        .ok_or_else(|| format!("chain {chain_id} is not configured"))?
+        .archive();
+
+    let (earliest, latest) = rpc::available_heights(&reqwest::Client::new(), endpoint)
+        .await
+        .map_err(|e| format!("failed to query the status of {chain_id}: {e}"))?;
+    let to = args.to.unwrap_or(latest).min(latest);
+
+    if args.from < earliest {
+        return Err(format!(
+            "the node of {chain_id} only has blocks from height {earliest}, use an archive node"
+        )
+        .into());
+    }
+
+    if args.from > to {
+        return Err(format!("nothing to backfill between {} and {to}", args.from).into());
+    }
+
+    let pool = db::connect(&config.database.path).await?;
+    db::setup(&pool).await;
+
+    // Counters are not exported, the collector rebuilds them with `populate_on_start`
+    let (metrics, _) = Metrics::new();
+    let status = Status::new();
+    status.register(
+        &chain_id,
+        &endpoint.url.to_string(),
+        endpoint.version_string(),
+    );
+
+    let client = client::create_client(
+        &endpoint.url,
+        endpoint.version_string(),
+        endpoint.auth.clone(),
+        config.performance.subscription_buffer,
+        endpoint.transport,
+        endpoint.poll_interval(),
+        endpoint.tls.as_ref(),
+        client::Keepalive::default(),
+    )
+    .await?;
+
+    info!("Backfilling {chain_id} from height {} to {to}", args.from);
+
+    let start = Instant::now();
+    let processing = collect::Processing::new(config, &chain_id);
+    let batch_size = config.performance.db_batch_size as u64;
+    let mut reported = args.from;
+
+    for first in (args.from..=to).step_by(batch_size as usize) {
+        let last = (first + batch_size - 1).min(to);
+        let heights: Vec<u64> = (first..=last).collect();
+
+        collect::process_batch(
+            &pool,
+            &chain_id,
+            client.as_ref(),
+            &heights,
+            &metrics,
+            &status,
+            &processing,
+            false,
+        )
+        .await?;
+
+        // Only the heights of the batches written are reported
+        if last + 1 - reported >= PROGRESS_INTERVAL {
+            info!("Processed blocks up to height {last}");
+            reported = last + 1;
+        }
+    }
+
+    info!(
+        "Backfilled {} blocks in {:?}",
+        to - args.from + 1,
+        start.elapsed()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use tokio::time;
+
+use crate::{
+    config::{Config, StuckPackets},
+    db,
+    monitor::format_age,
+};
+
+/// Number of channels listed in the stuck channels table
+const TOP_CHANNELS: i64 = 10;
+
+/// Show the activity of the chains and the channels with stuck packets in the terminal
+#[derive(clap::Args)]
+pub struct Args {
+    /// Seconds between two refreshes
+    #[clap(long, default_value = "5")]
+    interval: u64,
+
+    /// Print the dashboard once and exit
+    #[clap(long)]
+    once: bool,
+}
+
+/// Chain activity over the last hour
+#[derive(sqlx::FromRow)]
+struct ChainRow {
+    chain: String,
+    height: i64,
+    last_seen_seconds: i64,
+    txs: i64,
+    packets: i64,
+    pending: i64,
+}
+
+/// Channel with stuck packets
+#[derive(sqlx::FromRow)]
+struct ChannelRow {
+    chain: String,
+    src_channel: String,
+    dst_channel: String,
+    stuck: i64,
+    oldest_age_seconds: i64,
+}
+
+/// Render the dashboard from the database, until interrupted unless `--once` is set
+///
+/// Reading the database only, it can run next to the collector or against a copy of the
+/// database.
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    let pool = db::connect(&config.database.path).await?;
+    let mut interval = time::interval(Duration::from_secs(args.interval.max(1)));
+
+    loop {
+        interval.tick().await;
+
+        let dashboard = render(&pool, &config.stuck_packets).await?;
+
+        if args.once {
+            print!("{dashboard}");
+            return Ok(());
+        }
+
+        // Clear the screen and move the cursor home before redrawing
+        print!("\x1b[2J\x1b[H{dashboard}");
+    }
+}
+
+async fn render(pool: &SqlitePool, thresholds: &StuckPackets) -> sqlx::Result<String> {
+    let chains = sqlx::query_as::<_, ChainRow>(
+        r#"
+        SELECT
+            t.chain,
+            MAX(t.height) as height,
+            CAST((strftime('%s', 'now') - strftime('%s', MAX(t.created_at))) AS INTEGER) as last_seen_seconds,
+            (SELECT COUNT(*) FROM txs rt
+             WHERE rt.chain = t.chain AND rt.created_at >= datetime('now', '-1 hour')) as txs,
+            (SELECT COUNT(*) FROM packets p JOIN txs pt ON p.tx_id = pt.id
+             WHERE pt.chain = t.chain AND p.created_at >= datetime('now', '-1 hour')) as packets,
+            (SELECT COUNT(*) FROM packets p JOIN txs pt ON p.tx_id = pt.id
+             WHERE pt.chain = t.chain
+               AND p.msg_type_url = 'send_packet'
+               AND p.effected = 0
+               AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)) as pending
+        FROM txs t
+        GROUP BY t.chain
+        ORDER BY t.chain
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let query = format!(
+        r#"
+        SELECT
+            t.chain,
+            p.src_channel,
+            p.dst_channel,
+            COUNT(*) as stuck,
+            MAX(CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER)) as oldest_age_seconds
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+        GROUP BY t.chain, p.src_channel, p.dst_channel
+        ORDER BY stuck DESC
+        LIMIT ?
+        "#,
+        thresholds.sql("t.chain", "p.src_channel")
+    );
+
+    let channels = sqlx::query_as::<_, ChannelRow>(&query)
+        .bind(TOP_CHANNELS)
+        .fetch_all(pool)
+        .await?;
+
+    let mut out = format!(
+        "chainpulse dashboard, {}\n\n",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+    );
+
+    out += &format!(
+        "{:<24} {:>12} {:>10} {:>10} {:>10} {:>10}\n",
+        "CHAIN", "HEIGHT", "LAST TX", "TXS/1H", "PKTS/1H", "PENDING"
+    );
+
+    for chain in &chains {
+        out += &format!(
+            "{:<24} {:>12} {:>10} {:>10} {:>10} {:>10}\n",
+            chain.chain,
+            chain.height,
+            format_age(chain.last_seen_seconds),
+            chain.txs,
+            chain.packets,
+            chain.pending
+        );
+    }
+
+    out += &match thresholds.overrides.is_empty() {
+        true => format!(
+            "\nStuck for more than {}\n",
+            format_age(thresholds.threshold_seconds as i64)
+        ),
+        false => format!(
+            "\nStuck for more than {}, or the threshold of their chain or channel\n",
+            format_age(thresholds.threshold_seconds as i64)
+        ),
+    };
+
+    if channels.is_empty() {
+        out += "No stuck packets\n";
+        return Ok(out);
+    }
+
+    out += &format!(
+        "{:<24} {:<32} {:>8} {:>10}\n",
+        "CHAIN", "CHANNEL", "STUCK", "OLDEST"
+    );
+
+    for channel in &channels {
+        out += &format!(
+            "{:<24} {:<32} {:>8} {:>10}\n",
+            channel.chain,
+            format!("{} -> {}", channel.src_channel, channel.dst_channel),
+            channel.stuck,
+            format_age(channel.oldest_age_seconds)
+        );
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use futures::TryStreamExt;
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite};
+use tracing::info;
+
+use crate::{config::Config, db};
+
+/// Export the collected packets as CSV or JSON lines
+#[derive(clap::Args)]
+pub struct Args {
+    /// Only export the packets of this chain
+    #[clap(long)]
+    chain: Option<String>,
+
+    /// Only export the packets recorded from this time, as unix seconds or RFC 3339
+    #[clap(long, value_parser = parse_timestamp)]
+    from: Option<i64>,
+
+    /// Only export the packets recorded before this time, as unix seconds or RFC 3339
+    #[clap(long, value_parser = parse_timestamp)]
+    to: Option<i64>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "csv")]
+    format: Format,
+
+    /// File to write to, the standard output by default
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Columns of an [`ExportRow`], from the `packets p` joined with their `txs t`
+pub(crate) const COLUMNS: &str = r#"
+    t.chain, t.height, t.hash as tx_hash, p.msg_type_url, p.sequence,
+    p.src_channel, p.src_port, p.dst_channel, p.dst_port, p.signer,
+    p.effected, p.effected_signer, p.sender, p.receiver, p.denom, p.amount,
+    p.timeout_timestamp, p.ack_error, t.memo, p.created_at
+"#;
+
+/// A packet message along with the tx it was recorded in
+#[derive(Serialize, sqlx::FromRow)]
+pub(crate) struct ExportRow {
+    chain: String,
+    height: i64,
+    tx_hash: String,
+    msg_type_url: String,
+    sequence: i64,
+    src_channel: String,
+    src_port: String,
+    dst_channel: String,
+    dst_port: String,
+    signer: Option<String>,
+    effected: bool,
+    effected_signer: Option<String>,
+    sender: Option<String>,
+    receiver: Option<String>,
+    denom: Option<String>,
+    amount: Option<String>,
+    timeout_timestamp: Option<i64>,
+    ack_error: Option<String>,
+    memo: String,
+    created_at: String,
+}
+
+/// Stream the matching packets to the output, oldest first
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    let pool = db::connect(&config.database.path).await?;
+    db::setup(&pool).await;
+
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {COLUMNS} FROM packets p JOIN txs t ON p.tx_id = t.id WHERE 1 = 1"
+    ));
+
+    if let Some(chain) = &args.chain {
+        query.push(" AND t.chain = ").push_bind(chain);
+    }
+
+    if let Some(from) = args.from {
+        query
+            .push(" AND p.created_at >= datetime(")
+            .push_bind(from)
+            .push(", 'unixepoch')");
+    }
+
+    if let Some(to) = args.to {
+        query
+            .push(" AND p.created_at < datetime(")
+            .push_bind(to)
+            .push(", 'unixepoch')");
+    }
+
+    query.push(" ORDER BY p.id");
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let mut rows = query.build_query_as::<ExportRow>().fetch(&pool);
+    let mut count: u64 = 0;
+
+    match args.format {
+        Format::Csv => {
+            let mut csv = csv::Writer::from_writer(output);
+
+            while let Some(row) = rows.try_next().await? {
+                csv.serialize(&row)?;
+                count += 1;
+            }
+
+            csv.flush()?;
+        }
+        Format::Json => {
+            let mut output = BufWriter::new(output);
+
+            while let Some(row) = rows.try_next().await? {
+                serde_json::to_writer(&mut output, &row)?;
+                output.write_all(b"\n")?;
+                count += 1;
+            }
+
+            output.flush()?;
+        }
+    }
+
+    info!("Exported {count} packets");
+
+    Ok(())
+}
+
+/// Parse a timestamp given either as unix seconds or as RFC 3339, returning unix seconds
+fn parse_timestamp(value: &str) -> Result<i64, String> {
+    value
+        .parse::<i64>()
+        .ok()
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.timestamp())
+        })
+        .ok_or_else(|| format!("invalid timestamp `{value}`"))
+}
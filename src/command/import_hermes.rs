@@ -0,0 +1,464 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::{Filters, Relayer, COMET_VERSIONS};
+
+/// Time given to the node of a chain to report its CometBFT version
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Version assumed for the chains whose node cannot be queried
+const DEFAULT_COMET_VERSION: &str = "0.38";
+
+/// Port of the API and metrics server, unless Hermes already listens on it
+const DEFAULT_METRICS_PORT: u16 = 3000;
+
+/// Write a configuration collecting the chains and channels relayed by Hermes
+#[derive(clap::Args)]
+pub struct Args {
+    /// File to write the configuration to, the standard output by default
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Replace the output file when it exists
+    #[clap(long)]
+    force: bool,
+}
+
+/// Configuration of Hermes, of which only the chains and the REST server are imported
+#[derive(Debug, Deserialize)]
+struct HermesConfig {
+    #[serde(default)]
+    rest: Service,
+    #[serde(default)]
+    telemetry: Service,
+    #[serde(default)]
+    chains: Vec<HermesChain>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Service {
+    #[serde(default)]
+    enabled: bool,
+    host: Option<String>,
+    port: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HermesChain {
+    id: String,
+    rpc_addr: String,
+    /// WebSocket endpoint of Hermes before v1.6, replaced by `event_source`
+    websocket_addr: Option<String>,
+    event_source: Option<EventSource>,
+    compat_mode: Option<String>,
+    packet_filter: Option<PacketFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventSource {
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PacketFilter {
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    list: Vec<(String, String)>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Policy {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// Configuration of chainpulse written by the command
+#[derive(Serialize)]
+struct Imported {
+    chains: BTreeMap<String, ImportedChain>,
+    database: ImportedDatabase,
+    metrics: ImportedMetrics,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relayer: Option<Relayer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filters: Option<Filters>,
+}
+
+#[derive(Serialize)]
+struct ImportedChain {
+    url: String,
+    comet_version: String,
+}
+
+#[derive(Serialize)]
+struct ImportedDatabase {
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct ImportedMetrics {
+    enabled: bool,
+    port: u16,
+    stuck_packets: bool,
+}
+
+/// Derive the chains, their endpoints and the channels to collect from the configuration of
+/// Hermes at `path`, querying the CometBFT version of the chains without a `compat_mode`
+pub async fn run(path: &Path, args: Args) -> crate::Result<()> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let hermes: HermesConfig = toml::from_str(&content)
+        .map_err(|e| format!("invalid Hermes configuration {}: {e}", path.display()))?;
+
+    if hermes.chains.is_empty() {
+        return Err(format!("{} has no chains", path.display()).into());
+    }
+
+    if let Some(output) = &args.output {
+        if output.exists() && !args.force {
+            return Err(format!(
+                "{} already exists, pass --force to replace it",
+                output.display()
+            )
+            .into());
+        }
+    }
+
+    let client = reqwest::Client::new();
+    let versions = join_all(
+        hermes
+            .chains
+            .iter()
+            .map(|chain| comet_version(&client, chain)),
+    )
+    .await;
+
+    let mut chains = BTreeMap::new();
+
+    for (chain, comet_version) in hermes.chains.iter().zip(versions) {
+        let url = websocket_url(chain)
+            .ok_or_else(|| format!("invalid rpc_addr of {}: {}", chain.id, chain.rpc_addr))?;
+
+        chains.insert(chain.id.clone(), ImportedChain { url, comet_version });
+    }
+
+    let imported = Imported {
+        chains,
+        database: ImportedDatabase {
+            path: PathBuf::from("chainpulse.db"),
+        },
+        metrics: ImportedMetrics {
+            enabled: true,
+            port: metrics_port(&hermes),
+            stuck_packets: true,
+        },
+        relayer: hermes_url(&hermes.rest).map(|hermes_url| Relayer {
+            hermes_url: Some(hermes_url),
+            ..Relayer::default()
+        }),
+        filters: Some(filters(&hermes.chains)).filter(|filters| {
+            !filters.allow_ports.is_empty() || !filters.allow_channels.is_empty()
+        }),
+    };
+
+    let toml = toml::to_string_pretty(&imported)?;
+
+    match &args.output {
+        Some(output) => {
+            fs::write(output, toml)
+                .map_err(|e| format!("failed to write {}: {e}", output.display()))?;
+
+            info!(
+                "Imported {} chains from {} into {}",
+                imported.chains.len(),
+                path.display(),
+                output.display()
+            );
+        }
+        None => print!("{toml}"),
+    }
+
+    Ok(())
+}
+
+/// WebSocket endpoint of a chain, which is its event source or derived from its RPC address
+fn websocket_url(chain: &HermesChain) -> Option<String> {
+    let configured = chain
+        .event_source
+        .as_ref()
+        .and_then(|source| source.url.as_ref())
+        .or(chain.websocket_addr.as_ref());
+
+    if let Some(url) = configured {
+        return Some(url.clone());
+    }
+
+    // Chains pulling their events from the RPC endpoint have no WebSocket endpoint configured
+    let mut url = url::Url::parse(&chain.rpc_addr).ok()?;
+
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        _ => "ws",
+    };
+    url.set_scheme(scheme).ok()?;
+
+    let path = format!("{}/websocket", url.path().trim_end_matches('/'));
+    url.set_path(&path);
+
+    Some(url.to_string())
+}
+
+/// CometBFT version of a chain, its `compat_mode` or the one its node reports
+async fn comet_version(client: &reqwest::Client, chain: &HermesChain) -> String {
+    #[derive(Deserialize)]
+    struct Response {
+        result: NodeStatus,
+    }
+
+    #[derive(Deserialize)]
+    struct NodeStatus {
+        node_info: NodeInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct NodeInfo {
+        version: String,
+    }
+
+    if let Some(compat_mode) = &chain.compat_mode {
+        return compat_mode.clone();
+    }
+
+    let url = format!("{}/status", chain.rpc_addr.trim_end_matches('/'));
+
+    let response = async {
+        client
+            .get(url)
+            .timeout(STATUS_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Response>()
+            .await
+    };
+
+    let version = match response.await {
+        Ok(response) => response.result.node_info.version,
+        Err(e) => {
+            warn!(
+                "Failed to query the status of {}, set its comet_version if it is not {DEFAULT_COMET_VERSION}: {}",
+                chain.id,
+                e.without_url()
+            );
+            return DEFAULT_COMET_VERSION.to_string();
+        }
+    };
+
+    // Versions are configured with their major and minor components, such as `0.38`
+    let minor = version
+        .trim_start_matches('v')
+        .splitn(3, '.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".");
+
+    if !COMET_VERSIONS.contains(&minor.as_str()) {
+        warn!(
+            "{} runs CometBFT {version}, which is not supported, assuming {DEFAULT_COMET_VERSION}",
+            chain.id
+        );
+        return DEFAULT_COMET_VERSION.to_string();
+    }
+
+    minor
+}
+
+/// URL of the REST server of Hermes, used to trigger packet clearing
+fn hermes_url(rest: &Service) -> Option<String> {
+    if !rest.enabled {
+        return None;
+    }
+
+    let host = match rest.host.as_deref().unwrap_or("127.0.0.1") {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        host => host,
+    };
+
+    Some(format!("http://{host}:{}", rest.port.unwrap_or(3000)))
+}
+
+/// Port of the API and metrics server, which must not be one Hermes listens on when both run
+/// on the same host
+fn metrics_port(hermes: &HermesConfig) -> u16 {
+    let taken: Vec<u16> = [&hermes.rest, &hermes.telemetry]
+        .into_iter()
+        .filter(|service| service.enabled)
+        .filter_map(|service| service.port)
+        .collect();
+
+    (DEFAULT_METRICS_PORT..)
+        .find(|port| !taken.contains(port))
+        .unwrap_or(DEFAULT_METRICS_PORT)
+}
+
+/// Filters collecting the channels Hermes relays
+///
+/// The filters of chainpulse apply to every chain, so the channels allowed by each chain are
+/// only imported when all of them have an allow list, and are then allowed on every chain.
+/// Wildcards cannot be expressed either, so the ports or channels of a list holding one are all
+/// collected. Denied channels are never imported, as they would be dropped on every chain.
+fn filters(chains: &[HermesChain]) -> Filters {
+    let mut filters = Filters::default();
+
+    for chain in chains {
+        if let Some(filter) = chain.packet_filter.as_ref() {
+            if filter.policy == Policy::Deny && !filter.list.is_empty() {
+                warn!(
+                    "The channels denied on {} are not imported, as the filters apply to every chain: {}",
+                    chain.id,
+                    entries(&filter.list)
+                );
+            }
+        }
+    }
+
+    let unfiltered: Vec<&str> = chains
+        .iter()
+        .filter(|chain| {
+            chain.packet_filter.as_ref().map(|filter| filter.policy) != Some(Policy::Allow)
+        })
+        .map(|chain| chain.id.as_str())
+        .collect();
+
+    let allowed: Vec<&(String, String)> = chains
+        .iter()
+        .filter_map(|chain| chain.packet_filter.as_ref())
+        .filter(|filter| filter.policy == Policy::Allow)
+        .flat_map(|filter| &filter.list)
+        .collect();
+
+    if !unfiltered.is_empty() {
+        if !allowed.is_empty() {
+            warn!(
+                "Every channel is collected, as {} relay all of them",
+                unfiltered.join(", ")
+            );
+        }
+
+        return filters;
+    }
+
+    let wildcard = |id: &String| id.contains('*');
+
+    if allowed.iter().any(|(port, _)| wildcard(port)) {
+        warn!("Every port is collected, as the allowed ports include a wildcard");
+    } else {
+        filters.allow_ports = collect(allowed.iter().map(|(port, _)| port));
+    }
+
+    if allowed.iter().any(|(_, channel)| wildcard(channel)) {
+        warn!("Every channel is collected, as the allowed channels include a wildcard");
+    } else {
+        filters.allow_channels = collect(allowed.iter().map(|(_, channel)| channel));
+    }
+
+    filters
+}
+
+/// Sorted and deduplicated list of IDs
+fn collect<'a>(ids: impl Iterator<Item = &'a String>) -> Vec<String> {
+    ids.cloned().collect::<BTreeSet<_>>().into_iter().collect()
+}
+
+fn entries(list: &[(String, String)]) -> String {
+    list.iter()
+        .map(|(port, channel)| format!("{port}/{channel}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HERMES: &str = r#"
+        [rest]
+        enabled = true
+        host = '0.0.0.0'
+        port = 3000
+
+        [telemetry]
+        enabled = true
+        port = 3001
+
+        [[chains]]
+        id = 'osmosis-1'
+        rpc_addr = 'https://rpc.osmosis.zone:443'
+        event_source = { mode = 'push', url = 'wss://rpc.osmosis.zone:443/websocket', batch_delay = '500ms' }
+        compat_mode = '0.37'
+        [chains.packet_filter]
+        policy = 'allow'
+        list = [['transfer', 'channel-0'], ['transfer', 'channel-141']]
+
+        [[chains]]
+        id = 'cosmoshub-4'
+        rpc_addr = 'http://127.0.0.1:26657/'
+        event_source = { mode = 'pull', interval = '1s' }
+        [chains.packet_filter]
+        policy = 'allow'
+        list = [['transfer', 'channel-141'], ['icahost', 'channel-*']]
+    "#;
+
+    #[test]
+    fn test_import() {
+        let hermes: HermesConfig = toml::from_str(HERMES).unwrap();
+
+        let urls: Vec<_> = hermes.chains.iter().filter_map(websocket_url).collect();
+        assert_eq!(
+            urls,
+            [
+                "wss://rpc.osmosis.zone:443/websocket",
+                "ws://127.0.0.1:26657/websocket"
+            ]
+        );
+
+        assert_eq!(
+            hermes_url(&hermes.rest).as_deref(),
+            Some("http://127.0.0.1:3000")
+        );
+        assert_eq!(metrics_port(&hermes), 3002);
+
+        // The wildcard channel of the ICA host keeps every channel
+        let filters = filters(&hermes.chains);
+        assert_eq!(filters.allow_ports, ["icahost", "transfer"]);
+        assert!(filters.allow_channels.is_empty());
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut hermes: HermesConfig = toml::from_str(HERMES).unwrap();
+        hermes.chains[1].packet_filter.as_mut().unwrap().list.pop();
+
+        let imported = filters(&hermes.chains);
+        assert_eq!(imported.allow_ports, ["transfer"]);
+        assert_eq!(imported.allow_channels, ["channel-0", "channel-141"]);
+
+        // A chain relaying every channel needs all of them to be collected
+        hermes.chains[1].packet_filter.as_mut().unwrap().policy = Policy::Deny;
+
+        let imported = filters(&hermes.chains);
+        assert!(imported.allow_ports.is_empty());
+        assert!(imported.allow_channels.is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+pub mod backfill;
+pub mod dashboard;
+pub mod export;
+pub mod import_hermes;
+pub mod prune;
+pub mod replay;
+pub mod simulate;
+pub mod stats;
+pub mod validate;
+
+/// Commands of the binary, running the collector when none is given
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Run the collectors, the API server and the alert checks
+    Run,
+
+    /// Collect the IBC messages of past blocks of a chain
+    Backfill(backfill::Args),
+
+    /// Rebuild the packets, client updates, messages and events of the stored txs of a chain
+    Replay(replay::Args),
+
+    /// Delete the collected data older than a number of days
+    Prune(prune::Args),
+
+    /// Export the collected packets as CSV or JSON lines
+    Export(export::Args),
+
+    /// Inspect the database
+    #[clap(subcommand)]
+    Db(DbCommand),
+
+    /// Check that the configuration file is valid
+    ValidateConfig(validate::Args),
+
+    /// Show the activity of the chains and the stuck packets in the terminal
+    Dashboard(dashboard::Args),
+
+    /// Write a configuration collecting the chains and channels relayed by Hermes
+    ImportHermes(import_hermes::Args),
+
+    /// Record synthetic blocks of chains relaying packets, to benchmark the processing or test
+    /// it without live chains
+    Simulate(simulate::Args),
+}
+
+#[derive(clap::Subcommand)]
+pub enum DbCommand {
+    /// Print the size of the database and the number of rows of its tables and chains
+    Stats,
+}
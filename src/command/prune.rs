@@ -0,0 +1,82 @@
+use tracing::info;
+
+use crate::{config::Config, db};
+
+/// Delete the collected data older than a number of days
+#[derive(clap::Args)]
+pub struct Args {
+    /// Age in days of the oldest data to keep
+    #[clap(long)]
+    older_than_days: u32,
+
+    /// Report the number of rows which would be deleted, without deleting them
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Reclaim the space freed in the database file, which locks it for a while
+    #[clap(long)]
+    vacuum: bool,
+}
+
+/// Tables pruned, in deletion order, with the condition selecting their old rows
+///
+/// Txs are kept as long as a packet, client update, relay fee, decoded message or event which
+/// is kept refers to them. The daily rollups are not pruned, so the statistics of past days remain available.
+const TABLES: &[(&str, &str)] = &[
+    (
+        "event_attributes",
+        "event_id IN (SELECT id FROM tx_events WHERE created_at < datetime('now', ?))",
+    ),
+    ("tx_events", "created_at < datetime('now', ?)"),
+    ("packets", "created_at < datetime('now', ?)"),
+    ("client_updates", "created_at < datetime('now', ?)"),
+    ("relay_fees", "created_at < datetime('now', ?)"),
+    ("decoded_msgs", "created_at < datetime('now', ?)"),
+    (
+        "txs",
+        "created_at < datetime('now', ?)
+         AND id NOT IN (SELECT tx_id FROM packets)
+         AND id NOT IN (SELECT effected_tx FROM packets WHERE effected_tx IS NOT NULL)
+         AND id NOT IN (SELECT tx_id FROM client_updates)
+         AND id NOT IN (SELECT tx_id FROM relay_fees)
+         AND id NOT IN (SELECT tx_id FROM decoded_msgs)
+         AND id NOT IN (SELECT tx_id FROM tx_events)",
+    ),
+];
+
+/// Delete the old rows in a single transaction, rolled back on a dry run
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    let pool = db::connect(&config.database.path).await?;
+    db::setup(&pool).await;
+
+    let age = format!("-{} days", args.older_than_days);
+    let mut tx = pool.begin().await?;
+
+    for (table, condition) in TABLES {
+        let query = format!("DELETE FROM {table} WHERE {condition}");
+        let rows = sqlx::query(&query)
+            .bind(&age)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        match args.dry_run {
+            true => info!("Would delete {rows} rows from {table}"),
+            false => info!("Deleted {rows} rows from {table}"),
+        }
+    }
+
+    if args.dry_run {
+        tx.rollback().await?;
+        return Ok(());
+    }
+
+    tx.commit().await?;
+
+    if args.vacuum {
+        info!("Vacuuming the database...");
+        sqlx::query("VACUUM").execute(&pool).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use std::time::Instant;
+
+use sqlx::SqlitePool;
+use tendermint::chain;
+use tracing::info;
+
+use crate::{client, collect, config::Config, db, metrics::Metrics, rpc, status::Status};
+
+/// Number of blocks between two progress reports
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Rows derived from the txs of a chain between two heights, in deletion order
+const DERIVED: &[(&str, &str)] = &[
+    (
+        "event_attributes",
+        "event_id IN (SELECT id FROM tx_events WHERE tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?))",
+    ),
+    (
+        "tx_events",
+        "tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?)",
+    ),
+    (
+        "packets",
+        "tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?)",
+    ),
+    (
+        "client_updates",
+        "tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?)",
+    ),
+    (
+        "decoded_msgs",
+        "tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?)",
+    ),
+];
+
+/// Tables whose rows keep the timestamp of the tx they were first recorded with
+const TIMESTAMPED: &[&str] = &["tx_events", "packets", "client_updates", "decoded_msgs"];
+
+/// Rebuild the packets, client updates, decoded messages and events of the stored txs of a chain
+#[derive(clap::Args)]
+pub struct Args {
+    /// Chain to replay, which must be configured in `chains`
+    chain: String,
+
+    /// First height to replay, the lowest stored one by default
+    #[clap(long)]
+    from: Option<u64>,
+
+    /// Last height to replay, the highest stored one by default
+    #[clap(long)]
+    to: Option<u64>,
+}
+
+/// Delete the rows derived from the txs of the range, then fetch the blocks holding them again
+/// and process them with the current decoders
+///
+/// The txs are kept, along with the time they were collected, which the rebuilt rows take
+/// over. Packets are marked as effected or frontrun against the ones already recorded, so the
+/// range should run up to the highest stored height, as it does by default.
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    let chain_id = chain::Id::try_from(args.chain.as_str())?;
+
+    // Archive nodes of a chain reference keep the old blocks
+    let endpoint = &config
+        .chains
+        .endpoints
+        .get(&chain_id)
+        .ok_or_else(|| format!("chain {chain_id} is not configured"))?
+        .archive();
+
+    let pool = db::connect(&config.database.path).await?;
+    db::setup(&pool).await;
+
+    let (lowest, highest): (Option<i64>, Option<i64>) =
+        sqlx::query_as("SELECT MIN(height), MAX(height) FROM txs WHERE chain = ?")
+            .bind(chain_id.as_str())
+            .fetch_one(&pool)
+            .await?;
+
+    let (Some(lowest), Some(highest)) = (lowest, highest) else {
+        info!("No txs of {chain_id} are stored, nothing to replay");
+        return Ok(());
+    };
+
+    let from = args.from.unwrap_or(lowest as u64);
+    let to = args.to.unwrap_or(highest as u64);
+
+    let heights: Vec<i64> = sqlx::query_scalar(
+        "SELECT DISTINCT height FROM txs WHERE chain = ? AND height BETWEEN ? AND ? ORDER BY height",
+    )
+    .bind(chain_id.as_str())
+    .bind(from as i64)
+    .bind(to as i64)
+    .fetch_all(&pool)
+    .await?;
+
+    let Some(&first) = heights.first() else {
+        info!("No txs of {chain_id} are stored between {from} and {to}, nothing to replay");
+        return Ok(());
+    };
+
+    let (earliest, _) = rpc::available_heights(&reqwest::Client::new(), endpoint)
+        .await
+        .map_err(|e| format!("failed to query the status of {chain_id}: {e}"))?;
+
+    if (first as u64) < earliest {
+        return Err(format!(
+            "the node of {chain_id} only has blocks from height {earliest}, use an archive node"
+        )
+        .into());
+    }
+
+    // Counters are not exported, the collector rebuilds them with `populate_on_start`
+    let (metrics, _) = Metrics::new();
+    let status = Status::new();
+    status.register(
+        &chain_id,
+        &endpoint.url.to_string(),
+        endpoint.version_string(),
+    );
+
+    let client = client::create_client(
+        &endpoint.url,
+        endpoint.version_string(),
+        endpoint.auth.clone(),
+        config.performance.subscription_buffer,
+        endpoint.transport,
+        endpoint.poll_interval(),
+        endpoint.tls.as_ref(),
+        client::Keepalive::default(),
+    )
+    .await?;
+
+    info!(
+        "Replaying {} blocks of {chain_id} from height {from} to {to}",
+        heights.len()
+    );
+
+    delete_derived(&pool, &chain_id, from, to).await?;
+
+    let start = Instant::now();
+    let processing = collect::Processing::new(config, &chain_id);
+    let mut replayed = 0;
+    let mut reported = 0;
+
+    for batch in heights.chunks(config.performance.db_batch_size) {
+        let batch: Vec<u64> = batch.iter().map(|height| *height as u64).collect();
+        let (first, last) = (batch[0], batch[batch.len() - 1]);
+
+        collect::process_batch(
+            &pool,
+            &chain_id,
+            client.as_ref(),
+            &batch,
+            &metrics,
+            &status,
+            &processing,
+            false,
+        )
+        .await?;
+
+        restore_timestamps(&pool, &chain_id, first, last).await?;
+
+        replayed += batch.len();
+
+        if replayed - reported >= PROGRESS_INTERVAL {
+            info!("Replayed blocks up to height {last}");
+            reported = replayed;
+        }
+    }
+
+    info!("Replayed {} blocks in {:?}", heights.len(), start.elapsed());
+
+    Ok(())
+}
+
+/// Delete the rows derived from the txs of the range in a single transaction
+async fn delete_derived(
+    pool: &SqlitePool,
+    chain_id: &chain::Id,
+    from: u64,
+    to: u64,
+) -> crate::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for (table, condition) in DERIVED {
+        let query = format!("DELETE FROM {table} WHERE {condition}");
+        let rows = sqlx::query(&query)
+            .bind(chain_id.as_str())
+            .bind(from as i64)
+            .bind(to as i64)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        info!("Deleted {rows} rows from {table}");
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Timestamp the rows rebuilt from the txs of the blocks between two heights with the time the
+/// txs were collected
+async fn restore_timestamps(
+    pool: &SqlitePool,
+    chain_id: &chain::Id,
+    from: u64,
+    to: u64,
+) -> crate::Result<()> {
+    for table in TIMESTAMPED {
+        let query = format!(
+            "UPDATE {table}
+             SET created_at = (SELECT created_at FROM txs WHERE txs.id = {table}.tx_id)
+             WHERE tx_id IN (SELECT id FROM txs WHERE chain = ? AND height BETWEEN ? AND ?)"
+        );
+
+        sqlx::query(&query)
+            .bind(chain_id.as_str())
+            .bind(from as i64)
+            .bind(to as i64)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
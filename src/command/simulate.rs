@@ -0,0 +1,583 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use ibc_proto::{
+    cosmos::{
+        base::v1beta1::Coin,
+        tx::v1beta1::{AuthInfo, Fee, Tx, TxBody},
+    },
+    google::protobuf::Any,
+    ibc::{
+        applications::transfer::v1::MsgTransfer,
+        core::channel::v1::{MsgAcknowledgement, MsgRecvPacket, Packet},
+    },
+};
+use prost::Message;
+use sqlx::SqlitePool;
+use tendermint::{
+    account,
+    block::{header::Version, Commit, Header, Height},
+    chain, evidence,
+    hash::AppHash,
+    Block, Hash, Time,
+};
+use tokio::time;
+use tracing::info;
+
+use crate::{
+    client::{BlockResults, EventAttribute, TxEvent, TxResult},
+    collect::{self, Processing},
+    config::{Config, Mode},
+    db,
+    metrics::Metrics,
+    msg::FungibleTokenPacketData,
+    status::Status,
+};
+
+/// Number of blocks between two progress reports
+const PROGRESS_INTERVAL: u64 = 1000;
+
+/// Time of the first simulated block, 2024-01-01
+const GENESIS_TIME: i64 = 1_704_067_200;
+
+/// Timeout of the simulated packets, 2100-01-01, so that none of them expires
+const TIMEOUT_TIMESTAMP: u64 = 4_102_444_800_000_000_000;
+
+/// Denom and amount of the simulated transfers
+const DENOM: &str = "usim";
+const AMOUNT: &str = "1000";
+
+/// Generate synthetic blocks of chains relaying packets to each other, and record them as the
+/// collectors would
+#[derive(clap::Args)]
+pub struct Args {
+    /// Number of chains, each sending packets to the next one, from a channel named after the
+    /// index of the chain to that of the next one, such as `channel-0` to `channel-1`
+    #[clap(long, default_value_t = 2)]
+    chains: usize,
+
+    /// Number of blocks of each chain
+    #[clap(long, default_value_t = 1000)]
+    blocks: u64,
+
+    /// Transfers sent in each block of each chain
+    #[clap(long, default_value_t = 10)]
+    transfers: usize,
+
+    /// Share of the packets never relayed, left stuck
+    #[clap(long, default_value_t = 0.05)]
+    stuck_ratio: f64,
+
+    /// Share of the packets two relayers race to relay, one of them being frontrun
+    #[clap(long, default_value_t = 0.1)]
+    race_ratio: f64,
+
+    /// Blocks between the transfer of a packet and its relay, and between its relay and its
+    /// acknowledgement
+    #[clap(long, default_value_t = 2)]
+    relay_delay: u64,
+
+    /// Milliseconds to wait between two heights, as fast as possible by default
+    #[clap(long, default_value_t = 0)]
+    block_time_ms: u64,
+
+    /// Seed of the draws, the same seed generating the same blocks
+    #[clap(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Record the blocks in `database.path` rather than in a database in memory
+    #[clap(long)]
+    persist: bool,
+}
+
+/// Packets the simulated blocks should leave recorded, and those recorded once processed
+#[derive(Debug, Default, PartialEq)]
+struct Counts {
+    /// Transfers sent, recorded from their `send_packet` events
+    sent: i64,
+    /// Relays which were frontrun by another relayer
+    frontrun: i64,
+    /// Packets sent and not acknowledged, the stuck ones along with those still in flight
+    pending: i64,
+}
+
+/// Generate the blocks, record them in a single transaction per batch, then compare the
+/// packets recorded with those the blocks hold
+///
+/// The filters of the configuration are not applied, as they could leave out the simulated
+/// packets. In the light mode, the block results are not processed, so the pending packets are
+/// not compared.
+pub async fn run(config: &Config, args: Args) -> crate::Result<()> {
+    if args.chains == 0 || args.relay_delay == 0 {
+        return Err("at least one chain and a relay delay of one block are needed".into());
+    }
+
+    if !(0.0..=1.0).contains(&args.stuck_ratio) || !(0.0..=1.0).contains(&args.race_ratio) {
+        return Err("the stuck and race ratios must be between 0 and 1".into());
+    }
+
+    let pool = match args.persist {
+        true => db::connect(&config.database.path).await?,
+        false => db::connect_in_memory().await?,
+    };
+    db::setup(&pool).await;
+
+    let processing = Processing {
+        mode: config.mode,
+        ..Default::default()
+    };
+
+    info!(
+        "Simulating {} blocks of {} chains with {} transfers each",
+        args.blocks, args.chains, args.transfers
+    );
+
+    let start = Instant::now();
+    let (mut expected, txs) = simulate(
+        &pool,
+        &args,
+        &processing,
+        config.performance.db_batch_size as u64,
+    )
+    .await?;
+    let elapsed = start.elapsed();
+
+    let mut recorded = recorded(&pool).await?;
+
+    if processing.mode == Mode::Light {
+        (expected.sent, expected.pending) = (0, 0);
+        (recorded.sent, recorded.pending) = (0, 0);
+    }
+
+    let blocks = args.blocks * args.chains as u64;
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+
+    println!(
+        "Simulated {blocks} blocks and {txs} txs in {elapsed:.2?}: {:.0} blocks/s, {:.0} txs/s",
+        blocks as f64 / seconds,
+        txs as f64 / seconds
+    );
+    println!();
+    println!("{:<24} {:>12} {:>12}", "PACKETS", "EXPECTED", "RECORDED");
+    println!("{:<24} {:>12} {:>12}", "sent", expected.sent, recorded.sent);
+    println!(
+        "{:<24} {:>12} {:>12}",
+        "frontrun", expected.frontrun, recorded.frontrun
+    );
+    println!(
+        "{:<24} {:>12} {:>12}",
+        "pending", expected.pending, recorded.pending
+    );
+
+    if expected != recorded {
+        return Err("the recorded packets differ from the simulated ones".into());
+    }
+
+    Ok(())
+}
+
+/// Generate and record the blocks, returning the packets they hold and the number of txs
+async fn simulate(
+    pool: &SqlitePool,
+    args: &Args,
+    processing: &Processing,
+    batch_size: u64,
+) -> crate::Result<(Counts, u64)> {
+    let (metrics, _) = Metrics::new();
+    let status = Status::new();
+    let mut simulation = Simulation::new(args)?;
+    let mut txs = 0;
+    let batch_size = batch_size.max(1);
+
+    for chain_id in &simulation.chains {
+        status.register(chain_id, "simulated", "0.38");
+    }
+
+    for first in (1..=args.blocks).step_by(batch_size as usize) {
+        let last = (first + batch_size - 1).min(args.blocks);
+        let mut tx = pool.begin().await?;
+
+        for height in first..=last {
+            for chain in 0..args.chains {
+                let (block, results) = simulation.block(chain, height)?;
+                let results = (processing.mode == Mode::Full).then_some(&results);
+                txs += block.data.len() as u64;
+
+                collect::process_block(
+                    &mut tx,
+                    &simulation.chains[chain],
+                    &block,
+                    results,
+                    &metrics,
+                    &status,
+                    processing,
+                )
+                .await
+                .map_err(|e| format!("failed to process block {height}: {e}"))?;
+            }
+
+            if args.block_time_ms > 0 {
+                time::sleep(Duration::from_millis(args.block_time_ms)).await;
+            }
+        }
+
+        tx.commit().await?;
+
+        if last / PROGRESS_INTERVAL > (first - 1) / PROGRESS_INTERVAL {
+            info!("Processed blocks up to height {last}");
+        }
+    }
+
+    Ok((simulation.expected, txs))
+}
+
+/// Packets recorded in the database
+async fn recorded(pool: &SqlitePool) -> sqlx::Result<Counts> {
+    let (sent, frontrun, pending) = sqlx::query_as(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE msg_type_url = 'send_packet'),
+            COUNT(*) FILTER (WHERE msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+                               AND effected = 0),
+            COUNT(*) FILTER (WHERE msg_type_url = 'send_packet' AND effected = 0)
+        FROM packets
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Counts {
+        sent,
+        frontrun,
+        pending,
+    })
+}
+
+/// Chains generating their blocks, and the packets they are to relay and acknowledge
+struct Simulation {
+    chains: Vec<chain::Id>,
+    rng: Rng,
+    transfers: usize,
+    stuck_ratio: f64,
+    race_ratio: f64,
+    relay_delay: u64,
+    /// Next sequence of each chain
+    sequences: Vec<u64>,
+    /// Packets to receive by height and chain, and whether two relayers race to relay them
+    relays: BTreeMap<(u64, usize), Vec<(Packet, bool)>>,
+    /// Packets to acknowledge by height and chain
+    acks: BTreeMap<(u64, usize), Vec<Packet>>,
+    expected: Counts,
+}
+
+impl Simulation {
+    fn new(args: &Args) -> crate::Result<Self> {
+        let chains = (1..=args.chains)
+            .map(|i| chain::Id::try_from(format!("sim-{i}")))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            chains,
+            rng: Rng(args.seed),
+            transfers: args.transfers,
+            stuck_ratio: args.stuck_ratio,
+            race_ratio: args.race_ratio,
+            relay_delay: args.relay_delay,
+            sequences: vec![1; args.chains],
+            relays: BTreeMap::new(),
+            acks: BTreeMap::new(),
+            expected: Counts::default(),
+        })
+    }
+
+    /// Block of a chain at a height, with its results, holding the transfers it sends, then
+    /// the relays and acknowledgements due
+    fn block(&mut self, chain: usize, height: u64) -> crate::Result<(Block, BlockResults)> {
+        let counterparty = (chain + 1) % self.chains.len();
+        let mut txs: Vec<(Vec<u8>, TxResult)> = Vec::new();
+
+        for _ in 0..self.transfers {
+            let (packet, data) = self.packet(chain);
+            self.sequences[chain] += 1;
+            self.expected.sent += 1;
+            self.expected.pending += 1;
+
+            txs.push(transfer(&packet, data));
+
+            if !self.rng.chance(self.stuck_ratio) {
+                let raced = self.rng.chance(self.race_ratio);
+                self.relays
+                    .entry((height + self.relay_delay, counterparty))
+                    .or_default()
+                    .push((packet, raced));
+            }
+        }
+
+        for (packet, raced) in self.relays.remove(&(height, chain)).unwrap_or_default() {
+            txs.push(relay(&packet, "sim1relayer0"));
+
+            // The second relayer lands in the same block, and is frontrun
+            if raced {
+                txs.push(relay(&packet, "sim1relayer1"));
+                self.expected.frontrun += 1;
+            }
+
+            let source = (chain + self.chains.len() - 1) % self.chains.len();
+            self.acks
+                .entry((height + self.relay_delay, source))
+                .or_default()
+                .push(packet);
+        }
+
+        for packet in self.acks.remove(&(height, chain)).unwrap_or_default() {
+            txs.push(acknowledgement(&packet));
+            self.expected.pending -= 1;
+        }
+
+        let header = Header {
+            version: Version { block: 11, app: 0 },
+            chain_id: self.chains[chain].clone(),
+            height: Height::try_from(height)?,
+            time: Time::from_unix_timestamp(GENESIS_TIME + height as i64 * 6, 0)?,
+            last_block_id: None,
+            last_commit_hash: None,
+            data_hash: None,
+            validators_hash: Hash::None,
+            next_validators_hash: Hash::None,
+            consensus_hash: Hash::None,
+            app_hash: AppHash::default(),
+            last_results_hash: None,
+            evidence_hash: None,
+            proposer_address: account::Id::new([0; 20]),
+        };
+
+        let (data, txs_results) = txs.into_iter().unzip();
+        let last_commit = (height > 1).then(Commit::default);
+        let block = Block::new(header, data, evidence::List::default(), last_commit)?;
+
+        let results = BlockResults {
+            height: block.header.height,
+            txs_results,
+        };
+
+        Ok((block, results))
+    }
+
+    /// Next packet sent by a chain to the next one, with its data
+    fn packet(&self, chain: usize) -> (Packet, FungibleTokenPacketData) {
+        let counterparty = (chain + 1) % self.chains.len();
+
+        let data = FungibleTokenPacketData {
+            denom: DENOM.to_string(),
+            amount: AMOUNT.to_string(),
+            sender: format!("sim1sender{chain}"),
+            receiver: format!("sim1receiver{counterparty}"),
+            memo: String::new(),
+        };
+
+        let packet = Packet {
+            sequence: self.sequences[chain],
+            source_port: "transfer".to_string(),
+            source_channel: format!("channel-{chain}"),
+            destination_port: "transfer".to_string(),
+            destination_channel: format!("channel-{counterparty}"),
+            data: serde_json::to_vec(&data).unwrap_or_default(),
+            timeout_height: None,
+            timeout_timestamp: TIMEOUT_TIMESTAMP,
+        };
+
+        (packet, data)
+    }
+}
+
+/// `MsgTransfer` sending a packet, along with its `send_packet` event
+fn transfer(packet: &Packet, data: FungibleTokenPacketData) -> (Vec<u8>, TxResult) {
+    let msg = MsgTransfer {
+        source_port: packet.source_port.clone(),
+        source_channel: packet.source_channel.clone(),
+        token: Some(coin(AMOUNT)),
+        sender: data.sender,
+        receiver: data.receiver,
+        timeout_height: None,
+        timeout_timestamp: packet.timeout_timestamp,
+        memo: data.memo,
+    };
+
+    let event = TxEvent {
+        type_str: "send_packet".to_string(),
+        attributes: packet_attributes(packet, true),
+    };
+
+    // Transfers of the same sender and amount are told apart by the memo of their tx
+    let memo = format!("simulated transfer {}", packet.sequence);
+
+    (
+        tx("/ibc.applications.transfer.v1.MsgTransfer", msg, memo),
+        result(event),
+    )
+}
+
+/// `MsgRecvPacket` of a relayer, along with its `recv_packet` event
+fn relay(packet: &Packet, relayer: &str) -> (Vec<u8>, TxResult) {
+    let msg = MsgRecvPacket {
+        packet: Some(packet.clone()),
+        proof_commitment: vec![],
+        proof_height: None,
+        signer: relayer.to_string(),
+    };
+
+    let event = TxEvent {
+        type_str: "recv_packet".to_string(),
+        attributes: packet_attributes(packet, false),
+    };
+
+    (
+        tx("/ibc.core.channel.v1.MsgRecvPacket", msg, String::new()),
+        result(event),
+    )
+}
+
+/// `MsgAcknowledgement` of a packet, along with its `acknowledge_packet` event
+fn acknowledgement(packet: &Packet) -> (Vec<u8>, TxResult) {
+    let msg = MsgAcknowledgement {
+        packet: Some(packet.clone()),
+        acknowledgement: br#"{"result":"AQ=="}"#.to_vec(),
+        proof_acked: vec![],
+        proof_height: None,
+        signer: "sim1relayer0".to_string(),
+    };
+
+    let event = TxEvent {
+        type_str: "acknowledge_packet".to_string(),
+        attributes: packet_attributes(packet, false),
+    };
+
+    (
+        tx(
+            "/ibc.core.channel.v1.MsgAcknowledgement",
+            msg,
+            String::new(),
+        ),
+        result(event),
+    )
+}
+
+/// Encoded tx of a single message, paying a fee
+fn tx(type_url: &str, msg: impl Message, memo: String) -> Vec<u8> {
+    Tx {
+        body: Some(TxBody {
+            messages: vec![Any {
+                type_url: type_url.to_string(),
+                value: msg.encode_to_vec(),
+            }],
+            memo,
+            ..Default::default()
+        }),
+        auth_info: Some(AuthInfo {
+            fee: Some(Fee {
+                amount: vec![coin("5000")],
+                gas_limit: 200_000,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        signatures: vec![],
+    }
+    .encode_to_vec()
+}
+
+fn coin(amount: &str) -> Coin {
+    Coin {
+        denom: DENOM.to_string(),
+        amount: amount.to_string(),
+    }
+}
+
+fn result(event: TxEvent) -> TxResult {
+    TxResult {
+        code: 0,
+        events: vec![event],
+    }
+}
+
+/// Attributes of the packet events, with the data hex encoded as ibc-go emits it
+fn packet_attributes(packet: &Packet, with_data: bool) -> Vec<EventAttribute> {
+    let mut attributes = vec![
+        ("packet_sequence", packet.sequence.to_string()),
+        ("packet_src_port", packet.source_port.clone()),
+        ("packet_src_channel", packet.source_channel.clone()),
+        ("packet_dst_port", packet.destination_port.clone()),
+        ("packet_dst_channel", packet.destination_channel.clone()),
+        (
+            "packet_timeout_timestamp",
+            packet.timeout_timestamp.to_string(),
+        ),
+    ];
+
+    if with_data {
+        let data = subtle_encoding::hex::encode(&packet.data);
+        attributes.push(("packet_data", String::from_utf8_lossy(&data).into_owned()));
+    }
+
+    attributes
+        .into_iter()
+        .map(|(key, value)| EventAttribute {
+            key: key.to_string(),
+            value,
+        })
+        .collect()
+}
+
+/// Generator of the draws of a simulation, SplitMix64, the same seed giving the same draws
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Whether an event of the given probability happens
+    fn chance(&mut self, probability: f64) -> bool {
+        // The 53 high bits, uniform in [0, 1)
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_simulate() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let args = Args {
+            chains: 3,
+            blocks: 40,
+            transfers: 5,
+            stuck_ratio: 0.2,
+            race_ratio: 0.3,
+            relay_delay: 2,
+            block_time_ms: 0,
+            seed: 7,
+            persist: false,
+        };
+
+        let (expected, txs) = simulate(&pool, &args, &Processing::default(), 16)
+            .await
+            .unwrap();
+
+        assert_eq!(expected.sent, 600);
+        assert!(expected.frontrun > 0 && expected.pending > 0);
+        assert!(txs > 600);
+        assert_eq!(recorded(&pool).await.unwrap(), expected);
+    }
+}
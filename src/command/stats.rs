@@ -0,0 +1,99 @@
+use std::{fs, path::Path};
+
+use crate::{config::Config, db};
+
+/// Print the size of the database, the number of rows of its tables and the data collected
+/// for each chain
+pub async fn run(config: &Config) -> crate::Result<()> {
+    let path = &config.database.path;
+
+    if !path.exists() {
+        return Err(format!("no database at {}", path.display()).into());
+    }
+
+    let pool = db::connect(path).await?;
+
+    let wal = path.with_file_name(format!(
+        "{}-wal",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+
+    println!("Database: {}", path.display());
+    println!("Size:     {}", format_size(file_size(path)));
+    println!("WAL:      {}", format_size(file_size(&wal)));
+    println!();
+
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    println!("{:<24} {:>12}", "TABLE", "ROWS");
+
+    for table in tables {
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{table}\""))
+            .fetch_one(&pool)
+            .await?;
+
+        println!("{table:<24} {rows:>12}");
+    }
+
+    let chains = sqlx::query_as::<_, (String, i64, i64, i64, String, String)>(
+        r#"
+        SELECT
+            t.chain,
+            COUNT(DISTINCT t.id),
+            COUNT(p.id),
+            MAX(t.height),
+            MIN(t.created_at),
+            MAX(t.created_at)
+        FROM txs t
+        LEFT JOIN packets p ON p.tx_id = t.id
+        GROUP BY t.chain
+        ORDER BY t.chain
+        "#,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    println!();
+    println!(
+        "{:<24} {:>12} {:>12} {:>12}  {:<19}  LAST SEEN",
+        "CHAIN", "TXS", "PACKETS", "HEIGHT", "FIRST SEEN"
+    );
+
+    for (chain, txs, packets, height, first_seen, last_seen) in chains {
+        println!("{chain:<24} {txs:>12} {packets:>12} {height:>12}  {first_seen:<19}  {last_seen}");
+    }
+
+    Ok(())
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+/// Size in bytes, KiB, MiB or GiB
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+
+        size /= 1024.0;
+        unit = next;
+    }
+
+    match unit {
+        "B" => format!("{bytes} B"),
+        unit => format!("{size:.1} {unit}"),
+    }
+}
@@ -0,0 +1,439 @@
+use std::{fmt, path::Path, time::Duration};
+
+use futures::{future, StreamExt};
+use serde::Deserialize;
+use tendermint::{block::Height, chain};
+use tendermint_rpc::event::EventData;
+use tokio::time;
+
+use crate::{
+    client::{self, ChainClient},
+    config::{Config, Endpoint, Overrides, AUTO_COMET_VERSION, COMET_VERSIONS},
+    rpc,
+};
+
+/// Check that the configuration file loads, resolving its chain references and secrets
+#[derive(clap::Args)]
+pub struct Args {
+    /// Print the effective configuration, with credentials redacted, as JSON
+    #[clap(long)]
+    print: bool,
+
+    /// Connect to the endpoint of every chain, query its status and wait for a new block
+    #[clap(long)]
+    probe: bool,
+
+    /// Seconds to wait for each connection and for the first block when probing
+    #[clap(long, default_value = "30")]
+    timeout: u64,
+}
+
+/// Load the configuration as the collector would and summarize it, failing when it is invalid
+pub async fn run(path: &Path, overrides: &Overrides, args: Args) -> crate::Result<()> {
+    let mut config = Config::load(path)
+        .await
+        .map_err(|e| format!("{} is invalid: {e}", path.display()))?;
+
+    overrides.apply(&mut config);
+
+    if args.print {
+        println!("{}", serde_json::to_string_pretty(&config.sanitized())?);
+        return Ok(());
+    }
+
+    let chains: Vec<String> = config
+        .chains
+        .endpoints
+        .iter()
+        .map(|(chain_id, endpoint)| format!("{chain_id} ({})", endpoint.version))
+        .collect();
+
+    let mut notifiers = Vec::new();
+
+    if !config.webhooks.is_empty() {
+        notifiers.push(format!("{} webhooks", config.webhooks.len()));
+    }
+
+    for (name, enabled) in [
+        ("slack", config.slack.is_some()),
+        ("discord", config.discord.is_some()),
+        ("telegram", config.telegram.is_some()),
+        ("email", config.email.is_some()),
+    ] {
+        if enabled {
+            notifiers.push(name.to_string());
+        }
+    }
+
+    let api = match config.metrics.enabled {
+        true => format!("{}:{}", config.metrics.bind_address, config.metrics.port),
+        false => "disabled".to_string(),
+    };
+
+    println!("{} is valid", path.display());
+    println!("Chains:    {}", or_none(&chains));
+    println!("Database:  {}", config.database.path.display());
+    println!("API:       {api}");
+    println!("Notifiers: {}", or_none(&notifiers));
+
+    let notified = config.metrics.enabled && config.metrics.stuck_packets;
+
+    if !notifiers.is_empty() && !notified {
+        println!("Warning:   notifiers are only used with `metrics.stuck_packets` enabled");
+    }
+
+    if args.probe {
+        probe_all(&config, Duration::from_secs(args.timeout)).await?;
+    }
+
+    Ok(())
+}
+
+fn or_none(items: &[String]) -> String {
+    match items.is_empty() {
+        true => "none".to_string(),
+        false => items.join(", "),
+    }
+}
+
+/// Outcome of a check of an endpoint
+enum Check {
+    Pass,
+    Fail(String),
+    Skipped,
+}
+
+impl Check {
+    /// A failed check, with the error and its causes on a single line, without the location
+    /// some errors of the RPC client carry
+    fn fail(e: impl fmt::Display) -> Self {
+        let e = e.to_string();
+        let causes: Vec<&str> = e
+            .lines()
+            .take_while(|line| !line.starts_with("Location:"))
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && *line != "Caused by:")
+            .collect();
+
+        Check::Fail(causes.join(": "))
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let check = match self {
+            Check::Pass => "pass",
+            Check::Fail(_) => "FAIL",
+            Check::Skipped => "skipped",
+        };
+
+        // Pad the label, as the table columns are aligned with the width of the argument
+        f.pad(check)
+    }
+}
+
+/// Checks of the endpoint of a chain, along with what they found
+struct Probe {
+    chain_id: chain::Id,
+    connect: Check,
+    status: Check,
+    subscribe: Check,
+    notes: Vec<String>,
+}
+
+impl Probe {
+    fn failures(&self) -> impl Iterator<Item = (&str, &str)> {
+        [
+            ("connect", &self.connect),
+            ("status", &self.status),
+            ("subscribe", &self.subscribe),
+        ]
+        .into_iter()
+        .filter_map(|(name, check)| match check {
+            Check::Fail(e) => Some((name, e.as_str())),
+            _ => None,
+        })
+    }
+}
+
+/// Probe the endpoints of the chains concurrently, print the results as a table and fail when
+/// any check failed
+async fn probe_all(config: &Config, timeout: Duration) -> crate::Result<()> {
+    println!();
+    println!("Probing {} chains...", config.chains.endpoints.len());
+
+    let probes = future::join_all(
+        config
+            .chains
+            .endpoints
+            .iter()
+            .map(|(chain_id, endpoint)| probe(chain_id, endpoint, timeout)),
+    )
+    .await;
+
+    println!();
+    println!(
+        "{:<24} {:<8} {:<8} {:<10} NOTES",
+        "CHAIN", "CONNECT", "STATUS", "SUBSCRIBE"
+    );
+
+    for probe in &probes {
+        let row = format!(
+            "{:<24} {:<8} {:<8} {:<10} {}",
+            probe.chain_id.as_str(),
+            probe.connect,
+            probe.status,
+            probe.subscribe,
+            probe.notes.join(", ")
+        );
+
+        println!("{}", row.trim_end());
+    }
+
+    let failures: Vec<String> = probes
+        .iter()
+        .flat_map(|probe| {
+            probe
+                .failures()
+                .map(|(check, e)| format!("{} {check}: {e}", probe.chain_id))
+        })
+        .collect();
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+
+    for failure in &failures {
+        println!("{failure}");
+    }
+
+    Err(format!("{} checks failed", failures.len()).into())
+}
+
+/// Query the status of the RPC server of a chain, then connect to its WebSocket endpoint as
+/// the collector would and wait for a new block
+async fn probe(chain_id: &chain::Id, endpoint: &Endpoint, timeout: Duration) -> Probe {
+    let mut probe = Probe {
+        chain_id: chain_id.clone(),
+        connect: Check::Skipped,
+        status: Check::Skipped,
+        subscribe: Check::Skipped,
+        notes: Vec::new(),
+    };
+
+    probe.status = match node_status(endpoint).await {
+        Ok(status) => check_status(chain_id, endpoint, status, &mut probe.notes),
+        Err(e) => Check::fail(e),
+    };
+
+    if !endpoint.fallbacks.is_empty() {
+        probe
+            .notes
+            .push(format!("{} fallbacks", endpoint.fallbacks.len()));
+    }
+
+    // A single block is waited for
+    let connect = client::create_client(
+        &endpoint.url,
+        endpoint.version_string(),
+        endpoint.auth.clone(),
+        1,
+        endpoint.transport,
+        endpoint.poll_interval(),
+        endpoint.tls.as_ref(),
+        client::Keepalive::default(),
+    );
+
+    let client = match time::timeout(timeout, connect).await {
+        Ok(Ok(client)) => client,
+        Ok(Err(e)) => {
+            probe.connect = Check::fail(e);
+            return probe;
+        }
+        Err(_) => {
+            probe.connect = Check::Fail(format!("no connection after {timeout:?}"));
+            return probe;
+        }
+    };
+
+    // Subscribing is what establishes the connection of some clients
+    match time::timeout(timeout, first_block(client.as_ref())).await {
+        Ok(Ok(height)) => {
+            probe.connect = Check::Pass;
+            probe.subscribe = Check::Pass;
+            probe.notes.push(format!("received block {height}"));
+        }
+        Ok(Err(e)) => {
+            probe.connect = Check::Pass;
+            probe.subscribe = Check::fail(e);
+        }
+        Err(_) => {
+            probe.connect = Check::Pass;
+            probe.subscribe = Check::Fail(format!("no block after {timeout:?}"));
+        }
+    }
+
+    probe
+}
+
+/// Height of the first block received from a new subscription
+async fn first_block(client: &dyn ChainClient) -> crate::Result<Height> {
+    let mut subscription = client.subscribe_blocks().await?;
+
+    while let Some(event) = subscription.next().await {
+        if let EventData::NewBlock {
+            block: Some(block), ..
+        } = event?.data
+        {
+            return Ok(block.header.height);
+        }
+    }
+
+    Err("the subscription ended".into())
+}
+
+#[derive(Deserialize)]
+struct NodeStatus {
+    node_info: NodeInfo,
+    sync_info: SyncInfo,
+}
+
+#[derive(Deserialize)]
+struct NodeInfo {
+    network: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct SyncInfo {
+    latest_block_height: String,
+    catching_up: bool,
+}
+
+async fn node_status(endpoint: &Endpoint) -> crate::Result<NodeStatus> {
+    rpc::get(&reqwest::Client::new(), endpoint, "/status").await
+}
+
+/// Check that the node serves the configured chain with the configured CometBFT version
+fn check_status(
+    chain_id: &chain::Id,
+    endpoint: &Endpoint,
+    status: NodeStatus,
+    notes: &mut Vec<String>,
+) -> Check {
+    let NodeStatus {
+        node_info,
+        sync_info,
+    } = status;
+
+    notes.push(format!(
+        "height {}, CometBFT {}",
+        sync_info.latest_block_height, node_info.version
+    ));
+
+    if sync_info.catching_up {
+        notes.push("catching up".to_string());
+    }
+
+    if node_info.network != chain_id.as_str() {
+        return Check::Fail(format!("the node serves {}", node_info.network));
+    }
+
+    // Versions are compared on their major and minor components, such as `0.38`
+    let minor = rpc::minor_version(&node_info.version);
+
+    if endpoint.version == AUTO_COMET_VERSION {
+        if !COMET_VERSIONS.contains(&minor.as_str()) {
+            return Check::Fail(format!(
+                "the node runs CometBFT {}, expected one of {}",
+                node_info.version,
+                COMET_VERSIONS.join(", ")
+            ));
+        }
+    } else if minor != endpoint.version {
+        return Check::Fail(format!(
+            "the node runs CometBFT {}, but comet_version is {}",
+            node_info.version, endpoint.version
+        ));
+    }
+
+    Check::Pass
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tendermint_rpc::WebSocketClientUrl;
+
+    use super::*;
+    use crate::config::{CometVersion, Transport, TxFormat};
+
+    fn status(network: &str, version: &str) -> NodeStatus {
+        NodeStatus {
+            node_info: NodeInfo {
+                network: network.to_string(),
+                version: version.to_string(),
+            },
+            sync_info: SyncInfo {
+                latest_block_height: "100".to_string(),
+                catching_up: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_check_status() {
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        let endpoint = Endpoint {
+            url: WebSocketClientUrl::from_str("wss://rpc.example.com/websocket").unwrap(),
+            comet_version: CometVersion::V0_34,
+            version: "0.38".to_string(),
+            ibc_version: "v1".to_string(),
+            auth: Default::default(),
+            archive: false,
+            fallbacks: Vec::new(),
+            enabled: true,
+            start_paused: false,
+            tx_format: TxFormat::Cosmos,
+            transport: Transport::WebSocket,
+            tls: None,
+            tx_query: None,
+            max_backoff_seconds: None,
+            poll_interval_ms: 1000,
+        };
+
+        let check = |network, version| {
+            check_status(
+                &chain_id,
+                &endpoint,
+                status(network, version),
+                &mut Vec::new(),
+            )
+        };
+
+        assert!(matches!(check("osmosis-1", "0.38.12"), Check::Pass));
+        assert!(matches!(check("osmosis-1", "v0.38.0"), Check::Pass));
+        assert!(matches!(check("osmosis-1", "0.37.4"), Check::Fail(_)));
+        assert!(matches!(check("cosmoshub-4", "0.38.12"), Check::Fail(_)));
+
+        // Any version with a client passes when it is detected
+        let endpoint = Endpoint {
+            version: AUTO_COMET_VERSION.to_string(),
+            ..endpoint
+        };
+        let check = |network, version| {
+            check_status(
+                &chain_id,
+                &endpoint,
+                status(network, version),
+                &mut Vec::new(),
+            )
+        };
+
+        assert!(matches!(check("osmosis-1", "0.37.4"), Check::Pass));
+        assert!(matches!(check("osmosis-1", "0.39.0"), Check::Fail(_)));
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use ibc_proto::ibc::core::{
+    channel::v1::{QueryChannelRequest, QueryChannelResponse},
+    connection::v1::{QueryConnectionRequest, QueryConnectionResponse},
+};
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{Config, Endpoint},
+    expiry, rpc,
+};
+
+/// How often the channels seen since the last pass are resolved
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time before a channel which failed to resolve is queried again
+const RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Counterparty of a channel, as queried from the chain hosting it
+#[derive(Clone, Debug)]
+struct Counterparty {
+    connection_id: String,
+    client_id: String,
+    chain_id: String,
+    port_id: String,
+    channel_id: String,
+}
+
+/// Resolve the counterparty chain of every channel packets were sent on, once, and record it
+/// in the `channel_counterparties` table
+///
+/// The chain ID is the one of the light client backing the connection of the channel, found
+/// through the `Channel`, `Connection` and `ClientState` queries to the chain hosting the
+/// channel. Until a channel is resolved, its counterparty is taken from the packets received
+/// on the other end, when that chain is monitored too.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    let mut interval = time::interval(RESOLVE_INTERVAL);
+    let mut failed: HashMap<(String, String, String), Instant> = HashMap::new();
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+
+        let chains = config.borrow_and_update().chains.clone();
+
+        let channels = match unresolved_channels(&pool).await {
+            Ok(channels) => channels,
+            Err(e) => {
+                error!("Failed to load the channels to resolve: {e}");
+                continue;
+            }
+        };
+
+        for (chain_id, port_id, channel_id) in channels {
+            let Some(endpoint) = expiry::endpoint(&chains, &chain_id) else {
+                continue;
+            };
+
+            let key = (chain_id.clone(), port_id.clone(), channel_id.clone());
+
+            if failed
+                .get(&key)
+                .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER)
+            {
+                continue;
+            }
+
+            let counterparty = match resolve(&client, endpoint, &port_id, &channel_id).await {
+                Ok(counterparty) => counterparty,
+                Err(e) => {
+                    warn!(chain = %chain_id, "Failed to resolve the counterparty of {port_id}/{channel_id}: {e}");
+                    failed.insert(key, Instant::now());
+                    continue;
+                }
+            };
+
+            failed.remove(&key);
+
+            if let Err(e) = record(&pool, &chain_id, &port_id, &channel_id, &counterparty).await {
+                error!(chain = %chain_id, "Failed to record the counterparty of {port_id}/{channel_id}: {e}");
+                continue;
+            }
+
+            info!(
+                chain = %chain_id,
+                "Resolved {port_id}/{channel_id} to {}/{} on {}",
+                counterparty.port_id,
+                counterparty.channel_id,
+                counterparty.chain_id
+            );
+        }
+    }
+}
+
+/// Chain, port and channel of the channels packets were sent on, without a counterparty yet
+async fn unresolved_channels(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String, String)>> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT t.chain, p.src_port, p.src_channel
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND NOT EXISTS (
+              SELECT 1 FROM channel_counterparties c
+              WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel
+          )
+        ORDER BY t.chain, p.src_channel
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+async fn resolve(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    port_id: &str,
+    channel_id: &str,
+) -> crate::Result<Counterparty> {
+    let response: QueryChannelResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.core.channel.v1.Query/Channel",
+        QueryChannelRequest {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        },
+    )
+    .await?;
+
+    let channel = response.channel.ok_or("channel not found")?;
+    let counterparty = channel
+        .counterparty
+        .ok_or("no counterparty in the channel")?;
+    let connection_id = channel
+        .connection_hops
+        .into_iter()
+        .next()
+        .ok_or("no connection in the channel")?;
+
+    let response: QueryConnectionResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.core.connection.v1.Query/Connection",
+        QueryConnectionRequest {
+            connection_id: connection_id.clone(),
+        },
+    )
+    .await?;
+
+    let client_id = response
+        .connection
+        .ok_or_else(|| format!("connection {connection_id} not found"))?
+        .client_id;
+
+    let state = rpc::client_state(client, endpoint, &client_id).await?;
+
+    debug!("Channel {port_id}/{channel_id} runs over {connection_id} and {client_id}");
+
+    Ok(Counterparty {
+        connection_id,
+        client_id,
+        chain_id: state.chain_id,
+        port_id: counterparty.port_id,
+        channel_id: counterparty.channel_id,
+    })
+}
+
+async fn record(
+    pool: &SqlitePool,
+    chain_id: &str,
+    port_id: &str,
+    channel_id: &str,
+    counterparty: &Counterparty,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO channel_counterparties
+            (chain, port, channel, connection_id, client_id, counterparty_chain_id,
+             counterparty_port, counterparty_channel, resolved_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+        "#,
+    )
+    .bind(chain_id)
+    .bind(port_id)
+    .bind(channel_id)
+    .bind(&counterparty.connection_id)
+    .bind(&counterparty.client_id)
+    .bind(&counterparty.chain_id)
+    .bind(&counterparty.port_id)
+    .bind(&counterparty.channel_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use sqlx::SqlitePool;
+use tokio::time;
+use tracing::{debug, error, info};
+
+use crate::metrics::{CounterSnapshot, Metrics};
+
+/// How often the counters are snapshotted, the counts since the last snapshot being lost when
+/// the instance is killed
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Restore the packet and tx counters from their last snapshot, then snapshot them every
+/// minute, so that `increase()` over a window spanning a restart only sees the counts lost
+/// since the last snapshot
+///
+/// A standby taking over restores the snapshots of the previous leader.
+pub async fn run(pool: SqlitePool, metrics: Metrics) {
+    match restore(&pool, &metrics).await {
+        Ok(restored) => info!("Restored {restored} counter series from their snapshots"),
+        Err(e) => error!("Failed to restore the counters: {e}"),
+    }
+
+    let mut interval = time::interval(SNAPSHOT_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match save(&pool, &metrics).await {
+            Ok(saved) => debug!("Snapshotted {saved} counter series"),
+            Err(e) => error!("Failed to snapshot the counters: {e}"),
+        }
+    }
+}
+
+/// Raise the counters to the values of their snapshots
+pub async fn restore(pool: &SqlitePool, metrics: &Metrics) -> crate::Result<usize> {
+    let rows: Vec<(String, String, i64)> =
+        sqlx::query_as("SELECT name, labels, value FROM counter_snapshots")
+            .fetch_all(pool)
+            .await?;
+
+    let mut snapshots = Vec::with_capacity(rows.len());
+
+    for (name, labels, value) in rows {
+        let labels: BTreeMap<String, String> = serde_json::from_str(&labels)?;
+
+        snapshots.push(CounterSnapshot {
+            name,
+            labels,
+            value: value as u64,
+        });
+    }
+
+    Ok(metrics.restore_counters(&snapshots))
+}
+
+/// Replace the snapshots with the current values of the counters, dropping those of the series
+/// reset since
+pub async fn save(pool: &SqlitePool, metrics: &Metrics) -> crate::Result<usize> {
+    let snapshots = metrics.counter_snapshots();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM counter_snapshots")
+        .execute(&mut *tx)
+        .await?;
+
+    for snapshot in &snapshots {
+        sqlx::query(
+            r#"
+            INSERT INTO counter_snapshots (name, labels, value, updated_at)
+            VALUES (?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(&snapshot.name)
+        .bind(serde_json::to_string(&snapshot.labels)?)
+        .bind(snapshot.value as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(snapshots.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use tendermint::chain;
+
+    use super::*;
+    use crate::db;
+
+    #[tokio::test]
+    async fn test_save_restore() {
+        let pool = db::connect_in_memory().await.unwrap();
+        db::setup(&pool).await;
+
+        let chain_id = chain::Id::try_from("osmosis-1").unwrap();
+        let (metrics, _) = Metrics::new();
+
+        for _ in 0..3 {
+            metrics.chainpulse_txs(&chain_id);
+        }
+        metrics.ibc_effected_packets(
+            &chain_id,
+            "channel-0",
+            "transfer",
+            "channel-141",
+            "transfer",
+            "osmo1relayer",
+            "hermes",
+        );
+
+        assert_eq!(save(&pool, &metrics).await.unwrap(), 2);
+
+        let (restarted, _) = Metrics::new();
+        restarted.chainpulse_txs(&chain_id);
+        assert_eq!(restore(&pool, &restarted).await.unwrap(), 2);
+
+        // Restoring again, as on a second takeover, does not count the snapshots twice
+        restore(&pool, &restarted).await.unwrap();
+
+        let mut snapshots = restarted.counter_snapshots();
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].name, "chainpulse_txs");
+        assert_eq!(snapshots[0].value, 3);
+        assert_eq!(snapshots[1].name, "ibc_effected_packets");
+        assert_eq!(snapshots[1].labels["signer"], "osmo1relayer");
+        assert_eq!(snapshots[1].value, 1);
+    }
+}
@@ -1,6 +1,9 @@
-use std::path::Path;
+use std::{path::Path, str::FromStr};
 
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
 use time::PrimitiveDateTime;
 
 use crate::Result;
@@ -42,6 +45,8 @@ pub struct PacketRow {
     pub timeout_height_revision_height: Option<i64>,
     // Data integrity
     pub data_hash: Option<String>,
+    // Error carried by the acknowledgement, for MsgAcknowledgement rows
+    pub ack_error: Option<String>,
 }
 
 #[derive(Clone, Debug, sqlx::FromRow)]
@@ -73,6 +78,22 @@ pub async fn connect(path: &Path) -> Result<SqlitePool> {
     Ok(pool)
 }
 
+/// Database living in memory for as long as the pool, for dry runs leaving the database file
+/// untouched
+pub async fn connect_in_memory() -> Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str("sqlite::memory:")?;
+
+    // Every connection opens a database of its own, keep a single one for the whole run
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await?;
+
+    Ok(pool)
+}
+
 pub async fn setup(pool: &SqlitePool) {
     create_tables(pool).await;
     create_indexes(pool).await;
@@ -107,6 +128,62 @@ pub async fn create_tables(pool: &SqlitePool) {
         );
         "#,
         r#"
+        CREATE TABLE IF NOT EXISTS client_updates (
+            id                    INTEGER PRIMARY KEY AUTOINCREMENT,
+            tx_id                 INTEGER NOT NULL REFERENCES txs (id),
+            client_id             TEXT    NOT NULL,
+            counterparty_chain_id TEXT,
+            header_height         INTEGER,
+            header_time           TEXT,
+            signer                TEXT,
+            created_at            TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS unknown_msg_types (
+            chain        TEXT    NOT NULL,
+            type_url     TEXT    NOT NULL,
+            count        INTEGER NOT NULL,
+            first_seen   TEXT    NOT NULL,
+            last_seen    TEXT    NOT NULL,
+            PRIMARY KEY (chain, type_url)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS decoded_msgs (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            tx_id        INTEGER NOT NULL REFERENCES txs (id),
+            type_url     TEXT    NOT NULL,
+            fields       TEXT    NOT NULL,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_stats (
+            day                 TEXT    NOT NULL,
+            chain               TEXT    NOT NULL,
+            src_channel         TEXT    NOT NULL,
+            dst_channel         TEXT    NOT NULL,
+            packets             INTEGER NOT NULL,
+            acknowledged        INTEGER NOT NULL,
+            timed_out           INTEGER NOT NULL,
+            latency_seconds_sum INTEGER NOT NULL,
+            latency_count       INTEGER NOT NULL,
+            PRIMARY KEY (day, chain, src_channel, dst_channel)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_volume (
+            day          TEXT    NOT NULL,
+            chain        TEXT    NOT NULL,
+            src_channel  TEXT    NOT NULL,
+            denom        TEXT    NOT NULL,
+            packets      INTEGER NOT NULL,
+            amount       TEXT    NOT NULL,
+            PRIMARY KEY (day, chain, src_channel, denom)
+        );
+        "#,
+        r#"
         CREATE TABLE IF NOT EXISTS tx_events (
             id           INTEGER PRIMARY KEY AUTOINCREMENT,
             tx_id        INTEGER NOT NULL REFERENCES txs (id),
@@ -124,6 +201,159 @@ pub async fn create_tables(pool: &SqlitePool) {
             attribute_index INTEGER NOT NULL
         );
         "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS relay_fees (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            tx_id        INTEGER NOT NULL REFERENCES txs (id),
+            chain        TEXT    NOT NULL,
+            denom        TEXT    NOT NULL,
+            amount       TEXT    NOT NULL,
+            gas_wanted   INTEGER NOT NULL,
+            gas_price    REAL    NOT NULL,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS channel_counterparties (
+            chain                 TEXT NOT NULL,
+            port                  TEXT NOT NULL,
+            channel               TEXT NOT NULL,
+            connection_id         TEXT NOT NULL,
+            client_id             TEXT NOT NULL,
+            counterparty_chain_id TEXT NOT NULL,
+            counterparty_port     TEXT NOT NULL,
+            counterparty_channel  TEXT NOT NULL,
+            resolved_at           TEXT NOT NULL,
+            PRIMARY KEY (chain, port, channel)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS channel_states (
+            chain         TEXT NOT NULL,
+            port          TEXT NOT NULL,
+            channel       TEXT NOT NULL,
+            state         TEXT NOT NULL,
+            client_id     TEXT NOT NULL,
+            client_frozen BOOL NOT NULL,
+            checked_at    TEXT NOT NULL,
+            PRIMARY KEY (chain, port, channel)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS denom_traces (
+            chain       TEXT NOT NULL,
+            denom       TEXT NOT NULL,
+            trace       TEXT NOT NULL,
+            path        TEXT NOT NULL,
+            base_denom  TEXT NOT NULL,
+            resolved_at TEXT NOT NULL,
+            PRIMARY KEY (chain, denom)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS denom_prices (
+            base_denom TEXT    PRIMARY KEY,
+            id         TEXT    NOT NULL,
+            decimals   INTEGER NOT NULL,
+            usd        REAL    NOT NULL,
+            updated_at TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS clear_requests (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            targets      TEXT    NOT NULL,
+            commands     TEXT    NOT NULL,
+            triggered    BOOL    NOT NULL,
+            status       TEXT    NOT NULL,
+            error        TEXT,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS clear_actions (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            chain        TEXT    NOT NULL,
+            port         TEXT    NOT NULL,
+            channel      TEXT    NOT NULL,
+            sequences    TEXT    NOT NULL,
+            method       TEXT    NOT NULL,
+            command      TEXT    NOT NULL,
+            status       TEXT    NOT NULL,
+            error        TEXT,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            url          TEXT    NOT NULL,
+            secret       TEXT,
+            events       TEXT    NOT NULL,
+            chain        TEXT,
+            channel      TEXT,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS silences (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            chain        TEXT,
+            channel      TEXT,
+            rules        TEXT    NOT NULL,
+            starts_at    TEXT    NOT NULL,
+            ends_at      TEXT    NOT NULL,
+            comment      TEXT,
+            created_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS checkpoints (
+            chain        TEXT    PRIMARY KEY,
+            height       INTEGER NOT NULL,
+            block_time   TEXT,
+            updated_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS counter_snapshots (
+            name         TEXT    NOT NULL,
+            labels       TEXT    NOT NULL,
+            value        INTEGER NOT NULL,
+            updated_at   TEXT    NOT NULL,
+            PRIMARY KEY (name, labels)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS rate_limits (
+            chain        TEXT    NOT NULL,
+            channel      TEXT    NOT NULL,
+            denom        TEXT    NOT NULL,
+            quota        TEXT    NOT NULL,
+            direction    TEXT    NOT NULL,
+            flow         REAL    NOT NULL,
+            threshold    REAL    NOT NULL,
+            updated_at   TEXT    NOT NULL,
+            PRIMARY KEY (chain, channel, denom, quota, direction)
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS warehouse_cursors (
+            destination  TEXT    PRIMARY KEY,
+            last_id      INTEGER NOT NULL,
+            updated_at   TEXT    NOT NULL
+        );
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS instance_lock (
+            id           INTEGER PRIMARY KEY CHECK (id = 1),
+            instance     TEXT    NOT NULL,
+            pid          INTEGER NOT NULL,
+            host         TEXT    NOT NULL,
+            acquired_at  TEXT    NOT NULL,
+            heartbeat_at TEXT    NOT NULL
+        );
+        "#,
     ];
 
     for table in TABLES {
@@ -143,6 +373,10 @@ pub async fn create_tables(pool: &SqlitePool) {
         "ALTER TABLE packets ADD COLUMN timeout_height_revision_number INTEGER;",
         "ALTER TABLE packets ADD COLUMN timeout_height_revision_height INTEGER;",
         "ALTER TABLE packets ADD COLUMN data_hash TEXT;",
+        // Add acknowledgement result
+        "ALTER TABLE packets ADD COLUMN ack_error TEXT;",
+        // Add webhook body templates
+        "ALTER TABLE webhooks ADD COLUMN template TEXT;",
     ];
 
     for migration in MIGRATIONS {
@@ -176,6 +410,15 @@ async fn create_indexes(pool: &SqlitePool) {
         "CREATE        INDEX IF NOT EXISTS packets_timeout_ts  ON packets (timeout_timestamp) WHERE timeout_timestamp IS NOT NULL;",
         "CREATE        INDEX IF NOT EXISTS packets_timeout_pending ON packets (timeout_timestamp, effected) WHERE effected = 0 AND timeout_timestamp IS NOT NULL;",
         "CREATE        INDEX IF NOT EXISTS packets_data_hash   ON packets (data_hash) WHERE data_hash IS NOT NULL;",
+        "CREATE UNIQUE INDEX IF NOT EXISTS client_updates_unique ON client_updates (tx_id, client_id);",
+        "CREATE        INDEX IF NOT EXISTS client_updates_client ON client_updates (client_id);",
+        "CREATE        INDEX IF NOT EXISTS packets_ack_error   ON packets (ack_error) WHERE ack_error IS NOT NULL;",
+        "CREATE UNIQUE INDEX IF NOT EXISTS relay_fees_unique   ON relay_fees (tx_id, denom);",
+        "CREATE        INDEX IF NOT EXISTS relay_fees_chain    ON relay_fees (chain, created_at);",
+        "CREATE        INDEX IF NOT EXISTS clear_actions_channel ON clear_actions (chain, channel, created_at);",
+        "CREATE        INDEX IF NOT EXISTS denom_traces_trace  ON denom_traces (chain, trace);",
+        "CREATE        INDEX IF NOT EXISTS decoded_msgs_type_url ON decoded_msgs (type_url, id);",
+        "CREATE        INDEX IF NOT EXISTS decoded_msgs_tx_id  ON decoded_msgs (tx_id);",
         // Event indexes
         "CREATE UNIQUE INDEX IF NOT EXISTS tx_events_unique   ON tx_events (tx_id, event_type, event_index);",
         "CREATE        INDEX IF NOT EXISTS tx_events_tx_id    ON tx_events (tx_id);",
@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use ibc_proto::ibc::applications::transfer::v1::{
+    QueryDenomHashRequest, QueryDenomHashResponse, QueryDenomTraceRequest, QueryDenomTraceResponse,
+};
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{Config, Endpoint},
+    expiry,
+    metrics::Metrics,
+    rpc,
+};
+
+/// How often the denoms seen since the last pass are resolved
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time before a denom which failed to resolve is queried again
+const RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Full trace of a denom, given the columns holding the chain it was seen on and the denom,
+/// such as `t.chain` and `p.denom`
+///
+/// The trace of a resolved `ibc/` denom is its path and base denom, such as
+/// `transfer/channel-0/uatom`, other denoms are left as they are.
+pub fn trace_sql(chain: &str, denom: &str) -> String {
+    format!(
+        "COALESCE((SELECT d.trace FROM denom_traces d
+                   WHERE d.chain = {chain} AND d.denom = {denom}), {denom})"
+    )
+}
+
+/// Split an ICS-20 denom trace such as `transfer/channel-0/uatom` into its
+/// path (`transfer/channel-0`) and base denom (`uatom`)
+pub fn split_trace(denom: &str) -> (String, String) {
+    let segments: Vec<&str> = denom.split('/').collect();
+    let mut hops = 0;
+
+    // The path is made of port/channel pairs, the base denom may itself contain slashes
+    while hops * 2 + 2 < segments.len() && segments[hops * 2 + 1].starts_with("channel-") {
+        hops += 1;
+    }
+
+    (
+        segments[..hops * 2].join("/"),
+        segments[hops * 2..].join("/"),
+    )
+}
+
+/// IBC denom held on a chain, with the trace it was resolved to
+#[derive(Debug)]
+struct DenomTrace {
+    denom: String,
+    path: String,
+    base_denom: String,
+}
+
+impl DenomTrace {
+    fn trace(&self) -> String {
+        match self.path.is_empty() {
+            true => self.base_denom.clone(),
+            false => format!("{}/{}", self.path, self.base_denom),
+        }
+    }
+}
+
+/// Resolve the IBC denoms seen on the chains, once, and record them in the `denom_traces`
+/// table
+///
+/// The `ibc/` denoms of the relay fees, sent packets and rate limits are resolved with the `DenomTrace`
+/// query of the transfer module, and the traces of the sent packets, such as
+/// `transfer/channel-0/uatom`, with the `DenomHash` query, to the `ibc/` denom the sender held.
+/// The table serves as a cache, only the denoms it does not have yet are queried.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>, metrics: Metrics) {
+    let mut interval = time::interval(RESOLVE_INTERVAL);
+    let mut failed: HashMap<(String, String), Instant> = HashMap::new();
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+
+        let chains = config.borrow_and_update().chains.clone();
+
+        let denoms = match unresolved_denoms(&pool).await {
+            Ok(denoms) => denoms,
+            Err(e) => {
+                error!("Failed to load the denoms to resolve: {e}");
+                continue;
+            }
+        };
+
+        for (chain_id, denom) in denoms {
+            let Some(endpoint) = expiry::endpoint(&chains, &chain_id) else {
+                continue;
+            };
+
+            let key = (chain_id.clone(), denom.clone());
+
+            if failed
+                .get(&key)
+                .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER)
+            {
+                continue;
+            }
+
+            let trace = match resolve(&client, endpoint, &denom).await {
+                Ok(trace) => trace,
+                Err(e) => {
+                    warn!(chain = %chain_id, "Failed to resolve the trace of {denom}: {e}");
+                    failed.insert(key, Instant::now());
+                    continue;
+                }
+            };
+
+            failed.remove(&key);
+
+            if let Err(e) = record(&pool, &chain_id, &trace).await {
+                error!(chain = %chain_id, "Failed to record the trace of {denom}: {e}");
+                continue;
+            }
+
+            info!(chain = %chain_id, "Resolved {} to {}", trace.denom, trace.trace());
+        }
+
+        // Set on every pass, as the series of a chain are dropped when its metrics are reset
+        match traces(&pool).await {
+            Ok(traces) => {
+                for (chain_id, trace) in traces {
+                    if expiry::endpoint(&chains, &chain_id).is_some() {
+                        metrics.chainpulse_denom_trace(
+                            &chain_id,
+                            &trace.denom,
+                            &trace.path,
+                            &trace.base_denom,
+                        );
+                    }
+                }
+            }
+            Err(e) => error!("Failed to load the denom traces: {e}"),
+        }
+    }
+}
+
+/// Chain and denom of the `ibc/` denoms and packet traces not resolved yet
+async fn unresolved_denoms(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String)>> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT seen.chain, seen.denom
+        FROM (
+            SELECT t.chain, p.denom
+            FROM packets p
+            JOIN txs t ON p.tx_id = t.id
+            WHERE p.msg_type_url = 'send_packet'
+              AND (p.denom LIKE 'ibc/%' OR p.denom LIKE '%/channel-%')
+            UNION
+            SELECT chain, denom FROM relay_fees WHERE denom LIKE 'ibc/%'
+            UNION
+            SELECT chain, denom FROM rate_limits WHERE denom LIKE 'ibc/%'
+        ) seen
+        WHERE NOT EXISTS (
+            SELECT 1 FROM denom_traces d
+            WHERE d.chain = seen.chain AND (d.denom = seen.denom OR d.trace = seen.denom)
+        )
+        ORDER BY seen.chain, seen.denom
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Resolve an `ibc/` denom to its trace, or a trace to the `ibc/` denom holding it
+async fn resolve(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    denom: &str,
+) -> crate::Result<DenomTrace> {
+    let hash = match denom.strip_prefix("ibc/") {
+        Some(hash) => hash.to_string(),
+        None => {
+            let response: QueryDenomHashResponse = rpc::abci_query(
+                client,
+                endpoint,
+                "/ibc.applications.transfer.v1.Query/DenomHash",
+                QueryDenomHashRequest {
+                    trace: denom.to_string(),
+                },
+            )
+            .await?;
+
+            response.hash
+        }
+    };
+
+    let response: QueryDenomTraceResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.applications.transfer.v1.Query/DenomTrace",
+        QueryDenomTraceRequest { hash: hash.clone() },
+    )
+    .await?;
+
+    let trace = response
+        .denom_trace
+        .ok_or_else(|| format!("no trace for ibc/{hash}"))?;
+
+    debug!(
+        "Denom ibc/{hash} is {} over {}",
+        trace.base_denom, trace.path
+    );
+
+    Ok(DenomTrace {
+        denom: format!("ibc/{hash}"),
+        path: trace.path,
+        base_denom: trace.base_denom,
+    })
+}
+
+async fn record(pool: &SqlitePool, chain_id: &str, trace: &DenomTrace) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO denom_traces
+            (chain, denom, trace, path, base_denom, resolved_at)
+        VALUES (?, ?, ?, ?, ?, datetime('now'))
+        "#,
+    )
+    .bind(chain_id)
+    .bind(&trace.denom)
+    .bind(trace.trace())
+    .bind(&trace.path)
+    .bind(&trace.base_denom)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+async fn traces(pool: &SqlitePool) -> sqlx::Result<Vec<(String, DenomTrace)>> {
+    let rows: Vec<(String, String, String, String)> =
+        sqlx::query_as("SELECT chain, denom, path, base_denom FROM denom_traces")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(chain_id, denom, path, base_denom)| {
+            (
+                chain_id,
+                DenomTrace {
+                    denom,
+                    path,
+                    base_denom,
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_trace() {
+        assert_eq!(
+            split_trace("transfer/channel-0/uatom"),
+            ("transfer/channel-0".to_string(), "uatom".to_string())
+        );
+        assert_eq!(
+            split_trace("transfer/channel-0/transfer/channel-141/gamm/pool/1"),
+            (
+                "transfer/channel-0/transfer/channel-141".to_string(),
+                "gamm/pool/1".to_string()
+            )
+        );
+        assert_eq!(split_trace("uosmo"), (String::new(), "uosmo".to_string()));
+    }
+}
@@ -0,0 +1,581 @@
+use std::{collections::HashMap, fmt, fs, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use prost::{
+    bytes::Buf,
+    encoding::{decode_key, decode_varint, WireType},
+    Message,
+};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorSet,
+};
+use serde_json::{Map, Value};
+
+/// Deepest nesting of messages decoded, as for the messages decoded by prost
+const RECURSION_LIMIT: usize = 100;
+
+/// Full name of the `Any` type, whose value is decoded by its own type URL
+const ANY: &str = "google.protobuf.Any";
+
+/// Message and enum types of the protobuf descriptor sets of the configuration, which decode
+/// the messages chainpulse has no types for into maps of their fields
+#[derive(Default)]
+pub struct Registry {
+    /// Message types by full name, without the leading dot
+    messages: HashMap<String, DescriptorProto>,
+    /// Enum types by full name, without the leading dot
+    enums: HashMap<String, EnumDescriptorProto>,
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("messages", &self.messages.len())
+            .field("enums", &self.enums.len())
+            .finish()
+    }
+}
+
+impl Registry {
+    /// Registry of the types of the descriptor sets at the given paths, as written by
+    /// `protoc --include_imports --descriptor_set_out` or `buf build`
+    pub fn load(paths: &[PathBuf]) -> crate::Result<Self> {
+        let mut registry = Self::default();
+
+        for path in paths {
+            let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let set = FileDescriptorSet::decode(bytes.as_slice())
+                .map_err(|e| format!("{}: invalid descriptor set: {e}", path.display()))?;
+
+            registry.add(set);
+        }
+
+        Ok(registry)
+    }
+
+    /// Add the types of a descriptor set, replacing those of the same name
+    pub fn add(&mut self, set: FileDescriptorSet) {
+        for file in set.file {
+            let package = file.package().to_string();
+
+            for message in file.message_type {
+                self.add_message(&package, message);
+            }
+
+            for enumeration in file.enum_type {
+                self.enums
+                    .insert(qualify(&package, enumeration.name()), enumeration);
+            }
+        }
+    }
+
+    fn add_message(&mut self, scope: &str, mut message: DescriptorProto) {
+        let name = qualify(scope, message.name());
+
+        for nested in std::mem::take(&mut message.nested_type) {
+            self.add_message(&name, nested);
+        }
+
+        for enumeration in std::mem::take(&mut message.enum_type) {
+            self.enums
+                .insert(qualify(&name, enumeration.name()), enumeration);
+        }
+
+        self.messages.insert(name, message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Whether the registry holds the message type of a type URL
+    pub fn contains(&self, type_url: &str) -> bool {
+        self.messages.contains_key(type_name(type_url))
+    }
+
+    /// Decode a message of the type of a type URL into a map of its fields by name
+    ///
+    /// Fields follow the JSON mapping of protobuf: 64-bit integers are strings, bytes are
+    /// base64, enums are the names of their values, and `Any` values carry their type URL
+    /// under `@type`. Fields unknown to the descriptor, and those set to their default value
+    /// on the wire, are left out.
+    pub fn decode(&self, type_url: &str, bytes: &[u8]) -> crate::Result<Value> {
+        self.decode_message(type_name(type_url), bytes, 0)
+    }
+
+    fn decode_message(&self, name: &str, mut buf: &[u8], depth: usize) -> crate::Result<Value> {
+        if depth > RECURSION_LIMIT {
+            return Err("recursion limit reached".into());
+        }
+
+        if name == ANY {
+            return self.decode_any(buf, depth);
+        }
+
+        let message = self
+            .messages
+            .get(name)
+            .ok_or_else(|| format!("unknown message type `{name}`"))?;
+
+        let mut fields = Map::new();
+
+        while buf.has_remaining() {
+            let (number, wire_type) = decode_key(&mut buf)?;
+
+            let Some(field) = message
+                .field
+                .iter()
+                .find(|field| field.number() == number as i32)
+            else {
+                skip(wire_type, &mut buf)?;
+                continue;
+            };
+
+            let values = self.decode_field(field, wire_type, &mut buf, depth)?;
+
+            if field.label() == Label::Repeated {
+                let entry = fields
+                    .entry(field.name())
+                    .or_insert_with(|| Value::Array(Vec::new()));
+
+                if let Value::Array(array) = entry {
+                    array.extend(values);
+                }
+            } else if let Some(value) = values.into_iter().last() {
+                fields.insert(field.name().to_string(), value);
+            }
+        }
+
+        // Map fields are repeated entries on the wire
+        for field in &message.field {
+            if !self.is_map(field) {
+                continue;
+            }
+
+            if let Some(Value::Array(entries)) = fields.remove(field.name()) {
+                let map = entries
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        Value::Object(mut entry) => {
+                            let key = match entry.remove("key") {
+                                Some(Value::String(key)) => key,
+                                Some(key) => key.to_string(),
+                                None => String::new(),
+                            };
+                            Some((key, entry.remove("value").unwrap_or(Value::Null)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                fields.insert(field.name().to_string(), Value::Object(map));
+            }
+        }
+
+        Ok(Value::Object(fields))
+    }
+
+    /// An `Any` as the fields of its value along with its type URL, or its value in base64
+    /// when the registry does not hold its type
+    fn decode_any(&self, mut buf: &[u8], depth: usize) -> crate::Result<Value> {
+        let (mut type_url, mut value) = (String::new(), &[][..]);
+
+        while buf.has_remaining() {
+            match decode_key(&mut buf)? {
+                (1, WireType::LengthDelimited) => {
+                    type_url = String::from_utf8(take(&mut buf)?.to_vec())?;
+                }
+                (2, WireType::LengthDelimited) => value = take(&mut buf)?,
+                (_, wire_type) => skip(wire_type, &mut buf)?,
+            }
+        }
+
+        let mut fields = Map::new();
+        fields.insert("@type".to_string(), Value::String(type_url.clone()));
+
+        match self.decode_message(type_name(&type_url), value, depth + 1) {
+            Ok(Value::Object(decoded)) => fields.extend(decoded),
+            _ => {
+                fields.insert("value".to_string(), Value::String(STANDARD.encode(value)));
+            }
+        }
+
+        Ok(Value::Object(fields))
+    }
+
+    /// Values of a field of the wire, several for a packed repeated field
+    fn decode_field(
+        &self,
+        field: &FieldDescriptorProto,
+        wire_type: WireType,
+        buf: &mut &[u8],
+        depth: usize,
+    ) -> crate::Result<Vec<Value>> {
+        let kind = field.r#type();
+
+        let value = match (kind, wire_type) {
+            (Type::Message, WireType::LengthDelimited) => {
+                self.decode_message(type_name(field.type_name()), take(buf)?, depth + 1)?
+            }
+            (Type::String, WireType::LengthDelimited) => {
+                Value::String(String::from_utf8(take(buf)?.to_vec())?)
+            }
+            (Type::Bytes, WireType::LengthDelimited) => Value::String(STANDARD.encode(take(buf)?)),
+            (_, WireType::LengthDelimited) => {
+                let mut packed = take(buf)?;
+                let mut values = Vec::new();
+
+                while packed.has_remaining() {
+                    values.push(self.decode_scalar(field, scalar_wire_type(kind), &mut packed)?);
+                }
+
+                return Ok(values);
+            }
+            (_, wire_type) => self.decode_scalar(field, wire_type, buf)?,
+        };
+
+        Ok(vec![value])
+    }
+
+    fn decode_scalar(
+        &self,
+        field: &FieldDescriptorProto,
+        wire_type: WireType,
+        buf: &mut &[u8],
+    ) -> crate::Result<Value> {
+        let kind = field.r#type();
+
+        let value = match (kind, wire_type) {
+            (Type::Double, WireType::SixtyFourBit) => Value::from(f64::from_bits(fixed64(buf)?)),
+            (Type::Fixed64, WireType::SixtyFourBit) => Value::String(fixed64(buf)?.to_string()),
+            (Type::Sfixed64, WireType::SixtyFourBit) => {
+                Value::String((fixed64(buf)? as i64).to_string())
+            }
+            (Type::Float, WireType::ThirtyTwoBit) => Value::from(f32::from_bits(fixed32(buf)?)),
+            (Type::Fixed32, WireType::ThirtyTwoBit) => Value::from(fixed32(buf)?),
+            (Type::Sfixed32, WireType::ThirtyTwoBit) => Value::from(fixed32(buf)? as i32),
+            (_, WireType::Varint) => {
+                let varint = decode_varint(buf)?;
+
+                match kind {
+                    Type::Int64 => Value::String((varint as i64).to_string()),
+                    Type::Uint64 => Value::String(varint.to_string()),
+                    Type::Sint64 => Value::String(zigzag(varint).to_string()),
+                    Type::Int32 => Value::from(varint as i32),
+                    Type::Uint32 => Value::from(varint as u32),
+                    Type::Sint32 => Value::from(zigzag(varint) as i32),
+                    Type::Bool => Value::Bool(varint != 0),
+                    Type::Enum => self.enum_value(field, varint as i32),
+                    _ => return Err(mismatch(field, wire_type).into()),
+                }
+            }
+            _ => return Err(mismatch(field, wire_type).into()),
+        };
+
+        Ok(value)
+    }
+
+    /// Name of the value of an enum, or its number when unknown
+    fn enum_value(&self, field: &FieldDescriptorProto, number: i32) -> Value {
+        self.enums
+            .get(type_name(field.type_name()))
+            .and_then(|enumeration| {
+                enumeration
+                    .value
+                    .iter()
+                    .find(|value| value.number() == number)
+            })
+            .map(|value| Value::String(value.name().to_string()))
+            .unwrap_or_else(|| Value::from(number))
+    }
+
+    /// Whether a field is a map, whose entries are messages marked as such
+    fn is_map(&self, field: &FieldDescriptorProto) -> bool {
+        field.label() == Label::Repeated
+            && field.r#type() == Type::Message
+            && self
+                .messages
+                .get(type_name(field.type_name()))
+                .and_then(|entry| entry.options.as_ref())
+                .is_some_and(|options| options.map_entry())
+    }
+}
+
+/// Full name of a type, from a type URL or a reference to it with a leading dot
+fn type_name(type_url: &str) -> &str {
+    let name = type_url.rsplit('/').next().unwrap_or(type_url);
+    name.trim_start_matches('.')
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    match scope {
+        "" => name.to_string(),
+        scope => format!("{scope}.{name}"),
+    }
+}
+
+/// Wire type of the elements of a packed field of a scalar type
+fn scalar_wire_type(kind: Type) -> WireType {
+    match kind {
+        Type::Double | Type::Fixed64 | Type::Sfixed64 => WireType::SixtyFourBit,
+        Type::Float | Type::Fixed32 | Type::Sfixed32 => WireType::ThirtyTwoBit,
+        _ => WireType::Varint,
+    }
+}
+
+fn mismatch(field: &FieldDescriptorProto, wire_type: WireType) -> String {
+    format!(
+        "field `{}` of type {:?} has wire type {wire_type:?}",
+        field.name(),
+        field.r#type()
+    )
+}
+
+fn zigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Length delimited bytes of the wire
+fn take<'a>(buf: &mut &'a [u8]) -> crate::Result<&'a [u8]> {
+    let len = decode_varint(buf)? as usize;
+
+    if buf.len() < len {
+        return Err("buffer underflow".into());
+    }
+
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+
+    Ok(bytes)
+}
+
+fn fixed64(buf: &mut &[u8]) -> crate::Result<u64> {
+    if buf.remaining() < 8 {
+        return Err("buffer underflow".into());
+    }
+
+    Ok(buf.get_u64_le())
+}
+
+fn fixed32(buf: &mut &[u8]) -> crate::Result<u32> {
+    if buf.remaining() < 4 {
+        return Err("buffer underflow".into());
+    }
+
+    Ok(buf.get_u32_le())
+}
+
+fn skip(wire_type: WireType, buf: &mut &[u8]) -> crate::Result<()> {
+    match wire_type {
+        WireType::Varint => {
+            decode_varint(buf)?;
+        }
+        WireType::SixtyFourBit => {
+            fixed64(buf)?;
+        }
+        WireType::LengthDelimited => {
+            take(buf)?;
+        }
+        WireType::ThirtyTwoBit => {
+            fixed32(buf)?;
+        }
+        WireType::StartGroup | WireType::EndGroup => {
+            return Err("groups are not supported".into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use prost_types::{
+        EnumValueDescriptorProto, FieldDescriptorProto, FileDescriptorProto, MessageOptions,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct MsgRegister {
+        #[prost(string, tag = "1")]
+        owner: String,
+        #[prost(uint64, tag = "2")]
+        amount: u64,
+        #[prost(uint32, repeated, tag = "3")]
+        ids: Vec<u32>,
+        #[prost(message, optional, tag = "4")]
+        inner: Option<Inner>,
+        #[prost(map = "string, string", tag = "5")]
+        labels: HashMap<String, String>,
+        #[prost(int32, tag = "6")]
+        kind: i32,
+        #[prost(message, optional, tag = "7")]
+        any: Option<prost_types::Any>,
+        #[prost(sint64, tag = "8")]
+        offset: i64,
+        #[prost(bytes = "vec", tag = "9")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Inner {
+        #[prost(string, tag = "1")]
+        connection_id: String,
+    }
+
+    fn field(name: &str, number: i32, kind: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(kind as i32),
+            type_name: type_name.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn repeated(field: FieldDescriptorProto) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            label: Some(Label::Repeated as i32),
+            ..field
+        }
+    }
+
+    fn registry() -> Registry {
+        let register = DescriptorProto {
+            name: Some("MsgRegister".to_string()),
+            field: vec![
+                field("owner", 1, Type::String, None),
+                field("amount", 2, Type::Uint64, None),
+                repeated(field("ids", 3, Type::Uint32, None)),
+                field("inner", 4, Type::Message, Some(".test.v1.Inner")),
+                repeated(field(
+                    "labels",
+                    5,
+                    Type::Message,
+                    Some(".test.v1.MsgRegister.LabelsEntry"),
+                )),
+                field("kind", 6, Type::Enum, Some(".test.v1.Kind")),
+                field("any", 7, Type::Message, Some(".google.protobuf.Any")),
+                field("offset", 8, Type::Sint64, None),
+                field("data", 9, Type::Bytes, None),
+            ],
+            nested_type: vec![DescriptorProto {
+                name: Some("LabelsEntry".to_string()),
+                field: vec![
+                    field("key", 1, Type::String, None),
+                    field("value", 2, Type::String, None),
+                ],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let inner = DescriptorProto {
+            name: Some("Inner".to_string()),
+            field: vec![field("connection_id", 1, Type::String, None)],
+            ..Default::default()
+        };
+
+        let kind = EnumDescriptorProto {
+            name: Some("Kind".to_string()),
+            value: vec![EnumValueDescriptorProto {
+                name: Some("KIND_ORDERED".to_string()),
+                number: Some(2),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut registry = Registry::default();
+        registry.add(FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                package: Some("test.v1".to_string()),
+                message_type: vec![register, inner],
+                enum_type: vec![kind],
+                ..Default::default()
+            }],
+        });
+
+        registry
+    }
+
+    #[test]
+    fn test_decode() {
+        let registry = registry();
+
+        assert!(registry.contains("/test.v1.MsgRegister"));
+        assert!(registry.contains("/test.v1.MsgRegister.LabelsEntry"));
+        assert!(!registry.contains("/test.v1.MsgUnknown"));
+
+        let msg = MsgRegister {
+            owner: "neutron1owner".to_string(),
+            amount: u64::MAX,
+            ids: vec![1, 300],
+            inner: Some(Inner {
+                connection_id: "connection-0".to_string(),
+            }),
+            labels: HashMap::from([("purpose".to_string(), "ica".to_string())]),
+            kind: 2,
+            any: Some(prost_types::Any {
+                type_url: "/test.v1.Inner".to_string(),
+                value: Inner {
+                    connection_id: "connection-1".to_string(),
+                }
+                .encode_to_vec(),
+            }),
+            offset: -5,
+            data: vec![0xde, 0xad],
+        };
+
+        assert_eq!(
+            registry
+                .decode("/test.v1.MsgRegister", &msg.encode_to_vec())
+                .unwrap(),
+            json!({
+                "owner": "neutron1owner",
+                "amount": "18446744073709551615",
+                "ids": [1, 300],
+                "inner": { "connection_id": "connection-0" },
+                "labels": { "purpose": "ica" },
+                "kind": "KIND_ORDERED",
+                "any": { "@type": "/test.v1.Inner", "connection_id": "connection-1" },
+                "offset": "-5",
+                "data": "3q0=",
+            })
+        );
+
+        // Unknown Any values are kept in base64, and fields unknown to the descriptor skipped
+        let msg = MsgRegister {
+            kind: 7,
+            any: Some(prost_types::Any {
+                type_url: "/other.v1.Msg".to_string(),
+                value: vec![1, 2, 3],
+            }),
+            ..Default::default()
+        };
+
+        let mut bytes = msg.encode_to_vec();
+        prost::encoding::string::encode(42, &"extra".to_string(), &mut bytes);
+
+        assert_eq!(
+            registry.decode("/test.v1.MsgRegister", &bytes).unwrap(),
+            json!({
+                "kind": 7,
+                "any": { "@type": "/other.v1.Msg", "value": "AQID" },
+            })
+        );
+
+        assert!(registry
+            .decode("/test.v1.MsgRegister", &[0x0a, 0x05])
+            .is_err());
+    }
+}
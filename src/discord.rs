@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::{
+    config,
+    monitor::format_age,
+    webhook::{self, ChainEvent, EventKind, PacketEvent},
+};
+
+/// Timeout of a single message delivery
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest number of embeds Discord accepts in a message
+const MAX_EMBEDS: usize = 10;
+
+/// Number of packets listed in an embed
+const MAX_LISTED_PACKETS: usize = 10;
+
+const RED: u32 = 0xE74C3C;
+const ORANGE: u32 = 0xE67E22;
+const GREEN: u32 = 0x2ECC71;
+
+/// Posts packet events to a Discord channel as embeds, one per channel and event kind
+#[derive(Clone)]
+pub struct Discord {
+    config: config::Discord,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    username: &'static str,
+    embeds: Vec<Embed>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Embed {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    color: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<Field>,
+    timestamp: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Field {
+    name: &'static str,
+    value: String,
+    inline: bool,
+}
+
+impl Discord {
+    pub fn new(config: config::Discord) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn notify(self, events: Vec<PacketEvent>) {
+        let events: Vec<&PacketEvent> = events
+            .iter()
+            .filter(|event| {
+                (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                    && webhook::in_scope(&self.config.chain, &self.config.channel, &event.packet)
+            })
+            .collect();
+
+        self.send(&self.embeds(&events)).await;
+    }
+
+    /// Post an embed for each of the events about chains and channels
+    pub async fn announce(self, events: Vec<ChainEvent>) {
+        let embeds: Vec<Embed> = events
+            .iter()
+            .filter(|event| {
+                (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                    && event.in_scope(&self.config.chain, &self.config.channel)
+            })
+            .map(|event| Embed {
+                title: event.heading(),
+                url: None,
+                description: Some(event.summary.clone()),
+                color: match event.resolved {
+                    true => GREEN,
+                    false => RED,
+                },
+                fields: Vec::new(),
+                timestamp: event.timestamp.clone(),
+            })
+            .collect();
+
+        self.send(&embeds).await;
+    }
+
+    async fn send(&self, embeds: &[Embed]) {
+        for chunk in embeds.chunks(MAX_EMBEDS) {
+            let message = Message {
+                username: "chainpulse",
+                embeds: chunk.to_vec(),
+            };
+
+            self.post(&message).await;
+        }
+    }
+
+    /// One embed per event kind and channel
+    fn embeds(&self, events: &[&PacketEvent]) -> Vec<Embed> {
+        webhook::group_by_channel(events)
+            .iter()
+            .map(|events| self.embed(events))
+            .collect()
+    }
+
+    fn embed(&self, events: &[&PacketEvent]) -> Embed {
+        let first = events[0];
+        let kind = first.event;
+        let packet = &first.packet;
+
+        let color = match kind {
+            EventKind::PacketNearTimeout | EventKind::WatchedNearTimeout => ORANGE,
+            EventKind::PacketCleared | EventKind::StuckResolved => GREEN,
+            _ => RED,
+        };
+
+        let title = format!(
+            "{} on {} {} → {}",
+            kind.title(),
+            packet.chain_id,
+            packet.src_channel,
+            packet.dst_channel
+        );
+
+        if let (EventKind::StuckResolved, Some(alert)) = (kind, &first.alert) {
+            return Embed {
+                title,
+                url: None,
+                description: None,
+                color,
+                fields: vec![Field {
+                    name: "Stuck for",
+                    value: format_age(alert.firing_seconds),
+                    inline: true,
+                }],
+                timestamp: first.timestamp.clone(),
+            };
+        }
+
+        let url = self
+            .config
+            .api_url
+            .as_deref()
+            .and_then(|api_url| webhook::api_link(api_url, first));
+
+        let mut fields = vec![
+            Field {
+                name: "Packets",
+                value: webhook::packet_count(events).to_string(),
+                inline: true,
+            },
+            Field {
+                name: "Oldest",
+                value: format_age(
+                    events
+                        .iter()
+                        .map(|event| event.packet.age_seconds)
+                        .max()
+                        .unwrap_or_default(),
+                ),
+                inline: true,
+            },
+        ];
+
+        let next_timeout = events
+            .iter()
+            .filter_map(|event| event.packet.seconds_until_timeout)
+            .min();
+
+        if let (EventKind::PacketNearTimeout | EventKind::WatchedNearTimeout, Some(seconds)) =
+            (kind, next_timeout)
+        {
+            fields.push(Field {
+                name: "Times out in",
+                value: format_age(seconds),
+                inline: true,
+            });
+        }
+
+        let mut sequences: Vec<String> = events
+            .iter()
+            .take(MAX_LISTED_PACKETS)
+            .map(|event| {
+                let packet = &event.packet;
+
+                let line = match (&packet.amount, &packet.denom) {
+                    (Some(amount), Some(denom)) => {
+                        format!("#{} {amount} {denom}", packet.sequence)
+                    }
+                    _ => format!("#{}", packet.sequence),
+                };
+
+                // Watched packets are each acted upon, before their own timeout
+                match (kind, packet.seconds_until_timeout) {
+                    (EventKind::WatchedNearTimeout, Some(seconds)) => {
+                        format!("{line}, times out in {}", format_age(seconds))
+                    }
+                    _ => line,
+                }
+            })
+            .collect();
+
+        if events.len() > MAX_LISTED_PACKETS {
+            sequences.push(format!("and {} more", events.len() - MAX_LISTED_PACKETS));
+        }
+
+        fields.push(Field {
+            name: "Sequences",
+            value: sequences.join("\n"),
+            inline: false,
+        });
+
+        Embed {
+            title,
+            url,
+            description: None,
+            color,
+            fields,
+            timestamp: first.timestamp.clone(),
+        }
+    }
+
+    async fn post(&self, message: &Message) {
+        let result = self
+            .client
+            .post(&self.config.webhook_url)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(message)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => debug!("Posted {} embeds to Discord", message.embeds.len()),
+            Err(e) => warn!("Failed to post packet events to Discord: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhook::PacketSnapshot;
+
+    fn event(kind: EventKind, src_channel: &str, sequence: i64) -> PacketEvent {
+        PacketEvent::new(
+            kind,
+            PacketSnapshot {
+                chain_id: "osmosis-1".to_string(),
+                src_channel: src_channel.to_string(),
+                dst_channel: "channel-141".to_string(),
+                sequence,
+                sender: None,
+                receiver: None,
+                amount: Some("1000".to_string()),
+                denom: Some("uosmo".to_string()),
+                age_seconds: 960 + sequence,
+                seconds_until_timeout: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_embeds_grouped_by_kind_and_channel() {
+        let discord = Discord::new(config::Discord {
+            webhook_url: "https://discord.com/api/webhooks/1/x".to_string(),
+            api_url: Some("https://chainpulse.example.com/".to_string()),
+            events: vec![],
+            chain: None,
+            channel: None,
+        });
+
+        let events = [
+            event(EventKind::PacketStuck, "channel-0", 1),
+            event(EventKind::PacketStuck, "channel-0", 2),
+            event(EventKind::PacketStuck, "channel-1", 3),
+            event(EventKind::PacketCleared, "channel-0", 4),
+        ];
+        let embeds = discord.embeds(&events.iter().collect::<Vec<_>>());
+
+        assert_eq!(embeds.len(), 3);
+        assert_eq!(
+            embeds[0].title,
+            "Packets cleared on osmosis-1 channel-0 → channel-141"
+        );
+        assert_eq!(embeds[0].url, None);
+        assert_eq!(
+            embeds[1].url.as_deref(),
+            Some("https://chainpulse.example.com/api/v1/packets/stuck?chain=osmosis-1&channel=channel-0")
+        );
+        assert_eq!(embeds[1].fields[0].value, "2");
+        assert_eq!(embeds[1].fields[1].value, "16m");
+        assert_eq!(embeds[1].fields[2].value, "#1 1000 uosmo\n#2 1000 uosmo");
+    }
+}
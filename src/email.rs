@@ -0,0 +1,433 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time,
+};
+use tokio_rustls::{rustls, TlsConnector};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{self, SmtpSecurity},
+    monitor::format_age,
+    webhook::{self, ChainEvent, EventKind, PacketEvent},
+};
+
+/// Timeout of a whole SMTP session
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of packets listed per channel in a message
+const MAX_LISTED_PACKETS: usize = 20;
+
+/// Mails packet events to the recipients of the matching rules, after every scan
+/// or batched into a digest
+#[derive(Clone)]
+pub struct Email {
+    config: Arc<config::Email>,
+    digest: Arc<Mutex<Digest>>,
+}
+
+/// Events waiting for the next digest, per rule
+struct Digest {
+    pending: Vec<Pending>,
+    started_at: Instant,
+}
+
+#[derive(Clone, Default)]
+struct Pending {
+    packets: Vec<PacketEvent>,
+    chains: Vec<ChainEvent>,
+}
+
+impl Email {
+    pub fn new(config: config::Email) -> Self {
+        let digest = Digest {
+            pending: vec![Pending::default(); config.rules.len()],
+            started_at: Instant::now(),
+        };
+
+        Self {
+            config: Arc::new(config),
+            digest: Arc::new(Mutex::new(digest)),
+        }
+    }
+
+    /// Whether the notifier was built from these settings
+    pub fn uses(&self, config: &config::Email) -> bool {
+        serde_json::to_value(self.config.as_ref()).ok() == serde_json::to_value(config).ok()
+    }
+
+    /// Queue the events of a scan, sending the messages which are due in the background
+    ///
+    /// Called after every scan, even without events, so that digests go out on time.
+    pub fn notify(&self, events: &[PacketEvent], chain_events: &[ChainEvent]) {
+        let mut digest = self.digest.lock().unwrap();
+
+        for (rule, pending) in self.config.rules.iter().zip(&mut digest.pending) {
+            let subscribed = |kind| rule.events.is_empty() || rule.events.contains(&kind);
+
+            pending.packets.extend(
+                events
+                    .iter()
+                    .filter(|event| {
+                        subscribed(event.event)
+                            && webhook::in_scope(&rule.chain, &rule.channel, &event.packet)
+                    })
+                    .cloned(),
+            );
+            pending.chains.extend(
+                chain_events
+                    .iter()
+                    .filter(|event| {
+                        subscribed(event.event) && event.in_scope(&rule.chain, &rule.channel)
+                    })
+                    .cloned(),
+            );
+        }
+
+        let interval = self
+            .config
+            .digest_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60));
+
+        if interval.is_some_and(|interval| digest.started_at.elapsed() < interval) {
+            return;
+        }
+
+        digest.started_at = Instant::now();
+
+        for (to, subject, body) in self.messages(&mut digest) {
+            tokio::spawn(send(self.config.clone(), to, subject, body));
+        }
+    }
+
+    /// Send the pending digests without waiting for their interval, on shutdown
+    pub async fn flush(&self) {
+        let messages = self.messages(&mut self.digest.lock().unwrap());
+
+        future::join_all(
+            messages
+                .into_iter()
+                .map(|(to, subject, body)| send(self.config.clone(), to, subject, body)),
+        )
+        .await;
+    }
+
+    /// Recipients, subject and body of the messages of the pending events, which are cleared
+    fn messages(&self, digest: &mut Digest) -> Vec<(Vec<String>, String, String)> {
+        let batched = self.config.digest_minutes.is_some();
+        let mut messages = Vec::new();
+
+        for (rule, pending) in self.config.rules.iter().zip(&mut digest.pending) {
+            if pending.packets.is_empty() && pending.chains.is_empty() {
+                continue;
+            }
+
+            let Pending { packets, chains } = std::mem::take(pending);
+            let subject = subject(&packets, &chains, batched);
+            let body = body(&packets, &chains);
+
+            messages.push((rule.to.clone(), subject, body));
+        }
+
+        messages
+    }
+}
+
+/// `[chainpulse] Packets stuck on osmosis-1 channel-0`, or a count when there are several channels
+fn subject(events: &[PacketEvent], chain_events: &[ChainEvent], digest: bool) -> String {
+    let groups = webhook::group_by_channel(&events.iter().collect::<Vec<_>>());
+    let count = events.len() + chain_events.len();
+
+    match (groups.as_slice(), chain_events) {
+        ([], [event]) if !digest => format!("[chainpulse] {}", event.heading()),
+        ([events], []) if !digest => {
+            let first = events[0];
+            format!(
+                "[chainpulse] {} on {} {}",
+                first.event.title(),
+                first.packet.chain_id,
+                first.packet.src_channel
+            )
+        }
+        _ if digest => format!("[chainpulse] Digest of {count} events"),
+        _ => format!("[chainpulse] {count} events"),
+    }
+}
+
+/// Plain text listing of the events about chains, then of the packet events
+/// by event kind and channel
+fn body(events: &[PacketEvent], chain_events: &[ChainEvent]) -> String {
+    let mut lines = Vec::new();
+
+    for event in chain_events {
+        lines.push(event.heading());
+        lines.push(format!("  {}, at {}", event.summary, event.timestamp));
+        lines.push(String::new());
+    }
+
+    for events in webhook::group_by_channel(&events.iter().collect::<Vec<_>>()) {
+        let first = events[0];
+
+        if let (EventKind::StuckResolved, Some(alert)) = (first.event, &first.alert) {
+            lines.push(format!(
+                "{} on {} {} → {}, stuck for {}",
+                first.event.title(),
+                first.packet.chain_id,
+                first.packet.src_channel,
+                first.packet.dst_channel,
+                format_age(alert.firing_seconds)
+            ));
+            lines.push(String::new());
+            continue;
+        }
+
+        lines.push(format!(
+            "{} on {} {} → {} ({})",
+            first.event.title(),
+            first.packet.chain_id,
+            first.packet.src_channel,
+            first.packet.dst_channel,
+            webhook::packet_count(&events)
+        ));
+
+        for event in events.iter().take(MAX_LISTED_PACKETS) {
+            let packet = &event.packet;
+            let transfer = match (&packet.amount, &packet.denom) {
+                (Some(amount), Some(denom)) => format!(" {amount} {denom}"),
+                _ => String::new(),
+            };
+
+            let timeout = match (event.event, packet.seconds_until_timeout) {
+                (EventKind::WatchedNearTimeout, Some(seconds)) => {
+                    format!(", times out in {}", format_age(seconds))
+                }
+                _ => String::new(),
+            };
+
+            lines.push(format!(
+                "  #{}{transfer}, sent {} ago{timeout}, notified at {}",
+                packet.sequence,
+                format_age(packet.age_seconds),
+                event.timestamp
+            ));
+        }
+
+        if events.len() > MAX_LISTED_PACKETS {
+            lines.push(format!("  and {} more", events.len() - MAX_LISTED_PACKETS));
+        }
+
+        lines.push(String::new());
+    }
+
+    lines.join("\n")
+}
+
+/// RFC 5322 message, with lines ending in CRLF and dot-stuffed for the DATA command
+fn message(from: &str, to: &[String], subject: &str, body: &str) -> String {
+    let mut message = format!(
+        "From: {from}\r\nTo: {}\r\nSubject: {subject}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        to.join(", "),
+        chrono::Utc::now().to_rfc2822()
+    );
+
+    for line in body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+
+    message
+}
+
+/// Address of a `Name <address>` mailbox
+fn address(mailbox: &str) -> &str {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+async fn send(config: Arc<config::Email>, to: Vec<String>, subject: String, body: String) {
+    let message = message(&config.from, &to, &subject, &body);
+
+    match time::timeout(SMTP_TIMEOUT, deliver(&config, &to, &message)).await {
+        Ok(Ok(())) => debug!(host = %config.host, "Mailed packet events to {}", to.join(", ")),
+        Ok(Err(e)) => warn!(host = %config.host, "Failed to mail packet events: {e}"),
+        Err(_) => warn!(host = %config.host, "Timed out mailing packet events"),
+    }
+}
+
+/// Run an SMTP session delivering the message to the recipients
+async fn deliver(config: &config::Email, to: &[String], message: &str) -> crate::Result<()> {
+    let port = config.port.unwrap_or(match config.security {
+        SmtpSecurity::None => 25,
+        SmtpSecurity::Starttls => 587,
+        SmtpSecurity::Tls => 465,
+    });
+
+    let tcp = TcpStream::connect((config.host.as_str(), port)).await?;
+
+    let mut smtp = match config.security {
+        SmtpSecurity::Tls => Smtp::new(Box::new(tls_connect(&config.host, tcp).await?)),
+        _ => Smtp::new(Box::new(tcp)),
+    };
+
+    smtp.reply(220).await?;
+    smtp.command("EHLO chainpulse", 250).await?;
+
+    if config.security == SmtpSecurity::Starttls {
+        smtp.command("STARTTLS", 220).await?;
+        let tls = tls_connect(&config.host, smtp.into_inner()).await?;
+
+        smtp = Smtp::new(Box::new(tls));
+        smtp.command("EHLO chainpulse", 250).await?;
+    }
+
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        let credentials = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            format!("\0{username}\0{password}"),
+        );
+        smtp.command(&format!("AUTH PLAIN {credentials}"), 235)
+            .await?;
+    }
+
+    smtp.command(&format!("MAIL FROM:<{}>", address(&config.from)), 250)
+        .await?;
+
+    for recipient in to {
+        smtp.command(&format!("RCPT TO:<{}>", address(recipient)), 250)
+            .await?;
+    }
+
+    smtp.command("DATA", 354).await?;
+    smtp.write(&format!("{message}.\r\n")).await?;
+    smtp.reply(250).await?;
+
+    // The message is accepted at this point
+    let _ = smtp.command("QUIT", 221).await;
+
+    Ok(())
+}
+
+async fn tls_connect<S>(host: &str, stream: S) -> io::Result<tokio_rustls::client::TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_native_certs::load_native_certs()? {
+        let _ = roots.add(cert);
+    }
+
+    let tls = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    TlsConnector::from(Arc::new(tls))
+        .connect(server_name, stream)
+        .await
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Command and reply exchange with an SMTP server
+struct Smtp {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl Smtp {
+    fn new(stream: Box<dyn Stream>) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> Box<dyn Stream> {
+        self.stream.into_inner()
+    }
+
+    async fn write(&mut self, data: &str) -> io::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(data.as_bytes()).await?;
+        stream.flush().await
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> crate::Result<String> {
+        self.write(&format!("{command}\r\n")).await?;
+        self.reply(expected).await
+    }
+
+    /// Read a possibly multiline reply, failing unless it is in the class of the expected code
+    async fn reply(&mut self, expected: u16) -> crate::Result<String> {
+        let mut text = String::new();
+
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err("connection closed by the SMTP server".into());
+            }
+
+            let line = line.trim_end();
+            let (code, rest) = line.split_at(line.len().min(3));
+            let code: u16 = code
+                .parse()
+                .map_err(|_| format!("invalid SMTP reply: {line}"))?;
+
+            text.push_str(rest.get(1..).unwrap_or_default());
+            text.push('\n');
+
+            // Continuation lines have a dash after the code
+            if rest.starts_with('-') {
+                continue;
+            }
+
+            if code / 100 != expected / 100 {
+                return Err(format!("SMTP server replied {code} {}", text.trim_end()).into());
+            }
+
+            return Ok(text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let message = message(
+            "chainpulse <alerts@example.com>",
+            &["a@example.com".to_string(), "b@example.com".to_string()],
+            "[chainpulse] 2 packet events",
+            "Packets stuck\n.hidden\n",
+        );
+
+        assert!(message.starts_with(
+            "From: chainpulse <alerts@example.com>\r\nTo: a@example.com, b@example.com\r\n"
+        ));
+        assert!(message.ends_with("\r\n\r\nPackets stuck\r\n..hidden\r\n"));
+        assert_eq!(
+            address("chainpulse <alerts@example.com>"),
+            "alerts@example.com"
+        );
+        assert_eq!(address(" alerts@example.com "), "alerts@example.com");
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use ibc_proto::ibc::{
+    core::{
+        channel::v1::{QueryChannelClientStateRequest, QueryChannelClientStateResponse},
+        client::v1::{QueryConsensusStateRequest, QueryConsensusStateResponse},
+    },
+    lightclients::tendermint::v1::ConsensusState,
+};
+use serde_json::json;
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, warn};
+
+use crate::{
+    alert::{Alerts, Transition},
+    config::{Chains, Config, Endpoint},
+    metrics::Metrics,
+    monitor::format_age,
+    rpc::{self, decode_any},
+    webhook::{ChainEvent, EventKind, Webhooks},
+};
+
+/// How often the light clients are checked for expiry
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+const TENDERMINT_CONSENSUS_STATE: &str = "/ibc.lightclients.tendermint.v1.ConsensusState";
+
+/// State of a light client, as queried from the chain hosting it
+#[derive(Clone, Debug)]
+struct ProbedClient {
+    counterparty_chain_id: String,
+    trusting_period_seconds: i64,
+    latest_height: u64,
+    frozen: bool,
+}
+
+/// Periodically probe the light clients backing the channels which sent packets, exporting the
+/// time left before they expire, their latest height and whether they are frozen, and notify
+/// those with less than the configured margin left, or frozen, until they are updated
+///
+/// The client of a channel is queried from the chain once, and its state on every check. Clients
+/// expire a trusting period after the header of their last update, taken from the recorded
+/// `MsgUpdateClient` messages, or from the latest consensus state of the client when none was seen.
+pub async fn run(
+    pool: SqlitePool,
+    mut config: watch::Receiver<Config>,
+    metrics: Metrics,
+    webhooks: Webhooks,
+) {
+    let mut interval = time::interval(CHECK_INTERVAL);
+    let mut alerts = Alerts::new(config.borrow().alerts.clone());
+    let mut channel_clients: HashMap<(String, String), String> = HashMap::new();
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+
+        let Config {
+            chains,
+            alerts: settings,
+            ..
+        } = config.borrow_and_update().clone();
+
+        let expiry = settings.client_expiry.clone();
+
+        alerts.configure(settings);
+
+        let channels = match sending_channels(&pool).await {
+            Ok(channels) => channels,
+            Err(e) => {
+                error!("Failed to load the channels to check the clients of: {e}");
+                continue;
+            }
+        };
+
+        // Channels of each client, by chain and client ID
+        let mut backed: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+
+        for (chain_id, port_id, channel_id) in channels {
+            let Some(endpoint) = endpoint(&chains, &chain_id) else {
+                continue;
+            };
+
+            let key = (chain_id.clone(), channel_id.clone());
+
+            if !channel_clients.contains_key(&key) {
+                match channel_client(&client, endpoint, &port_id, &channel_id).await {
+                    Ok(client_id) => {
+                        channel_clients.insert(key.clone(), client_id);
+                    }
+                    Err(e) => {
+                        warn!(chain = %chain_id, "Failed to query the client of {port_id}/{channel_id}: {e}");
+                        continue;
+                    }
+                }
+            }
+
+            backed
+                .entry((chain_id, channel_clients[&key].clone()))
+                .or_default()
+                .push(channel_id);
+        }
+
+        metrics.reset_client_gauges();
+
+        let mut events = Vec::new();
+
+        for ((chain_id, client_id), channels) in backed {
+            let Some(endpoint) = endpoint(&chains, &chain_id) else {
+                continue;
+            };
+
+            let probed = match probe(&client, endpoint, &client_id).await {
+                Ok(probed) => probed,
+                Err(e) => {
+                    warn!(chain = %chain_id, "Failed to query the state of {client_id}: {e}");
+                    continue;
+                }
+            };
+
+            let header_age = match header_age(&pool, &chain_id, &client_id).await {
+                Ok(Some(age)) => age,
+                Ok(None) => match consensus_state_age(&client, endpoint, &client_id).await {
+                    Ok(age) => age,
+                    Err(e) => {
+                        warn!(chain = %chain_id, "Failed to query the consensus state of {client_id}: {e}");
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    error!(chain = %chain_id, "Failed to load the last update of {client_id}: {e}");
+                    continue;
+                }
+            };
+
+            let trusting_period_seconds = expiry
+                .as_ref()
+                .and_then(|expiry| expiry.trusting_period_hours)
+                .map_or(probed.trusting_period_seconds, |hours| hours as i64 * 3600);
+
+            let expires_in = trusting_period_seconds - header_age;
+
+            metrics.ibc_client_expiry_seconds(
+                &chain_id,
+                &client_id,
+                &probed.counterparty_chain_id,
+                expires_in,
+            );
+            metrics.ibc_client_latest_height(
+                &chain_id,
+                &client_id,
+                &probed.counterparty_chain_id,
+                probed.latest_height,
+            );
+            metrics.ibc_client_frozen(
+                &chain_id,
+                &client_id,
+                &probed.counterparty_chain_id,
+                probed.frozen,
+            );
+
+            let Some(expiry) = &expiry else {
+                continue;
+            };
+
+            let margin_seconds = expiry.margin_hours as i64 * 3600;
+            let expiring = probed.frozen || expires_in < margin_seconds;
+
+            let transition = alerts.condition(
+                EventKind::ClientExpiring,
+                &chain_id,
+                Some(&client_id),
+                expiring,
+            );
+
+            let details = json!({
+                "client_id": client_id,
+                "counterparty_chain_id": probed.counterparty_chain_id,
+                "channels": channels,
+                "trusting_period_seconds": trusting_period_seconds,
+                "seconds_since_last_header": header_age,
+                "expires_in_seconds": expires_in,
+                "latest_height": probed.latest_height,
+                "frozen": probed.frozen,
+            });
+
+            let event = match transition {
+                Some(Transition::Fired) => {
+                    let summary = match expires_in {
+                        _ if probed.frozen => format!(
+                            "Client {client_id} of {} is frozen, backing {}",
+                            probed.counterparty_chain_id,
+                            channels.join(", ")
+                        ),
+                        seconds if seconds > 0 => format!(
+                            "Client {client_id} of {} expires in {}, backing {}",
+                            probed.counterparty_chain_id,
+                            format_age(seconds),
+                            channels.join(", ")
+                        ),
+                        seconds => format!(
+                            "Client {client_id} of {} expired {} ago, backing {}",
+                            probed.counterparty_chain_id,
+                            format_age(-seconds),
+                            channels.join(", ")
+                        ),
+                    };
+
+                    ChainEvent::new(EventKind::ClientExpiring, &chain_id, None, summary, details)
+                }
+                Some(Transition::Resolved { firing_seconds }) => {
+                    let summary = format!(
+                        "Client {client_id} of {} was updated and expires in {}",
+                        probed.counterparty_chain_id,
+                        format_age(expires_in)
+                    );
+
+                    ChainEvent::new(EventKind::ClientExpiring, &chain_id, None, summary, details)
+                        .resolved(firing_seconds)
+                }
+                None => continue,
+            };
+
+            events.push(event);
+        }
+
+        if !events.is_empty() {
+            debug!("Notifying {} client expiry events", events.len());
+            webhooks.announce(events).await;
+        }
+    }
+}
+
+/// Endpoint of a monitored chain, by its ID
+pub(crate) fn endpoint<'a>(chains: &'a Chains, chain_id: &str) -> Option<&'a Endpoint> {
+    chains
+        .endpoints
+        .iter()
+        .find_map(|(id, endpoint)| (id.as_str() == chain_id).then_some(endpoint))
+}
+
+/// Chain, port and channel of every channel packets were sent on
+async fn sending_channels(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String, String)>> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT t.chain, p.src_port, p.src_channel
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+        ORDER BY t.chain, p.src_channel
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Seconds since the time of the header of the last recorded update of a client
+async fn header_age(pool: &SqlitePool, chain: &str, client_id: &str) -> sqlx::Result<Option<i64>> {
+    sqlx::query_scalar(
+        r#"
+        SELECT CAST((strftime('%s', 'now') - strftime('%s', COALESCE(u.header_time, u.created_at))) AS INTEGER)
+        FROM client_updates u
+        JOIN txs t ON u.tx_id = t.id
+        WHERE t.chain = ? AND u.client_id = ?
+        ORDER BY u.id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(chain)
+    .bind(client_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// ID of the light client backing a channel
+async fn channel_client(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    port_id: &str,
+    channel_id: &str,
+) -> crate::Result<String> {
+    let request = QueryChannelClientStateRequest {
+        port_id: port_id.to_string(),
+        channel_id: channel_id.to_string(),
+    };
+
+    let response: QueryChannelClientStateResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.core.channel.v1.Query/ChannelClientState",
+        request,
+    )
+    .await?;
+
+    let identified = response
+        .identified_client_state
+        .ok_or("no client state in the response")?;
+
+    Ok(identified.client_id)
+}
+
+/// Current state of a light client
+async fn probe(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    client_id: &str,
+) -> crate::Result<ProbedClient> {
+    let state = rpc::client_state(client, endpoint, client_id).await?;
+
+    let trusting_period = state
+        .trusting_period
+        .ok_or("no trusting period in the client state")?;
+
+    // A client is frozen at the height of the misbehaviour it was shown, and zero otherwise
+    let frozen = state
+        .frozen_height
+        .is_some_and(|height| height.revision_number != 0 || height.revision_height != 0);
+
+    Ok(ProbedClient {
+        counterparty_chain_id: state.chain_id,
+        trusting_period_seconds: trusting_period.seconds,
+        latest_height: state
+            .latest_height
+            .map_or(0, |height| height.revision_height),
+        frozen,
+    })
+}
+
+/// Seconds since the timestamp of the latest consensus state of a client
+async fn consensus_state_age(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    client_id: &str,
+) -> crate::Result<i64> {
+    let request = QueryConsensusStateRequest {
+        client_id: client_id.to_string(),
+        latest_height: true,
+        ..Default::default()
+    };
+
+    let response: QueryConsensusStateResponse = rpc::abci_query(
+        client,
+        endpoint,
+        "/ibc.core.client.v1.Query/ConsensusState",
+        request,
+    )
+    .await?;
+
+    let state: ConsensusState = decode_any(response.consensus_state, TENDERMINT_CONSENSUS_STATE)?;
+    let timestamp = state
+        .timestamp
+        .ok_or("no timestamp in the consensus state")?;
+
+    Ok(chrono::Utc::now().timestamp() - timestamp.seconds)
+}
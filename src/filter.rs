@@ -0,0 +1,134 @@
+use crate::config::Filters;
+
+/// Fields of a packet the filters apply to
+#[derive(Debug, Default)]
+pub struct Packet<'a> {
+    pub src_port: &'a str,
+    pub src_channel: &'a str,
+    pub dst_port: &'a str,
+    pub dst_channel: &'a str,
+    pub denom: Option<&'a str>,
+    pub amount: Option<&'a str>,
+}
+
+/// Whether a packet passes the filters of the configuration, and should be recorded
+pub fn allows(filters: &Filters, packet: &Packet<'_>) -> bool {
+    let ports = [packet.src_port, packet.dst_port];
+    let channels = [packet.src_channel, packet.dst_channel];
+
+    if !matches_list(&filters.allow_ports, &filters.deny_ports, &ports) {
+        return false;
+    }
+
+    if !matches_list(&filters.allow_channels, &filters.deny_channels, &channels) {
+        return false;
+    }
+
+    let Some(denom) = packet.denom else {
+        return filters.allow_denoms.is_empty();
+    };
+
+    let denoms = [denom, base_denom(denom)];
+
+    if !matches_list(&filters.allow_denoms, &filters.deny_denoms, &denoms) {
+        return false;
+    }
+
+    let minimum = denoms
+        .iter()
+        .find_map(|denom| filters.min_amounts.get(*denom));
+
+    match (minimum, packet.amount) {
+        (Some(minimum), Some(amount)) => amount
+            .parse::<u128>()
+            .is_ok_and(|amount| amount >= *minimum as u128),
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
+/// Whether any of the values is allowed, when the allow list is not empty, and none is denied
+fn matches_list(allow: &[String], deny: &[String], values: &[&str]) -> bool {
+    let listed = |list: &[String]| values.iter().any(|value| list.iter().any(|v| v == value));
+
+    (allow.is_empty() || listed(allow)) && !listed(deny)
+}
+
+/// Denom without the `{port}/{channel}/` hops of its trace, such as `uatom` for
+/// `transfer/channel-0/uatom`
+fn base_denom(denom: &str) -> &str {
+    let mut base = denom;
+
+    while let Some((_, rest)) = base.split_once('/') {
+        match rest.split_once('/') {
+            Some((channel, rest)) if channel.starts_with("channel-") => base = rest,
+            _ => break,
+        }
+    }
+
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let filters: Filters = toml::from_str(
+            r#"
+            allow_ports = ["transfer"]
+            deny_channels = ["channel-1"]
+            deny_denoms = ["uion"]
+            min_amounts = { uatom = 1000000 }
+            "#,
+        )
+        .unwrap();
+
+        let transfer = |channel, denom, amount| Packet {
+            src_port: "transfer",
+            src_channel: channel,
+            dst_port: "transfer",
+            dst_channel: "channel-141",
+            denom: Some(denom),
+            amount: Some(amount),
+        };
+
+        assert!(allows(&filters, &transfer("channel-0", "uosmo", "1")));
+        assert!(allows(
+            &filters,
+            &transfer("channel-0", "transfer/channel-0/uatom", "1000000")
+        ));
+        assert!(!allows(
+            &filters,
+            &transfer("channel-0", "transfer/channel-0/uatom", "999999")
+        ));
+        assert!(!allows(
+            &filters,
+            &transfer("channel-0", "uatom", "not an amount")
+        ));
+        assert!(!allows(&filters, &transfer("channel-1", "uosmo", "1")));
+        assert!(!allows(&filters, &transfer("channel-0", "uion", "1")));
+
+        let ica = Packet {
+            src_port: "icacontroller-osmo1abc",
+            dst_port: "icahost",
+            ..Default::default()
+        };
+
+        assert!(!allows(&filters, &ica));
+        assert!(allows(&Filters::default(), &ica));
+    }
+
+    #[test]
+    fn test_base_denom() {
+        assert_eq!(base_denom("uatom"), "uatom");
+        assert_eq!(base_denom("transfer/channel-0/uatom"), "uatom");
+        assert_eq!(
+            base_denom("transfer/channel-0/transfer/channel-141/uatom"),
+            "uatom"
+        );
+        assert_eq!(base_denom("gamm/pool/1"), "gamm/pool/1");
+        assert_eq!(base_denom("transfer/channel-0/gamm/pool/1"), "gamm/pool/1");
+    }
+}
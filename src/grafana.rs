@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::{
+    config,
+    monitor::format_age,
+    webhook::{self, ChainEvent, EventKind, PacketEvent},
+};
+
+/// Timeout of a single annotation
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Annotation of the Grafana HTTP API
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Annotation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dashboard_uid: Option<String>,
+    /// Milliseconds since the epoch
+    time: i64,
+    /// End of the region covered by the annotation, for the conditions which cleared
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_end: Option<i64>,
+    tags: Vec<String>,
+    text: String,
+}
+
+/// Publishes the events as annotations of Grafana, so that dashboards mark them along the
+/// metrics
+///
+/// An event firing is marked at the time it is published, one which cleared as a region from
+/// the time it fired until it cleared.
+#[derive(Clone)]
+pub struct Grafana {
+    config: config::Grafana,
+    client: reqwest::Client,
+}
+
+impl Grafana {
+    pub fn new(config: config::Grafana) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Annotate each kind of event on each channel once
+    pub async fn notify(self, events: Vec<PacketEvent>) {
+        let events: Vec<&PacketEvent> = events
+            .iter()
+            .filter(|event| {
+                (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                    && webhook::in_scope(&self.config.chain, &self.config.channel, &event.packet)
+            })
+            .collect();
+
+        for events in webhook::group_by_channel(&events) {
+            let annotation = self.packet_annotation(&events, Utc::now().timestamp_millis());
+            self.send(&annotation).await;
+        }
+    }
+
+    /// Annotate each of the events about chains and channels
+    pub async fn announce(self, events: Vec<ChainEvent>) {
+        for event in events.iter().filter(|event| {
+            (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                && event.in_scope(&self.config.chain, &self.config.channel)
+        }) {
+            let annotation = self.chain_annotation(event, Utc::now().timestamp_millis());
+            self.send(&annotation).await;
+        }
+    }
+
+    /// Annotation of events of the same kind on a channel
+    fn packet_annotation(&self, events: &[&PacketEvent], now: i64) -> Annotation {
+        let first = events[0];
+        let packet = &first.packet;
+
+        let heading = format!(
+            "{} on {} {} → {}",
+            first.event.title(),
+            packet.chain_id,
+            packet.src_channel,
+            packet.dst_channel
+        );
+
+        let (text, firing_seconds) = match (first.event, &first.alert) {
+            (EventKind::StuckResolved, Some(alert)) => (
+                format!("{heading}\nStuck for {}", format_age(alert.firing_seconds)),
+                Some(alert.firing_seconds),
+            ),
+            _ => {
+                let oldest = events
+                    .iter()
+                    .map(|event| event.packet.age_seconds)
+                    .max()
+                    .unwrap_or_default();
+
+                (
+                    format!(
+                        "{heading}\n{} packets, the oldest sent {} ago",
+                        webhook::packet_count(events),
+                        format_age(oldest)
+                    ),
+                    None,
+                )
+            }
+        };
+
+        let channels = [packet.src_channel.as_str(), packet.dst_channel.as_str()];
+        self.annotation(
+            first.event,
+            &packet.chain_id,
+            &channels,
+            text,
+            now,
+            firing_seconds,
+        )
+    }
+
+    fn chain_annotation(&self, event: &ChainEvent, now: i64) -> Annotation {
+        let channels: Vec<&str> = event.channel.iter().map(String::as_str).collect();
+
+        self.annotation(
+            event.event,
+            &event.chain_id,
+            &channels,
+            format!("{}\n{}", event.heading(), event.summary),
+            now,
+            event.firing_seconds.filter(|_| event.resolved),
+        )
+    }
+
+    fn annotation(
+        &self,
+        event: EventKind,
+        chain_id: &str,
+        channels: &[&str],
+        text: String,
+        now: i64,
+        firing_seconds: Option<i64>,
+    ) -> Annotation {
+        let mut tags = vec![
+            "chainpulse".to_string(),
+            event.as_str().to_string(),
+            chain_id.to_string(),
+        ];
+        tags.extend(channels.iter().map(|channel| channel.to_string()));
+        tags.extend(self.config.tags.iter().cloned());
+
+        let (time, time_end) = match firing_seconds {
+            Some(seconds) => (now - seconds * 1000, Some(now)),
+            None => (now, None),
+        };
+
+        Annotation {
+            dashboard_uid: self.config.dashboard_uid.clone(),
+            time,
+            time_end,
+            tags,
+            text,
+        }
+    }
+
+    async fn send(&self, annotation: &Annotation) {
+        let url = format!("{}/api/annotations", self.config.url.trim_end_matches('/'));
+
+        let result = self
+            .client
+            .post(url)
+            .bearer_auth(&self.config.token)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(annotation)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => debug!("Annotated {} in Grafana", annotation.tags[1]),
+            Err(e) => warn!("Failed to annotate {} in Grafana: {e}", annotation.tags[1]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhook::{AlertContext, PacketSnapshot};
+
+    #[test]
+    fn test_annotation() {
+        let grafana = Grafana::new(config::Grafana {
+            url: "https://grafana.example.com".to_string(),
+            token: "glsa_abc".to_string(),
+            dashboard_uid: Some("ibc".to_string()),
+            tags: vec!["mainnet".to_string()],
+            events: vec![],
+            chain: None,
+            channel: None,
+        });
+
+        let event = PacketEvent::new(
+            EventKind::StuckResolved,
+            PacketSnapshot {
+                chain_id: "osmosis-1".to_string(),
+                src_channel: "channel-0".to_string(),
+                dst_channel: "channel-141".to_string(),
+                sequence: 7,
+                sender: None,
+                receiver: None,
+                amount: None,
+                denom: None,
+                age_seconds: 3600,
+                seconds_until_timeout: None,
+            },
+        )
+        .with_alert(AlertContext {
+            packets: 3,
+            firing_seconds: 600,
+        });
+
+        assert_eq!(
+            grafana.packet_annotation(&[&event], 1_000_000),
+            Annotation {
+                dashboard_uid: Some("ibc".to_string()),
+                time: 400_000,
+                time_end: Some(1_000_000),
+                tags: vec![
+                    "chainpulse".to_string(),
+                    "stuck_resolved".to_string(),
+                    "osmosis-1".to_string(),
+                    "channel-0".to_string(),
+                    "channel-141".to_string(),
+                    "mainnet".to_string(),
+                ],
+                text: "Stuck packets resolved on osmosis-1 channel-0 → channel-141\nStuck for 10m"
+                    .to_string(),
+            }
+        );
+
+        let halted = ChainEvent::new(
+            EventKind::ChainHalted,
+            "osmosis-1",
+            None,
+            "No block for 5m".to_string(),
+            serde_json::Value::Null,
+        );
+
+        let annotation = grafana.chain_annotation(&halted, 1_000_000);
+        assert_eq!((annotation.time, annotation.time_end), (1_000_000, None));
+        assert_eq!(
+            annotation.text,
+            "Chain halted on osmosis-1\nNo block for 5m"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{sync::watch, time};
+use tracing::{debug, warn};
+
+use crate::{
+    alert::{Alerts, Transition},
+    config::{Config, Endpoint},
+    monitor::format_age,
+    rpc,
+    status::{ConnectionState, Status},
+    webhook::{ChainEvent, EventKind, Webhooks},
+};
+
+/// How often the chains are checked for halts
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Latest block known to the RPC endpoint of a chain
+#[derive(Debug)]
+struct Head {
+    height: u64,
+    time: String,
+}
+
+/// Periodically look for chains without a new block for longer than the configured multiple of
+/// their average block time, and notify the halts and their end
+///
+/// Before alerting, the RPC endpoint is asked for its latest block: when it has blocks the
+/// collector did not receive, or cannot be reached, the problem lies with chainpulse's connection
+/// to the chain rather than with the chain itself.
+pub async fn run(mut config: watch::Receiver<Config>, status: Status, webhooks: Webhooks) {
+    let mut interval = time::interval(CHECK_INTERVAL);
+    let mut alerts = Alerts::new(config.borrow().alerts.clone());
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+
+        let Config {
+            chains,
+            alerts: settings,
+            ..
+        } = config.borrow_and_update().clone();
+
+        let Some(halt) = settings.chain_halt.clone() else {
+            continue;
+        };
+
+        alerts.configure(settings);
+
+        let mut events = Vec::new();
+
+        for (chain_id, endpoint) in &chains.endpoints {
+            let Some(chain) = status.chain(chain_id) else {
+                continue;
+            };
+
+            let (Some(height), Some(lag), Some(block_time)) = (
+                chain.last_height,
+                chain.head_lag_seconds(),
+                chain.block_time_seconds,
+            ) else {
+                continue;
+            };
+
+            if matches!(
+                chain.state,
+                ConnectionState::Paused | ConnectionState::Disabled
+            ) {
+                continue;
+            }
+
+            let threshold = (block_time * halt.block_time_multiple).max(halt.min_seconds as f64);
+
+            let head = if (lag as f64) < threshold {
+                None
+            } else {
+                match probe(&client, endpoint).await {
+                    Ok(head) if head.height > height => {
+                        warn!(
+                            chain = %chain_id,
+                            "No block received for {}, but the endpoint is at height {} past {height}, \
+                             the connection to it is likely stalled",
+                            format_age(lag),
+                            head.height
+                        );
+                        None
+                    }
+                    Ok(head) => Some(head),
+                    Err(e) => {
+                        warn!(
+                            chain = %chain_id,
+                            "No block received for {} and the endpoint is unreachable, \
+                             not treating it as a chain halt: {e}",
+                            format_age(lag)
+                        );
+                        continue;
+                    }
+                }
+            };
+
+            let chain_id = chain_id.as_str();
+            let transition =
+                alerts.condition(EventKind::ChainHalted, chain_id, None, head.is_some());
+
+            match (transition, head) {
+                (Some(Transition::Fired), Some(head)) => {
+                    let summary = format!(
+                        "No new block for {} since height {height}, while blocks are produced \
+                         every {block_time:.1}s on average; the endpoint has no later block",
+                        format_age(lag)
+                    );
+
+                    events.push(ChainEvent::new(
+                        EventKind::ChainHalted,
+                        chain_id,
+                        None,
+                        summary,
+                        json!({
+                            "last_height": height,
+                            "last_block_time": chain.last_block_time.map(|t| t.to_rfc3339()),
+                            "seconds_since_last_block": lag,
+                            "block_time_seconds": block_time,
+                            "endpoint_height": head.height,
+                            "endpoint_block_time": head.time,
+                        }),
+                    ));
+                }
+                (Some(Transition::Resolved { firing_seconds }), _) => {
+                    let summary = format!(
+                        "Blocks are produced again, up to height {height}, after a halt of {}",
+                        format_age(firing_seconds)
+                    );
+
+                    events.push(
+                        ChainEvent::new(
+                            EventKind::ChainHalted,
+                            chain_id,
+                            None,
+                            summary,
+                            json!({ "last_height": height }),
+                        )
+                        .resolved(firing_seconds),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if !events.is_empty() {
+            debug!("Notifying {} chain halt events", events.len());
+            webhooks.announce(events).await;
+        }
+    }
+}
+
+/// Ask the RPC endpoint of a chain for its latest block
+async fn probe(client: &reqwest::Client, endpoint: &Endpoint) -> crate::Result<Head> {
+    #[derive(Deserialize)]
+    struct Status {
+        sync_info: SyncInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct SyncInfo {
+        latest_block_height: String,
+        latest_block_time: String,
+    }
+
+    let status: Status = rpc::get(client, endpoint, "/status").await?;
+
+    Ok(Head {
+        height: status.sync_info.latest_block_height.parse()?,
+        time: status.sync_info.latest_block_time,
+    })
+}
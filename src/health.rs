@@ -0,0 +1,189 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+use tokio::{sync::watch, time};
+use tracing::debug;
+
+use crate::{
+    alert::{Alerts, Transition},
+    config::Config,
+    monitor::format_age,
+    status::{ChainStatus, Status},
+    webhook::{ChainEvent, EventKind, Webhooks},
+};
+
+/// Failures and decode failures counted by a collector at some point
+#[derive(Clone, Copy)]
+struct Sample {
+    at: Instant,
+    failures: u64,
+    decode_failures: u64,
+}
+
+/// Periodically look for collectors which reconnect after failures too often, keep failing to
+/// decode messages, or have been unable to connect for too long, and notify these problems and
+/// their end
+///
+/// Failures are the errors and timeouts which made a collector reconnect, leaving out the
+/// periodic reconnections after a number of blocks. Paused collectors are not alerted on.
+/// Collectors are checked every `intervals.health_check_seconds`.
+pub async fn run(mut config: watch::Receiver<Config>, status: Status, webhooks: Webhooks) {
+    let mut period = config.borrow().intervals.health_check_seconds;
+    let mut interval = time::interval(Duration::from_secs(period));
+    let mut alerts = Alerts::new(config.borrow().alerts.clone());
+    let mut samples: HashMap<String, VecDeque<Sample>> = HashMap::new();
+
+    loop {
+        interval.tick().await;
+
+        let (settings, reloaded_period) = {
+            let config = config.borrow_and_update();
+            (config.alerts.clone(), config.intervals.health_check_seconds)
+        };
+
+        if reloaded_period != period {
+            period = reloaded_period;
+            let period = Duration::from_secs(period);
+            interval = time::interval_at(time::Instant::now() + period, period);
+        }
+
+        let Some(health) = settings.collector_health.clone() else {
+            continue;
+        };
+
+        alerts.configure(settings);
+
+        let window = Duration::from_secs(health.window_minutes * 60);
+        let backoff_seconds = health.backoff_minutes as i64 * 60;
+
+        // Chains no longer monitored
+        let chains = status.chains();
+        samples.retain(|chain_id, _| chains.iter().any(|chain| chain.chain_id == *chain_id));
+
+        let now = Instant::now();
+        let mut events = Vec::new();
+
+        for chain in chains {
+            let history = samples.entry(chain.chain_id.clone()).or_default();
+
+            history.push_back(Sample {
+                at: now,
+                failures: chain.errors + chain.timeouts,
+                decode_failures: chain.decode_failures,
+            });
+
+            while history
+                .front()
+                .is_some_and(|sample| now.duration_since(sample.at) > window)
+            {
+                history.pop_front();
+            }
+
+            // Counters only go down when reset through the admin API
+            let oldest = history[0];
+            let failures = (chain.errors + chain.timeouts).saturating_sub(oldest.failures);
+            let decode_failures = chain.decode_failures.saturating_sub(oldest.decode_failures);
+            let disconnected = chain
+                .disconnected_since
+                .map(|since| (chrono::Utc::now() - since).num_seconds());
+
+            let problems = [
+                (
+                    "reconnect_storm",
+                    failures >= health.max_reconnects,
+                    format!(
+                        "Reconnected {failures} times after errors or timeouts in the last {}",
+                        format_age(window.as_secs() as i64)
+                    ),
+                ),
+                (
+                    "decode_failures",
+                    decode_failures >= health.max_decode_failures,
+                    format!(
+                        "{decode_failures} messages failed to decode in the last {}",
+                        format_age(window.as_secs() as i64)
+                    ),
+                ),
+                (
+                    "backoff",
+                    disconnected.is_some_and(|seconds| seconds >= backoff_seconds),
+                    format!(
+                        "Unable to connect to {} for {}",
+                        chain.endpoint,
+                        format_age(disconnected.unwrap_or_default())
+                    ),
+                ),
+            ];
+
+            for (problem, met, summary) in problems {
+                let transition = alerts.condition(
+                    EventKind::CollectorUnhealthy,
+                    &chain.chain_id,
+                    Some(problem),
+                    met,
+                );
+
+                let event = match transition {
+                    Some(Transition::Fired) => {
+                        // Errors may carry a backtrace on the following lines
+                        let last_error = chain.last_error.as_deref().and_then(|e| e.lines().next());
+
+                        let summary = match last_error {
+                            Some(error) if problem != "decode_failures" => {
+                                format!("{summary}, last error: {error}")
+                            }
+                            _ => summary,
+                        };
+
+                        event(&chain, problem, summary, failures, decode_failures)
+                    }
+                    Some(Transition::Resolved { firing_seconds }) => {
+                        let summary = format!(
+                            "Collector recovered from {} after {}",
+                            problem.replace('_', " "),
+                            format_age(firing_seconds)
+                        );
+
+                        event(&chain, problem, summary, failures, decode_failures)
+                            .resolved(firing_seconds)
+                    }
+                    None => continue,
+                };
+
+                events.push(event);
+            }
+        }
+
+        if !events.is_empty() {
+            debug!("Notifying {} collector health events", events.len());
+            webhooks.announce(events).await;
+        }
+    }
+}
+
+fn event(
+    chain: &ChainStatus,
+    problem: &str,
+    summary: String,
+    failures: u64,
+    decode_failures: u64,
+) -> ChainEvent {
+    ChainEvent::new(
+        EventKind::CollectorUnhealthy,
+        &chain.chain_id,
+        None,
+        summary,
+        json!({
+            "problem": problem,
+            "state": chain.state,
+            "endpoint": chain.endpoint,
+            "disconnected_since": chain.disconnected_since.map(|time| time.to_rfc3339()),
+            "failures_in_window": failures,
+            "decode_failures_in_window": decode_failures,
+            "last_error": chain.last_error,
+        }),
+    )
+}
@@ -0,0 +1,472 @@
+use std::{future::Future, time::Duration};
+
+use prometheus::Registry;
+use sqlx::SqlitePool;
+use tokio::sync::{broadcast, watch};
+use tracing::{error, error_span, info, warn, Instrument, Span};
+
+use crate::{
+    api, channels, checkpoint, clear,
+    config::Config,
+    counterparty, counters, db, denoms, expiry, halt, health,
+    lock::Lock,
+    metrics::Metrics,
+    monitor, populate, prices, rate_limits, reconcile, relay,
+    reload::{ChainChanges, Collectors},
+    remote_write, report, rollup,
+    status::{Status, TaskState},
+    warehouse,
+    webhook::{Event, Webhooks},
+};
+
+/// Time given to the collectors to record the blocks they are processing on shutdown
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of events kept for the subscribers which lag behind, older ones being skipped
+const EVENTS_CAPACITY: usize = 1024;
+
+/// Builder of an instance of chainpulse, for the binary and the applications embedding it
+///
+/// ```no_run
+/// # async fn example(config: chainpulse::config::Config) -> chainpulse::Result<()> {
+/// let handle = chainpulse::Builder::new(config).serve_api(false).start().await?;
+/// let mut events = handle.events();
+///
+/// while let Ok(event) = events.recv().await {
+///     println!("{event:?}");
+/// }
+///
+/// handle.shutdown().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Builder {
+    config: Config,
+    in_memory: bool,
+    serve_api: bool,
+}
+
+impl Builder {
+    pub fn new(config: Config) -> Self {
+        Self {
+            serve_api: config.metrics.enabled,
+            config,
+            in_memory: false,
+        }
+    }
+
+    /// Record the blocks in a database in memory, discarded on shutdown, instead of
+    /// `database.path`, which is then neither written nor locked
+    pub fn in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Whether to serve the API and metrics on `metrics.port`, as the binary does when
+    /// `metrics.enabled` is set
+    ///
+    /// The metrics are recorded either way, an application serving them itself can gather
+    /// them from [`Handle::registry`].
+    pub fn serve_api(mut self, serve_api: bool) -> Self {
+        self.serve_api = serve_api;
+        self
+    }
+
+    /// Open the database and start the collectors of the chains, along with the API server and
+    /// the alert checks enabled in the configuration
+    pub async fn start(self) -> crate::Result<Handle> {
+        let Self {
+            config,
+            in_memory,
+            serve_api,
+        } = self;
+
+        let (metrics, registry) = Metrics::new();
+        let status = Status::new();
+
+        let pool = match in_memory {
+            true => db::connect_in_memory().await?,
+            false => db::connect(&config.database.path).await?,
+        };
+
+        db::setup(&pool).await;
+
+        // Two instances collecting into the same database would record every packet twice
+        let lock = if in_memory {
+            None
+        } else if config.database.high_availability {
+            let (lock, leading) = Lock::elect(&pool, &config.database.path).await?;
+            status.set_leading(leading);
+            tokio::spawn(
+                lock.campaign(pool.clone(), status.clone())
+                    .instrument(error_span!("lock")),
+            );
+            Some(lock)
+        } else {
+            let lock = Lock::acquire(&pool, &config.database.path).await?;
+            tokio::spawn(lock.keep(pool.clone()).instrument(error_span!("lock")));
+            Some(lock)
+        };
+
+        let (config_tx, config_rx) = watch::channel(config.clone());
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+
+        tokio::spawn(
+            report_leadership(
+                pool.clone(),
+                config_rx.clone(),
+                metrics.clone(),
+                status.clone(),
+            )
+            .instrument(error_span!("lock")),
+        );
+
+        if serve_api && config.metrics.enabled {
+            let server = api::run(
+                config_rx.clone(),
+                registry.clone(),
+                metrics.clone(),
+                pool.clone(),
+                status.clone(),
+                events.clone(),
+            );
+
+            tokio::spawn(
+                async move {
+                    if let Err(e) = server.await {
+                        error!("Metrics server failed: {e}");
+                    }
+                }
+                .instrument(error_span!("metrics")),
+            );
+        }
+
+        if config.metrics.enabled {
+            spawn_leading(&status, "rollup", error_span!("rollup"), {
+                let pool = pool.clone();
+                move || rollup::run(pool.clone())
+            });
+        }
+
+        // The counters of a dry run start from zero, as its database does
+        if config.metrics.enabled && config.metrics.persist_counters && !in_memory {
+            spawn_leading(&status, "counters", error_span!("counters"), {
+                let (pool, metrics) = (pool.clone(), metrics.clone());
+                move || counters::run(pool.clone(), metrics.clone())
+            });
+        }
+
+        let mut webhooks = None;
+
+        if config.metrics.enabled && config.metrics.stuck_packets {
+            let notifiers = Webhooks::new(&config, pool.clone(), events.clone());
+
+            // Alert checks are idle until enabled in the configuration, which may be reloaded
+            spawn_leading(&status, "halt", error_span!("halt"), {
+                let (config, status, webhooks) =
+                    (config_rx.clone(), status.clone(), notifiers.clone());
+                move || halt::run(config.clone(), status.clone(), webhooks.clone())
+            });
+
+            spawn_leading(&status, "expiry", error_span!("expiry"), {
+                let (pool, config, metrics, webhooks) = (
+                    pool.clone(),
+                    config_rx.clone(),
+                    metrics.clone(),
+                    notifiers.clone(),
+                );
+                move || {
+                    expiry::run(
+                        pool.clone(),
+                        config.clone(),
+                        metrics.clone(),
+                        webhooks.clone(),
+                    )
+                }
+            });
+
+            spawn_leading(&status, "relay", error_span!("relay"), {
+                let (pool, config, webhooks) = (pool.clone(), config_rx.clone(), notifiers.clone());
+                move || relay::run(pool.clone(), config.clone(), webhooks.clone())
+            });
+
+            // Clearing makes the relayer spend fees, which a dry run of chainpulse does not
+            if !in_memory {
+                spawn_leading(&status, "clear", error_span!("clear"), {
+                    let (pool, config) = (pool.clone(), config_rx.clone());
+                    move || clear::run(pool.clone(), config.clone())
+                });
+            }
+
+            spawn_leading(&status, "channels", error_span!("channels"), {
+                let (pool, config) = (pool.clone(), config_rx.clone());
+                move || channels::run(pool.clone(), config.clone())
+            });
+
+            spawn_leading(&status, "health", error_span!("health"), {
+                let (config, status, webhooks) =
+                    (config_rx.clone(), status.clone(), notifiers.clone());
+                move || health::run(config.clone(), status.clone(), webhooks.clone())
+            });
+
+            spawn_leading(&status, "monitor", error_span!("monitor"), {
+                let (pool, metrics, webhooks, config, status) = (
+                    pool.clone(),
+                    metrics.clone(),
+                    notifiers.clone(),
+                    config_rx.clone(),
+                    status.clone(),
+                );
+                move || {
+                    monitor::run(
+                        pool.clone(),
+                        metrics.clone(),
+                        webhooks.clone(),
+                        config.clone(),
+                        status.clone(),
+                    )
+                }
+            });
+
+            webhooks = Some(notifiers);
+        } else if !config.webhooks.is_empty()
+            || config.slack.is_some()
+            || config.discord.is_some()
+            || config.telegram.is_some()
+            || config.email.is_some()
+            || config.grafana.is_some()
+        {
+            warn!("Webhooks are configured but will not be notified, set `metrics.stuck_packets` to enable them");
+        }
+
+        // A standby populates nothing, its metrics only count from the takeover
+        if config.metrics.enabled && config.metrics.populate_on_start && status.is_leading() {
+            info!("Populating metrics on start");
+
+            for chain_id in config.chains.endpoints.keys() {
+                populate::run(chain_id, &pool, &metrics).await?;
+            }
+        }
+
+        spawn_leading(&status, "counterparty", error_span!("counterparty"), {
+            let (pool, config) = (pool.clone(), config_rx.clone());
+            move || counterparty::run(pool.clone(), config.clone())
+        });
+
+        spawn_leading(&status, "denoms", error_span!("denoms"), {
+            let (pool, config, metrics) = (pool.clone(), config_rx.clone(), metrics.clone());
+            move || denoms::run(pool.clone(), config.clone(), metrics.clone())
+        });
+
+        // A standby pushing its idle metrics would mix them with those of the leader
+        spawn_leading(&status, "remote_write", error_span!("remote_write"), {
+            let (registry, config) = (registry.clone(), config_rx.clone());
+            move || remote_write::run(registry.clone(), config.clone())
+        });
+
+        spawn_leading(&status, "rate_limits", error_span!("rate_limits"), {
+            let (pool, config, metrics) = (pool.clone(), config_rx.clone(), metrics.clone());
+            move || rate_limits::run(pool.clone(), metrics.clone(), config.clone())
+        });
+
+        spawn_leading(&status, "reconcile", error_span!("reconcile"), {
+            let (pool, config, metrics) = (pool.clone(), config_rx.clone(), metrics.clone());
+            move || reconcile::run(pool.clone(), metrics.clone(), config.clone())
+        });
+
+        spawn_leading(&status, "report", error_span!("report"), {
+            let (pool, config) = (pool.clone(), config_rx.clone());
+            move || report::run(pool.clone(), config.clone())
+        });
+
+        spawn_leading(&status, "prices", error_span!("prices"), {
+            let (pool, config) = (pool.clone(), config_rx.clone());
+            move || prices::run(pool.clone(), config.clone())
+        });
+
+        // The packets of a dry run are discarded, and so not exported either
+        if !in_memory {
+            spawn_leading(&status, "warehouse", error_span!("warehouse"), {
+                let (pool, config) = (pool.clone(), config_rx.clone());
+                move || warehouse::run(pool.clone(), config.clone())
+            });
+        }
+
+        let mut collectors = Collectors::new(
+            pool.clone(),
+            metrics.clone(),
+            status.clone(),
+            config_rx.clone(),
+        );
+        collectors.sync(&config.chains);
+
+        Ok(Handle {
+            config: config_tx,
+            registry,
+            metrics,
+            pool,
+            status,
+            collectors,
+            webhooks,
+            events,
+            lock,
+            in_memory,
+        })
+    }
+}
+
+/// Running instance of chainpulse, collecting the chains until shut down
+pub struct Handle {
+    config: watch::Sender<Config>,
+    registry: Registry,
+    metrics: Metrics,
+    pool: SqlitePool,
+    status: Status,
+    collectors: Collectors,
+    webhooks: Option<Webhooks>,
+    events: broadcast::Sender<Event>,
+    lock: Option<Lock>,
+    in_memory: bool,
+}
+
+impl Handle {
+    /// Registry of the Prometheus metrics of the instance
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Pool of the database the collectors write to
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Connection state and progress of the chains
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Configuration currently applied
+    pub fn config(&self) -> Config {
+        self.config.borrow().clone()
+    }
+
+    /// Subscribe to the alerts raised and cleared from now on
+    ///
+    /// Events are only raised with `metrics.stuck_packets`, which runs the alert checks, and
+    /// are published whether or not a notifier is configured. A subscriber lagging behind by
+    /// more than 1024 events skips the oldest ones.
+    pub fn events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Apply a new configuration without restarting, as a reload of the file does
+    ///
+    /// Chains are added, removed and reconnected, and the alert and notifier settings replaced.
+    /// The `database`, `metrics` and `performance` sections are those the instance was started
+    /// with.
+    pub fn reconfigure(&mut self, config: Config) -> ChainChanges {
+        let changes = self.collectors.sync(&config.chains);
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.reload(&config);
+        }
+
+        self.config.send_replace(config);
+
+        changes
+    }
+
+    /// Stop the collectors once the blocks they are processing are recorded, deliver the
+    /// pending notifications, snapshot the counters and close the database
+    pub async fn shutdown(mut self) {
+        self.collectors.drain(SHUTDOWN_TIMEOUT).await;
+
+        if let Some(webhooks) = self.webhooks {
+            webhooks.flush().await;
+        }
+
+        // The counters of a standby would overwrite those of the leader
+        if !self.in_memory && self.status.is_leading() {
+            let persist_counters = {
+                let metrics = &self.config.borrow().metrics;
+                metrics.enabled && metrics.persist_counters
+            };
+
+            if persist_counters {
+                match counters::save(&self.pool, &self.metrics).await {
+                    Ok(saved) => info!("Snapshotted {saved} counter series"),
+                    Err(e) => error!("Failed to snapshot the counters: {e}"),
+                }
+            }
+        }
+
+        if let Some(lock) = self.lock {
+            lock.release(&self.pool).await;
+        }
+
+        // Closing the last connection checkpoints the WAL into the database
+        self.pool.close().await;
+    }
+}
+
+/// Spawn a task running only while the instance leads, started again on every takeover
+///
+/// Without high availability the instance always leads, and the task runs until it returns.
+/// Its state is reported under `name` by the `/internal/tasks` endpoint.
+fn spawn_leading<F, T>(status: &Status, name: &'static str, span: Span, task: F)
+where
+    F: Fn() -> T + Send + 'static,
+    T: Future<Output = ()> + Send + 'static,
+{
+    let status = status.clone();
+
+    tokio::spawn(
+        async move {
+            loop {
+                status.task_state(name, TaskState::Standby);
+                status.leadership(true).await;
+                status.task_state(name, TaskState::Running);
+
+                tokio::select! {
+                    _ = task() => {
+                        status.task_state(name, TaskState::Stopped);
+                        return;
+                    }
+                    _ = status.leadership(false) => {}
+                }
+            }
+        }
+        .instrument(span),
+    );
+}
+
+/// Export the role of the instance, and report the checkpoints of the chains whenever it
+/// leads, as the blocks since the last checkpoint of the previous leader are caught up
+async fn report_leadership(
+    pool: SqlitePool,
+    config: watch::Receiver<Config>,
+    metrics: Metrics,
+    status: Status,
+) {
+    loop {
+        let leading = status.is_leading();
+        metrics.chainpulse_leader(leading);
+
+        if leading {
+            let (chains, resume) = {
+                let config = config.borrow();
+                (config.chains.clone(), config.resume.clone())
+            };
+
+            if let Err(e) = checkpoint::report(&pool, &chains, &resume).await {
+                warn!("Failed to read the checkpoints: {e}");
+            }
+        }
+
+        status.leadership(!leading).await;
+    }
+}
@@ -0,0 +1,52 @@
+pub mod alert;
+pub mod api;
+pub mod channels;
+pub mod checkpoint;
+pub mod clear;
+pub mod client;
+pub mod collect;
+pub mod command;
+pub mod config;
+pub mod counterparty;
+pub mod counters;
+pub mod db;
+pub mod denoms;
+pub mod descriptors;
+pub mod discord;
+pub mod email;
+pub mod expiry;
+pub mod filter;
+pub mod grafana;
+pub mod halt;
+pub mod health;
+pub mod instance;
+pub mod lock;
+pub mod metrics;
+pub mod monitor;
+pub mod msg;
+pub mod populate;
+pub mod prices;
+pub mod rate_limits;
+pub mod reconcile;
+pub mod relay;
+pub mod reload;
+pub mod remote_write;
+pub mod report;
+pub mod rollup;
+pub mod rpc;
+pub mod secret;
+pub mod silence;
+pub mod simple_auth_client;
+pub mod slack;
+pub mod status;
+pub mod telegram;
+pub mod tx_format;
+pub mod verify;
+pub mod warehouse;
+pub mod watchdog;
+pub mod webhook;
+
+pub use instance::{Builder, Handle};
+pub use webhook::Event;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
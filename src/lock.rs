@@ -0,0 +1,340 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sqlx::SqlitePool;
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::status::Status;
+
+/// How often the lock row of the database is refreshed
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time after which a lock row which is not refreshed is taken over, as its holder is gone
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Lock of the database held by a collecting instance of chainpulse, with a PID file next to the
+/// database and a row in the `instance_lock` table
+///
+/// The PID file stops a second process on the same host right away, while the row also covers
+/// hosts sharing the database, which are told apart by their hostname.
+pub struct Lock {
+    instance: String,
+    pid_file: PathBuf,
+}
+
+#[derive(sqlx::FromRow)]
+struct Holder {
+    instance: String,
+    pid: i64,
+    host: String,
+    acquired_at: String,
+    heartbeat_at: String,
+}
+
+impl Lock {
+    fn new(path: &Path) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        Self {
+            instance: format!("{}:{}:{started}", hostname(), std::process::id()),
+            pid_file: pid_file(path),
+        }
+    }
+
+    /// Take the lock of the database at `path`, failing when another live instance holds it
+    ///
+    /// A lock left by an instance which did not shut down cleanly is taken over once its
+    /// process is gone, or after [`STALE_AFTER`] when it ran on another host.
+    pub async fn acquire(pool: &SqlitePool, path: &Path) -> crate::Result<Self> {
+        let lock = Self::new(path);
+
+        lock.write_pid_file(path)?;
+
+        if let Err(e) = lock
+            .acquire_row(pool, std::process::id(), &hostname())
+            .await
+        {
+            lock.remove_pid_file();
+            return Err(e);
+        }
+
+        info!("Locked {} for this instance", path.display());
+
+        Ok(lock)
+    }
+
+    /// Take the lock of the database at `path` if it is free, or else stand by for it, as
+    /// instances in high availability do, returning whether this instance leads
+    ///
+    /// No PID file is written, as the standby may run on the same host as the leader.
+    pub async fn elect(pool: &SqlitePool, path: &Path) -> crate::Result<(Self, bool)> {
+        let lock = Self::new(path);
+
+        let leading = match lock
+            .acquire_row(pool, std::process::id(), &hostname())
+            .await
+        {
+            Ok(()) => {
+                info!("Locked {} for this instance, leading", path.display());
+                true
+            }
+            Err(e) => {
+                info!("Standing by, as {e}");
+                false
+            }
+        };
+
+        Ok((lock, leading))
+    }
+
+    /// Refresh the lock row while the instance leads, and try to take it over once it is stale
+    /// while standing by, until the instance shuts down
+    pub fn campaign(&self, pool: SqlitePool, status: Status) -> impl Future<Output = ()> {
+        let instance = self.instance.clone();
+        let pid = std::process::id();
+        let host = hostname();
+
+        async move {
+            let lock = Self {
+                instance,
+                pid_file: PathBuf::new(),
+            };
+            let mut interval = time::interval(HEARTBEAT_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                if !status.is_leading() {
+                    if lock.acquire_row(&pool, pid, &host).await.is_ok() {
+                        warn!("Took over the lock of the database from the leader, leading");
+                        status.set_leading(true);
+                    }
+                    continue;
+                }
+
+                match lock.refresh(&pool).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        error!("The lock of the database was taken over by another instance, standing by");
+                        status.set_leading(false);
+                        continue;
+                    }
+                    Err(e) => warn!("Failed to refresh the lock of the database: {e}"),
+                }
+            }
+        }
+    }
+
+    /// Refresh the lock row until the instance shuts down, so that it does not look stale
+    pub fn keep(&self, pool: SqlitePool) -> impl Future<Output = ()> {
+        let instance = self.instance.clone();
+
+        async move {
+            let mut interval = time::interval(HEARTBEAT_INTERVAL);
+
+            let lock = Self {
+                instance,
+                pid_file: PathBuf::new(),
+            };
+
+            loop {
+                interval.tick().await;
+
+                match lock.refresh(&pool).await {
+                    Ok(false) => {
+                        error!("The lock of the database was taken over by another instance, stop either of them");
+                    }
+                    Ok(true) => {}
+                    Err(e) => warn!("Failed to refresh the lock of the database: {e}"),
+                }
+            }
+        }
+    }
+
+    /// Refresh the heartbeat of the lock row, returning whether this instance still holds it
+    async fn refresh(&self, pool: &SqlitePool) -> sqlx::Result<bool> {
+        let done = sqlx::query(
+            "UPDATE instance_lock SET heartbeat_at = datetime('now') WHERE id = 1 AND instance = ?",
+        )
+        .bind(&self.instance)
+        .execute(pool)
+        .await?;
+
+        Ok(done.rows_affected() > 0)
+    }
+
+    /// Release the lock on shutdown, before the database is closed
+    pub async fn release(self, pool: &SqlitePool) {
+        let result = sqlx::query("DELETE FROM instance_lock WHERE id = 1 AND instance = ?")
+            .bind(&self.instance)
+            .execute(pool)
+            .await;
+
+        if let Err(e) = result {
+            warn!("Failed to release the lock of the database: {e}");
+        }
+
+        self.remove_pid_file();
+    }
+
+    async fn acquire_row(&self, pool: &SqlitePool, pid: u32, host: &str) -> crate::Result<()> {
+        let taken = sqlx::query(
+            r#"
+            INSERT INTO instance_lock (id, instance, pid, host, acquired_at, heartbeat_at)
+            VALUES (1, ?, ?, ?, datetime('now'), datetime('now'))
+            ON CONFLICT (id) DO UPDATE
+            SET instance = excluded.instance,
+                pid = excluded.pid,
+                host = excluded.host,
+                acquired_at = excluded.acquired_at,
+                heartbeat_at = excluded.heartbeat_at
+            WHERE heartbeat_at < datetime('now', ?)
+            "#,
+        )
+        .bind(&self.instance)
+        .bind(pid as i64)
+        .bind(host)
+        .bind(format!("-{} seconds", STALE_AFTER.as_secs()))
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if taken > 0 {
+            return Ok(());
+        }
+
+        let holder: Holder = sqlx::query_as(
+            "SELECT instance, pid, host, acquired_at, heartbeat_at FROM instance_lock WHERE id = 1",
+        )
+        .fetch_one(pool)
+        .await?;
+
+        // The process of the holder can only be checked on the same host, where a container
+        // restarted with the same PID is not the holder either
+        let gone =
+            holder.host == host && (holder.pid == pid as i64 || !is_running(holder.pid as u32));
+
+        if !gone {
+            return Err(format!(
+                "the database is locked by chainpulse with PID {} on {} since {}, last seen at {}",
+                holder.pid, holder.host, holder.acquired_at, holder.heartbeat_at
+            )
+            .into());
+        }
+
+        warn!(
+            "Taking over the lock of the database left by PID {}, which is not running",
+            holder.pid
+        );
+
+        // Only taken over if no other instance did in the meantime
+        let taken = sqlx::query(
+            r#"
+            UPDATE instance_lock
+            SET instance = ?, pid = ?, acquired_at = datetime('now'), heartbeat_at = datetime('now')
+            WHERE id = 1 AND instance = ?
+            "#,
+        )
+        .bind(&self.instance)
+        .bind(pid as i64)
+        .bind(&holder.instance)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if taken == 0 {
+            return Err("the lock of the database was taken by another instance".into());
+        }
+
+        Ok(())
+    }
+
+    /// Create the PID file, replacing the one of a process which is no longer running
+    fn write_pid_file(&self, path: &Path) -> crate::Result<()> {
+        let pid = std::process::id();
+
+        match fs::read_to_string(&self.pid_file) {
+            Ok(content) => match content.trim().parse::<u32>() {
+                Ok(other) if other != pid && is_running(other) => {
+                    return Err(format!(
+                        "chainpulse is already running with PID {other} on {}, see {}",
+                        path.display(),
+                        self.pid_file.display()
+                    )
+                    .into());
+                }
+                _ => warn!(
+                    "Replacing the stale PID file {}, left by a process which is not running",
+                    self.pid_file.display()
+                ),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("failed to read {}: {e}", self.pid_file.display()).into()),
+        }
+
+        fs::write(&self.pid_file, format!("{pid}\n"))
+            .map_err(|e| format!("failed to write {}: {e}", self.pid_file.display()))?;
+
+        Ok(())
+    }
+
+    /// Remove the PID file, unless another process replaced it
+    fn remove_pid_file(&self) {
+        let ours = fs::read_to_string(&self.pid_file)
+            .is_ok_and(|content| content.trim() == std::process::id().to_string());
+
+        if !ours {
+            return;
+        }
+
+        if let Err(e) = fs::remove_file(&self.pid_file) {
+            warn!(
+                "Failed to remove the PID file {}: {e}",
+                self.pid_file.display()
+            );
+        }
+    }
+}
+
+/// PID file next to the database, e.g. `chainpulse.db.pid`
+fn pid_file(path: &Path) -> PathBuf {
+    let mut file = path.as_os_str().to_owned();
+    file.push(".pid");
+
+    PathBuf::from(file)
+}
+
+/// Whether a process with the given PID is running on this host
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+
+    // Without procfs, signal 0 checks that the process exists without affecting it
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
@@ -1,120 +1,247 @@
-pub mod client;
-pub mod collect;
-pub mod config;
-pub mod db;
-pub mod metrics;
-pub mod msg;
-pub mod populate;
-pub mod simple_auth_client;
-
 use std::path::PathBuf;
 
-use clap::Parser;
-use futures::future;
-use sqlx::SqlitePool;
-use tendermint::chain;
-use tracing::{error, error_span, info, Instrument};
-
-use crate::config::{Config, Endpoint};
-use crate::metrics::Metrics;
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches};
+use tokio::runtime;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tracing::{error_span, info, warn, Instrument};
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+use chainpulse::command::{self, Command, DbCommand};
+use chainpulse::config::{Config, Overrides, Performance};
+use chainpulse::{reload, watchdog, Builder, Result};
 
 /// Collect and analyze txs containing IBC messages, export the collected metrics for Prometheus
 #[derive(clap::Parser)]
 struct App {
     /// Path to the configuration file
-    #[clap(short, long = "config", default_value = "chainpulse.toml")]
+    #[clap(
+        short,
+        long = "config",
+        env = "CHAINPULSE_CONFIG",
+        default_value = "chainpulse.toml",
+        global = true
+    )]
     config: PathBuf,
-}
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    setup_tracing();
-    setup_ctrlc_handler();
+    /// Port of the API and metrics server, overriding `metrics.port`
+    #[clap(long, env = "CHAINPULSE_METRICS_PORT", global = true)]
+    metrics_port: Option<u16>,
 
-    let app = App::parse();
-    let config = Config::load(&app.config)?;
+    /// Path to the SQLite database, overriding `database.path`
+    #[clap(long, env = "CHAINPULSE_DATABASE_PATH", global = true)]
+    database_path: Option<PathBuf>,
 
-    let (metrics, registry) = Metrics::new();
+    /// Log level, such as `debug`, or a filter such as `chainpulse=debug,sqlx=warn`,
+    /// overriding `RUST_LOG` [default: info]
+    #[clap(long, env = "CHAINPULSE_LOG_LEVEL", global = true)]
+    log_level: Option<String>,
 
-    let pool = db::connect(&config.database.path).await?;
-    db::setup(&pool).await;
+    /// File to which the current UNIX time is written every 10 seconds while chainpulse is
+    /// healthy, for container healthchecks
+    #[clap(long, env = "CHAINPULSE_HEALTH_FILE")]
+    health_file: Option<PathBuf>,
 
-    if config.metrics.enabled {
-        tokio::spawn(
-            metrics::run(config.metrics.port, registry, pool.clone())
-                .instrument(error_span!("metrics")),
+    /// Connect to the chains, decode their blocks, log and export the metrics without writing
+    /// to the database
+    #[clap(long, env = "CHAINPULSE_DRY_RUN")]
+    dry_run: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+fn main() -> Result<()> {
+    let matches = App::command().get_matches();
+    let app = App::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // `import-hermes` reads the file of Hermes given with `--config` rather than its own
+    let config_given = matches.value_source("config") == Some(ValueSource::CommandLine);
+
+    // A missing file is reported when it is loaded, in the runtime, as some commands run
+    // without it
+    let performance = match Performance::read(&app.config) {
+        Ok(performance) => performance,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Performance::default(),
+        Err(e) => return Err(format!("{}: {e}", app.config.display()).into()),
+    };
+
+    let runtime = match performance.worker_threads {
+        1 => runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?,
+        threads => runtime::Builder::new_multi_thread()
+            .worker_threads(threads)
+            .enable_all()
+            .build()?,
+    };
+
+    runtime.block_on(start(app, config_given))
+}
+
+/// Run the command given on the command line, on the runtime tuned by the configuration
+async fn start(app: App, config_given: bool) -> Result<()> {
+    let command = app.command.unwrap_or(Command::Run);
+
+    // Keep the standard output of the tools for their results
+    setup_tracing(app.log_level.as_deref(), matches!(command, Command::Run))?;
+
+    let overrides = Overrides {
+        metrics_port: app.metrics_port,
+        database_path: app.database_path,
+    };
+
+    let config = || async {
+        let mut config = Config::load(&app.config).await?;
+        overrides.apply(&mut config);
+        Ok::<_, std::io::Error>(config)
+    };
+
+    match command {
+        Command::Run => {
+            run(
+                app.config.clone(),
+                overrides.clone(),
+                app.health_file.clone(),
+                app.dry_run,
+            )
+            .await
+        }
+        Command::ValidateConfig(args) => {
+            command::validate::run(&app.config, &overrides, args).await
+        }
+        Command::Backfill(args) => command::backfill::run(&config().await?, args).await,
+        Command::Replay(args) => command::replay::run(&config().await?, args).await,
+        Command::Prune(args) => command::prune::run(&config().await?, args).await,
+        Command::Export(args) => command::export::run(&config().await?, args).await,
+        Command::Db(DbCommand::Stats) => command::stats::run(&config().await?).await,
+        Command::Dashboard(args) => command::dashboard::run(&config().await?, args).await,
+        Command::Simulate(args) => command::simulate::run(&config().await?, args).await,
+        Command::ImportHermes(args) => {
+            let path = match config_given {
+                true => app.config,
+                false => hermes_config()?,
+            };
+
+            command::import_hermes::run(&path, args).await
+        }
+    }
+}
+
+/// Collect the chains of the configuration, serving the API and running the alert checks
+/// until terminated
+///
+/// A dry run records the blocks in a database in memory, discarded on exit.
+async fn run(
+    path: PathBuf,
+    overrides: Overrides,
+    health_file: Option<PathBuf>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut signals = ShutdownSignals::new()?;
+
+    let mut config = Config::load(&path).await?;
+    overrides.apply(&mut config);
+
+    if dry_run {
+        warn!(
+            "Dry run, {} will not be written and the collected data is discarded on exit",
+            config.database.path.display()
         );
     }
 
+    let mut handle = Builder::new(config).in_memory(dry_run).start().await?;
 
-    if config.metrics.enabled && config.metrics.populate_on_start {
-        info!("Populating metrics on start");
+    tokio::spawn(
+        watchdog::run(handle.pool().clone(), health_file.clone())
+            .instrument(error_span!("watchdog")),
+    );
 
-        for chain_id in config.chains.endpoints.keys() {
-            populate::run(chain_id, &pool, &metrics).await?;
-        }
+    watchdog::ready();
+
+    let reload = reload::run(path, overrides, &mut handle).instrument(error_span!("reload"));
+
+    tokio::select! {
+        _ = reload => {}
+        signal = signals.recv() => info!("{signal} received, shutting down"),
     }
 
-    let handles = config
-        .chains
-        .endpoints
-        .into_iter()
-        .map(|(chain_id, endpoint)| {
-            metrics.chainpulse_chains();
+    watchdog::stopping();
 
-            let span = error_span!("collect", chain = %chain_id);
-            let task = collect(chain_id, endpoint, pool.clone(), metrics.clone()).instrument(span);
-            tokio::spawn(task)
-        })
-        .collect::<Vec<_>>();
+    // A second signal skips the remaining steps of the shutdown
+    tokio::spawn(async move {
+        let signal = signals.recv().await;
+        warn!("{signal} received again, exiting immediately");
+        std::process::exit(1);
+    });
+
+    handle.shutdown().await;
 
-    future::join_all(handles).await;
+    if let Some(path) = &health_file {
+        watchdog::remove_health_file(path);
+    }
+
+    info!("Shutdown complete");
 
     Ok(())
 }
 
-async fn collect(chain_id: chain::Id, endpoint: Endpoint, pool: SqlitePool, metrics: Metrics) {
-    // Always use the new v0.38-aware collector which handles all versions
-    let version = endpoint.version_string();
-
-    let result = collect::run(
-        chain_id,
-        version,
-        endpoint.url.clone(),
-        endpoint.username.clone(),
-        endpoint.password.clone(),
-        pool,
-        metrics,
-    )
-    .await;
-
-    if let Err(e) = result {
-        error!("{e}");
-    }
+/// Default configuration file of Hermes, `~/.hermes/config.toml`
+fn hermes_config() -> Result<PathBuf> {
+    let home =
+        std::env::var_os("HOME").ok_or("HOME is not set, pass the file of Hermes with --config")?;
+    Ok(PathBuf::from(home).join(".hermes").join("config.toml"))
 }
 
-fn setup_tracing() {
+fn setup_tracing(level: Option<&str>, stdout: bool) -> Result<()> {
     use tracing_subscriber::prelude::*;
-    use tracing_subscriber::{filter::EnvFilter, fmt};
+    use tracing_subscriber::{filter::EnvFilter, fmt, fmt::writer::BoxMakeWriter};
 
-    let fmt_layer = fmt::layer().with_target(false);
+    let writer = match stdout {
+        true => BoxMakeWriter::new(std::io::stdout),
+        false => BoxMakeWriter::new(std::io::stderr),
+    };
 
-    let filter_layer = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new("chainpulse=info"))
-        .unwrap();
+    let fmt_layer = fmt::layer().with_target(false).with_writer(writer);
+
+    // A bare level only applies to chainpulse, not to the libraries it uses
+    let filter_layer = match level {
+        Some(level) if level.contains(['=', ',']) => EnvFilter::try_new(level),
+        Some(level) => EnvFilter::try_new(format!("chainpulse={level}")),
+        None => {
+            EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("chainpulse=info"))
+        }
+    }
+    .map_err(|e| format!("invalid log level `{}`: {e}", level.unwrap_or_default()))?;
 
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer)
         .init();
+
+    Ok(())
+}
+
+/// Ctrl-C and SIGTERM, SIGHUP being used to reload the configuration
+///
+/// The handlers are installed on start, so that the default action of the signals, which does
+/// not apply when running as PID 1 in a container, is replaced by a graceful shutdown.
+struct ShutdownSignals {
+    interrupt: Signal,
+    terminate: Signal,
 }
 
-fn setup_ctrlc_handler() {
-    ctrlc::set_handler(move || {
-        info!("Ctrl-C received, shutting down");
-        std::process::exit(0);
-    })
-    .expect("Error setting Ctrl-C handler");
+impl ShutdownSignals {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    /// Wait for the next signal, returning its name
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.interrupt.recv() => "Ctrl-C",
+            _ = self.terminate.recv() => "SIGTERM",
+        }
+    }
 }
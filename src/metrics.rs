@@ -1,26 +1,26 @@
-use std::{collections::HashMap, net::SocketAddr};
-
-use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::get,
-    Router, Server,
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
 };
+
 use prometheus::{
+    core::{Collector, MetricVec, MetricVecBuilder},
     register_gauge_vec_with_registry, register_int_counter_vec_with_registry,
-    register_int_gauge_vec_with_registry, Encoder, GaugeVec as PrometheusGaugeVec, IntCounterVec,
-    IntGaugeVec, Registry, TextEncoder,
+    register_int_gauge_vec_with_registry, GaugeVec as PrometheusGaugeVec, IntCounterVec,
+    IntGaugeVec, Registry,
 };
-use serde::{Deserialize, Serialize};
-use sqlx::{Row, SqlitePool};
 use tendermint::chain;
-use tracing::info;
 
 type GaugeVec = IntGaugeVec;
 type CounterVec = IntCounterVec;
 
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+/// Value of a series of a counter, as snapshotted to the database
+#[derive(Clone, Debug, PartialEq)]
+pub struct CounterSnapshot {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: u64,
+}
 
 #[derive(Clone)]
 pub struct Metrics {
@@ -40,6 +40,9 @@ pub struct Metrics {
     /// The number of chains being monitored
     chainpulse_chains: GaugeVec,
 
+    /// Whether the instance leads, or stands by in high availability
+    chainpulse_leader: GaugeVec,
+
     /// The number of txs processed
     /// Labels: ['chain_id']
     chainpulse_txs: CounterVec,
@@ -60,11 +63,46 @@ pub struct Metrics {
     /// Labels: ['chain_id']
     chainpulse_errors: CounterVec,
 
+    /// Gas price paid by the last relay tx, fee over gas wanted
+    /// Labels: ['chain_id', 'denom']
+    chainpulse_gas_price: PrometheusGaugeVec,
+
+    /// Round-trip time of the last WebSocket ping answered by the node of a chain
+    /// Labels: ['chain_id']
+    chainpulse_ws_ping_rtt_seconds: PrometheusGaugeVec,
+
+    /// Backoff of the last reconnect of a chain, 0 once connected again
+    /// Labels: ['chain_id']
+    chainpulse_reconnect_backoff_seconds: PrometheusGaugeVec,
+
+    /// Trace of the IBC denoms, always 1, to label the metrics by denom with their base denom
+    /// Labels: ['chain_id', 'denom', 'path', 'base_denom']
+    chainpulse_denom_trace: GaugeVec,
 
     /// Time since packet creation for unrelayed packets
     /// Labels: ['src_chain', 'dst_chain', 'channel']
     ibc_packet_age_unrelayed: PrometheusGaugeVec,
 
+    /// Value in USD of the stuck packets, for the denoms with a price
+    /// Labels: ['src_chain', 'dst_chain', 'channel']
+    ibc_stuck_value_usd: PrometheusGaugeVec,
+
+    /// Pending packets of channels which are closed or whose client is frozen
+    /// Labels: ['src_chain', 'dst_chain', 'channel', 'reason']
+    ibc_packets_unrecoverable: GaugeVec,
+
+    /// Stuck packets whose path is held by an exhausted rate limit
+    /// Labels: ['src_chain', 'dst_chain', 'channel']
+    ibc_packets_rate_limited: GaugeVec,
+
+    /// Net flow of a rate limit over its threshold
+    /// Labels: ['chain_id', 'channel', 'denom', 'quota', 'direction']
+    ibc_rate_limit_utilization: PrometheusGaugeVec,
+
+    /// Packets whose record disagrees with the packet commitments of the sending chain
+    /// Labels: ['chain_id', 'port', 'channel', 'kind']
+    ibc_packet_drift: GaugeVec,
+
     /// Packets nearing timeout
     /// Labels: ['src_chain', 'dst_chain', 'src_channel', 'dst_channel', 'timeout_type']
     ibc_packets_near_timeout: GaugeVec,
@@ -72,6 +110,18 @@ pub struct Metrics {
     /// Time until packet timeout in seconds
     /// Labels: ['src_chain', 'dst_chain', 'src_channel', 'dst_channel']
     ibc_packet_timeout_seconds: PrometheusGaugeVec,
+
+    /// Time until a light client backing a channel expires in seconds
+    /// Labels: ['chain_id', 'client_id', 'counterparty_chain_id']
+    ibc_client_expiry_seconds: GaugeVec,
+
+    /// Latest height of the counterparty known to a light client
+    /// Labels: ['chain_id', 'client_id', 'counterparty_chain_id']
+    ibc_client_latest_height: GaugeVec,
+
+    /// Whether a light client is frozen
+    /// Labels: ['chain_id', 'client_id', 'counterparty_chain_id']
+    ibc_client_frozen: GaugeVec,
 }
 
 impl Metrics {
@@ -137,6 +187,14 @@ impl Metrics {
         )
         .unwrap();
 
+        let chainpulse_leader = register_int_gauge_vec_with_registry!(
+            "chainpulse_leader",
+            "Whether the instance leads, or stands by in high availability",
+            &[],
+            registry
+        )
+        .unwrap();
+
         let chainpulse_txs = register_int_counter_vec_with_registry!(
             "chainpulse_txs",
             "The number of txs processed",
@@ -177,6 +235,37 @@ impl Metrics {
         )
         .unwrap();
 
+        let chainpulse_gas_price = register_gauge_vec_with_registry!(
+            "chainpulse_gas_price",
+            "Gas price paid by the last relay tx, as its fee over its gas wanted",
+            &["chain_id", "denom"],
+            registry
+        )
+        .unwrap();
+
+        let chainpulse_ws_ping_rtt_seconds = register_gauge_vec_with_registry!(
+            "chainpulse_ws_ping_rtt_seconds",
+            "Round-trip time of the last WebSocket ping answered by the node of a chain",
+            &["chain_id"],
+            registry
+        )
+        .unwrap();
+
+        let chainpulse_reconnect_backoff_seconds = register_gauge_vec_with_registry!(
+            "chainpulse_reconnect_backoff_seconds",
+            "Backoff of the last reconnect of a chain, 0 once connected again",
+            &["chain_id"],
+            registry
+        )
+        .unwrap();
+
+        let chainpulse_denom_trace = register_int_gauge_vec_with_registry!(
+            "chainpulse_denom_trace",
+            "Path and base denom of an IBC denom, resolved from the chain holding it",
+            &["chain_id", "denom", "path", "base_denom"],
+            registry
+        )
+        .unwrap();
 
         let ibc_packet_age_unrelayed = register_gauge_vec_with_registry!(
             "ibc_packet_age_seconds",
@@ -186,6 +275,46 @@ impl Metrics {
         )
         .unwrap();
 
+        let ibc_stuck_value_usd = register_gauge_vec_with_registry!(
+            "ibc_stuck_value_usd",
+            "Value in USD of the stuck packets of a channel, for the denoms with a price",
+            &["src_chain", "dst_chain", "channel"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_packets_unrecoverable = register_int_gauge_vec_with_registry!(
+            "ibc_packets_unrecoverable",
+            "Number of pending packets of channels which are closed or whose client is frozen",
+            &["src_chain", "dst_chain", "channel", "reason"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_packets_rate_limited = register_int_gauge_vec_with_registry!(
+            "ibc_packets_rate_limited",
+            "Number of stuck packets of a channel whose path is held by an exhausted rate limit",
+            &["src_chain", "dst_chain", "channel"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_rate_limit_utilization = register_gauge_vec_with_registry!(
+            "ibc_rate_limit_utilization",
+            "Net flow of a rate limit over its threshold, exhausted from 1",
+            &["chain_id", "channel", "denom", "quota", "direction"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_packet_drift = register_int_gauge_vec_with_registry!(
+            "ibc_packet_drift",
+            "Number of packets committed but not recorded (missing), or pending but not committed (stale)",
+            &["chain_id", "port", "channel", "kind"],
+            registry
+        )
+        .unwrap();
+
         let ibc_packets_near_timeout = register_int_gauge_vec_with_registry!(
             "ibc_packets_near_timeout",
             "Number of packets nearing their timeout deadline",
@@ -202,20 +331,57 @@ impl Metrics {
         )
         .unwrap();
 
+        let ibc_client_expiry_seconds = register_int_gauge_vec_with_registry!(
+            "ibc_client_expiry_seconds",
+            "Time until a light client backing a channel expires in seconds (negative if expired)",
+            &["chain_id", "client_id", "counterparty_chain_id"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_client_latest_height = register_int_gauge_vec_with_registry!(
+            "ibc_client_latest_height",
+            "Latest height of the counterparty chain known to a light client",
+            &["chain_id", "client_id", "counterparty_chain_id"],
+            registry
+        )
+        .unwrap();
+
+        let ibc_client_frozen = register_int_gauge_vec_with_registry!(
+            "ibc_client_frozen",
+            "Whether a light client is frozen after a misbehaviour (1) or not (0)",
+            &["chain_id", "client_id", "counterparty_chain_id"],
+            registry
+        )
+        .unwrap();
+
         (
             Self {
                 ibc_effected_packets,
                 ibc_uneffected_packets,
                 ibc_frontrun_counter,
                 chainpulse_chains,
+                chainpulse_leader,
                 chainpulse_txs,
                 chainpulse_packets,
                 chainpulse_reconnects,
                 chainpulse_timeouts,
                 chainpulse_errors,
+                chainpulse_gas_price,
+                chainpulse_ws_ping_rtt_seconds,
+                chainpulse_reconnect_backoff_seconds,
+                chainpulse_denom_trace,
                 ibc_packet_age_unrelayed,
+                ibc_stuck_value_usd,
+                ibc_packets_unrecoverable,
+                ibc_packets_rate_limited,
+                ibc_rate_limit_utilization,
+                ibc_packet_drift,
                 ibc_packets_near_timeout,
                 ibc_packet_timeout_seconds,
+                ibc_client_expiry_seconds,
+                ibc_client_latest_height,
+                ibc_client_frozen,
             },
             registry,
         )
@@ -301,6 +467,18 @@ impl Metrics {
         self.chainpulse_chains.with_label_values(&[]).inc();
     }
 
+    pub fn chainpulse_leader(&self, leading: bool) {
+        self.chainpulse_leader
+            .with_label_values(&[])
+            .set(leading as i64);
+    }
+
+    /// Stop counting a chain which is no longer monitored, and drop its series
+    pub fn chainpulse_chain_removed(&self, chain_id: &chain::Id) {
+        self.chainpulse_chains.with_label_values(&[]).dec();
+        self.reset_chain(chain_id);
+    }
+
     pub fn chainpulse_txs(&self, chain_id: &chain::Id) {
         self.chainpulse_txs
             .with_label_values(&[chain_id.as_ref()])
@@ -331,6 +509,36 @@ impl Metrics {
             .inc();
     }
 
+    pub fn chainpulse_gas_price(&self, chain_id: &chain::Id, denom: &str, gas_price: f64) {
+        self.chainpulse_gas_price
+            .with_label_values(&[chain_id.as_ref(), denom])
+            .set(gas_price);
+    }
+
+    pub fn chainpulse_ws_ping_rtt_seconds(&self, chain_id: &chain::Id, rtt: Duration) {
+        self.chainpulse_ws_ping_rtt_seconds
+            .with_label_values(&[chain_id.as_ref()])
+            .set(rtt.as_secs_f64());
+    }
+
+    pub fn chainpulse_reconnect_backoff_seconds(&self, chain_id: &chain::Id, backoff: Duration) {
+        self.chainpulse_reconnect_backoff_seconds
+            .with_label_values(&[chain_id.as_ref()])
+            .set(backoff.as_secs_f64());
+    }
+
+    pub fn chainpulse_denom_trace(
+        &self,
+        chain_id: &str,
+        denom: &str,
+        path: &str,
+        base_denom: &str,
+    ) {
+        self.chainpulse_denom_trace
+            .with_label_values(&[chain_id, denom, path, base_denom])
+            .set(1);
+    }
+
     pub fn ibc_packet_age_unrelayed(
         &self,
         src_chain: &str,
@@ -343,6 +551,70 @@ impl Metrics {
             .set(age_seconds);
     }
 
+    pub fn ibc_stuck_value_usd(
+        &self,
+        src_chain: &str,
+        dst_chain: &str,
+        channel: &str,
+        usd: f64,
+    ) {
+        self.ibc_stuck_value_usd
+            .with_label_values(&[src_chain, dst_chain, channel])
+            .set(usd);
+    }
+
+    pub fn ibc_packets_unrecoverable(
+        &self,
+        src_chain: &str,
+        dst_chain: &str,
+        channel: &str,
+        reason: &str,
+        count: i64,
+    ) {
+        self.ibc_packets_unrecoverable
+            .with_label_values(&[src_chain, dst_chain, channel, reason])
+            .set(count);
+    }
+
+    pub fn ibc_packets_rate_limited(
+        &self,
+        src_chain: &str,
+        dst_chain: &str,
+        channel: &str,
+        count: i64,
+    ) {
+        self.ibc_packets_rate_limited
+            .with_label_values(&[src_chain, dst_chain, channel])
+            .set(count);
+    }
+
+    pub fn ibc_rate_limit_utilization(
+        &self,
+        chain_id: &str,
+        channel: &str,
+        denom: &str,
+        quota: &str,
+        direction: &str,
+        utilization: f64,
+    ) {
+        self.ibc_rate_limit_utilization
+            .with_label_values(&[chain_id, channel, denom, quota, direction])
+            .set(utilization);
+    }
+
+    pub fn ibc_packet_drift(
+        &self,
+        chain_id: &str,
+        port: &str,
+        channel: &str,
+        kind: &str,
+        count: i64,
+    ) {
+        self.ibc_packet_drift
+            .with_label_values(&[chain_id, port, channel, kind])
+            .set(count);
+    }
+
     pub fn ibc_packets_near_timeout(
         &self,
         src_chain: &str,
@@ -369,771 +641,183 @@ impl Metrics {
             .with_label_values(&[src_chain, dst_chain, src_channel, dst_channel])
             .set(seconds_until_timeout);
     }
-}
-
-pub async fn run(port: u16, registry: Registry, db: SqlitePool) -> Result<()> {
-    let state = ApiState { registry, db };
-
-    let app = Router::new()
-        .route("/metrics", get(get_metrics))
-        .route("/api/v1/packets/by-user", get(get_packets_by_user))
-        .route("/api/v1/packets/stuck", get(get_stuck_packets))
-        .route(
-            "/api/v1/packets/:chain/:channel/:sequence",
-            get(get_packet_details),
-        )
-        .route("/api/v1/channels/congestion", get(get_channel_congestion))
-        .with_state(state);
-
-    let server =
-        Server::bind(&SocketAddr::from(([0, 0, 0, 0], port))).serve(app.into_make_service());
-
-    info!("Metrics server listening at http://localhost:{port}/metrics");
-    server.await?;
-
-    Ok(())
-}
-
-pub async fn get_metrics(State(state): State<ApiState>) -> String {
-    let mut buffer = vec![];
-    let encoder = TextEncoder::new();
 
-    let metric_families = state.registry.gather();
-    encoder.encode(&metric_families, &mut buffer).unwrap();
-
-    String::from_utf8(buffer).unwrap()
-}
-
-// API State and types
-#[derive(Clone)]
-struct ApiState {
-    registry: Registry,
-    db: SqlitePool,
-}
-
-#[derive(Debug, Deserialize)]
-struct UserPacketsQuery {
-    address: String,
-    #[serde(default)]
-    role: String, // sender, receiver, both (default)
-    #[serde(default = "default_limit")]
-    limit: i64,
-    #[serde(default)]
-    offset: i64,
-}
-
-fn default_limit() -> i64 {
-    100
-}
-
-#[derive(Debug, Deserialize)]
-struct StuckPacketsQuery {
-    #[serde(default = "default_min_age")]
-    min_age_seconds: i64,
-    #[serde(default = "default_limit")]
-    limit: i64,
-}
-
-fn default_min_age() -> i64 {
-    900 // 15 minutes default
-}
-
-#[derive(Debug, Serialize)]
-struct StuckPacketsResponse {
-    packets: Vec<StuckPacketInfo>,
-    total: i64,
-}
-
-#[derive(Debug, Serialize)]
-struct StuckPacketInfo {
-    chain_id: String,
-    sequence: i64,
-    src_channel: String,
-    dst_channel: String,
-    sender: Option<String>,
-    receiver: Option<String>,
-    amount: Option<String>,
-    denom: Option<String>,
-    age_seconds: i64,
-    timeout_timestamp: Option<i64>,
-    seconds_until_timeout: Option<i64>,
-}
-
-#[derive(Debug, Serialize)]
-struct UserPacketsResponse {
-    packets: Vec<PacketInfo>,
-    total: i64,
-    api_version: String,
-}
-
-#[derive(Debug, Serialize)]
-struct ChannelCongestionResponse {
-    channels: Vec<ChannelCongestion>,
-    total_stuck: i64,
-}
-
-#[derive(Debug, Serialize)]
-struct ChannelCongestion {
-    chain_id: String,
-    src_channel: String,
-    dst_channel: String,
-    stuck_count: i64,
-    oldest_age_seconds: i64,
-    total_value: std::collections::HashMap<String, String>,
-}
-
-#[derive(Debug, Serialize)]
-struct PacketInfo {
-    chain_id: String,
-    sequence: i64,
-    src_channel: String,
-    dst_channel: String,
-    sender: Option<String>,
-    receiver: Option<String>,
-    amount: Option<String>,
-    denom: Option<String>,
-    age_seconds: i64,
-    relay_attempts: i64,
-    last_attempt_by: Option<String>,
-    ibc_version: String,
-}
-
-// API Handlers
-async fn get_packets_by_user(
-    State(state): State<ApiState>,
-    Query(params): Query<UserPacketsQuery>,
-) -> std::result::Result<Json<UserPacketsResponse>, StatusCode> {
-    // Validate address format (basic check)
-    if params.address.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+    pub fn ibc_client_expiry_seconds(
+        &self,
+        chain_id: &str,
+        client_id: &str,
+        counterparty_chain_id: &str,
+        seconds_until_expiry: i64,
+    ) {
+        self.ibc_client_expiry_seconds
+            .with_label_values(&[chain_id, client_id, counterparty_chain_id])
+            .set(seconds_until_expiry);
     }
 
-    let role_condition = match params.role.as_str() {
-        "sender" => "sender = ?",
-        "receiver" => "receiver = ?",
-        _ => "(sender = ? OR receiver = ?)",
-    };
-
-    // Build query to get packets
-    let query = format!(
-        r#"
-        SELECT 
-            t.chain as chain_id,
-            p.sequence,
-            p.src_channel,
-            p.dst_channel,
-            p.sender,
-            p.receiver,
-            p.amount,
-            p.denom,
-            p.ibc_version,
-            p.signer as last_attempt_by,
-            p.effected,
-            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
-            (SELECT COUNT(*) FROM packets p2 WHERE p2.src_channel = p.src_channel 
-             AND p2.dst_channel = p.dst_channel AND p2.sequence = p.sequence) as relay_attempts
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE {}
-        ORDER BY p.created_at DESC
-        LIMIT ? OFFSET ?
-        "#,
-        role_condition
-    );
-
-    let packets = if params.role == "sender" || params.role == "receiver" {
-        sqlx::query_as::<
-            _,
-            (
-                String,
-                i64,
-                String,
-                String,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                String,
-                bool,
-                i64,
-                i64,
-            ),
-        >(&query)
-        .bind(&params.address)
-        .bind(params.limit)
-        .bind(params.offset)
-        .fetch_all(&state.db)
-        .await
-    } else {
-        sqlx::query_as::<
-            _,
-            (
-                String,
-                i64,
-                String,
-                String,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                String,
-                bool,
-                i64,
-                i64,
-            ),
-        >(&query)
-        .bind(&params.address)
-        .bind(&params.address)
-        .bind(params.limit)
-        .bind(params.offset)
-        .fetch_all(&state.db)
-        .await
-    };
-
-    match packets {
-        Ok(rows) => {
-            let packets: Vec<PacketInfo> = rows
-                .into_iter()
-                .map(|row| PacketInfo {
-                    chain_id: row.0,
-                    sequence: row.1,
-                    src_channel: row.2,
-                    dst_channel: row.3,
-                    sender: row.4,
-                    receiver: row.5,
-                    amount: row.6,
-                    denom: row.7,
-                    ibc_version: row.8.unwrap_or_else(|| "v1".to_string()),
-                    last_attempt_by: Some(row.9),
-                    age_seconds: row.11,
-                    relay_attempts: row.12,
-                })
-                .collect();
+    pub fn ibc_client_latest_height(
+        &self,
+        chain_id: &str,
+        client_id: &str,
+        counterparty_chain_id: &str,
+        height: u64,
+    ) {
+        self.ibc_client_latest_height
+            .with_label_values(&[chain_id, client_id, counterparty_chain_id])
+            .set(height as i64);
+    }
 
-            let total = packets.len() as i64;
+    pub fn ibc_client_frozen(
+        &self,
+        chain_id: &str,
+        client_id: &str,
+        counterparty_chain_id: &str,
+        frozen: bool,
+    ) {
+        self.ibc_client_frozen
+            .with_label_values(&[chain_id, client_id, counterparty_chain_id])
+            .set(frozen as i64);
+    }
 
-            Ok(Json(UserPacketsResponse {
-                packets,
-                total,
-                api_version: "1.0".to_string(),
-            }))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    /// Clear the stuck, stuck value, unrecoverable and rate limited packet gauges before they are
+    /// refreshed by a scan
+    pub fn reset_unrelayed_gauges(&self) {
+        self.ibc_packet_age_unrelayed.reset();
+        self.ibc_stuck_value_usd.reset();
+        self.ibc_packets_unrecoverable.reset();
+        self.ibc_packets_rate_limited.reset();
     }
-}
 
-async fn get_packet_details(
-    State(state): State<ApiState>,
-    Path((chain, channel, sequence)): Path<(String, String, i64)>,
-) -> std::result::Result<Json<PacketInfo>, StatusCode> {
-    let query = r#"
-        SELECT 
-            t.chain as chain_id,
-            p.sequence,
-            p.src_channel,
-            p.dst_channel,
-            p.sender,
-            p.receiver,
-            p.amount,
-            p.denom,
-            p.ibc_version,
-            p.signer as last_attempt_by,
-            p.effected,
-            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
-            (SELECT COUNT(*) FROM packets p2 WHERE p2.src_channel = p.src_channel 
-             AND p2.dst_channel = p.dst_channel AND p2.sequence = p.sequence) as relay_attempts
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE t.chain = ? AND p.src_channel = ? AND p.sequence = ?
-        LIMIT 1
-    "#;
-
-    match sqlx::query_as::<
-        _,
-        (
-            String,
-            i64,
-            String,
-            String,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            String,
-            bool,
-            i64,
-            i64,
-        ),
-    >(query)
-    .bind(chain)
-    .bind(channel)
-    .bind(sequence)
-    .fetch_one(&state.db)
-    .await
-    {
-        Ok(row) => Ok(Json(PacketInfo {
-            chain_id: row.0,
-            sequence: row.1,
-            src_channel: row.2,
-            dst_channel: row.3,
-            sender: row.4,
-            receiver: row.5,
-            amount: row.6,
-            denom: row.7,
-            ibc_version: row.8.unwrap_or_else(|| "v1".to_string()),
-            last_attempt_by: Some(row.9),
-            age_seconds: row.11,
-            relay_attempts: row.12,
-        })),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    /// Clear the rate limit gauges before they are refreshed
+    pub fn reset_rate_limit_gauges(&self) {
+        self.ibc_rate_limit_utilization.reset();
     }
-}
 
-async fn get_stuck_packets(
-    State(state): State<ApiState>,
-    Query(params): Query<StuckPacketsQuery>,
-) -> std::result::Result<Json<StuckPacketsResponse>, StatusCode> {
-    // Query for send_packet events that haven't been acknowledged or timed out
-    let query = r#"
-        SELECT 
-            t.chain as chain_id,
-            p.sequence,
-            p.src_channel,
-            p.dst_channel,
-            p.sender,
-            p.receiver,
-            p.amount,
-            p.denom,
-            p.timeout_timestamp,
-            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
-            CASE 
-                WHEN p.timeout_timestamp IS NOT NULL 
-                THEN CAST((p.timeout_timestamp / 1000000000 - strftime('%s', 'now')) AS INTEGER)
-                ELSE NULL 
-            END as seconds_until_timeout
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE p.msg_type_url = 'send_packet'
-          AND p.effected = 0
-          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > ?
-          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
-        ORDER BY p.created_at ASC
-        LIMIT ?
-    "#;
-    
-    match sqlx::query_as::<
-        _,
-        (
-            String,
-            i64,
-            String,
-            String,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            Option<i64>,
-            i64,
-            Option<i64>,
-        ),
-    >(query)
-    .bind(params.min_age_seconds)
-    .bind(params.limit)
-    .fetch_all(&state.db)
-    .await
-    {
-        Ok(rows) => {
-            let packets: Vec<StuckPacketInfo> = rows
-                .into_iter()
-                .map(|row| StuckPacketInfo {
-                    chain_id: row.0,
-                    sequence: row.1,
-                    src_channel: row.2,
-                    dst_channel: row.3,
-                    sender: row.4,
-                    receiver: row.5,
-                    amount: row.6,
-                    denom: row.7,
-                    timeout_timestamp: row.8,
-                    age_seconds: row.9,
-                    seconds_until_timeout: row.10,
-                })
-                .collect();
-            
-            let total = packets.len() as i64;
-            
-            Ok(Json(StuckPacketsResponse { packets, total }))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    /// Clear the timeout gauges before they are refreshed by a scan
+    pub fn reset_near_timeout_gauges(&self) {
+        self.ibc_packets_near_timeout.reset();
+        self.ibc_packet_timeout_seconds.reset();
     }
-}
 
-async fn get_channel_congestion(
-    State(state): State<ApiState>,
-) -> std::result::Result<Json<ChannelCongestionResponse>, StatusCode> {
-    // Query for channels with stuck packets
-    let query = r#"
-        SELECT 
-            t.chain as chain_id,
-            p.src_channel,
-            p.dst_channel,
-            COUNT(*) as stuck_count,
-            MIN(CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER)) as oldest_age_seconds,
-            GROUP_CONCAT(
-                CASE 
-                    WHEN p.denom IS NOT NULL AND p.amount IS NOT NULL 
-                    THEN p.denom || ':' || p.amount 
-                    ELSE NULL 
-                END
-            ) as amounts
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE p.msg_type_url = 'send_packet'
-          AND p.effected = 0
-          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > 900
-          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
-        GROUP BY t.chain, p.src_channel, p.dst_channel
-        ORDER BY stuck_count DESC
-        LIMIT 100
-    "#;
-    
-    match sqlx::query_as::<_, (String, String, String, i64, i64, Option<String>)>(query)
-        .fetch_all(&state.db)
-        .await
-    {
-        Ok(rows) => {
-            let mut total_stuck = 0i64;
-            let channels: Vec<ChannelCongestion> = rows
-                .into_iter()
-                .map(|row| {
-                    let mut total_value = HashMap::new();
-                    if let Some(amounts_str) = row.5 {
-                        // Parse the concatenated amounts
-                        for amount_pair in amounts_str.split(',') {
-                            if let Some((denom, amount)) = amount_pair.split_once(':') {
-                                total_value
-                                    .entry(denom.to_string())
-                                    .and_modify(|e: &mut String| {
-                                        if let (Ok(existing), Ok(new)) = (e.parse::<u128>(), amount.parse::<u128>()) {
-                                            *e = (existing + new).to_string();
-                                        }
-                                    })
-                                    .or_insert_with(|| amount.to_string());
-                            }
-                        }
-                    }
-                    
-                    total_stuck += row.3;
-                    
-                    ChannelCongestion {
-                        chain_id: row.0,
-                        src_channel: row.1,
-                        dst_channel: row.2,
-                        stuck_count: row.3,
-                        oldest_age_seconds: row.4,
-                        total_value,
-                    }
-                })
-                .collect();
-            
-            Ok(Json(ChannelCongestionResponse {
-                channels,
-                total_stuck,
-            }))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    /// Clear the light client gauges before they are refreshed by a check
+    pub fn reset_client_gauges(&self) {
+        self.ibc_client_expiry_seconds.reset();
+        self.ibc_client_latest_height.reset();
+        self.ibc_client_frozen.reset();
     }
-}
 
-// Timeout-based query endpoints
+    /// Drop every series recorded for a chain
+    pub fn reset_chain(&self, chain_id: &chain::Id) {
+        let chain_id = chain_id.as_str();
+
+        remove_series(&self.ibc_effected_packets, "chain_id", chain_id);
+        remove_series(&self.ibc_uneffected_packets, "chain_id", chain_id);
+        remove_series(&self.ibc_frontrun_counter, "chain_id", chain_id);
+        remove_series(&self.chainpulse_txs, "chain_id", chain_id);
+        remove_series(&self.chainpulse_packets, "chain_id", chain_id);
+        remove_series(&self.chainpulse_reconnects, "chain_id", chain_id);
+        remove_series(&self.chainpulse_timeouts, "chain_id", chain_id);
+        remove_series(&self.chainpulse_errors, "chain_id", chain_id);
+        remove_series(&self.chainpulse_gas_price, "chain_id", chain_id);
+        remove_series(&self.chainpulse_ws_ping_rtt_seconds, "chain_id", chain_id);
+        remove_series(&self.chainpulse_reconnect_backoff_seconds, "chain_id", chain_id);
+        remove_series(&self.chainpulse_denom_trace, "chain_id", chain_id);
+        remove_series(&self.ibc_packet_age_unrelayed, "src_chain", chain_id);
+        remove_series(&self.ibc_stuck_value_usd, "src_chain", chain_id);
+        remove_series(&self.ibc_packets_unrecoverable, "src_chain", chain_id);
+        remove_series(&self.ibc_packets_rate_limited, "src_chain", chain_id);
+        remove_series(&self.ibc_rate_limit_utilization, "chain_id", chain_id);
+        remove_series(&self.ibc_packet_drift, "chain_id", chain_id);
+        remove_series(&self.ibc_packets_near_timeout, "src_chain", chain_id);
+        remove_series(&self.ibc_packet_timeout_seconds, "src_chain", chain_id);
+        remove_series(&self.ibc_client_expiry_seconds, "chain_id", chain_id);
+        remove_series(&self.ibc_client_latest_height, "chain_id", chain_id);
+        remove_series(&self.ibc_client_frozen, "chain_id", chain_id);
+    }
 
-#[derive(Debug, Deserialize)]
-struct ExpiringPacketsQuery {
-    #[serde(default = "default_expiring_minutes")]
-    minutes: i64,
-}
+    /// Counters of the packets and txs, carried on across restarts by their snapshots
+    fn persisted_counters(&self) -> [&CounterVec; 5] {
+        [
+            &self.ibc_effected_packets,
+            &self.ibc_uneffected_packets,
+            &self.ibc_frontrun_counter,
+            &self.chainpulse_txs,
+            &self.chainpulse_packets,
+        ]
+    }
 
-fn default_expiring_minutes() -> i64 {
-    60 // 1 hour default
-}
+    /// Current value of every series of the persisted counters
+    pub fn counter_snapshots(&self) -> Vec<CounterSnapshot> {
+        let mut snapshots = Vec::new();
+
+        for vec in self.persisted_counters() {
+            for family in vec.collect() {
+                for metric in family.get_metric() {
+                    snapshots.push(CounterSnapshot {
+                        name: family.get_name().to_string(),
+                        labels: metric
+                            .get_label()
+                            .iter()
+                            .map(|pair| (pair.get_name().to_string(), pair.get_value().to_string()))
+                            .collect(),
+                        value: metric.get_counter().get_value() as u64,
+                    });
+                }
+            }
+        }
 
-#[derive(Debug, Serialize)]
-struct ExpiringPacketsResponse {
-    packets: Vec<ExpiringPacketInfo>,
-    api_version: String,
-}
+        snapshots
+    }
 
-#[derive(Debug, Serialize)]
-struct ExpiringPacketInfo {
-    chain_id: String,
-    sequence: i64,
-    src_channel: String,
-    dst_channel: String,
-    sender: Option<String>,
-    receiver: Option<String>,
-    amount: Option<String>,
-    denom: Option<String>,
-    seconds_until_timeout: i64,
-    timeout_type: String,
-    timeout_value: String,
-}
+    /// Raise the persisted counters to the values of their snapshots, so restoring them twice
+    /// counts them once
+    ///
+    /// Snapshots of a counter no longer persisted, or whose labels changed, are skipped.
+    pub fn restore_counters(&self, snapshots: &[CounterSnapshot]) -> usize {
+        let mut restored = 0;
 
-async fn get_expiring_packets(
-    State(state): State<ApiState>,
-    Query(params): Query<ExpiringPacketsQuery>,
-) -> std::result::Result<Json<ExpiringPacketsResponse>, StatusCode> {
-    let query = r#"
-        SELECT 
-            t.chain,
-            p.sequence,
-            p.src_channel,
-            p.dst_channel,
-            p.sender,
-            p.receiver,
-            p.amount,
-            p.denom,
-            p.timeout_timestamp,
-            p.timeout_height_revision_number,
-            p.timeout_height_revision_height,
-            (p.timeout_timestamp - strftime('%s', 'now') * 1000000000) / 1000000000 as seconds_until_timeout
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE p.effected = 0 
-          AND p.timeout_timestamp IS NOT NULL
-          AND p.timeout_timestamp > strftime('%s', 'now') * 1000000000
-          AND p.timeout_timestamp < (strftime('%s', 'now') + ? * 60) * 1000000000
-        ORDER BY p.timeout_timestamp ASC
-        LIMIT 100
-    "#;
-
-    match sqlx::query(query)
-        .bind(params.minutes)
-        .fetch_all(&state.db)
-        .await
-    {
-        Ok(rows) => {
-            let packets = rows
+        for snapshot in snapshots {
+            let Some(vec) = self
+                .persisted_counters()
                 .into_iter()
-                .map(|row| {
-                    let timeout_type = if row.get::<Option<i64>, _>(9).is_some() {
-                        "height".to_string()
-                    } else {
-                        "timestamp".to_string()
-                    };
-                    
-                    let timeout_value = if timeout_type == "height" {
-                        format!("{}-{}", 
-                            row.get::<Option<i64>, _>(9).unwrap_or(0),
-                            row.get::<Option<i64>, _>(10).unwrap_or(0)
-                        )
-                    } else {
-                        let ts = row.get::<Option<i64>, _>(8).unwrap_or(0);
-                        // Convert nanoseconds to ISO timestamp
-                        let secs = ts / 1_000_000_000;
-                        chrono::DateTime::from_timestamp(secs, 0)
-                            .map(|dt| dt.to_rfc3339())
-                            .unwrap_or_else(|| ts.to_string())
-                    };
-
-                    ExpiringPacketInfo {
-                        chain_id: row.get(0),
-                        sequence: row.get(1),
-                        src_channel: row.get(2),
-                        dst_channel: row.get(3),
-                        sender: row.get(4),
-                        receiver: row.get(5),
-                        amount: row.get(6),
-                        denom: row.get(7),
-                        seconds_until_timeout: row.get(11),
-                        timeout_type,
-                        timeout_value,
-                    }
-                })
+                .find(|vec| vec.desc().iter().any(|desc| desc.fq_name == snapshot.name))
+            else {
+                continue;
+            };
+
+            let labels: HashMap<&str, &str> = snapshot
+                .labels
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
                 .collect();
 
-            Ok(Json(ExpiringPacketsResponse {
-                packets,
-                api_version: "1.0".to_string(),
-            }))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
-}
-
-#[derive(Debug, Serialize)]
-struct ExpiredPacketsResponse {
-    packets: Vec<ExpiredPacketInfo>,
-    api_version: String,
-}
-
-#[derive(Debug, Serialize)]
-struct ExpiredPacketInfo {
-    chain_id: String,
-    sequence: i64,
-    src_channel: String,
-    dst_channel: String,
-    sender: Option<String>,
-    receiver: Option<String>,
-    amount: Option<String>,
-    denom: Option<String>,
-    seconds_since_timeout: i64,
-    timeout_type: String,
-}
+            let Ok(counter) = vec.get_metric_with(&labels) else {
+                continue;
+            };
 
-async fn get_expired_packets(
-    State(state): State<ApiState>,
-) -> std::result::Result<Json<ExpiredPacketsResponse>, StatusCode> {
-    let query = r#"
-        SELECT 
-            t.chain,
-            p.sequence,
-            p.src_channel,
-            p.dst_channel,
-            p.sender,
-            p.receiver,
-            p.amount,
-            p.denom,
-            p.timeout_timestamp,
-            p.timeout_height_revision_number,
-            p.timeout_height_revision_height,
-            (strftime('%s', 'now') * 1000000000 - p.timeout_timestamp) / 1000000000 as seconds_since_timeout
-        FROM packets p
-        JOIN txs t ON p.tx_id = t.id
-        WHERE p.effected = 0 
-          AND p.timeout_timestamp IS NOT NULL
-          AND p.timeout_timestamp < strftime('%s', 'now') * 1000000000
-        ORDER BY p.timeout_timestamp DESC
-        LIMIT 100
-    "#;
-
-    match sqlx::query(query)
-        .fetch_all(&state.db)
-        .await
-    {
-        Ok(rows) => {
-            let packets = rows
-                .into_iter()
-                .map(|row| {
-                    let timeout_type = if row.get::<Option<i64>, _>(9).is_some() {
-                        "height".to_string()
-                    } else {
-                        "timestamp".to_string()
-                    };
-
-                    ExpiredPacketInfo {
-                        chain_id: row.get(0),
-                        sequence: row.get(1),
-                        src_channel: row.get(2),
-                        dst_channel: row.get(3),
-                        sender: row.get(4),
-                        receiver: row.get(5),
-                        amount: row.get(6),
-                        denom: row.get(7),
-                        seconds_since_timeout: row.get(11),
-                        timeout_type,
-                    }
-                })
-                .collect();
+            if snapshot.value > counter.get() {
+                counter.inc_by(snapshot.value - counter.get());
+            }
 
-            Ok(Json(ExpiredPacketsResponse {
-                packets,
-                api_version: "1.0".to_string(),
-            }))
+            restored += 1;
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
-}
-
-#[derive(Debug, Serialize)]
-struct DuplicatePacketsResponse {
-    duplicates: Vec<DuplicateGroup>,
-    api_version: String,
-}
 
-#[derive(Debug, Serialize)]
-struct DuplicateGroup {
-    data_hash: String,
-    count: i64,
-    packets: Vec<DuplicatePacketInfo>,
+        restored
+    }
 }
 
-#[derive(Debug, Serialize)]
-struct DuplicatePacketInfo {
-    chain_id: String,
-    sequence: i64,
-    src_channel: String,
-    sender: Option<String>,
-    created_at: String,
-}
+/// Remove every series of `vec` whose `label` is set to `value`
+fn remove_series<T: MetricVecBuilder>(vec: &MetricVec<T>, label: &str, value: &str) {
+    for family in vec.collect() {
+        for metric in family.get_metric() {
+            let labels: HashMap<&str, &str> = metric
+                .get_label()
+                .iter()
+                .map(|pair| (pair.get_name(), pair.get_value()))
+                .collect();
 
-async fn get_duplicate_packets(
-    State(state): State<ApiState>,
-) -> std::result::Result<Json<DuplicatePacketsResponse>, StatusCode> {
-    // First get duplicate hashes
-    let hash_query = r#"
-        SELECT data_hash, COUNT(*) as count
-        FROM packets
-        WHERE data_hash IS NOT NULL
-        GROUP BY data_hash
-        HAVING COUNT(*) > 1
-        ORDER BY count DESC
-        LIMIT 20
-    "#;
-
-    match sqlx::query(hash_query).fetch_all(&state.db).await {
-        Ok(hash_rows) => {
-            let mut duplicates = Vec::new();
-
-            for hash_row in hash_rows {
-                let data_hash: String = hash_row.get(0);
-                let count: i64 = hash_row.get(1);
-
-                // Get details for each duplicate
-                let detail_query = r#"
-                    SELECT 
-                        t.chain,
-                        p.sequence,
-                        p.src_channel,
-                        p.sender,
-                        p.created_at
-                    FROM packets p
-                    JOIN txs t ON p.tx_id = t.id
-                    WHERE p.data_hash = ?
-                    ORDER BY p.created_at ASC
-                "#;
-
-                if let Ok(detail_rows) = sqlx::query(detail_query)
-                    .bind(&data_hash)
-                    .fetch_all(&state.db)
-                    .await
-                {
-                    let packets = detail_rows
-                        .into_iter()
-                        .map(|row| DuplicatePacketInfo {
-                            chain_id: row.get(0),
-                            sequence: row.get(1),
-                            src_channel: row.get(2),
-                            sender: row.get(3),
-                            created_at: row.get(4),
-                        })
-                        .collect();
-
-                    duplicates.push(DuplicateGroup {
-                        data_hash,
-                        count,
-                        packets,
-                    });
-                }
+            if labels.get(label) == Some(&value) {
+                let _ = vec.remove(&labels);
             }
-
-            Ok(Json(DuplicatePacketsResponse {
-                duplicates,
-                api_version: "1.0".to_string(),
-            }))
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
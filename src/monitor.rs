@@ -0,0 +1,570 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{error, info};
+
+use crate::{
+    alert::Alerts,
+    channels,
+    config::{self, Config, StuckPackets},
+    denoms,
+    metrics::Metrics,
+    prices::UsdPrices,
+    rate_limits,
+    rollup::sum_amounts,
+    status::Status,
+    verify,
+    webhook::{EventKind, PacketEvent, PacketSnapshot, Webhooks},
+};
+
+/// Window before its timeout in which a pending packet counts as nearing timeout
+pub const NEAR_TIMEOUT_SECONDS: i64 = 3600;
+
+/// Outcome of a single stuck packet scan
+#[derive(Debug, Default, Serialize)]
+pub struct ScanSummary {
+    pub stuck_packets: i64,
+    pub stuck_channels: usize,
+    pub near_timeout_packets: i64,
+    pub duration_ms: u128,
+}
+
+/// Compact age such as `45s`, `12m`, `3h05m` or `2d04h`
+pub fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+
+    match seconds {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+        s => format!("{}d{:02}h", s / 86400, s % 86400 / 3600),
+    }
+}
+
+/// Source chain, source channel and sequence of a packet
+type PacketKey = (String, String, i64);
+
+/// Conditions the pending packets are checked for, each on its own schedule
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Check {
+    Stuck,
+    NearTimeout,
+}
+
+impl Check {
+    /// Name the runs of the check are reported under
+    fn name(self) -> &'static str {
+        match self {
+            Check::Stuck => "stuck_scan",
+            Check::NearTimeout => "timeout_scan",
+        }
+    }
+}
+
+/// Stuck packets seen by the previous scan, used to notify the cleared ones,
+/// the packets of watched addresses already notified as nearing timeout,
+/// and the alerts of the check they fired
+struct Tracker {
+    check: Check,
+    initialized: bool,
+    stuck: HashMap<PacketKey, PacketSnapshot>,
+    watched: HashSet<PacketKey>,
+    alerts: Alerts,
+}
+
+impl Tracker {
+    fn new(check: Check, alerts: config::Alerts) -> Self {
+        Self {
+            check,
+            initialized: false,
+            stuck: HashMap::new(),
+            watched: HashSet::new(),
+            alerts: Alerts::new(alerts),
+        }
+    }
+}
+
+/// Interval ticking every period, from one period from now
+fn interval(seconds: u64) -> time::Interval {
+    let period = Duration::from_secs(seconds);
+    time::interval_at(time::Instant::now() + period, period)
+}
+
+/// Periodically refresh the stuck packet and timeout gauges and notify webhooks, every
+/// `intervals.stuck_scan_seconds` and `intervals.timeout_scan_seconds`
+///
+/// When `stuck_packets.verify` is set, the packets past their threshold are first verified
+/// against the chains, those found relayed being marked so and cleared.
+///
+/// Reloaded alert settings, stuck thresholds and intervals apply from the next scan. Every run
+/// of a check is recorded in `status`, along with its last error.
+pub async fn run(
+    pool: SqlitePool,
+    metrics: Metrics,
+    webhooks: Webhooks,
+    mut config: watch::Receiver<Config>,
+    status: Status,
+) {
+    let mut settings = config.borrow_and_update().clone();
+
+    let mut stuck = Tracker::new(Check::Stuck, settings.alerts.clone());
+    let mut timeouts = Tracker::new(Check::NearTimeout, settings.alerts.clone());
+    let client = reqwest::Client::new();
+
+    // Both checks run on start
+    let mut stuck_interval =
+        time::interval(Duration::from_secs(settings.intervals.stuck_scan_seconds));
+    let mut timeout_interval =
+        time::interval(Duration::from_secs(settings.intervals.timeout_scan_seconds));
+
+    loop {
+        let check = tokio::select! {
+            _ = stuck_interval.tick() => Check::Stuck,
+            _ = timeout_interval.tick() => Check::NearTimeout,
+        };
+
+        if config.has_changed().unwrap_or(false) {
+            let reloaded = config.borrow_and_update().clone();
+
+            if reloaded.intervals.stuck_scan_seconds != settings.intervals.stuck_scan_seconds {
+                stuck_interval = interval(reloaded.intervals.stuck_scan_seconds);
+            }
+
+            if reloaded.intervals.timeout_scan_seconds != settings.intervals.timeout_scan_seconds {
+                timeout_interval = interval(reloaded.intervals.timeout_scan_seconds);
+            }
+
+            stuck.alerts.configure(reloaded.alerts.clone());
+            timeouts.alerts.configure(reloaded.alerts.clone());
+            settings = reloaded;
+        }
+
+        let tracker = match check {
+            Check::Stuck => &mut stuck,
+            Check::NearTimeout => &mut timeouts,
+        };
+
+        let started = Utc::now();
+        let mut failure = None;
+
+        if check == Check::Stuck && settings.stuck_packets.verify {
+            let verified =
+                verify::verify_stuck(&client, &pool, &settings.chains, &settings.stuck_packets)
+                    .await;
+
+            if let Err(e) = verified {
+                let message = format!("Failed to verify the stuck packets: {e}");
+                error!("{message}");
+                failure = Some(message);
+            }
+        }
+
+        match check {
+            Check::Stuck => match scan_stuck(&pool, &metrics, &settings.stuck_packets).await {
+                Ok((packets, channels)) => {
+                    info!("Found {packets} stuck packets on {channels} channels")
+                }
+                Err(e) => {
+                    let message = format!("Stuck packet scan failed: {e}");
+                    error!("{message}");
+                    failure = Some(message);
+                }
+            },
+            Check::NearTimeout => match scan_near_timeout(&pool, &metrics).await {
+                Ok(packets) => info!("Found {packets} packets nearing timeout"),
+                Err(e) => {
+                    let message = format!("Timeout scan failed: {e}");
+                    error!("{message}");
+                    failure = Some(message);
+                }
+            },
+        }
+
+        match packet_events(&pool, tracker, &settings).await {
+            Ok(events) => webhooks.dispatch(events).await,
+            Err(e) => {
+                let message = format!("Failed to compute packet events: {e}");
+                error!("{message}");
+                failure = Some(message);
+            }
+        }
+
+        status.check_ran(check.name(), started, failure);
+    }
+}
+
+/// Evaluate the alerts of a check on the pending packets and compare them against its previous
+/// scan, building the events to notify
+///
+/// The first scan only records the current state, so that a restart does not notify
+/// every packet which is already stuck.
+async fn packet_events(
+    pool: &SqlitePool,
+    tracker: &mut Tracker,
+    config: &Config,
+) -> sqlx::Result<Vec<PacketEvent>> {
+    let thresholds = &config.stuck_packets;
+
+    let condition = match tracker.check {
+        Check::Stuck => format!(
+            "CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+             AND {} IS NULL",
+            thresholds.sql("t.chain", "p.src_channel"),
+            channels::unrecoverable_sql("t.chain", "p.src_port", "p.src_channel")
+        ),
+        Check::NearTimeout => {
+            "p.timeout_timestamp < (strftime('%s', 'now') + ?) * 1000000000".to_string()
+        }
+    };
+
+    let query = format!(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            p.src_channel,
+            p.dst_channel,
+            p.sequence,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            CASE WHEN p.timeout_timestamp IS NOT NULL
+                THEN CAST((p.timeout_timestamp / 1000000000 - strftime('%s', 'now')) AS INTEGER)
+            END as seconds_until_timeout
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND {condition}
+        "#
+    );
+
+    let mut pending = sqlx::query_as::<_, PacketSnapshot>(&query);
+    if tracker.check == Check::NearTimeout {
+        pending = pending.bind(NEAR_TIMEOUT_SECONDS);
+    }
+    let pending = pending.fetch_all(pool).await?;
+
+    let watchlist: HashSet<&String> = config.alerts.watched_addresses.iter().collect();
+
+    let mut active = Vec::new();
+    let mut stuck = HashMap::new();
+    let mut watched = HashSet::new();
+    let mut events = Vec::new();
+
+    for packet in pending {
+        let key = (
+            packet.chain_id.clone(),
+            packet.src_channel.clone(),
+            packet.sequence,
+        );
+
+        match tracker.check {
+            Check::NearTimeout => {
+                active.push((EventKind::PacketNearTimeout, packet.clone()));
+
+                let is_watched = [&packet.sender, &packet.receiver]
+                    .into_iter()
+                    .flatten()
+                    .any(|address| watchlist.contains(address));
+
+                // Every packet of a watched address is notified, once
+                if is_watched {
+                    if !tracker.watched.contains(&key) {
+                        events.push(PacketEvent::new(
+                            EventKind::WatchedNearTimeout,
+                            packet.clone(),
+                        ));
+                    }
+
+                    watched.insert(key);
+                }
+            }
+            Check::Stuck => {
+                active.push((EventKind::PacketStuck, packet.clone()));
+                stuck.insert(key, packet);
+            }
+        }
+    }
+
+    events.extend(tracker.alerts.evaluate(active));
+
+    // Stuck packets only leave the pending set once relayed or timed out
+    for (key, packet) in tracker.stuck.drain() {
+        if stuck.contains_key(&key) || !is_effected(pool, &key).await? {
+            continue;
+        }
+
+        events.push(PacketEvent::new(EventKind::PacketCleared, packet));
+    }
+
+    tracker.stuck = stuck;
+    tracker.watched = watched;
+
+    if !tracker.initialized {
+        tracker.initialized = true;
+        events.clear();
+    }
+
+    Ok(events)
+}
+
+async fn is_effected(
+    pool: &SqlitePool,
+    (chain, channel, sequence): &PacketKey,
+) -> sqlx::Result<bool> {
+    let effected = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT p.effected
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND t.chain = ? AND p.src_channel = ? AND p.sequence = ?
+        "#,
+    )
+    .bind(chain)
+    .bind(channel)
+    .bind(sequence)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(effected.unwrap_or(false))
+}
+
+/// Scan the database for stuck and soon to time out packets and update the gauges
+pub async fn scan(
+    pool: &SqlitePool,
+    metrics: &Metrics,
+    thresholds: &StuckPackets,
+) -> sqlx::Result<ScanSummary> {
+    let start = time::Instant::now();
+
+    let (stuck_packets, stuck_channels) = scan_stuck(pool, metrics, thresholds).await?;
+    let near_timeout_packets = scan_near_timeout(pool, metrics).await?;
+
+    Ok(ScanSummary {
+        stuck_packets,
+        stuck_channels,
+        near_timeout_packets,
+        duration_ms: start.elapsed().as_millis(),
+    })
+}
+
+/// Refresh the gauges of the stuck packets, returning their number and the number of channels
+/// they are stuck on
+async fn scan_stuck(
+    pool: &SqlitePool,
+    metrics: &Metrics,
+    thresholds: &StuckPackets,
+) -> sqlx::Result<(i64, usize)> {
+    // The destination chain is the one resolved from the channel, or else the one we have seen
+    // a packet being received on
+    let query = format!(
+        r#"
+        SELECT
+            t.chain,
+            COALESCE(
+                (SELECT c.counterparty_chain_id FROM channel_counterparties c
+                 WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel),
+                (SELECT rt.chain FROM packets r
+                 JOIN txs rt ON r.tx_id = rt.id
+                 WHERE r.src_channel = p.src_channel AND r.dst_channel = p.dst_channel
+                   AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+                 LIMIT 1),
+                'unknown'
+            ) as dst_chain,
+            p.src_channel,
+            COUNT(*) as stuck_count,
+            MAX(CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER)) as oldest_age_seconds,
+            {} as unrecoverable,
+            SUM({} IS NOT NULL) as rate_limited
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+        GROUP BY t.chain, p.src_port, p.src_channel, p.dst_channel
+        "#,
+        channels::unrecoverable_sql("t.chain", "p.src_port", "p.src_channel"),
+        rate_limits::exhausted_sql(
+            "t.chain",
+            "p.src_port",
+            "p.src_channel",
+            "p.dst_port",
+            "p.dst_channel",
+            "p.denom"
+        ),
+        thresholds.sql("t.chain", "p.src_channel")
+    );
+
+    let stuck =
+        sqlx::query_as::<_, (String, String, String, i64, i64, Option<String>, i64)>(&query)
+            .fetch_all(pool)
+            .await?;
+
+    let values = stuck_values(pool, thresholds).await?;
+
+    metrics.reset_unrelayed_gauges();
+
+    for ((src_chain, dst_chain, channel), usd) in values {
+        metrics.ibc_stuck_value_usd(&src_chain, &dst_chain, &channel, usd);
+    }
+
+    let mut channels = 0;
+    let mut packets = 0;
+
+    for (src_chain, dst_chain, channel, count, oldest_age_seconds, unrecoverable, rate_limited) in
+        stuck
+    {
+        // Packets of closed channels or frozen clients cannot be relayed, and are not stuck
+        if let Some(reason) = unrecoverable {
+            metrics.ibc_packets_unrecoverable(&src_chain, &dst_chain, &channel, &reason, count);
+            continue;
+        }
+
+        // Packets held by a rate limit are stuck until its period ends
+        if rate_limited > 0 {
+            metrics.ibc_packets_rate_limited(&src_chain, &dst_chain, &channel, rate_limited);
+        }
+
+        metrics.ibc_packet_age_unrelayed(
+            &src_chain,
+            &dst_chain,
+            &channel,
+            oldest_age_seconds as f64,
+        );
+        channels += 1;
+        packets += count;
+    }
+
+    Ok((packets, channels))
+}
+
+/// Value in USD of the stuck packets of each channel, by source chain, destination chain and
+/// channel, for the channels with packets in a priced denom
+async fn stuck_values(
+    pool: &SqlitePool,
+    thresholds: &StuckPackets,
+) -> sqlx::Result<BTreeMap<(String, String, String), f64>> {
+    let prices = UsdPrices::load(pool).await?;
+    let mut values = BTreeMap::new();
+
+    if prices.is_empty() {
+        return Ok(values);
+    }
+
+    let query = format!(
+        r#"
+        SELECT
+            t.chain,
+            COALESCE(
+                (SELECT c.counterparty_chain_id FROM channel_counterparties c
+                 WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel),
+                (SELECT rt.chain FROM packets r
+                 JOIN txs rt ON r.tx_id = rt.id
+                 WHERE r.src_channel = p.src_channel AND r.dst_channel = p.dst_channel
+                   AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+                 LIMIT 1),
+                'unknown'
+            ) as dst_chain,
+            p.src_channel,
+            {} as trace,
+            GROUP_CONCAT(p.amount) as amounts
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND p.denom IS NOT NULL
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+          AND {} IS NULL
+        GROUP BY t.chain, p.src_port, p.src_channel, p.dst_channel, trace
+        "#,
+        denoms::trace_sql("t.chain", "p.denom"),
+        thresholds.sql("t.chain", "p.src_channel"),
+        channels::unrecoverable_sql("t.chain", "p.src_port", "p.src_channel")
+    );
+
+    let rows = sqlx::query_as::<_, (String, String, String, String, String)>(&query)
+        .fetch_all(pool)
+        .await?;
+
+    for (src_chain, dst_chain, channel, trace, amounts) in rows {
+        if let Some(usd) = prices.value(&trace, &sum_amounts(&amounts)) {
+            *values.entry((src_chain, dst_chain, channel)).or_default() += usd;
+        }
+    }
+
+    Ok(values)
+}
+
+/// Refresh the gauges of the packets nearing their timeout, returning their number
+async fn scan_near_timeout(pool: &SqlitePool, metrics: &Metrics) -> sqlx::Result<i64> {
+    let near_timeout = sqlx::query_as::<_, (String, String, String, String, i64, i64)>(
+        r#"
+        SELECT
+            t.chain,
+            COALESCE(
+                (SELECT c.counterparty_chain_id FROM channel_counterparties c
+                 WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel),
+                (SELECT rt.chain FROM packets r
+                 JOIN txs rt ON r.tx_id = rt.id
+                 WHERE r.src_channel = p.src_channel AND r.dst_channel = p.dst_channel
+                   AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+                 LIMIT 1),
+                'unknown'
+            ) as dst_chain,
+            p.src_channel,
+            p.dst_channel,
+            COUNT(*) as near_timeout_count,
+            MIN(p.timeout_timestamp / 1000000000 - strftime('%s', 'now')) as seconds_until_timeout
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND p.timeout_timestamp IS NOT NULL
+          AND p.timeout_timestamp > strftime('%s', 'now') * 1000000000
+          AND p.timeout_timestamp < (strftime('%s', 'now') + ?) * 1000000000
+        GROUP BY t.chain, p.src_channel, p.dst_channel
+        "#,
+    )
+    .bind(NEAR_TIMEOUT_SECONDS)
+    .fetch_all(pool)
+    .await?;
+
+    metrics.reset_near_timeout_gauges();
+
+    let mut packets = 0;
+
+    for (src_chain, dst_chain, src_channel, dst_channel, count, seconds_until_timeout) in
+        near_timeout
+    {
+        metrics.ibc_packets_near_timeout(
+            &src_chain,
+            &dst_chain,
+            &src_channel,
+            &dst_channel,
+            "timestamp",
+            count,
+        );
+        metrics.ibc_packet_timeout_seconds(
+            &src_chain,
+            &dst_chain,
+            &src_channel,
+            &dst_channel,
+            seconds_until_timeout as f64,
+        );
+        packets += count;
+    }
+
+    Ok(packets)
+}
@@ -11,6 +11,7 @@ use ibc_proto::{
             },
             client::v1::{MsgCreateClient, MsgUpdateClient},
         },
+        lightclients::tendermint::v1::Header as TendermintHeader,
     },
 };
 
@@ -30,6 +31,56 @@ pub struct FungibleTokenPacketData {
     pub memo: String,
 }
 
+/// Channel acknowledgement, JSON encoded by ibc-go as `{"result": ...}` or `{"error": ...}`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Acknowledgement {
+    Result(String),
+    Error(String),
+}
+
+impl Acknowledgement {
+    /// The error carried by an acknowledgement, if it can be decoded and is one
+    pub fn error(ack: &[u8]) -> Option<String> {
+        match serde_json::from_slice::<Self>(ack) {
+            Ok(Self::Error(error)) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Light client update, with the counterparty header when the client is a Tendermint client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientUpdate {
+    pub client_id: String,
+    pub counterparty_chain_id: Option<String>,
+    pub header_height: Option<i64>,
+    /// Unix seconds
+    pub header_time: Option<i64>,
+}
+
+impl ClientUpdate {
+    pub fn from_msg(msg: &MsgUpdateClient) -> Self {
+        let header = msg
+            .client_message
+            .as_ref()
+            .filter(|any| any.type_url == "/ibc.lightclients.tendermint.v1.Header")
+            .and_then(|any| TendermintHeader::decode(any.value.as_slice()).ok())
+            .and_then(|header| header.signed_header)
+            .and_then(|signed_header| signed_header.header);
+
+        Self {
+            client_id: msg.client_id.clone(),
+            counterparty_chain_id: header.as_ref().map(|header| header.chain_id.clone()),
+            header_height: header.as_ref().map(|header| header.height),
+            header_time: header
+                .as_ref()
+                .and_then(|header| header.time.as_ref())
+                .map(|time| time.seconds),
+        }
+    }
+}
+
 /// Enhanced packet info that works for both IBC v1 and future v2
 #[derive(Debug, Clone)]
 pub struct UniversalPacketInfo {
@@ -58,7 +109,18 @@ pub struct UniversalPacketInfo {
 impl UniversalPacketInfo {
     /// Extract user data from a packet if it's a fungible token transfer
     pub fn from_packet(packet: &Packet) -> Self {
-        let (sender, receiver, denom, amount, transfer_memo) = if packet.source_port == "transfer" {
+        Self::new(packet, true)
+    }
+
+    /// Info of a packet without decoding its data
+    pub fn without_user_data(packet: &Packet) -> Self {
+        Self::new(packet, false)
+    }
+
+    fn new(packet: &Packet, user_data: bool) -> Self {
+        let is_transfer = user_data && packet.source_port == "transfer";
+
+        let (sender, receiver, denom, amount, transfer_memo) = if is_transfer {
             match serde_json::from_slice::<FungibleTokenPacketData>(&packet.data) {
                 Ok(ft_data) => (
                     Some(ft_data.sender),
@@ -166,6 +228,22 @@ impl Msg {
         }
     }
     
+    /// Client and counterparty header of a MsgUpdateClient
+    pub fn client_update(&self) -> Option<ClientUpdate> {
+        match self {
+            Self::UpdateClient(msg) => Some(ClientUpdate::from_msg(msg)),
+            _ => None,
+        }
+    }
+
+    /// Error returned by the destination chain, for acknowledgements of failed packets
+    pub fn ack_error(&self) -> Option<String> {
+        match self {
+            Self::Acknowledgement(msg) => Acknowledgement::error(&msg.acknowledgement),
+            _ => None,
+        }
+    }
+
     /// Get transfer details from MsgTransfer
     pub fn transfer(&self) -> Option<&MsgTransfer> {
         match self {
@@ -342,6 +420,55 @@ mod tests {
         assert_eq!(parsed.memo, "");
     }
 
+    #[test]
+    fn test_acknowledgement_error() {
+        assert_eq!(
+            Acknowledgement::error(
+                br#"{"error":"ABCI code: 5: error handling packet: see events for details"}"#
+            ),
+            Some("ABCI code: 5: error handling packet: see events for details".to_string())
+        );
+        assert_eq!(Acknowledgement::error(br#"{"result":"AQ=="}"#), None);
+        assert_eq!(Acknowledgement::error(b"\x01"), None);
+    }
+
+    #[test]
+    fn test_client_update_from_tendermint_header() {
+        // The tendermint-proto types are not nameable from this crate, fill them in place
+        let mut header = TendermintHeader {
+            signed_header: Some(Default::default()),
+            ..Default::default()
+        };
+
+        let signed_header = header.signed_header.as_mut().unwrap();
+        signed_header.header = Some(Default::default());
+
+        let inner = signed_header.header.as_mut().unwrap();
+        inner.chain_id = "osmosis-1".to_string();
+        inner.height = 12_345;
+        inner.time = Some(Default::default());
+        inner.time.as_mut().unwrap().seconds = 1_700_000_000;
+
+        let msg = MsgUpdateClient {
+            client_id: "07-tendermint-1".to_string(),
+            client_message: Some(Any {
+                type_url: "/ibc.lightclients.tendermint.v1.Header".to_string(),
+                value: header.encode_to_vec(),
+            }),
+            signer: "cosmos1relayer".to_string(),
+        };
+
+        assert_eq!(
+            ClientUpdate::from_msg(&msg),
+            ClientUpdate {
+                client_id: "07-tendermint-1".to_string(),
+                counterparty_chain_id: Some("osmosis-1".to_string()),
+                header_height: Some(12_345),
+                header_time: Some(1_700_000_000),
+            }
+        );
+    }
+
     #[test]
     fn test_universal_packet_info_from_transfer_packet() {
         use ibc_proto::ibc::core::channel::v1::Packet;
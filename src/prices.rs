@@ -0,0 +1,200 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{Config, Prices},
+    denoms,
+};
+
+/// Timeout of the requests made to the price feed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Refresh the USD prices of the denoms of `prices` every `refresh_minutes`, recording them in
+/// the `denom_prices` table
+///
+/// The prices of the denoms no longer configured are dropped, the others are kept when the
+/// price feed cannot be reached.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    let client = reqwest::Client::new();
+
+    loop {
+        let prices = config.borrow_and_update().prices.clone();
+
+        let Some(prices) = prices else {
+            // Idle until a reload configures the prices
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        match refresh(&client, &pool, &prices).await {
+            Ok(priced) => info!("Refreshed the USD prices of {priced} denoms"),
+            Err(e) => error!("Failed to refresh the USD prices: {e}"),
+        }
+
+        tokio::select! {
+            _ = time::sleep(Duration::from_secs(prices.refresh_minutes * 60)) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn refresh(
+    client: &reqwest::Client,
+    pool: &SqlitePool,
+    prices: &Prices,
+) -> crate::Result<usize> {
+    let usd = fetch(client, prices).await?;
+    let mut priced = 0;
+
+    for (base_denom, denom) in &prices.denoms {
+        let Some(price) = usd.get(&denom.id) else {
+            warn!(
+                "No USD price for {base_denom}, whose coin {} is unknown",
+                denom.id
+            );
+            continue;
+        };
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO denom_prices (base_denom, id, decimals, usd, updated_at)
+            VALUES (?, ?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(base_denom)
+        .bind(&denom.id)
+        .bind(denom.decimals)
+        .bind(price)
+        .execute(pool)
+        .await?;
+
+        debug!("Priced {base_denom} at {price} USD");
+        priced += 1;
+    }
+
+    let stored: Vec<String> = sqlx::query_scalar("SELECT base_denom FROM denom_prices")
+        .fetch_all(pool)
+        .await?;
+
+    for base_denom in stored {
+        if !prices.denoms.contains_key(&base_denom) {
+            sqlx::query("DELETE FROM denom_prices WHERE base_denom = ?")
+                .bind(&base_denom)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(priced)
+}
+
+/// USD price of the coins of the configured denoms, by coin ID
+async fn fetch(client: &reqwest::Client, prices: &Prices) -> crate::Result<HashMap<String, f64>> {
+    if prices.denoms.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let ids: Vec<&str> = prices
+        .denoms
+        .values()
+        .map(|denom| denom.id.as_str())
+        .collect();
+
+    let mut request = client
+        .get(format!("{}/simple/price", prices.url.trim_end_matches('/')))
+        .query(&[("ids", ids.join(",").as_str()), ("vs_currencies", "usd")])
+        .timeout(REQUEST_TIMEOUT);
+
+    if let Some(api_key) = &prices.api_key {
+        let header = match prices.url.contains("pro-api.") {
+            true => "x-cg-pro-api-key",
+            false => "x-cg-demo-api-key",
+        };
+
+        request = request.header(header, api_key);
+    }
+
+    let response: BTreeMap<String, HashMap<String, f64>> =
+        request.send().await?.error_for_status()?.json().await?;
+
+    Ok(response
+        .into_iter()
+        .filter_map(|(id, mut quotes)| Some((id, quotes.remove("usd")?)))
+        .collect())
+}
+
+/// USD prices of the base denoms, as last refreshed
+#[derive(Debug, Default)]
+pub struct UsdPrices {
+    /// Price of a whole coin and decimals, by base denom
+    prices: HashMap<String, (f64, u32)>,
+}
+
+impl UsdPrices {
+    pub async fn load(pool: &SqlitePool) -> sqlx::Result<Self> {
+        let rows: Vec<(String, f64, u32)> =
+            sqlx::query_as("SELECT base_denom, usd, decimals FROM denom_prices")
+                .fetch_all(pool)
+                .await?;
+
+        Ok(Self {
+            prices: rows
+                .into_iter()
+                .map(|(base_denom, usd, decimals)| (base_denom, (usd, decimals)))
+                .collect(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    /// Value in USD of an amount of a denom, given as its ICS-20 trace, when its base denom is
+    /// priced
+    pub fn value(&self, trace: &str, amount: &str) -> Option<f64> {
+        let (_, base_denom) = denoms::split_trace(trace);
+        let (usd, decimals) = self.prices.get(&base_denom)?;
+        let amount: f64 = amount.parse().ok()?;
+
+        Some(amount / 10f64.powf(*decimals as f64) * usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        let prices = UsdPrices {
+            prices: HashMap::from([
+                ("uatom".to_string(), (8.5, 6)),
+                ("aevmos".to_string(), (0.5, 18)),
+            ]),
+        };
+
+        assert_eq!(prices.value("uatom", "2000000"), Some(17.0));
+        assert_eq!(
+            prices.value("transfer/channel-0/uatom", "1000000"),
+            Some(8.5)
+        );
+        assert_eq!(
+            prices.value("aevmos", "40000000000000000000000"),
+            Some(20000.0)
+        );
+        assert_eq!(prices.value("uosmo", "1000000"), None);
+        assert_eq!(prices.value("uatom", "not-a-number"), None);
+    }
+}
@@ -0,0 +1,487 @@
+use std::time::Duration;
+
+use prost::Message;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::{Chains, Config, Endpoint, RateLimits},
+    denoms, expiry,
+    metrics::Metrics,
+    rpc,
+};
+
+/// How often the rate limits are queried
+const REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Direction of the exhausted rate limit holding the packets sent by a chain on a channel, given
+/// the columns holding the chain, the source and destination ports and channels and the denom
+/// of the packets, such as `t.chain`, `p.src_port`, `p.src_channel`, `p.dst_port`,
+/// `p.dst_channel` and `p.denom`
+///
+/// The expression is `send` when the send quota of the denom on the sending chain is exhausted,
+/// `recv` when the receive quota of the denom as received on the counterparty is, and `NULL`
+/// otherwise. The `ibc/` denoms of the quotas are matched once their trace is resolved.
+pub fn exhausted_sql(
+    chain: &str,
+    port: &str,
+    channel: &str,
+    dst_port: &str,
+    dst_channel: &str,
+    denom: &str,
+) -> String {
+    let trace = denoms::trace_sql("r.chain", "r.denom");
+    let prefix = format!("{port} || '/' || {channel} || '/'");
+
+    // Tokens returning to the counterparty are unwrapped, the others get its prefix
+    format!(
+        "(SELECT r.direction FROM rate_limits r
+          WHERE r.flow >= r.threshold AND (
+            (r.direction = 'send' AND r.chain = {chain} AND r.channel = {channel}
+             AND {trace} = {denom})
+            OR (r.direction = 'recv' AND r.channel = {dst_channel}
+             AND r.chain = (SELECT c.counterparty_chain_id FROM channel_counterparties c
+                            WHERE c.chain = {chain} AND c.port = {port} AND c.channel = {channel})
+             AND {trace} = CASE
+                 WHEN SUBSTR({denom}, 1, LENGTH({prefix})) = {prefix}
+                 THEN SUBSTR({denom}, LENGTH({prefix}) + 1)
+                 ELSE {dst_port} || '/' || {dst_channel} || '/' || {denom}
+               END))
+          LIMIT 1)"
+    )
+}
+
+/// Quota of a rate limit in one direction, with the net flow of its current period
+#[derive(Debug, PartialEq)]
+struct Quota {
+    channel: String,
+    /// Denom held on the chain, native or `ibc/`
+    denom: String,
+    /// Name of the quota, its duration for the module
+    name: String,
+    direction: &'static str,
+    /// Outflow net of the inflow when sending, and the other way around when receiving
+    flow: f64,
+    /// Net flow at which the quota is exhausted, a percentage of the value of the channel
+    threshold: f64,
+}
+
+/// Query the rate limits of the chains every 5 minutes, recording their quotas in the
+/// `rate_limits` table and refreshing the `ibc_rate_limit_utilization` gauge
+///
+/// Packets are held by a rate limit once the net flow of their denom on their channel reaches
+/// the threshold of a quota, until its period ends. The quotas of a chain which fails to answer
+/// are kept until it does.
+pub async fn run(pool: SqlitePool, metrics: Metrics, mut config: watch::Receiver<Config>) {
+    let client = reqwest::Client::new();
+
+    loop {
+        let (rate_limits, chains) = {
+            let config = config.borrow_and_update();
+            (config.rate_limits.clone(), config.chains.clone())
+        };
+
+        if rate_limits.is_empty() {
+            // Idle until a reload configures rate limits
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        }
+
+        if let Err(e) = refresh(&client, &pool, &metrics, &rate_limits, &chains).await {
+            error!("Failed to record the rate limits: {e}");
+        }
+
+        tokio::select! {
+            _ = time::sleep(REFRESH_INTERVAL) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn refresh(
+    client: &reqwest::Client,
+    pool: &SqlitePool,
+    metrics: &Metrics,
+    rate_limits: &[RateLimits],
+    chains: &Chains,
+) -> sqlx::Result<()> {
+    for settings in rate_limits {
+        let Some(endpoint) = expiry::endpoint(chains, &settings.chain) else {
+            warn!(
+                "Rate limits of {} are not queried, the chain is not monitored",
+                settings.chain
+            );
+            continue;
+        };
+
+        let quotas = match &settings.contract {
+            Some(contract) => contract_quotas(client, endpoint, contract, settings).await,
+            None => module_quotas(client, endpoint, &settings.query_path).await,
+        };
+
+        match quotas {
+            Ok(quotas) => {
+                debug!(chain = %settings.chain, "Recorded {} rate limit quotas", quotas.len());
+                record(pool, &settings.chain, &quotas).await?;
+            }
+            Err(e) => warn!(chain = %settings.chain, "Failed to query the rate limits: {e}"),
+        }
+    }
+
+    // Quotas of the chains no longer configured are dropped
+    let recorded: Vec<String> = sqlx::query_scalar("SELECT DISTINCT chain FROM rate_limits")
+        .fetch_all(pool)
+        .await?;
+
+    for chain in recorded {
+        if !rate_limits.iter().any(|settings| settings.chain == chain) {
+            sqlx::query("DELETE FROM rate_limits WHERE chain = ?")
+                .bind(&chain)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    let rows: Vec<(String, String, String, String, String, f64, f64)> = sqlx::query_as(
+        "SELECT chain, channel, denom, quota, direction, flow, threshold FROM rate_limits",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    metrics.reset_rate_limit_gauges();
+
+    for (chain, channel, denom, quota, direction, flow, threshold) in rows {
+        metrics.ibc_rate_limit_utilization(
+            &chain,
+            &channel,
+            &denom,
+            &quota,
+            &direction,
+            flow / threshold,
+        );
+    }
+
+    Ok(())
+}
+
+/// Replace the quotas recorded for a chain
+async fn record(pool: &SqlitePool, chain: &str, quotas: &[Quota]) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM rate_limits WHERE chain = ?")
+        .bind(chain)
+        .execute(&mut *tx)
+        .await?;
+
+    for quota in quotas {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO rate_limits
+                (chain, channel, denom, quota, direction, flow, threshold, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(chain)
+        .bind(&quota.channel)
+        .bind(&quota.denom)
+        .bind(&quota.name)
+        .bind(quota.direction)
+        .bind(quota.flow)
+        .bind(quota.threshold)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Send and receive quotas of a rate limit, leaving out those with a zero threshold, which are
+/// not measured before the value of the channel is
+#[allow(clippy::too_many_arguments)]
+fn quotas(
+    channel: &str,
+    denom: &str,
+    name: &str,
+    inflow: f64,
+    outflow: f64,
+    channel_value: f64,
+    max_percent_send: f64,
+    max_percent_recv: f64,
+) -> Vec<Quota> {
+    [
+        ("send", outflow - inflow, max_percent_send),
+        ("recv", inflow - outflow, max_percent_recv),
+    ]
+    .into_iter()
+    .map(|(direction, flow, max_percent)| Quota {
+        channel: channel.to_string(),
+        denom: denom.to_string(),
+        name: name.to_string(),
+        direction,
+        flow: flow.max(0.0),
+        threshold: channel_value * max_percent / 100.0,
+    })
+    .filter(|quota| quota.threshold > 0.0)
+    .collect()
+}
+
+/// Integer encoded as a string, such as the `Int` and `Uint256` amounts
+fn amount(value: &str) -> f64 {
+    value.parse().unwrap_or_default()
+}
+
+/// `QueryAllRateLimitsRequest` of the rate limit module of Stride
+#[derive(Clone, PartialEq, Message)]
+struct QueryAllRateLimitsRequest {}
+
+#[derive(Clone, PartialEq, Message)]
+struct QueryAllRateLimitsResponse {
+    #[prost(message, repeated, tag = "1")]
+    rate_limits: Vec<ModuleRateLimit>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ModuleRateLimit {
+    #[prost(message, optional, tag = "1")]
+    path: Option<ModulePath>,
+    #[prost(message, optional, tag = "2")]
+    quota: Option<ModuleQuota>,
+    #[prost(message, optional, tag = "3")]
+    flow: Option<ModuleFlow>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ModulePath {
+    #[prost(string, tag = "1")]
+    denom: String,
+    #[prost(string, tag = "2")]
+    channel_id: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ModuleQuota {
+    #[prost(string, tag = "1")]
+    max_percent_send: String,
+    #[prost(string, tag = "2")]
+    max_percent_recv: String,
+    #[prost(uint64, tag = "3")]
+    duration_hours: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ModuleFlow {
+    #[prost(string, tag = "1")]
+    inflow: String,
+    #[prost(string, tag = "2")]
+    outflow: String,
+    #[prost(string, tag = "3")]
+    channel_value: String,
+}
+
+async fn module_quotas(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    query_path: &str,
+) -> crate::Result<Vec<Quota>> {
+    let response: QueryAllRateLimitsResponse =
+        rpc::abci_query(client, endpoint, query_path, QueryAllRateLimitsRequest {}).await?;
+
+    Ok(module_rate_limits(response))
+}
+
+fn module_rate_limits(response: QueryAllRateLimitsResponse) -> Vec<Quota> {
+    response
+        .rate_limits
+        .into_iter()
+        .flat_map(|rate_limit| {
+            let (Some(path), Some(quota), Some(flow)) =
+                (rate_limit.path, rate_limit.quota, rate_limit.flow)
+            else {
+                return Vec::new();
+            };
+
+            quotas(
+                &path.channel_id,
+                &path.denom,
+                &format!("{}h", quota.duration_hours),
+                amount(&flow.inflow),
+                amount(&flow.outflow),
+                amount(&flow.channel_value),
+                amount(&quota.max_percent_send),
+                amount(&quota.max_percent_recv),
+            )
+        })
+        .collect()
+}
+
+/// `QuerySmartContractStateRequest` of the CosmWasm module
+#[derive(Clone, PartialEq, Message)]
+struct QuerySmartContractStateRequest {
+    #[prost(string, tag = "1")]
+    address: String,
+    #[prost(bytes = "vec", tag = "2")]
+    query_data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct QuerySmartContractStateResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    data: Vec<u8>,
+}
+
+/// Rate limit of the `ibc-rate-limit` contract, as answered to `get_quotas`
+#[derive(Debug, Deserialize)]
+struct ContractRateLimit {
+    quota: ContractQuota,
+    flow: ContractFlow,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContractQuota {
+    name: String,
+    max_percentage_send: u32,
+    max_percentage_recv: u32,
+    /// Value of the channel at the start of the period, unset before the first transfer
+    #[serde(default)]
+    channel_value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContractFlow {
+    inflow: String,
+    outflow: String,
+}
+
+async fn contract_quotas(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    contract: &str,
+    settings: &RateLimits,
+) -> crate::Result<Vec<Quota>> {
+    let mut quotas = Vec::new();
+
+    for path in &settings.paths {
+        let query = serde_json::json!({
+            "get_quotas": { "channel_id": path.channel, "denom": path.denom }
+        });
+
+        let response: QuerySmartContractStateResponse = rpc::abci_query(
+            client,
+            endpoint,
+            "/cosmwasm.wasm.v1.Query/SmartContractState",
+            QuerySmartContractStateRequest {
+                address: contract.to_string(),
+                query_data: serde_json::to_vec(&query)?,
+            },
+        )
+        .await?;
+
+        let rate_limits: Vec<ContractRateLimit> = serde_json::from_slice(&response.data)?;
+        quotas.extend(contract_rate_limits(
+            &path.channel,
+            &path.denom,
+            rate_limits,
+        ));
+    }
+
+    Ok(quotas)
+}
+
+fn contract_rate_limits(
+    channel: &str,
+    denom: &str,
+    rate_limits: Vec<ContractRateLimit>,
+) -> Vec<Quota> {
+    rate_limits
+        .into_iter()
+        .flat_map(|rate_limit| {
+            quotas(
+                channel,
+                denom,
+                &rate_limit.quota.name,
+                amount(&rate_limit.flow.inflow),
+                amount(&rate_limit.flow.outflow),
+                rate_limit
+                    .quota
+                    .channel_value
+                    .as_deref()
+                    .map(amount)
+                    .unwrap_or_default(),
+                rate_limit.quota.max_percentage_send as f64,
+                rate_limit.quota.max_percentage_recv as f64,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotas() {
+        let response = QueryAllRateLimitsResponse {
+            rate_limits: vec![ModuleRateLimit {
+                path: Some(ModulePath {
+                    denom: "ustrd".to_string(),
+                    channel_id: "channel-0".to_string(),
+                }),
+                quota: Some(ModuleQuota {
+                    max_percent_send: "10".to_string(),
+                    max_percent_recv: "0".to_string(),
+                    duration_hours: 24,
+                }),
+                flow: Some(ModuleFlow {
+                    inflow: "20000".to_string(),
+                    outflow: "120000".to_string(),
+                    channel_value: "1000000".to_string(),
+                }),
+            }],
+        };
+
+        let response = QueryAllRateLimitsResponse::decode(response.encode_to_vec().as_slice());
+
+        // The receive quota, of a zero threshold, is left out
+        assert_eq!(
+            module_rate_limits(response.unwrap()),
+            [Quota {
+                channel: "channel-0".to_string(),
+                denom: "ustrd".to_string(),
+                name: "24h".to_string(),
+                direction: "send",
+                flow: 100000.0,
+                threshold: 100000.0,
+            }]
+        );
+
+        let rate_limits = serde_json::from_str(
+            r#"[{
+                "quota": {
+                    "name": "osmo-weekly",
+                    "max_percentage_send": 30,
+                    "max_percentage_recv": 30,
+                    "duration": 604800,
+                    "channel_value": "2000"
+                },
+                "flow": { "inflow": "300", "outflow": "100", "period_end": "1700000000000000000" }
+            }]"#,
+        )
+        .unwrap();
+
+        let quotas = contract_rate_limits("channel-0", "uosmo", rate_limits);
+        assert_eq!(quotas.len(), 2);
+        assert_eq!((quotas[0].direction, quotas[0].flow), ("send", 0.0));
+        assert_eq!(
+            (quotas[1].direction, quotas[1].flow, quotas[1].threshold),
+            ("recv", 200.0, 600.0)
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use ibc_proto::{
+    cosmos::base::query::v1beta1::PageRequest,
+    ibc::core::channel::v1::{QueryPacketCommitmentsRequest, QueryPacketCommitmentsResponse},
+};
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{Chains, Config, Endpoint},
+    expiry,
+    metrics::Metrics,
+    rpc, verify,
+};
+
+/// Commitments of a single page of the query
+const COMMITMENTS_PER_PAGE: u64 = 1000;
+
+/// Disagreement between the packets recorded on a channel and the commitments of its chain
+#[derive(Debug, Default, PartialEq)]
+struct Drift {
+    /// Sequences committed by the chain without a packet recorded
+    missing: Vec<u64>,
+    /// Sequences of packets recorded as pending whose commitment the chain deleted
+    stale: Vec<u64>,
+}
+
+/// Sequence of a packet sent on a channel, and whether it is recorded as pending
+struct Recorded {
+    sequence: u64,
+    pending: bool,
+}
+
+/// Compare the packets recorded with the packet commitments of the monitored chains every
+/// `reconcile.interval_minutes`, refreshing the `ibc_packet_drift` gauge
+///
+/// Commitments without a packet recorded were missed, by the collector being down or a
+/// subscription dropping events, and can be recorded by a backfill of the blocks sending them.
+/// Pending packets without a commitment were acknowledged or timed out unseen, and are marked
+/// relayed with `repair`.
+pub async fn run(pool: SqlitePool, metrics: Metrics, mut config: watch::Receiver<Config>) {
+    let client = reqwest::Client::new();
+
+    loop {
+        let (reconcile, chains) = {
+            let config = config.borrow_and_update();
+            (config.reconcile.clone(), config.chains.clone())
+        };
+
+        let Some(reconcile) = reconcile else {
+            // Idle until a reload enables the reconciliation
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        match reconcile_all(&client, &pool, &metrics, &chains, reconcile.repair).await {
+            Ok((missing, stale)) => {
+                info!("Reconciled the packets, {missing} missing and {stale} stale")
+            }
+            Err(e) => error!("Failed to reconcile the packets: {e}"),
+        }
+
+        tokio::select! {
+            _ = time::sleep(Duration::from_secs(reconcile.interval_minutes * 60)) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Reconcile every channel packets were sent on by a monitored chain, returning the number of
+/// missing and stale packets
+async fn reconcile_all(
+    client: &reqwest::Client,
+    pool: &SqlitePool,
+    metrics: &Metrics,
+    chains: &Chains,
+    repair: bool,
+) -> sqlx::Result<(usize, usize)> {
+    let (mut missing, mut stale) = (0, 0);
+
+    for (chain, port, channel) in sending_channels(pool).await? {
+        let Some(endpoint) = expiry::endpoint(chains, &chain) else {
+            continue;
+        };
+
+        // Recorded first, so that the packets sent meanwhile are committed after the last
+        // sequence recorded rather than missing
+        let recorded = recorded(pool, &chain, &port, &channel).await?;
+
+        let committed = match commitments(client, endpoint, &port, &channel).await {
+            Ok(committed) => committed,
+            Err(e) => {
+                warn!(
+                    chain = %chain,
+                    "Failed to query the packet commitments of {port}/{channel}: {e}"
+                );
+                continue;
+            }
+        };
+
+        let drift = drift(&committed, &recorded);
+
+        metrics.ibc_packet_drift(
+            &chain,
+            &port,
+            &channel,
+            "missing",
+            drift.missing.len() as i64,
+        );
+        metrics.ibc_packet_drift(&chain, &port, &channel, "stale", drift.stale.len() as i64);
+
+        if !drift.missing.is_empty() {
+            warn!(
+                chain = %chain,
+                "{} packets sent on {port}/{channel} are not recorded, sequences {:?}",
+                drift.missing.len(),
+                drift.missing
+            );
+        }
+
+        if repair && !drift.stale.is_empty() {
+            let sequences = drift.stale.iter().copied().collect();
+            let repaired = verify::mark_relayed(pool, &chain, &port, &channel, &sequences).await?;
+
+            info!(
+                chain = %chain,
+                "Marked {repaired} stale packets of {port}/{channel} as relayed"
+            );
+        }
+
+        missing += drift.missing.len();
+        stale += drift.stale.len();
+    }
+
+    Ok((missing, stale))
+}
+
+/// Missing and stale packets of a channel
+///
+/// Only the sequences between the first and the last packets recorded can be missing, the
+/// packets before having been sent before the channel was monitored and those after being sent
+/// since the packets were loaded. Packets past their timeout are left out of the stale ones,
+/// their commitment being deleted by a refund as well.
+fn drift(committed: &BTreeSet<u64>, recorded: &[Recorded]) -> Drift {
+    let sequences: BTreeSet<u64> = recorded.iter().map(|packet| packet.sequence).collect();
+
+    let (Some(&first), Some(&last)) = (sequences.first(), sequences.last()) else {
+        return Drift::default();
+    };
+
+    Drift {
+        missing: committed
+            .range(first..=last)
+            .filter(|sequence| !sequences.contains(sequence))
+            .copied()
+            .collect(),
+        stale: recorded
+            .iter()
+            .filter(|packet| packet.pending && !committed.contains(&packet.sequence))
+            .map(|packet| packet.sequence)
+            .collect(),
+    }
+}
+
+/// Chain, port and channel of every channel packets were sent on
+async fn sending_channels(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String, String)>> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT t.chain, p.src_port, p.src_channel
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url IN ('send_packet', 'timeout_packet')
+        ORDER BY t.chain, p.src_port, p.src_channel
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Packets sent by a chain on a channel, the pending ones being neither relayed nor past their
+/// timeout
+async fn recorded(
+    pool: &SqlitePool,
+    chain: &str,
+    port: &str,
+    channel: &str,
+) -> sqlx::Result<Vec<Recorded>> {
+    let rows: Vec<(i64, bool)> = sqlx::query_as(
+        r#"
+        SELECT
+            p.sequence,
+            p.msg_type_url = 'send_packet' AND p.effected = 0
+              AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE t.chain = ? AND p.src_port = ? AND p.src_channel = ?
+          AND p.msg_type_url IN ('send_packet', 'timeout_packet')
+        "#,
+    )
+    .bind(chain)
+    .bind(port)
+    .bind(channel)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(sequence, pending)| Recorded {
+            sequence: sequence as u64,
+            pending,
+        })
+        .collect())
+}
+
+/// Sequences of the packets committed by the chain on a channel, which are neither
+/// acknowledged nor timed out
+async fn commitments(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    port: &str,
+    channel: &str,
+) -> crate::Result<BTreeSet<u64>> {
+    let mut sequences = BTreeSet::new();
+    let mut key = Vec::new();
+
+    loop {
+        let response: QueryPacketCommitmentsResponse = rpc::abci_query(
+            client,
+            endpoint,
+            "/ibc.core.channel.v1.Query/PacketCommitments",
+            QueryPacketCommitmentsRequest {
+                port_id: port.to_string(),
+                channel_id: channel.to_string(),
+                pagination: Some(PageRequest {
+                    key,
+                    limit: COMMITMENTS_PER_PAGE,
+                    ..Default::default()
+                }),
+            },
+        )
+        .await?;
+
+        sequences.extend(response.commitments.iter().map(|state| state.sequence));
+
+        match response.pagination {
+            Some(page) if !page.next_key.is_empty() => key = page.next_key,
+            _ => return Ok(sequences),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift() {
+        let recorded = |sequence, pending| Recorded { sequence, pending };
+
+        let packets = [
+            recorded(10, false),
+            recorded(11, true),
+            recorded(12, true),
+            recorded(15, true),
+        ];
+
+        // 3 was sent before the channel was monitored, 16 since the packets were loaded
+        let committed = BTreeSet::from([3, 12, 13, 14, 15, 16]);
+
+        assert_eq!(
+            drift(&committed, &packets),
+            Drift {
+                missing: vec![13, 14],
+                stale: vec![11],
+            }
+        );
+
+        assert_eq!(drift(&committed, &[]), Drift::default());
+    }
+}
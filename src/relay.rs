@@ -0,0 +1,197 @@
+use std::{collections::HashSet, time::Duration};
+
+use serde_json::json;
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error};
+
+use crate::{
+    alert::{Alerts, Transition},
+    config::Config,
+    monitor::format_age,
+    webhook::{ChainEvent, EventKind, Webhooks},
+};
+
+/// How often the channels are checked for relay activity
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Source chain, source channel and destination channel of a path
+type PathKey = (String, String, String);
+
+/// Path whose packets were relayed before and are waiting for a relayer
+#[derive(Debug, sqlx::FromRow)]
+struct IdlePath {
+    chain: String,
+    src_channel: String,
+    dst_channel: String,
+    relays: i64,
+    seconds_since_last_relay: i64,
+    last_relayer: Option<String>,
+    waiting_packets: i64,
+}
+
+/// Periodically look for channels which historically had their packets relayed, but had no
+/// successful relay submission within the configured window while packets were sent on them,
+/// and notify those until packets are relayed again
+///
+/// Successful submissions are the effected `MsgRecvPacket`, `MsgAcknowledgement` and
+/// `MsgTimeout` messages of the path, on either chain.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>, webhooks: Webhooks) {
+    let mut interval = time::interval(CHECK_INTERVAL);
+    let mut alerts = Alerts::new(config.borrow().alerts.clone());
+    let mut firing: HashSet<PathKey> = HashSet::new();
+
+    loop {
+        interval.tick().await;
+
+        let settings = config.borrow_and_update().alerts.clone();
+
+        let Some(no_relayer) = settings.no_active_relayer.clone() else {
+            continue;
+        };
+
+        alerts.configure(settings);
+
+        let window_seconds = no_relayer.window_minutes as i64 * 60;
+
+        let paths = match idle_paths(&pool, no_relayer.min_relays).await {
+            Ok(paths) => paths,
+            Err(e) => {
+                error!("Failed to load the relay activity of the channels: {e}");
+                continue;
+            }
+        };
+
+        let mut events = Vec::new();
+        let mut idle = HashSet::new();
+
+        for path in paths {
+            if path.seconds_since_last_relay < window_seconds {
+                continue;
+            }
+
+            let key = (
+                path.chain.clone(),
+                path.src_channel.clone(),
+                path.dst_channel.clone(),
+            );
+            idle.insert(key);
+
+            let transition = alerts.condition(
+                EventKind::NoActiveRelayer,
+                &path.chain,
+                Some(&path.src_channel),
+                true,
+            );
+
+            if transition != Some(Transition::Fired) {
+                continue;
+            }
+
+            let summary = format!(
+                "No successful relay on {} → {} for {}, with {} packets sent since{}",
+                path.src_channel,
+                path.dst_channel,
+                format_age(path.seconds_since_last_relay),
+                path.waiting_packets,
+                match &path.last_relayer {
+                    Some(relayer) => format!(", last relayed by {relayer}"),
+                    None => String::new(),
+                }
+            );
+
+            events.push(ChainEvent::new(
+                EventKind::NoActiveRelayer,
+                &path.chain,
+                Some(&path.src_channel),
+                summary,
+                json!({
+                    "src_channel": path.src_channel,
+                    "dst_channel": path.dst_channel,
+                    "relays": path.relays,
+                    "seconds_since_last_relay": path.seconds_since_last_relay,
+                    "last_relayer": path.last_relayer,
+                    "waiting_packets": path.waiting_packets,
+                }),
+            ));
+        }
+
+        let resolved: Vec<PathKey> = firing.difference(&idle).cloned().collect();
+        firing = idle;
+
+        for (chain, src_channel, dst_channel) in resolved {
+            let transition = alerts.condition(
+                EventKind::NoActiveRelayer,
+                &chain,
+                Some(&src_channel),
+                false,
+            );
+
+            if let Some(Transition::Resolved { firing_seconds }) = transition {
+                let summary = format!(
+                    "Packets are relayed again on {src_channel} → {dst_channel}, {} after the alert fired",
+                    format_age(firing_seconds)
+                );
+
+                events.push(
+                    ChainEvent::new(
+                        EventKind::NoActiveRelayer,
+                        &chain,
+                        Some(&src_channel),
+                        summary,
+                        json!({ "src_channel": src_channel, "dst_channel": dst_channel }),
+                    )
+                    .resolved(firing_seconds),
+                );
+            }
+        }
+
+        if !events.is_empty() {
+            debug!("Notifying {} relayer activity events", events.len());
+            webhooks.announce(events).await;
+        }
+    }
+}
+
+/// Paths with at least `min_relays` successful relay submissions and packets sent since the last
+/// one, along with how long ago it happened and who submitted it
+async fn idle_paths(pool: &SqlitePool, min_relays: i64) -> sqlx::Result<Vec<IdlePath>> {
+    sqlx::query_as(
+        r#"
+        WITH relays AS (
+            SELECT
+                r.src_channel,
+                r.dst_channel,
+                COUNT(*) as relays,
+                MAX(r.id) as last_relay_id,
+                MIN(CAST((strftime('%s', 'now') - strftime('%s', r.created_at)) AS INTEGER)) as seconds_since_last_relay
+            FROM packets r
+            WHERE r.effected = 1
+              AND r.msg_type_url IN (
+                  '/ibc.core.channel.v1.MsgRecvPacket',
+                  '/ibc.core.channel.v1.MsgAcknowledgement',
+                  '/ibc.core.channel.v1.MsgTimeout'
+              )
+            GROUP BY r.src_channel, r.dst_channel
+            HAVING COUNT(*) >= ?
+        )
+        SELECT
+            t.chain,
+            p.src_channel,
+            p.dst_channel,
+            r.relays,
+            r.seconds_since_last_relay,
+            (SELECT l.signer FROM packets l WHERE l.id = r.last_relay_id) as last_relayer,
+            COUNT(*) as waiting_packets
+        FROM relays r
+        JOIN packets p ON p.src_channel = r.src_channel AND p.dst_channel = r.dst_channel
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) < r.seconds_since_last_relay
+        GROUP BY t.chain, p.src_channel, p.dst_channel
+        "#,
+    )
+    .bind(min_relays)
+    .fetch_all(pool)
+    .await
+}
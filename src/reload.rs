@@ -0,0 +1,300 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::future;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tendermint::chain;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+    task::JoinHandle,
+    time,
+};
+use tracing::{error, error_span, info, warn, Instrument};
+
+use crate::{
+    collect,
+    config::{Chains, Config, Endpoint, Overrides},
+    instance::Handle,
+    metrics::Metrics,
+    status::{ConnectionState, Status},
+};
+
+/// Collectors of the monitored chains, started and stopped as the configuration changes
+pub struct Collectors {
+    running: BTreeMap<chain::Id, (Endpoint, JoinHandle<()>)>,
+    pool: SqlitePool,
+    metrics: Metrics,
+    status: Status,
+    config: watch::Receiver<Config>,
+}
+
+/// Changes applied to the collectors
+#[derive(Debug, Default)]
+pub struct ChainChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub restarted: Vec<String>,
+}
+
+impl Collectors {
+    pub fn new(
+        pool: SqlitePool,
+        metrics: Metrics,
+        status: Status,
+        config: watch::Receiver<Config>,
+    ) -> Self {
+        Self {
+            running: BTreeMap::new(),
+            pool,
+            metrics,
+            status,
+            config,
+        }
+    }
+
+    /// Start the collectors of the new chains, stop those of the chains which are gone, and
+    /// restart those whose endpoint changed
+    pub fn sync(&mut self, chains: &Chains) -> ChainChanges {
+        let mut changes = ChainChanges::default();
+
+        let stale: Vec<chain::Id> = self
+            .running
+            .iter()
+            .filter(|(chain_id, (endpoint, _))| chains.endpoints.get(chain_id) != Some(endpoint))
+            .map(|(chain_id, _)| chain_id.clone())
+            .collect();
+
+        for chain_id in stale {
+            self.stop(&chain_id);
+
+            match chains.endpoints.contains_key(&chain_id) {
+                true => changes.restarted.push(chain_id.to_string()),
+                false => changes.removed.push(chain_id.to_string()),
+            }
+        }
+
+        for (chain_id, endpoint) in &chains.endpoints {
+            if self.running.contains_key(chain_id) {
+                continue;
+            }
+
+            self.start(chain_id.clone(), endpoint.clone());
+
+            if !changes.restarted.contains(&chain_id.to_string()) {
+                changes.added.push(chain_id.to_string());
+            }
+        }
+
+        changes
+    }
+
+    fn start(&mut self, chain_id: chain::Id, endpoint: Endpoint) {
+        self.status.register(
+            &chain_id,
+            &endpoint.url.to_string(),
+            endpoint.version_string(),
+        );
+        self.metrics.chainpulse_chains();
+
+        // Disabled and paused chains wait for the admin API to resume them
+        if !endpoint.enabled {
+            self.status.set_state(&chain_id, ConnectionState::Disabled);
+        }
+
+        if !endpoint.enabled || endpoint.start_paused {
+            self.status.control(&chain_id).set_paused(true);
+        }
+
+        // Not nested in the span of the reload starting it
+        let span = error_span!(parent: None, "collect", chain = %chain_id);
+        let task = collect(
+            chain_id.clone(),
+            endpoint.clone(),
+            self.pool.clone(),
+            self.metrics.clone(),
+            self.status.clone(),
+            self.config.clone(),
+        )
+        .instrument(span);
+
+        self.running
+            .insert(chain_id, (endpoint, tokio::spawn(task)));
+    }
+
+    /// Ask every collector to stop once the block it is processing is recorded, and wait for
+    /// them to stop, aborting those still running after the timeout
+    pub async fn drain(&mut self, timeout: Duration) {
+        let running = std::mem::take(&mut self.running);
+
+        for chain_id in running.keys() {
+            self.status.control(chain_id).request_stop();
+        }
+
+        let mut handles: Vec<_> = running.into_values().map(|(_, handle)| handle).collect();
+        let stopped = future::join_all(handles.iter_mut());
+
+        if time::timeout(timeout, stopped).await.is_err() {
+            warn!("Collectors still running after {timeout:?}, aborting them");
+
+            for handle in handles {
+                handle.abort();
+            }
+        }
+    }
+
+    fn stop(&mut self, chain_id: &chain::Id) {
+        if let Some((_, handle)) = self.running.remove(chain_id) {
+            handle.abort();
+            self.status.unregister(chain_id);
+            self.metrics.chainpulse_chain_removed(chain_id);
+        }
+    }
+}
+
+async fn collect(
+    chain_id: chain::Id,
+    endpoint: Endpoint,
+    pool: SqlitePool,
+    metrics: Metrics,
+    status: Status,
+    config: watch::Receiver<Config>,
+) {
+    // Always use the new v0.38-aware collector which handles all versions
+    let version = endpoint.version_string();
+
+    let result = collect::run(
+        chain_id,
+        version,
+        endpoint.nodes(),
+        pool,
+        metrics,
+        status,
+        config,
+    )
+    .await;
+
+    if let Err(e) = result {
+        error!("{e}");
+    }
+}
+
+/// Reload the configuration file on SIGHUP, applying the changes without restarting
+///
+/// Chains are added, removed and reconnected, and the alert and notifier settings replaced,
+/// keeping the state of the firing alerts. The `database`, `metrics` and `performance` sections
+/// are only applied on restart. An invalid file is reported and the current configuration kept.
+///
+/// With `secrets.refresh_minutes`, the credentials of the endpoints are also resolved again on
+/// schedule.
+pub async fn run(path: PathBuf, overrides: Overrides, handle: &mut Handle) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP, the configuration will not be reloaded: {e}");
+            return future::pending().await;
+        }
+    };
+
+    loop {
+        // A reload may change the interval
+        let refresh = handle.config().secrets.refresh_minutes;
+        let refresh = async {
+            match refresh {
+                Some(minutes) => time::sleep(Duration::from_secs(minutes * 60)).await,
+                None => future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            hangup = hangups.recv() => {
+                if hangup.is_none() {
+                    return;
+                }
+
+                info!("SIGHUP received, reloading {}", path.display());
+                reload(&path, &overrides, handle).await;
+            }
+            _ = refresh => refresh_credentials(&path, handle).await,
+        }
+    }
+}
+
+/// Load the configuration file again and apply it
+async fn reload(path: &Path, overrides: &Overrides, handle: &mut Handle) {
+    let mut reloaded = match Config::load(path).await {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            error!("Failed to reload the configuration, keeping the current one: {e}");
+            return;
+        }
+    };
+
+    overrides.apply(&mut reloaded);
+
+    let current = handle.config();
+
+    if !same(&current.database, &reloaded.database) {
+        warn!("The `database` settings changed, restart to apply them");
+    }
+
+    if !same(&current.metrics, &reloaded.metrics) {
+        warn!("The `metrics` settings changed, restart to apply them");
+    }
+
+    if !same(&current.performance, &reloaded.performance) {
+        warn!("The `performance` settings changed, restart to apply them");
+    }
+
+    reloaded.database = current.database;
+    reloaded.metrics = current.metrics;
+    reloaded.performance = current.performance;
+
+    let changes = handle.reconfigure(reloaded);
+
+    info!(
+        added = ?changes.added,
+        removed = ?changes.removed,
+        restarted = ?changes.restarted,
+        "Configuration reloaded"
+    );
+}
+
+/// Resolve the credentials of the endpoints again, and reconnect the chains whose credentials
+/// were rotated, leaving the rest of the configuration as it is
+async fn refresh_credentials(path: &Path, handle: &mut Handle) {
+    info!("Refreshing the credentials of the endpoints");
+
+    let loaded = match Config::load(path).await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            error!("Failed to refresh the credentials, keeping the current ones: {e}");
+            return;
+        }
+    };
+
+    let mut refreshed = handle.config();
+
+    for (chain_id, endpoint) in refreshed.chains.endpoints.iter_mut() {
+        let Some(loaded) = loaded.chains.endpoints.get(chain_id) else {
+            continue;
+        };
+
+        if !endpoint.rotate_credentials(loaded) && endpoint != loaded {
+            warn!("The settings of {chain_id} changed, send SIGHUP to apply them");
+        }
+    }
+
+    let changes = handle.reconfigure(refreshed);
+
+    info!(rotated = ?changes.restarted, "Credentials refreshed");
+}
+
+/// Whether two sections of the configuration are identical
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
@@ -0,0 +1,367 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::Utc;
+use prometheus::{
+    proto::{Metric, MetricFamily, MetricType},
+    Registry,
+};
+use prost::Message;
+use tokio::{sync::watch, time};
+use tracing::{debug, error};
+
+use crate::config::{Config, RemoteWrite};
+
+/// Timeout of the requests made to the receiver
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Series of a single request, as Prometheus sends by default
+const MAX_SERIES_PER_REQUEST: usize = 2000;
+
+/// Length of the literals the payloads are split into, which Snappy encoders do not exceed
+const SNAPPY_LITERAL_LEN: usize = 65536;
+
+/// `WriteRequest` of the remote write protocol, leaving out the metadata
+#[derive(Clone, PartialEq, Message)]
+struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    timeseries: Vec<TimeSeries>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TimeSeries {
+    /// Labels sorted by name, including `__name__`
+    #[prost(message, repeated, tag = "1")]
+    labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    samples: Vec<Sample>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Label {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(string, tag = "2")]
+    value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Sample {
+    #[prost(double, tag = "1")]
+    value: f64,
+    /// Milliseconds since the epoch
+    #[prost(int64, tag = "2")]
+    timestamp: i64,
+}
+
+/// Push the metrics of `registry` to the receiver of `remote_write` every `interval_seconds`
+///
+/// A push which fails is not retried, the series having a gap until the next one.
+pub async fn run(registry: Registry, mut config: watch::Receiver<Config>) {
+    let client = reqwest::Client::new();
+
+    loop {
+        let remote_write = config.borrow_and_update().remote_write.clone();
+
+        let Some(remote_write) = remote_write else {
+            // Idle until a reload configures the receiver
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        match push(&client, &registry, &remote_write).await {
+            Ok(series) => debug!("Pushed {series} series to {}", remote_write.url),
+            Err(e) => error!("Failed to push the metrics to {}: {e}", remote_write.url),
+        }
+
+        tokio::select! {
+            _ = time::sleep(Duration::from_secs(remote_write.interval_seconds)) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn push(
+    client: &reqwest::Client,
+    registry: &Registry,
+    remote_write: &RemoteWrite,
+) -> crate::Result<usize> {
+    let timeseries = timeseries(
+        &registry.gather(),
+        &remote_write.labels,
+        Utc::now().timestamp_millis(),
+    );
+
+    for chunk in timeseries.chunks(MAX_SERIES_PER_REQUEST) {
+        let body = WriteRequest {
+            timeseries: chunk.to_vec(),
+        }
+        .encode_to_vec();
+
+        let mut request = client
+            .post(&remote_write.url)
+            .header("Content-Encoding", "snappy")
+            .header("Content-Type", "application/x-protobuf")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+            .timeout(REQUEST_TIMEOUT)
+            .body(snappy(&body));
+
+        if let Some(username) = &remote_write.username {
+            request = request.basic_auth(username, remote_write.password.as_ref());
+        }
+
+        if let Some(token) = &remote_write.bearer_token {
+            request = request.bearer_auth(token);
+        }
+
+        for (name, value) in &remote_write.headers {
+            request = request.header(name, value);
+        }
+
+        request.send().await?.error_for_status()?;
+    }
+
+    Ok(timeseries.len())
+}
+
+/// Series of the metric families, with a sample at `timestamp` each, as they would be scraped
+///
+/// Histograms and summaries are split into their `_bucket` or quantile, `_sum` and `_count`
+/// series, and the `labels` added to the series which do not have them already.
+fn timeseries(
+    families: &[MetricFamily],
+    labels: &BTreeMap<String, String>,
+    timestamp: i64,
+) -> Vec<TimeSeries> {
+    let mut timeseries = Vec::new();
+
+    for family in families {
+        let name = family.get_name();
+
+        for metric in family.get_metric() {
+            let mut add = |suffix: &str, extra: Option<(&str, String)>, value: f64| {
+                timeseries.push(series(
+                    &format!("{name}{suffix}"),
+                    metric,
+                    extra,
+                    labels,
+                    value,
+                    timestamp,
+                ));
+            };
+
+            match family.get_field_type() {
+                MetricType::COUNTER => add("", None, metric.get_counter().get_value()),
+                MetricType::GAUGE => add("", None, metric.get_gauge().get_value()),
+                MetricType::UNTYPED => add("", None, metric.get_untyped().get_value()),
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+
+                    for bucket in histogram.get_bucket() {
+                        add(
+                            "_bucket",
+                            Some(("le", bucket.get_upper_bound().to_string())),
+                            bucket.get_cumulative_count() as f64,
+                        );
+                    }
+
+                    let count = histogram.get_sample_count() as f64;
+                    add("_bucket", Some(("le", "+Inf".to_string())), count);
+                    add("_sum", None, histogram.get_sample_sum());
+                    add("_count", None, count);
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+
+                    for quantile in summary.get_quantile() {
+                        add(
+                            "",
+                            Some(("quantile", quantile.get_quantile().to_string())),
+                            quantile.get_value(),
+                        );
+                    }
+
+                    add("_sum", None, summary.get_sample_sum());
+                    add("_count", None, summary.get_sample_count() as f64);
+                }
+            }
+        }
+    }
+
+    timeseries
+}
+
+fn series(
+    name: &str,
+    metric: &Metric,
+    extra: Option<(&str, String)>,
+    labels: &BTreeMap<String, String>,
+    value: f64,
+    timestamp: i64,
+) -> TimeSeries {
+    let mut series_labels: Vec<Label> = metric
+        .get_label()
+        .iter()
+        .map(|pair| Label {
+            name: pair.get_name().to_string(),
+            value: pair.get_value().to_string(),
+        })
+        .chain(extra.map(|(name, value)| Label {
+            name: name.to_string(),
+            value,
+        }))
+        .collect();
+
+    for (name, value) in labels {
+        if !series_labels.iter().any(|label| &label.name == name) {
+            series_labels.push(Label {
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    series_labels.push(Label {
+        name: "__name__".to_string(),
+        value: name.to_string(),
+    });
+
+    // Receivers expect the labels sorted, and treat an empty value as a missing label
+    series_labels.retain(|label| !label.value.is_empty());
+    series_labels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    TimeSeries {
+        labels: series_labels,
+        samples: vec![Sample { value, timestamp }],
+    }
+}
+
+/// Data in the block format of Snappy, as literals only
+///
+/// Every Snappy decoder reads such a block, which is not compressed, but spares a dependency
+/// for payloads which are small to begin with.
+fn snappy(data: &[u8]) -> Vec<u8> {
+    let mut block = Vec::with_capacity(data.len() + data.len() / SNAPPY_LITERAL_LEN * 3 + 8);
+
+    // Preamble: the length of the data as a varint
+    let mut len = data.len() as u64;
+    while len >= 0x80 {
+        block.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    block.push(len as u8);
+
+    for literal in data.chunks(SNAPPY_LITERAL_LEN) {
+        let n = literal.len() - 1;
+
+        // Lengths below 60 fit in the tag, longer ones follow it on one or two bytes
+        match n {
+            0..=59 => block.push((n as u8) << 2),
+            60..=0xff => block.extend([60 << 2, n as u8]),
+            _ => block.extend([61 << 2, n as u8, (n >> 8) as u8]),
+        }
+
+        block.extend_from_slice(literal);
+    }
+
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{register_histogram_with_registry, register_int_counter_vec_with_registry};
+
+    use super::*;
+
+    #[test]
+    fn test_snappy() {
+        assert_eq!(snappy(b""), vec![0]);
+        assert_eq!(snappy(b"hello"), [&[5, 4 << 2][..], b"hello"].concat());
+
+        let data = vec![7; 300];
+        let block = snappy(&data);
+        assert_eq!(block[..5], [0xac, 0x02, 61 << 2, 0x2b, 0x01]);
+        assert_eq!(block[5..], data[..]);
+
+        let data = vec![7; SNAPPY_LITERAL_LEN + 1];
+        let block = snappy(&data);
+        assert_eq!(block[..3], [0x81, 0x80, 0x04]);
+        assert_eq!(block[3..6], [61 << 2, 0xff, 0xff]);
+        assert_eq!(block[6 + SNAPPY_LITERAL_LEN..][..1], [0]);
+    }
+
+    #[test]
+    fn test_timeseries() {
+        let registry = Registry::new();
+
+        let counter = register_int_counter_vec_with_registry!(
+            "chainpulse_txs",
+            "The number of txs processed",
+            &["chain_id", "memo"],
+            registry
+        )
+        .unwrap();
+        counter.with_label_values(&["osmosis-1", ""]).inc_by(3);
+
+        let histogram = register_histogram_with_registry!(
+            "relay_seconds",
+            "Time to relay",
+            vec![1.0, 5.0],
+            registry
+        )
+        .unwrap();
+        histogram.observe(2.0);
+
+        let labels = BTreeMap::from([
+            ("chain_id".to_string(), "external".to_string()),
+            ("instance".to_string(), "pod-0".to_string()),
+        ]);
+
+        let series = timeseries(&registry.gather(), &labels, 1000);
+        let names: Vec<Vec<(&str, &str)>> = series
+            .iter()
+            .map(|series| {
+                series
+                    .labels
+                    .iter()
+                    .map(|label| (label.name.as_str(), label.value.as_str()))
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            names[0],
+            [
+                ("__name__", "chainpulse_txs"),
+                ("chain_id", "osmosis-1"),
+                ("instance", "pod-0")
+            ]
+        );
+        assert_eq!(
+            series[0].samples,
+            [Sample {
+                value: 3.0,
+                timestamp: 1000
+            }]
+        );
+
+        assert_eq!(names.len(), 6);
+        assert_eq!(
+            names[1][..2],
+            [
+                ("__name__", "relay_seconds_bucket"),
+                ("chain_id", "external")
+            ]
+        );
+        assert_eq!(names[1][3], ("le", "1"));
+        assert_eq!(names[3][3], ("le", "+Inf"));
+        assert_eq!(series[2].samples[0].value, 1.0);
+        assert_eq!(names[4][0], ("__name__", "relay_seconds_sum"));
+        assert_eq!(series[5].samples[0].value, 1.0);
+    }
+}
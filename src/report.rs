@@ -0,0 +1,598 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Datelike, Duration as Days, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::{fs, sync::watch, time};
+use tracing::{error, info};
+
+use crate::config::{Config, ReportPeriod, StuckPackets};
+
+/// Interval between the checks for a period whose report is missing
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// SLA report of the packets sent during a period
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Name of the period, such as `2026-09`, `2026-W41` or `2026-10-15`
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub generated_at: String,
+    pub channels: Vec<ChannelReport>,
+    pub relayers: Vec<RelayerReport>,
+}
+
+/// Packets sent by a chain on a channel during the period
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ChannelReport {
+    pub chain_id: String,
+    pub src_channel: String,
+    pub dst_channel: String,
+    pub packets: u64,
+    pub acknowledged: u64,
+    pub timed_out: u64,
+    pub pending: u64,
+    /// Share of the packets acknowledged among those acknowledged or timed out
+    pub success_rate: Option<f64>,
+    /// Share of the packets relayed within their stuck threshold
+    pub within_threshold_rate: Option<f64>,
+    /// Packets relayed past their stuck threshold, timed out, or still pending past it
+    pub stuck_incidents: u64,
+    pub latency: Latency,
+}
+
+/// Packets of the period relayed by a relayer
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RelayerReport {
+    pub signer: String,
+    /// Packets relayed, by the first effected `MsgRecvPacket` or else the acknowledgement
+    pub relayed: u64,
+    /// `MsgRecvPacket` of the relayer which were not effected, another relayer being first
+    pub redundant: u64,
+    /// Share of the `MsgRecvPacket` of the relayer which were effected
+    pub success_rate: Option<f64>,
+    /// Share of the packets relayed within their stuck threshold
+    pub within_threshold_rate: Option<f64>,
+    pub latency: Latency,
+}
+
+/// Percentiles of the relay latency of the packets, in seconds
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Latency {
+    pub count: usize,
+    pub p50_seconds: Option<i64>,
+    pub p90_seconds: Option<i64>,
+    pub p99_seconds: Option<i64>,
+}
+
+impl Latency {
+    fn new(mut samples: Vec<i64>) -> Self {
+        samples.sort_unstable();
+
+        Self {
+            count: samples.len(),
+            p50_seconds: percentile(&samples, 50.0),
+            p90_seconds: percentile(&samples, 90.0),
+            p99_seconds: percentile(&samples, 99.0),
+        }
+    }
+}
+
+/// Packet sent during the period, with its relay
+#[derive(Debug, sqlx::FromRow)]
+struct PacketRow {
+    chain: String,
+    src_channel: String,
+    dst_channel: String,
+    timed_out: bool,
+    effected: bool,
+    sent_at: i64,
+    relayed_at: Option<i64>,
+    relayer: Option<String>,
+    threshold_seconds: i64,
+}
+
+/// `MsgRecvPacket` of a relayer during the period
+#[derive(Debug, sqlx::FromRow)]
+struct RecvRow {
+    signer: String,
+    effected: i64,
+    redundant: i64,
+}
+
+/// Render the report of the last complete period every `reports.period`, unless already on
+/// disk, writing it to `reports.directory` as both `<name>.json` and `<name>.html`
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    loop {
+        let (reports, thresholds) = {
+            let config = config.borrow_and_update();
+            (config.reports.clone(), config.stuck_packets.clone())
+        };
+
+        let Some(reports) = reports else {
+            // Idle until a reload configures the reports
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        let now = Utc::now();
+        let (name, from, to) = last_period(reports.period, now.date_naive());
+        let path = reports.directory.join(format!("{name}.json"));
+
+        if !fs::try_exists(&path).await.unwrap_or(false) {
+            match generate(&pool, &thresholds, name, from, to, now).await {
+                Ok(report) => match write(&reports.directory, &report).await {
+                    Ok(()) => info!("Rendered the SLA report {}", report.name),
+                    Err(e) => error!("Failed to write the SLA report {}: {e}", report.name),
+                },
+                Err(e) => error!("Failed to render the SLA report: {e}"),
+            }
+        }
+
+        tokio::select! {
+            _ = time::sleep(CHECK_INTERVAL) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Name and bounds of the last period complete at the given date, the end being excluded
+fn last_period(period: ReportPeriod, today: NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    match period {
+        ReportPeriod::Daily => {
+            let from = today - Days::days(1);
+            (from.format("%Y-%m-%d").to_string(), from, today)
+        }
+        ReportPeriod::Weekly => {
+            let to = today - Days::days(today.weekday().num_days_from_monday() as i64);
+            let from = to - Days::days(7);
+            let week = from.iso_week();
+            (format!("{}-W{:02}", week.year(), week.week()), from, to)
+        }
+        ReportPeriod::Monthly => {
+            let to = today.with_day(1).unwrap_or(today);
+            let from = (to - Days::days(1)).with_day(1).unwrap_or(to);
+            (from.format("%Y-%m").to_string(), from, to)
+        }
+    }
+}
+
+/// Report of the packets sent between `from` and `to`, the pending ones being stuck when past
+/// their threshold at `now`
+pub async fn generate(
+    pool: &SqlitePool,
+    thresholds: &StuckPackets,
+    name: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    now: DateTime<Utc>,
+) -> sqlx::Result<Report> {
+    let timestamp = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let (from, to) = (timestamp(from), timestamp(to));
+
+    // Latency runs from the send_packet to the first effected MsgRecvPacket on the
+    // destination chain, or to the acknowledgement when the destination is not monitored,
+    // and the relayer is the signer of either
+    let query = format!(
+        r#"
+        SELECT
+            t.chain,
+            p.src_channel,
+            p.dst_channel,
+            p.msg_type_url = 'timeout_packet' as timed_out,
+            p.effected,
+            CAST(strftime('%s', p.created_at) AS INTEGER) as sent_at,
+            CAST(strftime('%s', COALESCE(r.created_at, a.created_at)) AS INTEGER) as relayed_at,
+            COALESCE(r.signer,
+                (SELECT k.signer FROM packets k
+                 WHERE k.tx_id = p.effected_tx AND k.src_channel = p.src_channel
+                   AND k.sequence = p.sequence
+                   AND k.msg_type_url = '/ibc.core.channel.v1.MsgAcknowledgement'
+                 LIMIT 1)
+            ) as relayer,
+            {} as threshold_seconds
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        LEFT JOIN txs a ON p.effected_tx = a.id
+        LEFT JOIN packets r ON r.id = (
+            SELECT r.id FROM packets r
+            WHERE r.src_channel = p.src_channel AND r.dst_channel = p.dst_channel
+              AND r.sequence = p.sequence AND r.effected = 1
+              AND r.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+            ORDER BY r.created_at
+            LIMIT 1
+        )
+        WHERE p.msg_type_url IN ('send_packet', 'timeout_packet')
+          AND p.created_at >= datetime(?, 'unixepoch')
+          AND p.created_at < datetime(?, 'unixepoch')
+        "#,
+        thresholds.sql("t.chain", "p.src_channel")
+    );
+
+    let packets = sqlx::query_as::<_, PacketRow>(&query)
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(pool)
+        .await?;
+
+    let recvs = sqlx::query_as::<_, RecvRow>(
+        r#"
+        SELECT
+            p.signer,
+            SUM(p.effected = 1) as effected,
+            SUM(p.effected = 0) as redundant
+        FROM packets p
+        WHERE p.msg_type_url = '/ibc.core.channel.v1.MsgRecvPacket'
+          AND p.signer IS NOT NULL
+          AND p.created_at >= datetime(?, 'unixepoch')
+          AND p.created_at < datetime(?, 'unixepoch')
+        GROUP BY p.signer
+        "#,
+    )
+    .bind(from.timestamp())
+    .bind(to.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    let (channels, relayers) = aggregate(packets, recvs, now.timestamp());
+    let rfc3339 = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    Ok(Report {
+        name,
+        from: rfc3339(from),
+        to: rfc3339(to),
+        generated_at: rfc3339(now),
+        channels,
+        relayers,
+    })
+}
+
+/// Reports of the channels and relayers of the packets, ordered by chain and channel, and by
+/// the number of packets relayed
+fn aggregate(
+    packets: Vec<PacketRow>,
+    recvs: Vec<RecvRow>,
+    now: i64,
+) -> (Vec<ChannelReport>, Vec<RelayerReport>) {
+    #[derive(Default)]
+    struct Relayed {
+        latencies: Vec<i64>,
+        within_threshold: u64,
+    }
+
+    let mut channels: BTreeMap<(String, String, String), (ChannelReport, Relayed)> =
+        BTreeMap::new();
+    let mut relayers: BTreeMap<String, (RelayerReport, Relayed)> = BTreeMap::new();
+
+    for packet in packets {
+        let key = (packet.chain, packet.src_channel, packet.dst_channel);
+        let (channel, relayed) = channels.entry(key.clone()).or_insert_with(|| {
+            let channel = ChannelReport {
+                chain_id: key.0,
+                src_channel: key.1,
+                dst_channel: key.2,
+                ..Default::default()
+            };
+            (channel, Relayed::default())
+        });
+
+        channel.packets += 1;
+
+        if packet.timed_out {
+            channel.timed_out += 1;
+            channel.stuck_incidents += 1;
+            continue;
+        }
+
+        if !packet.effected {
+            channel.pending += 1;
+            if now - packet.sent_at > packet.threshold_seconds {
+                channel.stuck_incidents += 1;
+            }
+            continue;
+        }
+
+        channel.acknowledged += 1;
+
+        let latency = packet
+            .relayed_at
+            .map(|relayed_at| relayed_at - packet.sent_at)
+            .filter(|latency| *latency >= 0);
+
+        let Some(latency) = latency else {
+            continue;
+        };
+
+        let within_threshold = latency <= packet.threshold_seconds;
+        if !within_threshold {
+            channel.stuck_incidents += 1;
+        }
+
+        relayed.latencies.push(latency);
+        relayed.within_threshold += within_threshold as u64;
+
+        if let Some(signer) = packet.relayer {
+            let (relayer, relayed) = relayers.entry(signer.clone()).or_insert_with(|| {
+                let relayer = RelayerReport {
+                    signer,
+                    ..Default::default()
+                };
+                (relayer, Relayed::default())
+            });
+
+            relayer.relayed += 1;
+            relayed.latencies.push(latency);
+            relayed.within_threshold += within_threshold as u64;
+        }
+    }
+
+    for recv in recvs {
+        let (relayer, _) = relayers.entry(recv.signer.clone()).or_insert_with(|| {
+            let relayer = RelayerReport {
+                signer: recv.signer.clone(),
+                ..Default::default()
+            };
+            (relayer, Relayed::default())
+        });
+
+        relayer.redundant = recv.redundant as u64;
+        relayer.success_rate = rate(
+            recv.effected as u64,
+            (recv.effected + recv.redundant) as u64,
+        );
+    }
+
+    let channels = channels
+        .into_values()
+        .map(|(mut channel, relayed)| {
+            channel.success_rate = rate(
+                channel.acknowledged,
+                channel.acknowledged + channel.timed_out,
+            );
+            channel.within_threshold_rate =
+                rate(relayed.within_threshold, relayed.latencies.len() as u64);
+            channel.latency = Latency::new(relayed.latencies);
+            channel
+        })
+        .collect();
+
+    let mut relayers: Vec<RelayerReport> = relayers
+        .into_values()
+        .map(|(mut relayer, relayed)| {
+            relayer.within_threshold_rate =
+                rate(relayed.within_threshold, relayed.latencies.len() as u64);
+            relayer.latency = Latency::new(relayed.latencies);
+            relayer
+        })
+        .collect();
+
+    relayers.sort_by(|a, b| b.relayed.cmp(&a.relayed).then(a.signer.cmp(&b.signer)));
+
+    (channels, relayers)
+}
+
+fn rate(count: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| count as f64 / total as f64)
+}
+
+fn percentile(sorted: &[i64], pct: f64) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Write the report to the directory as JSON and HTML
+async fn write(directory: &Path, report: &Report) -> crate::Result<()> {
+    fs::create_dir_all(directory).await?;
+
+    let path =
+        |extension: &str| -> PathBuf { directory.join(format!("{}.{extension}", report.name)) };
+
+    // HTML first, so that a report whose JSON is on disk is complete
+    fs::write(path("html"), html(report)).await?;
+    fs::write(path("json"), serde_json::to_vec_pretty(report)?).await?;
+
+    Ok(())
+}
+
+/// Render the report as a standalone HTML page
+pub fn html(report: &Report) -> String {
+    let rate = |rate: Option<f64>| {
+        rate.map(|rate| format!("{:.2}%", rate * 100.0))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let seconds = |seconds: Option<i64>| {
+        seconds
+            .map(|seconds| format!("{seconds}s"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    let mut page = String::new();
+
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Chainpulse SLA report {name}</title>\n\
+         <style>body {{ font-family: sans-serif; }} \
+         table {{ border-collapse: collapse; margin-bottom: 2em; }} \
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+         th:first-child, td:first-child {{ text-align: left; }}</style>\n\
+         </head>\n<body>\n<h1>SLA report {name}</h1>\n\
+         <p>Packets sent from {from} to {to}, generated at {generated_at}</p>\n",
+        name = escape(&report.name),
+        from = escape(&report.from),
+        to = escape(&report.to),
+        generated_at = escape(&report.generated_at),
+    );
+
+    page.push_str(
+        "<h2>Channels</h2>\n<table>\n<tr><th>Channel</th><th>Packets</th>\
+         <th>Acknowledged</th><th>Timed out</th><th>Pending</th><th>Success rate</th>\
+         <th>Within threshold</th><th>Stuck incidents</th><th>p50</th><th>p90</th>\
+         <th>p99</th></tr>\n",
+    );
+
+    for channel in &report.channels {
+        let _ = writeln!(
+            page,
+            "<tr><td>{} {} &rarr; {}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&channel.chain_id),
+            escape(&channel.src_channel),
+            escape(&channel.dst_channel),
+            channel.packets,
+            channel.acknowledged,
+            channel.timed_out,
+            channel.pending,
+            rate(channel.success_rate),
+            rate(channel.within_threshold_rate),
+            channel.stuck_incidents,
+            seconds(channel.latency.p50_seconds),
+            seconds(channel.latency.p90_seconds),
+            seconds(channel.latency.p99_seconds),
+        );
+    }
+
+    page.push_str(
+        "</table>\n<h2>Relayers</h2>\n<table>\n<tr><th>Relayer</th><th>Relayed</th>\
+         <th>Redundant</th><th>Success rate</th><th>Within threshold</th><th>p50</th>\
+         <th>p90</th><th>p99</th></tr>\n",
+    );
+
+    for relayer in &report.relayers {
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td>{}</td></tr>",
+            escape(&relayer.signer),
+            relayer.relayed,
+            relayer.redundant,
+            rate(relayer.success_rate),
+            rate(relayer.within_threshold_rate),
+            seconds(relayer.latency.p50_seconds),
+            seconds(relayer.latency.p90_seconds),
+            seconds(relayer.latency.p99_seconds),
+        );
+    }
+
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_period() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2026, 1, 7);
+
+        assert_eq!(
+            last_period(ReportPeriod::Monthly, today),
+            ("2025-12".to_string(), date(2025, 12, 1), date(2026, 1, 1))
+        );
+        assert_eq!(
+            last_period(ReportPeriod::Weekly, today),
+            ("2026-W01".to_string(), date(2025, 12, 29), date(2026, 1, 5))
+        );
+        assert_eq!(
+            last_period(ReportPeriod::Daily, today),
+            ("2026-01-06".to_string(), date(2026, 1, 6), today)
+        );
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let packet =
+            |timed_out, effected, relayed_at: Option<i64>, relayer: Option<&str>| PacketRow {
+                chain: "osmosis-1".to_string(),
+                src_channel: "channel-0".to_string(),
+                dst_channel: "channel-141".to_string(),
+                timed_out,
+                effected,
+                sent_at: 1000,
+                relayed_at,
+                relayer: relayer.map(str::to_string),
+                threshold_seconds: 60,
+            };
+
+        let packets = vec![
+            packet(false, true, Some(1010), Some("osmo1a")),
+            packet(false, true, Some(1100), Some("osmo1b")),
+            packet(false, true, Some(1020), Some("osmo1a")),
+            packet(true, true, Some(2000), None),
+            packet(false, false, None, None),
+        ];
+
+        let recvs = vec![RecvRow {
+            signer: "osmo1b".to_string(),
+            effected: 1,
+            redundant: 3,
+        }];
+
+        let (channels, relayers) = aggregate(packets, recvs, 1030);
+
+        assert_eq!(
+            channels,
+            vec![ChannelReport {
+                chain_id: "osmosis-1".to_string(),
+                src_channel: "channel-0".to_string(),
+                dst_channel: "channel-141".to_string(),
+                packets: 5,
+                acknowledged: 3,
+                timed_out: 1,
+                pending: 1,
+                success_rate: Some(0.75),
+                within_threshold_rate: Some(2.0 / 3.0),
+                // Relayed past the threshold and timed out, the pending one being within it
+                stuck_incidents: 2,
+                latency: Latency {
+                    count: 3,
+                    p50_seconds: Some(20),
+                    p90_seconds: Some(100),
+                    p99_seconds: Some(100),
+                },
+            }]
+        );
+
+        assert_eq!(relayers[0].signer, "osmo1a");
+        assert_eq!(relayers[0].relayed, 2);
+        assert_eq!(relayers[0].success_rate, None);
+        assert_eq!(relayers[1].signer, "osmo1b");
+        assert_eq!(relayers[1].redundant, 3);
+        assert_eq!(relayers[1].success_rate, Some(0.25));
+        assert_eq!(relayers[1].within_threshold_rate, Some(0.0));
+    }
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use tokio::time;
+use tracing::{debug, error, info};
+
+/// How often the daily rollups of recent days are refreshed
+pub const ROLLUP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Number of past days refreshed on every run, on top of the current one,
+/// so that packets relayed after midnight are accounted for on the day they were sent
+pub const REFRESH_DAYS: i64 = 2;
+
+/// Periodically aggregate packets into the `daily_stats` and `daily_volume` tables
+///
+/// The first run rebuilds every day, later runs only the most recent ones.
+pub async fn run(pool: SqlitePool) {
+    let mut interval = time::interval(ROLLUP_INTERVAL);
+    let mut since = None;
+
+    loop {
+        interval.tick().await;
+
+        match refresh(&pool, since).await {
+            Ok(days) if since.is_none() => info!("Rolled up packet statistics for {days} days"),
+            Ok(days) => debug!("Refreshed packet statistics for {days} days"),
+            Err(e) => {
+                error!("Failed to roll up packet statistics: {e}");
+                continue;
+            }
+        }
+
+        since = Some(REFRESH_DAYS);
+    }
+}
+
+/// Recompute the rollups of the last `days` days and of the current one,
+/// or of every day when `days` is `None`, returning the number of days rolled up
+pub async fn refresh(pool: &SqlitePool, days: Option<i64>) -> sqlx::Result<i64> {
+    // Days are compared as `YYYY-MM-DD` strings, which sort chronologically
+    let since = match days {
+        Some(days) => {
+            sqlx::query_scalar::<_, String>("SELECT date('now', '-' || ? || ' days')")
+                .bind(days)
+                .fetch_one(pool)
+                .await?
+        }
+        None => String::new(),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM daily_stats WHERE day >= ?")
+        .bind(&since)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM daily_volume WHERE day >= ?")
+        .bind(&since)
+        .execute(&mut *tx)
+        .await?;
+
+    // Acknowledged packets keep their `send_packet` type and are marked effected,
+    // relayed timeouts are turned into `timeout_packet`
+    let stats = r#"
+        INSERT INTO daily_stats (
+            day, chain, src_channel, dst_channel,
+            packets, acknowledged, timed_out, latency_seconds_sum, latency_count
+        )
+        SELECT
+            date(p.created_at) as day,
+            t.chain,
+            p.src_channel,
+            p.dst_channel,
+            COUNT(*),
+            SUM(p.msg_type_url = 'send_packet' AND p.effected = 1),
+            SUM(p.msg_type_url = 'timeout_packet'),
+            COALESCE(SUM(
+                CASE WHEN p.msg_type_url = 'send_packet' AND a.id IS NOT NULL
+                    THEN MAX(strftime('%s', a.created_at) - strftime('%s', p.created_at), 0)
+                END
+            ), 0),
+            COUNT(CASE WHEN p.msg_type_url = 'send_packet' THEN a.id END)
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        LEFT JOIN txs a ON p.effected_tx = a.id
+        WHERE p.msg_type_url IN ('send_packet', 'timeout_packet')
+          AND p.created_at >= ?
+        GROUP BY day, t.chain, p.src_channel, p.dst_channel
+    "#;
+
+    sqlx::query(stats).bind(&since).execute(&mut *tx).await?;
+
+    // Amounts do not fit in SQLite integers, so they are summed here
+    let volumes = sqlx::query_as::<_, (String, String, String, String, i64, String)>(
+        r#"
+        SELECT
+            date(p.created_at) as day,
+            t.chain,
+            p.src_channel,
+            p.denom,
+            COUNT(*),
+            GROUP_CONCAT(p.amount)
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url IN ('send_packet', 'timeout_packet')
+          AND p.denom IS NOT NULL
+          AND p.amount IS NOT NULL
+          AND p.created_at >= ?
+        GROUP BY day, t.chain, p.src_channel, p.denom
+        "#,
+    )
+    .bind(&since)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (day, chain, src_channel, denom, packets, amounts) in volumes {
+        sqlx::query(
+            r#"
+            INSERT INTO daily_volume (day, chain, src_channel, denom, packets, amount)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(day)
+        .bind(chain)
+        .bind(src_channel)
+        .bind(denom)
+        .bind(packets)
+        .bind(sum_amounts(&amounts))
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let days =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(DISTINCT day) FROM daily_stats WHERE day >= ?")
+            .bind(&since)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    tx.commit().await?;
+
+    Ok(days)
+}
+
+/// Sum comma separated token amounts without losing precision, skipping non-integer amounts
+pub fn sum_amounts(amounts: &str) -> String {
+    amounts
+        .split(',')
+        .filter_map(|amount| amount.trim().parse::<u128>().ok())
+        .fold(0u128, u128::saturating_add)
+        .to_string()
+}
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use ibc_proto::{
+    google::protobuf::Any,
+    ibc::{
+        core::client::v1::{QueryClientStateRequest, QueryClientStateResponse},
+        lightclients::tendermint::v1::ClientState,
+    },
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use tendermint_rpc::WebSocketClientUrl;
+
+use crate::{config::Endpoint, simple_auth_client::AuthMethod};
+
+/// Timeout of a query to the RPC endpoint of a chain
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const TENDERMINT_CLIENT_STATE: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+
+/// GET a route of the CometBFT RPC server behind an endpoint, returning the JSON-RPC result
+pub async fn get<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    route: &str,
+) -> crate::Result<T> {
+    get_node(client, &endpoint.url, &endpoint.auth, route).await
+}
+
+/// GET a route of the CometBFT RPC server behind a WebSocket URL, returning the JSON-RPC result
+pub async fn get_node<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &WebSocketClientUrl,
+    auth: &AuthMethod,
+    route: &str,
+) -> crate::Result<T> {
+    #[derive(Deserialize)]
+    struct Response<T> {
+        result: Option<T>,
+        error: Option<serde_json::Value>,
+    }
+
+    let url = rpc_url(&url.to_string()).ok_or_else(|| format!("no RPC URL for {url}"))?;
+
+    let request = client.get(format!("{url}{route}")).timeout(QUERY_TIMEOUT);
+    let request = authenticate(request, auth);
+
+    // The URL may hold credentials
+    let response: Response<T> = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.without_url())?
+        .json()
+        .await
+        .map_err(|e| e.without_url())?;
+
+    match (response.result, response.error) {
+        (Some(result), _) => Ok(result),
+        (None, error) => Err(format!("RPC error: {}", error.unwrap_or_default()).into()),
+    }
+}
+
+/// Earliest and latest heights the node behind an endpoint can serve
+pub async fn available_heights(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+) -> crate::Result<(u64, u64)> {
+    #[derive(Deserialize)]
+    struct NodeStatus {
+        sync_info: SyncInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct SyncInfo {
+        earliest_block_height: String,
+        latest_block_height: String,
+    }
+
+    let status: NodeStatus = get(client, endpoint, "/status").await?;
+
+    Ok((
+        status.sync_info.earliest_block_height.parse()?,
+        status.sync_info.latest_block_height.parse()?,
+    ))
+}
+
+/// CometBFT version the node behind a WebSocket URL reports, such as `0.38.12`
+pub async fn comet_version(
+    client: &reqwest::Client,
+    url: &WebSocketClientUrl,
+    auth: &AuthMethod,
+) -> crate::Result<String> {
+    #[derive(Deserialize)]
+    struct NodeStatus {
+        node_info: NodeInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct NodeInfo {
+        version: String,
+    }
+
+    let status: NodeStatus = get_node(client, url, auth, "/status").await?;
+
+    Ok(status.node_info.version)
+}
+
+/// Major and minor components of a CometBFT version, such as `0.38` for `v0.38.12`, which
+/// `comet_version` is compared on
+///
+/// From 1.0, the minor versions of a major one share their protocol, `1.1.2` being `1.0`.
+pub fn minor_version(version: &str) -> String {
+    let mut components = version.trim_start_matches('v').splitn(3, '.');
+    let major = components.next().unwrap_or_default();
+
+    match components.next() {
+        Some(_) if major != "0" => format!("{major}.0"),
+        Some(minor) => format!("{major}.{minor}"),
+        None => major.to_string(),
+    }
+}
+
+/// Add the credentials of an endpoint to a request to its RPC server
+pub fn authenticate(
+    request: reqwest::RequestBuilder,
+    auth: &AuthMethod,
+) -> reqwest::RequestBuilder {
+    match auth {
+        AuthMethod::None => request,
+        AuthMethod::Basic { username, password } => request.basic_auth(username, Some(password)),
+        AuthMethod::Bearer { token } => request.bearer_auth(token),
+        AuthMethod::ApiKey { header_name, key } => request.header(header_name, key),
+    }
+}
+
+/// Query a gRPC service of the application through the `abci_query` route
+pub async fn abci_query<Response: prost::Message + Default>(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    path: &str,
+    request: impl prost::Message,
+) -> crate::Result<Response> {
+    #[derive(Deserialize)]
+    struct AbciQuery {
+        response: AbciResponse,
+    }
+
+    #[derive(Deserialize)]
+    struct AbciResponse {
+        code: u32,
+        #[serde(default)]
+        log: String,
+        #[serde(default)]
+        value: Option<String>,
+    }
+
+    let data = subtle_encoding::hex::encode(request.encode_to_vec());
+    let route = format!(
+        "/abci_query?path=\"{path}\"&data=0x{}",
+        String::from_utf8_lossy(&data)
+    );
+
+    let query: AbciQuery = get(client, endpoint, &route).await?;
+    let response = query.response;
+
+    if response.code != 0 {
+        return Err(format!(
+            "{path} failed with code {}: {}",
+            response.code, response.log
+        )
+        .into());
+    }
+
+    let value = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        response.value.unwrap_or_default(),
+    )?;
+
+    Ok(Response::decode(value.as_slice())?)
+}
+
+/// Tendermint state of a light client
+pub async fn client_state(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    client_id: &str,
+) -> crate::Result<ClientState> {
+    let response: QueryClientStateResponse = abci_query(
+        client,
+        endpoint,
+        "/ibc.core.client.v1.Query/ClientState",
+        QueryClientStateRequest {
+            client_id: client_id.to_string(),
+        },
+    )
+    .await?;
+
+    decode_any(response.client_state, TENDERMINT_CLIENT_STATE)
+}
+
+/// Decode a protobuf `Any` of the expected type
+pub fn decode_any<T: prost::Message + Default>(
+    any: Option<Any>,
+    type_url: &str,
+) -> crate::Result<T> {
+    let any = any.ok_or("missing state")?;
+
+    if any.type_url != type_url {
+        return Err(format!("unsupported light client state {}", any.type_url).into());
+    }
+
+    Ok(T::decode(any.value.as_slice())?)
+}
+
+/// HTTP URL of the RPC server behind a WebSocket endpoint, such as
+/// `https://rpc.example.com` for `wss://rpc.example.com/websocket`
+pub fn rpc_url(websocket_url: &str) -> Option<String> {
+    let mut url = url::Url::parse(websocket_url).ok()?;
+
+    let scheme = match url.scheme() {
+        "wss" => "https",
+        _ => "http",
+    };
+    url.set_scheme(scheme).ok()?;
+
+    let path = url.path().trim_end_matches('/');
+    let path = path.strip_suffix("/websocket").unwrap_or(path).to_string();
+    url.set_path(&path);
+
+    Some(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// WebSocket URL of an RPC server, such as `wss://rpc.example.com` for
+/// `https://rpc.example.com`, which `rpc_url` maps back
+pub fn websocket_url(rpc_url: &str) -> String {
+    if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        rpc_url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_url() {
+        assert_eq!(
+            rpc_url("wss://rpc.example.com/websocket").as_deref(),
+            Some("https://rpc.example.com")
+        );
+        assert_eq!(
+            rpc_url("ws://127.0.0.1:26657/websocket").as_deref(),
+            Some("http://127.0.0.1:26657")
+        );
+        assert_eq!(
+            rpc_url("wss://example.com/osmosis/rpc/websocket").as_deref(),
+            Some("https://example.com/osmosis/rpc")
+        );
+        assert_eq!(
+            rpc_url(&websocket_url("https://example.com/osmosis/rpc")).as_deref(),
+            Some("https://example.com/osmosis/rpc")
+        );
+        assert_eq!(
+            websocket_url("ws://127.0.0.1:26657/websocket"),
+            "ws://127.0.0.1:26657/websocket"
+        );
+    }
+
+    #[test]
+    fn test_minor_version() {
+        assert_eq!(minor_version("0.38.12"), "0.38");
+        assert_eq!(minor_version("v0.34.27-terra.rc.1"), "0.34");
+        assert_eq!(minor_version("1.0.0"), "1.0");
+        assert_eq!(minor_version("v1.1.2"), "1.0");
+        assert_eq!(minor_version("1"), "1");
+    }
+}
@@ -0,0 +1,336 @@
+use std::{fs, io, path::Path};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config;
+
+/// Prefix of the values read from Vault, such as `vault:secret/data/chainpulse#osmosis`
+const VAULT_PREFIX: &str = "vault:";
+
+/// Prefix of the values read from AWS Secrets Manager, such as `aws:prod/chainpulse#osmosis`
+const AWS_PREFIX: &str = "aws:";
+
+/// Resolves the credentials of the endpoints from files and secret stores
+///
+/// Values starting with `vault:` or `aws:` reference a secret, followed by its path and, after
+/// a `#`, the key of the secret holding the value. Other values are used as they are.
+pub struct Resolver {
+    secrets: config::Secrets,
+    client: reqwest::Client,
+}
+
+impl Resolver {
+    pub fn new(secrets: config::Secrets) -> Self {
+        Self {
+            secrets,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The value, or the secret it references
+    pub async fn resolve(&self, value: &str) -> crate::Result<String> {
+        if let Some(reference) = value.strip_prefix(VAULT_PREFIX) {
+            return self.vault(reference).await;
+        }
+
+        if let Some(reference) = value.strip_prefix(AWS_PREFIX) {
+            return self.aws(reference).await;
+        }
+
+        Ok(value.to_string())
+    }
+
+    /// A password read from a file, or given as a value which may reference a secret
+    pub async fn password(
+        &self,
+        value: Option<&str>,
+        file: Option<&Path>,
+    ) -> crate::Result<Option<String>> {
+        match (value, file) {
+            (Some(_), Some(_)) => Err("both a password and a password file are set".into()),
+            (None, Some(file)) => Ok(Some(read_file(file)?)),
+            (Some(value), None) => Ok(Some(self.resolve(value).await?)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Read a secret from the KV engine of Vault, version 1 or 2
+    async fn vault(&self, reference: &str) -> crate::Result<String> {
+        let vault = self
+            .secrets
+            .vault
+            .as_ref()
+            .ok_or("`vault:` reference used but `secrets.vault` is not configured")?;
+
+        let (path, key) = reference
+            .rsplit_once('#')
+            .ok_or("Vault references must end with `#<key>`")?;
+
+        let token = match &vault.token_file {
+            Some(file) => read_file(file)?,
+            None => std::env::var("VAULT_TOKEN")
+                .map_err(|_| "set `secrets.vault.token_file` or `VAULT_TOKEN`")?,
+        };
+
+        let url = format!(
+            "{}/v1/{}",
+            vault.address.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+
+        let mut request = self.client.get(&url).header("X-Vault-Token", token);
+
+        if let Some(namespace) = &vault.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(format!("Vault responded with {status} for {path}").into());
+        }
+
+        let body: Value = response.json().await?;
+
+        // Version 2 nests the secret along with its metadata
+        let data = match &body["data"] {
+            data if data["metadata"].is_object() => &data["data"],
+            data => data,
+        };
+
+        data[key]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("no key `{key}` in the Vault secret {path}").into())
+    }
+
+    /// Read a secret from AWS Secrets Manager, using the credentials of the environment
+    async fn aws(&self, reference: &str) -> crate::Result<String> {
+        let aws = self
+            .secrets
+            .aws
+            .as_ref()
+            .ok_or("`aws:` reference used but `secrets.aws` is not configured")?;
+
+        let (secret_id, key) = match reference.rsplit_once('#') {
+            Some((secret_id, key)) => (secret_id, Some(key)),
+            None => (reference, None),
+        };
+
+        let credentials = Credentials::from_env()?;
+
+        let endpoint = match &aws.endpoint {
+            Some(endpoint) => url::Url::parse(endpoint)?,
+            None => url::Url::parse(&format!(
+                "https://secretsmanager.{}.amazonaws.com",
+                aws.region
+            ))?,
+        };
+
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("invalid endpoint {endpoint}").into()),
+        };
+
+        let body = serde_json::to_string(&json!({ "SecretId": secret_id }))?;
+        let now = Utc::now();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+            ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+        ];
+
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+
+        let authorization = sign(
+            &credentials,
+            &aws.region,
+            "secretsmanager",
+            "POST",
+            "/",
+            &mut headers,
+            body.as_bytes(),
+            now,
+        );
+
+        let mut request = self
+            .client
+            .post(endpoint)
+            .header("authorization", authorization)
+            .body(body);
+
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(
+                format!("AWS Secrets Manager responded with {status} for {secret_id}").into(),
+            );
+        }
+
+        let response: Value = response.json().await?;
+        let secret = response["SecretString"]
+            .as_str()
+            .ok_or_else(|| format!("the AWS secret {secret_id} has no string value"))?;
+
+        let Some(key) = key else {
+            return Ok(secret.to_string());
+        };
+
+        let secret: Value = serde_json::from_str(secret)
+            .map_err(|_| format!("the AWS secret {secret_id} is not a JSON object"))?;
+
+        secret[key]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("no key `{key}` in the AWS secret {secret_id}").into())
+    }
+}
+
+/// Read a secret from a file, such as a Docker or Kubernetes secret, without its final newline
+pub fn read_file(path: &Path) -> io::Result<String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to read {}: {e}", path.display())))?;
+
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// AWS credentials
+pub(crate) struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    pub(crate) session_token: Option<String>,
+}
+
+impl Credentials {
+    pub(crate) fn from_env() -> crate::Result<Self> {
+        let var = |name| std::env::var(name).map_err(|_| format!("`{name}` is not set"));
+
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+/// `Authorization` header of a request without query to a path of an AWS service, signed with
+/// Signature Version 4
+///
+/// The path must be URI encoded. The headers are sorted, and must include `host` and
+/// `x-amz-date`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn sign(
+    credentials: &Credentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    path: &str,
+    headers: &mut [(&str, String)],
+    body: &[u8],
+    time: DateTime<Utc>,
+) -> String {
+    headers.sort();
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{method}\n{path}\n\n{canonical_headers}\n{signed_headers}\n{}",
+        hex(&Sha256::digest(body))
+    );
+
+    let date = time.format("%Y%m%d").to_string();
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        time.format("%Y%m%dT%H%M%SZ"),
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [region, service, "aws4_request"].iter().fold(
+        hmac(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            &date,
+        ),
+        |key, part| hmac(&key, part),
+    );
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        credentials.access_key_id
+    )
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    String::from_utf8(subtle_encoding::hex::encode(bytes)).expect("hex is ASCII")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // `get-vanilla` case of the AWS Signature Version 4 test suite
+        let credentials = Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+
+        let mut headers = vec![
+            ("x-amz-date", "20150830T123600Z".to_string()),
+            ("host", "example.amazonaws.com".to_string()),
+        ];
+
+        let time = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let authorization = sign(
+            &credentials,
+            "us-east-1",
+            "service",
+            "GET",
+            "/",
+            &mut headers,
+            b"",
+            time,
+        );
+
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+}
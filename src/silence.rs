@@ -0,0 +1,426 @@
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{config, webhook::EventKind};
+
+/// Longest recurring silence, bounding the schedule lookup
+pub const MAX_DURATION_MINUTES: u64 = 7 * 24 * 60;
+
+/// Silences from the configuration file and from the API, muting the matching events
+#[derive(Clone)]
+pub struct Silences {
+    configured: Vec<(config::Silence, Window)>,
+    pool: SqlitePool,
+}
+
+/// When a silence applies
+#[derive(Clone, Debug)]
+pub enum Window {
+    /// From every time matching the schedule, for the duration
+    Recurring {
+        schedule: Schedule,
+        duration: Duration,
+    },
+    /// Once, between the two times
+    Once {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+impl Window {
+    pub fn new(silence: &config::Silence) -> Result<Self, String> {
+        match (
+            &silence.schedule,
+            silence.duration_minutes,
+            &silence.start,
+            &silence.end,
+        ) {
+            (Some(schedule), Some(minutes), None, None) if minutes <= MAX_DURATION_MINUTES => {
+                Ok(Self::Recurring {
+                    schedule: Schedule::parse(schedule)?,
+                    duration: Duration::minutes(minutes as i64),
+                })
+            }
+            (Some(_), Some(_), None, None) => Err(format!(
+                "duration_minutes must be at most {MAX_DURATION_MINUTES}"
+            )),
+            (None, None, Some(start), Some(end)) => {
+                let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+                match start < end {
+                    true => Ok(Self::Once { start, end }),
+                    false => Err("end must be after start".to_string()),
+                }
+            }
+            _ => Err("either schedule and duration_minutes, or start and end, are required".into()),
+        }
+    }
+
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        match self {
+            Self::Once { start, end } => *start <= time && time < *end,
+            Self::Recurring { schedule, duration } => {
+                let Ok(minute) = time.duration_trunc(Duration::minutes(1)) else {
+                    return false;
+                };
+
+                // The silence applies if it started within its duration before now
+                (0..duration.num_minutes())
+                    .map(|ago| minute - Duration::minutes(ago))
+                    .any(|start| schedule.matches(start))
+            }
+        }
+    }
+}
+
+/// Cron schedule of five fields, minute, hour, day of month, month and day of week, in UTC
+///
+/// Fields are `*`, numbers, ranges such as `1-5`, steps such as `*/15` or `0-30/10`, and lists
+/// of those separated by commas. Sunday is both 0 and 7. As in cron, a time matches when either
+/// the day of month or the day of week does, if both are restricted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "invalid schedule `{expression}`: expected 5 fields, found {}",
+                fields.len()
+            ));
+        };
+
+        let field = |field: &str, min: u32, max: u32| {
+            parse_field(field, min, max)
+                .ok_or_else(|| format!("invalid schedule `{expression}`: invalid field `{field}`"))
+        };
+
+        let mut weekdays_mask = field(weekdays, 0, 7)?;
+        if weekdays_mask & (1 << 7) != 0 {
+            weekdays_mask |= 1;
+        }
+
+        Ok(Self {
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)?,
+            days: field(days, 1, 31)?,
+            months: field(months, 1, 12)?,
+            weekdays: weekdays_mask,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    pub fn matches(&self, time: DateTime<Utc>) -> bool {
+        let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month())
+            && day_matches
+    }
+}
+
+/// Bit mask of the values of a cron field
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut mask = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return None;
+        }
+
+        for value in (start..=end).step_by(step) {
+            mask |= 1 << value;
+        }
+    }
+
+    Some(mask)
+}
+
+/// A silence created through the API
+#[derive(Clone, Debug, Serialize)]
+pub struct RegisteredSilence {
+    pub id: i64,
+    pub chain: Option<String>,
+    pub channel: Option<String>,
+    pub rules: Vec<EventKind>,
+    pub starts_at: String,
+    pub ends_at: String,
+    pub comment: Option<String>,
+    pub created_at: String,
+}
+
+/// Silence to create through the API, starting now unless `starts_at` is set
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewSilence {
+    pub chain: Option<String>,
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub rules: Vec<EventKind>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub duration_minutes: Option<u64>,
+    pub comment: Option<String>,
+}
+
+impl Silences {
+    pub fn new(configured: Vec<config::Silence>, pool: SqlitePool) -> Self {
+        // Windows are validated when the configuration is loaded
+        let configured = configured
+            .into_iter()
+            .filter_map(|silence| {
+                let window = Window::new(&silence).ok()?;
+                Some((silence, window))
+            })
+            .collect();
+
+        Self { configured, pool }
+    }
+
+    /// Whether an event of a rule, about a chain and some channels, is currently silenced
+    ///
+    /// Silences of a channel do not apply to events about a whole chain.
+    pub fn covers(
+        &self,
+        active: &[RegisteredSilence],
+        rule: EventKind,
+        chain: &str,
+        channels: &[&str],
+    ) -> bool {
+        let now = Utc::now();
+
+        let applies = |silence_chain: &Option<String>,
+                       silence_channel: &Option<String>,
+                       rules: &[EventKind]| {
+            silence_chain
+                .as_ref()
+                .is_none_or(|silence_chain| silence_chain == chain)
+                && silence_channel
+                    .as_ref()
+                    .is_none_or(|silence_channel| channels.contains(&silence_channel.as_str()))
+                && (rules.is_empty() || rules.contains(&rule))
+        };
+
+        self.configured.iter().any(|(silence, window)| {
+            applies(&silence.chain, &silence.channel, &silence.rules) && window.contains(now)
+        }) || active
+            .iter()
+            .any(|silence| applies(&silence.chain, &silence.channel, &silence.rules))
+    }
+
+    /// Silences created through the API which currently apply
+    pub async fn active(&self) -> sqlx::Result<Vec<RegisteredSilence>> {
+        Ok(list(&self.pool)
+            .await?
+            .into_iter()
+            .filter(|silence| silence.is_active())
+            .collect())
+    }
+}
+
+impl RegisteredSilence {
+    pub fn is_active(&self) -> bool {
+        let now = Utc::now().format(DB_TIME_FORMAT).to_string();
+        self.starts_at <= now && now < self.ends_at
+    }
+}
+
+/// Format of the times stored in the database, as produced by SQLite's `datetime`
+const DB_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Silences created through the API which did not end yet
+pub async fn list(pool: &SqlitePool) -> sqlx::Result<Vec<RegisteredSilence>> {
+    let rows = sqlx::query_as::<
+        _,
+        (
+            i64,
+            Option<String>,
+            Option<String>,
+            String,
+            String,
+            String,
+            Option<String>,
+            String,
+        ),
+    >(
+        r#"
+        SELECT id, chain, channel, rules, starts_at, ends_at, comment, created_at
+        FROM silences
+        WHERE ends_at > datetime('now')
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, chain, channel, rules, starts_at, ends_at, comment, created_at)| {
+                RegisteredSilence {
+                    id,
+                    chain,
+                    channel,
+                    rules: serde_json::from_str(&rules).unwrap_or_default(),
+                    starts_at,
+                    ends_at,
+                    comment,
+                    created_at,
+                }
+            },
+        )
+        .collect())
+}
+
+impl NewSilence {
+    /// Start and end of the silence, in the format of the database
+    pub fn window(&self) -> Result<(String, String), String> {
+        let starts_at = match &self.starts_at {
+            Some(time) => parse_time(time)?,
+            None => Utc::now(),
+        };
+
+        let ends_at = match (&self.ends_at, self.duration_minutes) {
+            (Some(time), None) => parse_time(time)?,
+            (None, Some(minutes)) => starts_at + Duration::minutes(minutes as i64),
+            _ => return Err("either ends_at or duration_minutes is required".to_string()),
+        };
+
+        if ends_at <= starts_at {
+            return Err("the silence must end after it starts".to_string());
+        }
+
+        if ends_at <= Utc::now() {
+            return Err("the silence already ended".to_string());
+        }
+
+        Ok((
+            starts_at.format(DB_TIME_FORMAT).to_string(),
+            ends_at.format(DB_TIME_FORMAT).to_string(),
+        ))
+    }
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("invalid time `{time}`: {e}"))
+}
+
+/// Store a silence created through the API, between times returned by [`NewSilence::window`]
+pub async fn register(
+    pool: &SqlitePool,
+    silence: NewSilence,
+    (starts_at, ends_at): (String, String),
+) -> sqlx::Result<RegisteredSilence> {
+    let query = r#"
+        INSERT INTO silences (chain, channel, rules, starts_at, ends_at, comment, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+        RETURNING id, created_at
+    "#;
+
+    let (id, created_at) = sqlx::query_as::<_, (i64, String)>(query)
+        .bind(&silence.chain)
+        .bind(&silence.channel)
+        .bind(serde_json::to_string(&silence.rules).unwrap_or_default())
+        .bind(&starts_at)
+        .bind(&ends_at)
+        .bind(&silence.comment)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(RegisteredSilence {
+        id,
+        chain: silence.chain,
+        channel: silence.channel,
+        rules: silence.rules,
+        starts_at,
+        ends_at,
+        comment: silence.comment,
+        created_at,
+    })
+}
+
+/// Remove a silence created through the API, returning whether it existed
+pub async fn remove(pool: &SqlitePool, id: i64) -> sqlx::Result<bool> {
+    let result = sqlx::query("DELETE FROM silences WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_schedule() {
+        // Saturdays at 02:00
+        let schedule = Schedule::parse("0 2 * * 6").unwrap();
+        assert!(schedule.matches(time("2025-01-18T02:00:00Z")));
+        assert!(!schedule.matches(time("2025-01-18T02:01:00Z")));
+        assert!(!schedule.matches(time("2025-01-19T02:00:00Z")));
+
+        let schedule = Schedule::parse("*/15 9-17 1,15 * 7").unwrap();
+        assert!(schedule.matches(time("2025-01-15T09:45:00Z")));
+        assert!(schedule.matches(time("2025-01-19T17:30:00Z")));
+        assert!(!schedule.matches(time("2025-01-16T12:00:00Z")));
+
+        assert!(Schedule::parse("0 2 * *").is_err());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+
+        let window = Window::Recurring {
+            schedule: Schedule::parse("0 2 * * 6").unwrap(),
+            duration: Duration::minutes(90),
+        };
+        assert!(window.contains(time("2025-01-18T03:29:59Z")));
+        assert!(!window.contains(time("2025-01-18T03:30:00Z")));
+        assert!(!window.contains(time("2025-01-18T01:59:00Z")));
+    }
+}
@@ -1,36 +1,82 @@
 use async_tungstenite::{
-    tokio::connect_async_with_config,
-    tungstenite::{client::IntoClientRequest, http::HeaderValue, Message},
+    tokio::connect_async_with_tls_connector_and_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
 };
 use futures::{SinkExt, StreamExt};
 use std::sync::Arc;
-use tendermint::Block;
+use tendermint_rpc::event::Event;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Clone)]
+use crate::client::{
+    keepalive::{Keepalive, Pinger},
+    subscription,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum AuthMethod {
+    #[default]
     None,
-    Basic { username: String, password: String },
-    Bearer { token: String },
-    ApiKey { header_name: String, key: String },
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        header_name: String,
+        key: String,
+    },
+}
+
+impl AuthMethod {
+    /// Name of the method, as in `chains.json`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthMethod::None => "none",
+            AuthMethod::Basic { .. } => "basic",
+            AuthMethod::Bearer { .. } => "bearer",
+            AuthMethod::ApiKey { .. } => "api-key",
+        }
+    }
 }
 
-/// Simple authenticated WebSocket client for block subscriptions
+/// Simple authenticated WebSocket client for event subscriptions
 pub struct SimpleAuthClient {
     url: String,
     auth_method: AuthMethod,
+    /// TLS configuration of an endpoint with TLS settings of its own
+    tls: Option<Arc<rustls::ClientConfig>>,
+    keepalive: Keepalive,
 }
 
 impl SimpleAuthClient {
-    pub fn new(url: String, auth_method: AuthMethod) -> Self {
-        Self { url, auth_method }
+    pub fn new(
+        url: String,
+        auth_method: AuthMethod,
+        tls: Option<Arc<rustls::ClientConfig>>,
+        keepalive: Keepalive,
+    ) -> Self {
+        Self {
+            url,
+            auth_method,
+            tls,
+            keepalive,
+        }
     }
 
-    /// Subscribe to blocks and return a stream
-    pub async fn subscribe_blocks(
+    /// Subscribe to the events of the queries and return a stream, the attributes of the
+    /// events of the txs being base64-encoded up to CometBFT 0.34
+    pub async fn subscribe(
         self,
-    ) -> Result<BlockStream, Box<dyn std::error::Error + Send + Sync>> {
+        queries: &[String],
+        base64_attributes: bool,
+    ) -> Result<EventStream, Box<dyn std::error::Error + Send + Sync>> {
         // Initialize rustls crypto provider if not already initialized
         let _ = rustls::crypto::ring::default_provider().install_default();
 
@@ -38,6 +84,14 @@ impl SimpleAuthClient {
         info!("Connecting to WebSocket URL: {}", self.url);
         let mut request = self.url.into_client_request()?;
 
+        // Credentials are marked sensitive, so that the headers logged below leave them out
+        let sensitive = |value: &str| {
+            HeaderValue::from_str(value).map(|mut value| {
+                value.set_sensitive(true);
+                value
+            })
+        };
+
         match &self.auth_method {
             AuthMethod::None => {}
             AuthMethod::Basic { username, password } => {
@@ -49,23 +103,38 @@ impl SimpleAuthClient {
                 debug!("Using Basic Auth with username: {}", username);
                 request
                     .headers_mut()
-                    .insert("Authorization", HeaderValue::from_str(&auth_header)?);
-
-                // Add Origin header - some WebSocket servers require this
-                if let Ok(origin) = HeaderValue::from_str(&format!(
-                    "https://{}",
-                    request.uri().host().unwrap_or("localhost")
-                )) {
-                    request.headers_mut().insert("Origin", origin);
-                }
+                    .insert("Authorization", sensitive(&auth_header)?);
+            }
+            AuthMethod::Bearer { token } => {
+                debug!("Using Bearer Auth");
+                request
+                    .headers_mut()
+                    .insert("Authorization", sensitive(&format!("Bearer {}", token))?);
+            }
+            AuthMethod::ApiKey { header_name, key } => {
+                debug!("Using API key header: {}", header_name);
+                request.headers_mut().insert(
+                    HeaderName::from_bytes(header_name.as_bytes())?,
+                    sensitive(key)?,
+                );
+            }
+        }
+
+        // Add Origin header - some WebSocket servers require this
+        if self.auth_method != AuthMethod::None {
+            if let Ok(origin) = HeaderValue::from_str(&format!(
+                "https://{}",
+                request.uri().host().unwrap_or("localhost")
+            )) {
+                request.headers_mut().insert("Origin", origin);
             }
-            _ => return Err("Unsupported auth method".into()),
         }
 
         info!("Connecting to WebSocket with authentication...");
         debug!("Request headers: {:?}", request.headers());
 
-        let result = connect_async_with_config(request, None).await;
+        let connector = self.tls.map(tokio_rustls::TlsConnector::from);
+        let result = connect_async_with_tls_connector_and_config(request, connector, None).await;
         match &result {
             Ok(_) => info!("WebSocket handshake successful"),
             Err(e) => error!("WebSocket handshake failed: {:?}", e),
@@ -75,57 +144,78 @@ impl SimpleAuthClient {
 
         let (mut write, mut read) = ws_stream.split();
 
-        // Send subscription request
-        let subscribe_msg = r#"{"jsonrpc":"2.0","method":"subscribe","params":{"query":"tm.event = 'NewBlock'"},"id":1}"#;
-        write
-            .send(Message::Text(subscribe_msg.to_string().into()))
-            .await?;
+        // Send subscription requests
+        for (id, query) in queries.iter().enumerate() {
+            let subscribe_msg = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "subscribe",
+                "params": {"query": query},
+                "id": id + 1,
+            });
+            write
+                .send(Message::Text(subscribe_msg.to_string().into()))
+                .await?;
+        }
 
         // Read subscription response
         if let Some(Ok(Message::Text(response))) = read.next().await {
             debug!("Subscription response: {}", response);
         }
 
-        Ok(BlockStream {
+        Ok(EventStream {
             read: Arc::new(Mutex::new(read)),
+            write,
+            pinger: Pinger::new(&self.keepalive),
+            base64_attributes,
         })
     }
 }
 
-/// Stream of blocks from WebSocket
-pub struct BlockStream {
-    read: Arc<
-        Mutex<
-            futures::stream::SplitStream<
-                async_tungstenite::WebSocketStream<async_tungstenite::tokio::ConnectStream>,
-            >,
-        >,
-    >,
+type Connection = async_tungstenite::WebSocketStream<async_tungstenite::tokio::ConnectStream>;
+
+/// Stream of events from WebSocket
+pub struct EventStream {
+    read: Arc<Mutex<futures::stream::SplitStream<Connection>>>,
+    write: futures::stream::SplitSink<Connection, Message>,
+    pinger: Pinger,
+    base64_attributes: bool,
 }
 
-impl BlockStream {
-    /// Get next block
-    pub async fn next(&mut self) -> Option<Block> {
+impl EventStream {
+    /// Get next event, none once the connection closes or does not answer a ping
+    pub async fn next(&mut self) -> Option<Event> {
         let mut read = self.read.lock().await;
 
-        while let Some(result) = read.next().await {
+        loop {
+            let result = tokio::select! {
+                result = read.next() => result?,
+                _ = self.pinger.tick() => {
+                    let sent = match self.pinger.ping() {
+                        Ok(payload) => self
+                            .write
+                            .send(Message::Ping(payload.into()))
+                            .await
+                            .map_err(Into::into),
+                        Err(e) => Err(e),
+                    };
+
+                    if let Err(e) = sent {
+                        warn!("WebSocket connection dead: {}", e);
+                        return None;
+                    }
+
+                    continue;
+                }
+            };
+
             match result {
                 Ok(Message::Text(text)) => {
-                    // Try to parse as event
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                        // Check if it's a block event
-                        if let Some(block_json) =
-                            json["result"]["data"]["value"]["block"].as_object()
-                        {
-                            // Parse block
-                            if let Ok(block) = serde_json::from_value::<Block>(
-                                serde_json::Value::Object(block_json.clone()),
-                            ) {
-                                return Some(block);
-                            }
-                        }
+                    // Responses and events which fail to parse are skipped
+                    if let Some(event) = subscription::parse_event(&text, self.base64_attributes) {
+                        return Some(event);
                     }
                 }
+                Ok(Message::Pong(payload)) => self.pinger.pong(&payload),
                 Ok(Message::Close(_)) => {
                     info!("WebSocket closed");
                     return None;
@@ -137,7 +227,5 @@ impl BlockStream {
                 _ => {} // Ignore other message types
             }
         }
-
-        None
     }
 }
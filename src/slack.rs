@@ -0,0 +1,277 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use sqlx::SqlitePool;
+use tracing::{debug, warn};
+
+use crate::{
+    config,
+    monitor::format_age,
+    webhook::{self, ChainEvent, EventKind, PacketEvent, PacketSnapshot},
+};
+
+/// Timeout of a single message delivery
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Message posted for a channel when none is configured
+pub const DEFAULT_TEMPLATE: &str =
+    ":rotating_light: *Stuck packets on {chain} {src_channel} → {dst_channel}*
+{count} pending, the oldest for {oldest_age}
+{packets}
+{link}";
+
+/// Message posted when the packets stuck on a channel are all gone, when none is configured
+pub const DEFAULT_RESOLVED_TEMPLATE: &str =
+    ":white_check_mark: *Stuck packets resolved on {chain} {src_channel} → {dst_channel}*
+Stuck for {duration}";
+
+/// Posts a message to Slack for every channel on which packets got stuck, and once they are gone
+#[derive(Clone)]
+pub struct Slack {
+    config: config::Slack,
+    thresholds: config::StuckPackets,
+    client: reqwest::Client,
+}
+
+impl Slack {
+    pub fn new(config: config::Slack, thresholds: config::StuckPackets) -> Self {
+        Self {
+            config,
+            thresholds,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The same notifier posting stuck channels with another template
+    pub fn with_template(&self, template: &str) -> Self {
+        Self {
+            config: config::Slack {
+                template: template.to_string(),
+                ..self.config.clone()
+            },
+            thresholds: self.thresholds.clone(),
+            client: self.client.clone(),
+        }
+    }
+
+    /// Notify the channels of the newly stuck packets among the events,
+    /// describing every packet currently stuck on them, and the resolved channels
+    pub async fn notify(self, pool: SqlitePool, events: Vec<PacketEvent>) {
+        let channels: BTreeSet<_> = events
+            .iter()
+            .filter(|event| {
+                event.event == EventKind::PacketStuck
+                    && webhook::in_scope(&self.config.chain, &self.config.channel, &event.packet)
+            })
+            .map(|event| {
+                let packet = &event.packet;
+                (
+                    packet.chain_id.clone(),
+                    packet.src_channel.clone(),
+                    packet.dst_channel.clone(),
+                )
+            })
+            .collect();
+
+        for (chain, src_channel, dst_channel) in channels {
+            let threshold = self.thresholds.threshold(&chain, &src_channel);
+
+            let stuck =
+                match stuck_packets(&pool, &chain, &src_channel, &dst_channel, threshold).await {
+                    Ok(stuck) if !stuck.is_empty() => stuck,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        warn!(%chain, %src_channel, "Failed to load stuck packets for Slack: {e}");
+                        continue;
+                    }
+                };
+
+            let text = self.message(&chain, &src_channel, &dst_channel, &stuck);
+            self.post(&text).await;
+        }
+
+        for event in events.iter().filter(|event| {
+            event.event == EventKind::StuckResolved
+                && webhook::in_scope(&self.config.chain, &self.config.channel, &event.packet)
+        }) {
+            let packet = &event.packet;
+            let duration = event.alert.as_ref().map_or(0, |alert| alert.firing_seconds);
+
+            let text = render(
+                &self.config.resolved_template,
+                &[
+                    ("chain", packet.chain_id.clone()),
+                    ("src_channel", packet.src_channel.clone()),
+                    ("dst_channel", packet.dst_channel.clone()),
+                    ("duration", format_age(duration)),
+                ],
+            );
+            self.post(&text).await;
+        }
+    }
+
+    /// Post a message for each of the events about chains and channels
+    pub async fn announce(self, events: Vec<ChainEvent>) {
+        for event in events
+            .iter()
+            .filter(|event| event.in_scope(&self.config.chain, &self.config.channel))
+        {
+            let icon = match event.resolved {
+                true => ":white_check_mark:",
+                false => ":rotating_light:",
+            };
+
+            self.post(&format!("{icon} *{}*\n{}", event.heading(), event.summary))
+                .await;
+        }
+    }
+
+    /// Render the template for a channel, given its stuck packets, oldest first
+    fn message(
+        &self,
+        chain: &str,
+        src_channel: &str,
+        dst_channel: &str,
+        stuck: &[PacketSnapshot],
+    ) -> String {
+        let packets: Vec<String> = stuck
+            .iter()
+            .take(self.config.top_packets)
+            .map(packet_line)
+            .collect();
+
+        let link = self
+            .config
+            .api_url
+            .as_deref()
+            .map(|api_url| {
+                format!(
+                    "<{}/api/v1/packets/stuck?chain={chain}&channel={src_channel}|View stuck packets>",
+                    api_url.trim_end_matches('/')
+                )
+            })
+            .unwrap_or_default();
+
+        let oldest_age = stuck.first().map_or(0, |packet| packet.age_seconds);
+
+        render(
+            &self.config.template,
+            &[
+                ("chain", chain.to_string()),
+                ("src_channel", src_channel.to_string()),
+                ("dst_channel", dst_channel.to_string()),
+                ("count", stuck.len().to_string()),
+                ("oldest_age", format_age(oldest_age)),
+                ("packets", packets.join("\n")),
+                ("link", link),
+            ],
+        )
+    }
+
+    async fn post(&self, text: &str) {
+        let result = self
+            .client
+            .post(&self.config.webhook_url)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(&serde_json::json!({ "text": text }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => debug!("Posted alert to Slack"),
+            Err(e) => warn!("Failed to post alert to Slack: {e}"),
+        }
+    }
+}
+
+/// `• #12345 1000000 uosmo, pending for 3h05m`
+fn packet_line(packet: &PacketSnapshot) -> String {
+    let transfer = match (&packet.amount, &packet.denom) {
+        (Some(amount), Some(denom)) => format!(" {amount} {denom}"),
+        _ => String::new(),
+    };
+
+    format!(
+        "• #{}{transfer}, pending for {}",
+        packet.sequence,
+        format_age(packet.age_seconds)
+    )
+}
+
+/// Substitute the `{name}` placeholders of a template, dropping lines left empty
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    let rendered = values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        });
+
+    rendered
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Packets stuck on a channel, oldest first
+async fn stuck_packets(
+    pool: &SqlitePool,
+    chain: &str,
+    src_channel: &str,
+    dst_channel: &str,
+    threshold_seconds: i64,
+) -> sqlx::Result<Vec<PacketSnapshot>> {
+    sqlx::query_as::<_, PacketSnapshot>(
+        r#"
+        SELECT
+            t.chain as chain_id,
+            p.src_channel,
+            p.dst_channel,
+            p.sequence,
+            p.sender,
+            p.receiver,
+            p.amount,
+            p.denom,
+            CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) as age_seconds,
+            CASE WHEN p.timeout_timestamp IS NOT NULL
+                THEN CAST((p.timeout_timestamp / 1000000000 - strftime('%s', 'now')) AS INTEGER)
+            END as seconds_until_timeout
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND t.chain = ? AND p.src_channel = ? AND p.dst_channel = ?
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > ?
+        ORDER BY p.created_at ASC
+        "#,
+    )
+    .bind(chain)
+    .bind(src_channel)
+    .bind(dst_channel)
+    .bind(threshold_seconds)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let values = [
+            ("count", "3".to_string()),
+            ("chain", "osmosis-1".to_string()),
+            ("link", String::new()),
+        ];
+
+        assert_eq!(
+            render(
+                "{count} stuck on {chain} ({count})\n{link}\n{unknown}",
+                &values
+            ),
+            "3 stuck on osmosis-1 (3)\n{unknown}"
+        );
+    }
+}
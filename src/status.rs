@@ -0,0 +1,413 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tendermint::chain;
+use tokio::sync::{watch, Notify};
+
+/// Weight of the latest block interval in the moving average of the block time
+const BLOCK_TIME_WEIGHT: f64 = 0.05;
+
+/// Connection state of a chain collector
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting,
+    Paused,
+    /// Disabled in the configuration, until resumed through the admin API
+    Disabled,
+}
+
+/// Runtime status of a single chain collector
+#[derive(Clone, Debug)]
+pub struct ChainStatus {
+    pub chain_id: String,
+    pub endpoint: String,
+    pub comet_version: String,
+    pub state: ConnectionState,
+    /// When the collector lost its connection, or started if it never connected,
+    /// unless it is connected, paused or disabled
+    pub disconnected_since: Option<DateTime<Utc>>,
+    pub last_height: Option<u64>,
+    pub last_block_time: Option<DateTime<Utc>>,
+    /// Moving average of the time between processed blocks
+    pub block_time_seconds: Option<f64>,
+    pub errors: u64,
+    pub reconnects: u64,
+    pub timeouts: u64,
+    /// Messages of the collected transactions which failed to decode
+    pub decode_failures: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// When the collector reconnects, while it waits before doing so
+    pub backoff_until: Option<DateTime<Utc>>,
+}
+
+impl ChainStatus {
+    fn set_state(&mut self, state: ConnectionState) {
+        self.disconnected_since = match state {
+            ConnectionState::Connected | ConnectionState::Paused | ConnectionState::Disabled => {
+                None
+            }
+            _ => self.disconnected_since.or_else(|| Some(Utc::now())),
+        };
+        if state != ConnectionState::Reconnecting {
+            self.backoff_until = None;
+        }
+        self.state = state;
+    }
+
+    /// Seconds elapsed since the last processed block was produced
+    pub fn head_lag_seconds(&self) -> Option<i64> {
+        self.last_block_time
+            .map(|time| (Utc::now() - time).num_seconds().max(0))
+    }
+}
+
+/// Signals sent to a chain collector by the admin API, and on shutdown
+#[derive(Debug)]
+pub struct ChainControl {
+    reconnect: Notify,
+    paused: watch::Sender<bool>,
+    stopping: watch::Sender<bool>,
+}
+
+impl Default for ChainControl {
+    fn default() -> Self {
+        Self {
+            reconnect: Notify::new(),
+            paused: watch::channel(false).0,
+            stopping: watch::channel(false).0,
+        }
+    }
+}
+
+impl ChainControl {
+    /// Ask the collector to drop its connection and reconnect immediately
+    pub fn request_reconnect(&self) {
+        self.reconnect.notify_one();
+    }
+
+    /// Resolves once a reconnect has been requested
+    pub async fn reconnect_requested(&self) {
+        self.reconnect.notified().await;
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.send_replace(paused);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Resolves once the collector is paused
+    pub async fn paused(&self) {
+        self.wait_for(true).await;
+    }
+
+    /// Resolves once the collector is resumed
+    pub async fn resumed(&self) {
+        self.wait_for(false).await;
+    }
+
+    /// Ask the collector to stop once the block it is processing, if any, is recorded
+    pub fn request_stop(&self) {
+        self.stopping.send_replace(true);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stopping.borrow()
+    }
+
+    /// Resolves once the collector is asked to stop
+    pub async fn stop_requested(&self) {
+        let mut rx = self.stopping.subscribe();
+        let _ = rx.wait_for(|stopping| *stopping).await;
+    }
+
+    async fn wait_for(&self, paused: bool) {
+        let mut rx = self.paused.subscribe();
+        // The sender lives as long as `self`, so this never fails while we wait
+        let _ = rx.wait_for(|value| *value == paused).await;
+    }
+}
+
+/// State of a background task which only runs while the instance leads
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Waiting for the instance to lead
+    Standby,
+    /// Returned, as the tasks of a check disabled for good do
+    Stopped,
+}
+
+/// Runtime status of a background task
+#[derive(Clone, Debug)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// Times the task was started, once per takeover in high availability
+    pub starts: u64,
+}
+
+/// Runtime status of a periodic check of the monitor
+#[derive(Clone, Debug)]
+pub struct CheckStatus {
+    pub name: String,
+    pub runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_run_seconds: Option<f64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+/// Shared view of all chain collectors, updated by the collectors and read by the API
+#[derive(Clone)]
+pub struct Status {
+    chains: Arc<RwLock<BTreeMap<chain::Id, ChainStatus>>>,
+    controls: Arc<RwLock<BTreeMap<chain::Id, Arc<ChainControl>>>>,
+    tasks: Arc<RwLock<BTreeMap<String, TaskStatus>>>,
+    checks: Arc<RwLock<BTreeMap<String, CheckStatus>>>,
+    /// Whether the instance records the blocks and runs the alert checks, rather than standing
+    /// by for the leader to fail
+    leading: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            chains: Default::default(),
+            controls: Default::default(),
+            tasks: Default::default(),
+            checks: Default::default(),
+            leading: Arc::new(watch::channel(true).0),
+        }
+    }
+}
+
+impl Status {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_leading(&self, leading: bool) {
+        self.leading.send_replace(leading);
+    }
+
+    pub fn is_leading(&self) -> bool {
+        *self.leading.borrow()
+    }
+
+    /// Resolves once the instance leads, if `leading`, or stands by otherwise
+    pub async fn leadership(&self, leading: bool) {
+        let mut rx = self.leading.subscribe();
+        // The sender lives as long as `self`, so this never fails while we wait
+        let _ = rx.wait_for(|value| *value == leading).await;
+    }
+
+    pub fn register(&self, chain_id: &chain::Id, endpoint: &str, comet_version: &str) {
+        let status = ChainStatus {
+            chain_id: chain_id.to_string(),
+            endpoint: redact_url(endpoint),
+            comet_version: comet_version.to_string(),
+            state: ConnectionState::Connecting,
+            disconnected_since: Some(Utc::now()),
+            last_height: None,
+            last_block_time: None,
+            block_time_seconds: None,
+            errors: 0,
+            reconnects: 0,
+            timeouts: 0,
+            decode_failures: 0,
+            last_error: None,
+            last_error_at: None,
+            backoff_until: None,
+        };
+
+        self.chains
+            .write()
+            .unwrap()
+            .insert(chain_id.clone(), status);
+    }
+
+    /// Record the endpoint a collector failed over to
+    pub fn set_endpoint(&self, chain_id: &chain::Id, endpoint: &str) {
+        self.update(chain_id, |status| status.endpoint = redact_url(endpoint));
+    }
+
+    pub fn set_state(&self, chain_id: &chain::Id, state: ConnectionState) {
+        self.update(chain_id, |status| status.set_state(state));
+    }
+
+    pub fn block_processed(&self, chain_id: &chain::Id, height: u64, time: tendermint::Time) {
+        let time = DateTime::from_timestamp_nanos(time.unix_timestamp_nanos() as i64);
+
+        self.update(chain_id, |status| {
+            // Blocks missed while reconnecting are spread over the elapsed time
+            if let (Some(last_height), Some(last_time)) =
+                (status.last_height, status.last_block_time)
+            {
+                if height > last_height {
+                    let interval = (time - last_time).num_milliseconds() as f64
+                        / 1000.0
+                        / (height - last_height) as f64;
+
+                    status.block_time_seconds = Some(match status.block_time_seconds {
+                        Some(average) => average + (interval - average) * BLOCK_TIME_WEIGHT,
+                        None => interval,
+                    });
+                }
+            }
+
+            status.set_state(ConnectionState::Connected);
+            status.last_height = Some(height);
+            status.last_block_time = Some(time);
+        });
+    }
+
+    pub fn error(&self, chain_id: &chain::Id, error: &str) {
+        self.update(chain_id, |status| {
+            status.errors += 1;
+            status.last_error = Some(error.to_string());
+            status.last_error_at = Some(Utc::now());
+        });
+    }
+
+    /// Record that a collector waits for the given delay before reconnecting
+    pub fn backoff(&self, chain_id: &chain::Id, delay: Duration) {
+        let until = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+        self.update(chain_id, |status| status.backoff_until = Some(until));
+    }
+
+    pub fn timeout(&self, chain_id: &chain::Id) {
+        self.update(chain_id, |status| status.timeouts += 1);
+    }
+
+    pub fn decode_failure(&self, chain_id: &chain::Id) {
+        self.update(chain_id, |status| status.decode_failures += 1);
+    }
+
+    /// Forget a chain which is no longer monitored
+    pub fn unregister(&self, chain_id: &chain::Id) {
+        self.chains.write().unwrap().remove(chain_id);
+        self.controls.write().unwrap().remove(chain_id);
+    }
+
+    /// Clear the error, reconnect, timeout and decode failure counters of a chain
+    pub fn reset_counters(&self, chain_id: &chain::Id) {
+        self.update(chain_id, |status| {
+            status.errors = 0;
+            status.reconnects = 0;
+            status.timeouts = 0;
+            status.decode_failures = 0;
+            status.last_error = None;
+            status.last_error_at = None;
+        });
+    }
+
+    pub fn reconnect(&self, chain_id: &chain::Id) {
+        self.update(chain_id, |status| {
+            status.reconnects += 1;
+            status.set_state(ConnectionState::Reconnecting);
+        });
+    }
+
+    /// Snapshot of the status of all registered chains, ordered by chain id
+    pub fn chains(&self) -> Vec<ChainStatus> {
+        self.chains.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn chain(&self, chain_id: &chain::Id) -> Option<ChainStatus> {
+        self.chains.read().unwrap().get(chain_id).cloned()
+    }
+
+    pub fn contains(&self, chain_id: &chain::Id) -> bool {
+        self.chains.read().unwrap().contains_key(chain_id)
+    }
+
+    /// Control handle for the collector of a chain
+    pub fn control(&self, chain_id: &chain::Id) -> Arc<ChainControl> {
+        self.controls
+            .write()
+            .unwrap()
+            .entry(chain_id.clone())
+            .or_default()
+            .clone()
+    }
+
+    /// Record the state of a background task, counting its starts
+    pub fn task_state(&self, name: &str, state: TaskState) {
+        let mut tasks = self.tasks.write().unwrap();
+        let task = tasks.entry(name.to_string()).or_insert_with(|| TaskStatus {
+            name: name.to_string(),
+            state,
+            starts: 0,
+        });
+
+        if state == TaskState::Running {
+            task.starts += 1;
+        }
+        task.state = state;
+    }
+
+    /// Record a run of a check of the monitor which started at `started`, and its error if any
+    pub fn check_ran(&self, name: &str, started: DateTime<Utc>, error: Option<String>) {
+        let mut checks = self.checks.write().unwrap();
+        let check = checks
+            .entry(name.to_string())
+            .or_insert_with(|| CheckStatus {
+                name: name.to_string(),
+                runs: 0,
+                last_run_at: None,
+                last_run_seconds: None,
+                last_error: None,
+                last_error_at: None,
+            });
+
+        check.runs += 1;
+        check.last_run_at = Some(started);
+        check.last_run_seconds = Some((Utc::now() - started).num_milliseconds() as f64 / 1000.0);
+
+        if let Some(error) = error {
+            check.last_error = Some(error);
+            check.last_error_at = Some(Utc::now());
+        }
+    }
+
+    /// Snapshot of the background tasks, ordered by name
+    pub fn tasks(&self) -> Vec<TaskStatus> {
+        self.tasks.read().unwrap().values().cloned().collect()
+    }
+
+    /// Snapshot of the checks of the monitor which ran, ordered by name
+    pub fn checks(&self) -> Vec<CheckStatus> {
+        self.checks.read().unwrap().values().cloned().collect()
+    }
+
+    fn update(&self, chain_id: &chain::Id, f: impl FnOnce(&mut ChainStatus)) {
+        if let Some(status) = self.chains.write().unwrap().get_mut(chain_id) {
+            f(status);
+        }
+    }
+}
+
+/// Strip any credentials embedded in an endpoint URL
+fn redact_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::{
+    config,
+    monitor::format_age,
+    webhook::{self, ChainEvent, EventKind, PacketEvent},
+};
+
+/// Timeout of a single message delivery
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of packets listed in a message
+const MAX_LISTED_PACKETS: usize = 10;
+
+/// Sends packet events to a Telegram chat through a bot, one message per channel and event kind
+#[derive(Clone)]
+pub struct Telegram {
+    config: config::Telegram,
+    client: reqwest::Client,
+}
+
+impl Telegram {
+    pub fn new(config: config::Telegram) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn notify(self, events: Vec<PacketEvent>) {
+        let events: Vec<&PacketEvent> = events
+            .iter()
+            .filter(|event| {
+                (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                    && webhook::in_scope(&self.config.chain, &self.config.channel, &event.packet)
+            })
+            .collect();
+
+        for events in webhook::group_by_channel(&events) {
+            let text = self.message(&events);
+            self.send(&text).await;
+        }
+    }
+
+    /// Send a message for each of the events about chains and channels
+    pub async fn announce(self, events: Vec<ChainEvent>) {
+        for event in events.iter().filter(|event| {
+            (self.config.events.is_empty() || self.config.events.contains(&event.event))
+                && event.in_scope(&self.config.chain, &self.config.channel)
+        }) {
+            let text = format!(
+                "<b>{}</b>\n{}",
+                escape(&event.heading()),
+                escape(&event.summary)
+            );
+            self.send(&text).await;
+        }
+    }
+
+    /// HTML message describing events of the same kind on a channel
+    fn message(&self, events: &[&PacketEvent]) -> String {
+        let first = events[0];
+        let packet = &first.packet;
+
+        let oldest = events
+            .iter()
+            .map(|event| event.packet.age_seconds)
+            .max()
+            .unwrap_or_default();
+
+        let title = format!(
+            "<b>{} on {} {} → {}</b>",
+            first.event.title(),
+            escape(&packet.chain_id),
+            escape(&packet.src_channel),
+            escape(&packet.dst_channel)
+        );
+
+        if let (EventKind::StuckResolved, Some(alert)) = (first.event, &first.alert) {
+            return format!("{title}\nStuck for {}", format_age(alert.firing_seconds));
+        }
+
+        let mut lines = vec![
+            title,
+            format!(
+                "{} packets, the oldest sent {} ago",
+                webhook::packet_count(events),
+                format_age(oldest)
+            ),
+        ];
+
+        for event in events.iter().take(MAX_LISTED_PACKETS) {
+            let packet = &event.packet;
+
+            let line = match (&packet.amount, &packet.denom) {
+                (Some(amount), Some(denom)) => format!(
+                    "• #{} {} {}",
+                    packet.sequence,
+                    escape(amount),
+                    escape(denom)
+                ),
+                _ => format!("• #{}", packet.sequence),
+            };
+
+            lines.push(match (event.event, packet.seconds_until_timeout) {
+                (EventKind::WatchedNearTimeout, Some(seconds)) => {
+                    format!("{line}, times out in {}", format_age(seconds))
+                }
+                _ => line,
+            });
+        }
+
+        if events.len() > MAX_LISTED_PACKETS {
+            lines.push(format!("and {} more", events.len() - MAX_LISTED_PACKETS));
+        }
+
+        let link = self
+            .config
+            .api_url
+            .as_deref()
+            .and_then(|api_url| webhook::api_link(api_url, first));
+
+        if let Some(link) = link {
+            lines.push(format!(
+                "<a href=\"{}\">View in chainpulse</a>",
+                escape(&link)
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    async fn send(&self, text: &str) {
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            self.config.bot_token
+        );
+
+        let result = self
+            .client
+            .post(url)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(&serde_json::json!({
+                "chat_id": self.config.chat_id,
+                "text": text,
+                "parse_mode": "HTML",
+                "disable_web_page_preview": true,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => debug!("Sent packet events to Telegram"),
+            // The URL holds the bot token
+            Err(e) => warn!(
+                "Failed to send packet events to Telegram: {}",
+                e.without_url()
+            ),
+        }
+    }
+}
+
+/// Escape the characters Telegram treats as HTML markup
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhook::PacketSnapshot;
+
+    #[test]
+    fn test_message() {
+        let telegram = Telegram::new(config::Telegram {
+            bot_token: "123:abc".to_string(),
+            chat_id: "-1001".to_string(),
+            api_url: Some("https://chainpulse.example.com".to_string()),
+            events: vec![],
+            chain: None,
+            channel: None,
+        });
+
+        let event = PacketEvent::new(
+            EventKind::PacketNearTimeout,
+            PacketSnapshot {
+                chain_id: "osmosis-1".to_string(),
+                src_channel: "channel-0".to_string(),
+                dst_channel: "channel-141".to_string(),
+                sequence: 7,
+                sender: None,
+                receiver: None,
+                amount: Some("1000".to_string()),
+                denom: Some("ibc/<hash>".to_string()),
+                age_seconds: 3 * 3600 + 300,
+                seconds_until_timeout: Some(600),
+            },
+        );
+
+        assert_eq!(
+            telegram.message(&[&event]),
+            "<b>Packets nearing timeout on osmosis-1 channel-0 → channel-141</b>\n\
+             1 packets, the oldest sent 3h05m ago\n\
+             • #7 1000 ibc/&lt;hash&gt;\n\
+             <a href=\"https://chainpulse.example.com/api/v1/packets/expiring?chain=osmosis-1&amp;channel=channel-0\">View in chainpulse</a>"
+        );
+    }
+}
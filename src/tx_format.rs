@@ -0,0 +1,362 @@
+use ibc_proto::{
+    cosmos::tx::v1beta1::{AuthInfo, Tx, TxRaw},
+    google::protobuf::Any,
+};
+use prost::{
+    encoding::{self, DecodeContext, WireType},
+    DecodeError, Message,
+};
+use tendermint::crypto::{default::Sha256, Sha256 as _};
+
+use crate::{config::TxFormat, msg::Msg};
+
+/// Prefix of the type URLs of the IBC messages
+const IBC_TYPE_URL_PREFIX: &[u8] = b"/ibc.";
+
+/// Suffix of the type URLs of the EVM transactions of Ethermint chains, such as
+/// `/ethermint.evm.v1.MsgEthereumTx` or `/injective.evm.v1beta1.MsgEthereumTx`
+const EVM_TX_TYPE_URL_SUFFIX: &str = ".MsgEthereumTx";
+
+/// Transaction of a block, reduced to what is recorded of it whatever its format
+#[derive(Debug, Default)]
+pub struct DecodedTx {
+    /// Hash identifying the transaction in the `txs` table
+    pub hash: String,
+    /// Memo of the transaction, empty for the formats whose memo is not in the clear
+    pub memo: String,
+    /// Messages of the transaction, only the IBC ones for the formats other than `cosmos`
+    pub messages: Vec<Any>,
+    /// Fee and signers, of the Cosmos SDK transactions only
+    pub auth_info: Option<AuthInfo>,
+}
+
+/// Decode a transaction of a block in the format of its chain
+pub fn decode(format: TxFormat, bytes: &[u8]) -> crate::Result<DecodedTx> {
+    match format {
+        TxFormat::Cosmos => cosmos(bytes),
+        TxFormat::Namada => Ok(namada(bytes)),
+        TxFormat::Penumbra => penumbra(bytes),
+        TxFormat::Opaque => Ok(opaque(bytes)),
+    }
+}
+
+/// Cosmos SDK transactions, of which the EVM transactions of Ethermint chains such as Evmos
+/// and Injective are left out, their IBC activity being recorded from the events
+fn cosmos(bytes: &[u8]) -> crate::Result<DecodedTx> {
+    let mut decoded = match Tx::decode(bytes) {
+        Ok(tx) => {
+            // Hashed as re-encoded, as the txs recorded before the other formats were
+            let hash = hash(&tx.encode_to_vec());
+            let body = tx.body.ok_or("missing tx body")?;
+
+            DecodedTx {
+                hash,
+                memo: body.memo,
+                messages: body.messages,
+                auth_info: tx.auth_info,
+            }
+        }
+        // The extension options and fields of Ethermint chains do not always decode as the
+        // types of the SDK
+        Err(_) => tolerant(bytes)?,
+    };
+
+    decoded
+        .messages
+        .retain(|msg| !msg.type_url.ends_with(EVM_TX_TYPE_URL_SUFFIX));
+
+    Ok(decoded)
+}
+
+/// Cosmos SDK transaction whose body is read field by field, keeping the messages which decode
+/// and skipping the fields which do not, and whose fees are left out when they do not decode
+fn tolerant(bytes: &[u8]) -> crate::Result<DecodedTx> {
+    let raw = TxRaw::decode(bytes)?;
+    let mut body = raw.body_bytes.as_slice();
+    let mut decoded = DecodedTx {
+        hash: hash(bytes),
+        auth_info: AuthInfo::decode(raw.auth_info_bytes.as_slice()).ok(),
+        ..Default::default()
+    };
+
+    while !body.is_empty() {
+        match encoding::decode_key(&mut body)? {
+            (1, WireType::LengthDelimited) => {
+                if let Ok(msg) = Any::decode(length_delimited(&mut body)?) {
+                    decoded.messages.push(msg);
+                }
+            }
+            (2, WireType::LengthDelimited) => {
+                decoded.memo = String::from_utf8_lossy(length_delimited(&mut body)?).into_owned();
+            }
+            (tag, wire_type) => {
+                encoding::skip_field(wire_type, tag, &mut body, DecodeContext::default())?
+            }
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Value of a length delimited field, whose key was read from `buf`
+fn length_delimited<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+    let len = encoding::decode_varint(buf)? as usize;
+
+    if len > buf.len() {
+        return Err(DecodeError::new("buffer underflow"));
+    }
+
+    let (value, rest) = buf.split_at(len);
+    *buf = rest;
+
+    Ok(value)
+}
+
+/// Namada transactions are Borsh encoded, their data sections carrying the IBC messages as
+/// Protobuf `Any`s, which are extracted without decoding the rest of the transaction
+fn namada(bytes: &[u8]) -> DecodedTx {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        match ibc_any(&bytes[offset..]) {
+            Some((any, len)) => {
+                messages.push(any);
+                offset += len;
+            }
+            None => offset += 1,
+        }
+    }
+
+    DecodedTx {
+        hash: hash(bytes),
+        messages,
+        ..Default::default()
+    }
+}
+
+/// IBC message encoded as an `Any` at the start of `bytes`, with its encoded length
+fn ibc_any(bytes: &[u8]) -> Option<(Any, usize)> {
+    // Field 1, the type URL, then field 2, the value, both length delimited
+    let (type_url, rest) = field(bytes, 0x0a)?;
+
+    if !type_url.starts_with(IBC_TYPE_URL_PREFIX) {
+        return None;
+    }
+
+    let (value, rest) = field(rest, 0x12)?;
+
+    let any = Any {
+        type_url: String::from_utf8(type_url.to_vec()).ok()?,
+        value: value.to_vec(),
+    };
+
+    // Bytes which merely look like an IBC message are told apart by decoding it
+    match Msg::decode(any.clone()) {
+        Ok(msg) if msg.is_ibc() => Some((any, bytes.len() - rest.len())),
+        _ => None,
+    }
+}
+
+/// Length delimited field with the given key at the start of `bytes`, and the bytes after it
+fn field(bytes: &[u8], key: u8) -> Option<(&[u8], &[u8])> {
+    let (&first, mut rest) = bytes.split_first()?;
+
+    if first != key {
+        return None;
+    }
+
+    let len = encoding::decode_varint(&mut rest).ok()? as usize;
+
+    (len <= rest.len()).then(|| rest.split_at(len))
+}
+
+/// `penumbra.core.transaction.v1.Transaction`, leaving out the signature and anchor
+#[derive(Clone, PartialEq, Message)]
+struct PenumbraTransaction {
+    #[prost(message, optional, tag = "1")]
+    body: Option<PenumbraTransactionBody>,
+}
+
+/// `penumbra.core.transaction.v1.TransactionBody`, leaving out the parameters and the memo,
+/// which is encrypted
+#[derive(Clone, PartialEq, Message)]
+struct PenumbraTransactionBody {
+    #[prost(message, repeated, tag = "1")]
+    actions: Vec<PenumbraAction>,
+}
+
+/// `penumbra.core.transaction.v1.Action`, leaving out the actions other than the IBC relay
+///
+/// The ICS-20 withdrawals send their packets without a message, and so are not recorded, the
+/// packets being recorded on their receipt by the counterparty.
+#[derive(Clone, PartialEq, Message)]
+struct PenumbraAction {
+    #[prost(message, optional, tag = "17")]
+    ibc_relay_action: Option<PenumbraIbcRelay>,
+}
+
+/// `penumbra.core.component.ibc.v1.IbcRelay`
+#[derive(Clone, PartialEq, Message)]
+struct PenumbraIbcRelay {
+    #[prost(message, optional, tag = "1")]
+    raw_action: Option<Any>,
+}
+
+fn penumbra(bytes: &[u8]) -> crate::Result<DecodedTx> {
+    let tx = PenumbraTransaction::decode(bytes)?;
+    let body = tx.body.ok_or("missing tx body")?;
+
+    Ok(DecodedTx {
+        hash: hash(bytes),
+        messages: body
+            .actions
+            .into_iter()
+            .filter_map(|action| action.ibc_relay_action?.raw_action)
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// Transactions which are not decoded, of which no message is extracted
+fn opaque(bytes: &[u8]) -> DecodedTx {
+    DecodedTx {
+        hash: hash(bytes),
+        ..Default::default()
+    }
+}
+
+/// Hash of a transaction, as the nodes list it
+pub(crate) fn hash(bytes: &[u8]) -> String {
+    let hash = Sha256::digest(bytes);
+    String::from_utf8_lossy(&subtle_encoding::hex::encode_upper(hash)).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_proto::{
+        cosmos::tx::v1beta1::TxBody,
+        ibc::core::channel::v1::{MsgRecvPacket, Packet},
+    };
+
+    use super::*;
+
+    fn recv_packet() -> Any {
+        Any {
+            type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(),
+            value: MsgRecvPacket {
+                packet: Some(Packet {
+                    sequence: 7,
+                    source_port: "transfer".to_string(),
+                    source_channel: "channel-0".to_string(),
+                    destination_port: "transfer".to_string(),
+                    destination_channel: "channel-1".to_string(),
+                    ..Default::default()
+                }),
+                signer: "tnam1relayer".to_string(),
+                ..Default::default()
+            }
+            .encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_ethermint() {
+        let any = recv_packet();
+        let evm = Any {
+            type_url: "/ethermint.evm.v1.MsgEthereumTx".to_string(),
+            value: vec![0x0a, 0x00],
+        };
+
+        let mut body = TxBody {
+            messages: vec![evm.clone(), any.clone()],
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        // An extension option of an unexpected wire type, which the SDK types reject
+        body.extend([0xf8, 0x3f, 0x01]);
+
+        let bytes = TxRaw {
+            body_bytes: body,
+            auth_info_bytes: vec![0xff],
+            signatures: vec![],
+        }
+        .encode_to_vec();
+
+        assert!(Tx::decode(bytes.as_slice()).is_err());
+
+        let tx = decode(TxFormat::Cosmos, &bytes).unwrap();
+        assert_eq!(tx.messages, std::slice::from_ref(&any));
+        assert_eq!(tx.hash, hash(&bytes));
+        assert!(tx.auth_info.is_none());
+
+        let tx = Tx {
+            body: Some(TxBody {
+                messages: vec![evm, any.clone()],
+                memo: "relayed".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let decoded = decode(TxFormat::Cosmos, &tx.encode_to_vec()).unwrap();
+        assert_eq!(decoded.messages, [any]);
+        assert_eq!(decoded.memo, "relayed");
+    }
+
+    #[test]
+    fn test_namada() {
+        let any = recv_packet();
+
+        // Borsh encoded sections around the message, one of which looks like an `Any`
+        let mut bytes = vec![
+            0x0a, 0x05, b'/', b'i', b'b', b'c', b'.', 0x12, 0xff, 3, 0, 0, 0,
+        ];
+        bytes.extend((any.encode_to_vec().len() as u32).to_le_bytes());
+        bytes.extend(any.encode_to_vec());
+        bytes.extend([1, 2, 3]);
+
+        let tx = decode(TxFormat::Namada, &bytes).unwrap();
+        assert_eq!(tx.messages, [any]);
+        assert_eq!(tx.hash, hash(&bytes));
+        assert_eq!(tx.memo, "");
+        assert!(decode(TxFormat::Cosmos, &bytes).is_err());
+    }
+
+    #[test]
+    fn test_penumbra() {
+        let any = recv_packet();
+
+        let tx = PenumbraTransaction {
+            body: Some(PenumbraTransactionBody {
+                actions: vec![
+                    PenumbraAction {
+                        ibc_relay_action: None,
+                    },
+                    PenumbraAction {
+                        ibc_relay_action: Some(PenumbraIbcRelay {
+                            raw_action: Some(any.clone()),
+                        }),
+                    },
+                ],
+            }),
+        };
+
+        let tx = decode(TxFormat::Penumbra, &tx.encode_to_vec()).unwrap();
+        assert_eq!(tx.messages, [any]);
+        assert!(tx.auth_info.is_none());
+    }
+
+    #[test]
+    fn test_opaque() {
+        let bytes = recv_packet().encode_to_vec();
+
+        // Even a transaction holding an IBC message is not decoded
+        let tx = decode(TxFormat::Opaque, &bytes).unwrap();
+        assert!(tx.messages.is_empty());
+        assert_eq!(tx.hash, hash(&bytes));
+
+        assert!(decode(TxFormat::Opaque, &[]).is_ok());
+    }
+}
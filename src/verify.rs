@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ibc_proto::ibc::core::channel::v1::{
+    QueryUnreceivedAcksRequest, QueryUnreceivedAcksResponse, QueryUnreceivedPacketsRequest,
+    QueryUnreceivedPacketsResponse,
+};
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+
+use crate::{
+    config::{Chains, StuckPackets},
+    expiry, rpc,
+};
+
+/// Most sequences queried at once, keeping the query within the URL length limits of the nodes
+const SEQUENCES_PER_QUERY: usize = 200;
+
+/// Chain and ports and channels of both ends a packet is relayed between, the destination chain
+/// being the one resolved from the channel
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Path {
+    chain: String,
+    src_port: String,
+    src_channel: String,
+    dst_chain: Option<String>,
+    dst_port: String,
+    dst_channel: String,
+}
+
+/// Query the chains for the pending packets past their stuck threshold, and mark as relayed the
+/// ones the chains know to be, returning their number
+///
+/// Packets relayed by parties outside the monitored chains, or whose relaying was missed, are
+/// known to be so by the chains. The source chain deletes the commitment of a packet once its
+/// acknowledgement or timeout is received, so that the sequences missing from its unreceived
+/// acks were relayed. The destination chain, when monitored, keeps a receipt of the packets it
+/// received, so that the sequences missing from its unreceived packets were too.
+///
+/// A chain which fails to answer leaves its packets stuck.
+pub async fn verify_stuck(
+    client: &reqwest::Client,
+    pool: &SqlitePool,
+    chains: &Chains,
+    thresholds: &StuckPackets,
+) -> sqlx::Result<u64> {
+    let mut verified = 0;
+
+    for (path, sequences) in candidates(pool, thresholds).await? {
+        let relayed = relayed(client, chains, &path, &sequences).await;
+
+        if relayed.is_empty() {
+            continue;
+        }
+
+        info!(
+            chain = %path.chain,
+            "Verified {} stuck packets on {}/{} as relayed",
+            relayed.len(),
+            path.src_port,
+            path.src_channel
+        );
+
+        verified += mark_relayed(
+            pool,
+            &path.chain,
+            &path.src_port,
+            &path.src_channel,
+            &relayed,
+        )
+        .await?;
+    }
+
+    Ok(verified)
+}
+
+/// Sequences of the packets of a path which its chains know to be relayed
+async fn relayed(
+    client: &reqwest::Client,
+    chains: &Chains,
+    path: &Path,
+    sequences: &[u64],
+) -> BTreeSet<u64> {
+    let mut relayed = BTreeSet::new();
+
+    if let Some(endpoint) = expiry::endpoint(chains, &path.chain) {
+        for chunk in sequences.chunks(SEQUENCES_PER_QUERY) {
+            let response: crate::Result<QueryUnreceivedAcksResponse> = rpc::abci_query(
+                client,
+                endpoint,
+                "/ibc.core.channel.v1.Query/UnreceivedAcks",
+                QueryUnreceivedAcksRequest {
+                    port_id: path.src_port.clone(),
+                    channel_id: path.src_channel.clone(),
+                    packet_ack_sequences: chunk.to_vec(),
+                },
+            )
+            .await;
+
+            match response {
+                Ok(response) => relayed.extend(missing(chunk, &response.sequences)),
+                Err(e) => {
+                    warn!(
+                        chain = %path.chain,
+                        "Failed to query the unreceived acks of {}/{}: {e}",
+                        path.src_port,
+                        path.src_channel
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    let dst_endpoint = path
+        .dst_chain
+        .as_deref()
+        .and_then(|dst_chain| expiry::endpoint(chains, dst_chain));
+
+    if let (Some(dst_chain), Some(endpoint)) = (&path.dst_chain, dst_endpoint) {
+        let unknown: Vec<u64> = sequences
+            .iter()
+            .copied()
+            .filter(|sequence| !relayed.contains(sequence))
+            .collect();
+
+        for chunk in unknown.chunks(SEQUENCES_PER_QUERY) {
+            let response: crate::Result<QueryUnreceivedPacketsResponse> = rpc::abci_query(
+                client,
+                endpoint,
+                "/ibc.core.channel.v1.Query/UnreceivedPackets",
+                QueryUnreceivedPacketsRequest {
+                    port_id: path.dst_port.clone(),
+                    channel_id: path.dst_channel.clone(),
+                    packet_commitment_sequences: chunk.to_vec(),
+                },
+            )
+            .await;
+
+            match response {
+                Ok(response) => relayed.extend(missing(chunk, &response.sequences)),
+                Err(e) => {
+                    warn!(
+                        chain = %dst_chain,
+                        "Failed to query the unreceived packets of {}/{}: {e}",
+                        path.dst_port,
+                        path.dst_channel
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    relayed
+}
+
+/// Sequences queried which are missing from the sequences the chain answered with
+fn missing<'a>(queried: &'a [u64], answered: &'a [u64]) -> impl Iterator<Item = u64> + 'a {
+    queried
+        .iter()
+        .copied()
+        .filter(|sequence| !answered.contains(sequence))
+}
+
+/// Sequences of the pending packets past their stuck threshold, by path
+async fn candidates(
+    pool: &SqlitePool,
+    thresholds: &StuckPackets,
+) -> sqlx::Result<BTreeMap<Path, Vec<u64>>> {
+    let query = format!(
+        r#"
+        SELECT
+            t.chain,
+            p.src_port,
+            p.src_channel,
+            (SELECT c.counterparty_chain_id FROM channel_counterparties c
+             WHERE c.chain = t.chain AND c.port = p.src_port AND c.channel = p.src_channel) as dst_chain,
+            p.dst_port,
+            p.dst_channel,
+            p.sequence
+        FROM packets p
+        JOIN txs t ON p.tx_id = t.id
+        WHERE p.msg_type_url = 'send_packet'
+          AND p.effected = 0
+          AND (p.timeout_timestamp IS NULL OR p.timeout_timestamp > strftime('%s', 'now') * 1000000000)
+          AND CAST((strftime('%s', 'now') - strftime('%s', p.created_at)) AS INTEGER) > {}
+        ORDER BY t.chain, p.src_port, p.src_channel, p.sequence
+        "#,
+        thresholds.sql("t.chain", "p.src_channel")
+    );
+
+    let rows =
+        sqlx::query_as::<_, (String, String, String, Option<String>, String, String, i64)>(&query)
+            .fetch_all(pool)
+            .await?;
+
+    let mut candidates: BTreeMap<Path, Vec<u64>> = BTreeMap::new();
+
+    for (chain, src_port, src_channel, dst_chain, dst_port, dst_channel, sequence) in rows {
+        let path = Path {
+            chain,
+            src_port,
+            src_channel,
+            dst_chain,
+            dst_port,
+            dst_channel,
+        };
+
+        candidates.entry(path).or_default().push(sequence as u64);
+    }
+
+    Ok(candidates)
+}
+
+/// Mark the pending packets sent by a chain on a channel with the given sequences as relayed,
+/// without a tx relaying them
+pub(crate) async fn mark_relayed(
+    pool: &SqlitePool,
+    chain: &str,
+    port: &str,
+    channel: &str,
+    sequences: &BTreeSet<u64>,
+) -> sqlx::Result<u64> {
+    let mut marked = 0;
+
+    for sequence in sequences {
+        let result = sqlx::query(
+            r#"
+            UPDATE packets
+            SET effected = 1
+            WHERE msg_type_url = 'send_packet'
+              AND effected = 0
+              AND src_port = ? AND src_channel = ? AND sequence = ?
+              AND tx_id IN (SELECT id FROM txs WHERE chain = ?)
+            "#,
+        )
+        .bind(port)
+        .bind(channel)
+        .bind(*sequence as i64)
+        .bind(chain)
+        .execute(pool)
+        .await?;
+
+        marked += result.rows_affected();
+    }
+
+    Ok(marked)
+}
@@ -0,0 +1,532 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chrono::{DateTime, Utc};
+use ring::{
+    rand::SystemRandom,
+    signature::{RsaKeyPair, RSA_PKCS1_SHA256},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tokio::{sync::watch, time};
+use tracing::{debug, error, info};
+
+use crate::{
+    command::export::{ExportRow, COLUMNS},
+    config::{BigQuery, Config, ObjectStore, Warehouse},
+    secret::{self, Credentials},
+};
+
+/// Timeout of the requests made to the warehouse
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Rows of a single streaming insert, as recommended by the quotas of BigQuery
+const BIGQUERY_INSERT_ROWS: usize = 500;
+
+/// Scope of the access tokens of the BigQuery API
+const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// Access token of the service account of the Google Cloud instance
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Packet exported to the warehouse, identified by its row in the database
+#[derive(Serialize, sqlx::FromRow)]
+struct PacketRow {
+    id: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    packet: ExportRow,
+}
+
+/// Warehouse receiving the packets, each with its own progress
+enum Destination<'a> {
+    BigQuery(&'a BigQuery),
+    ObjectStore(&'a ObjectStore),
+}
+
+impl Destination<'_> {
+    /// Name of the destination in the `warehouse_cursors` table and the logs
+    fn name(&self) -> &'static str {
+        match self {
+            Self::BigQuery(_) => "bigquery",
+            Self::ObjectStore(_) => "object_store",
+        }
+    }
+}
+
+/// Export the packets recorded since the previous export to the destinations of `warehouse`
+/// every `interval_minutes`, resuming from the last exported packet after a restart
+///
+/// The export is append-only, the cursor being the id of the last exported packet. Packets are
+/// exported once, as first recorded: a `send_packet` relayed or timed out afterwards keeps
+/// `effected = false` in the warehouse, its relay being exported with the `MsgRecvPacket`,
+/// `MsgAcknowledgement` or `MsgTimeout` of the relayer.
+pub async fn run(pool: SqlitePool, mut config: watch::Receiver<Config>) {
+    let mut exporter = Exporter::new(pool);
+
+    loop {
+        let warehouse = config.borrow_and_update().warehouse.clone();
+
+        let Some(warehouse) = warehouse else {
+            // Idle until a reload configures the warehouse
+            if config.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        exporter.export(&warehouse).await;
+
+        tokio::select! {
+            _ = time::sleep(Duration::from_secs(warehouse.interval_minutes * 60)) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+struct Exporter {
+    pool: SqlitePool,
+    client: reqwest::Client,
+    /// Access token of BigQuery, along with the time it expires
+    token: Option<(String, Instant)>,
+    /// BigQuery tables known to exist
+    tables: HashSet<String>,
+}
+
+impl Exporter {
+    fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            client: reqwest::Client::new(),
+            token: None,
+            tables: HashSet::new(),
+        }
+    }
+
+    async fn export(&mut self, warehouse: &Warehouse) {
+        let destinations = [
+            warehouse.bigquery.as_ref().map(Destination::BigQuery),
+            warehouse
+                .object_store
+                .as_ref()
+                .map(Destination::ObjectStore),
+        ];
+
+        for destination in destinations.into_iter().flatten() {
+            let name = destination.name();
+
+            match self.export_to(&destination, warehouse.batch_size).await {
+                Ok(0) => debug!("No new packets to export to {name}"),
+                Ok(exported) => info!("Exported {exported} packets to {name}"),
+                Err(e) => error!("Failed to export the packets to {name}: {e}"),
+            }
+        }
+    }
+
+    /// Push the packets after the cursor of the destination in batches, moving the cursor
+    /// past each batch once it is accepted
+    async fn export_to(
+        &mut self,
+        destination: &Destination<'_>,
+        batch_size: usize,
+    ) -> crate::Result<usize> {
+        let mut exported = 0;
+
+        loop {
+            let cursor = cursor(&self.pool, destination.name()).await?;
+
+            let rows: Vec<PacketRow> = sqlx::query_as(&format!(
+                "SELECT p.id, {COLUMNS} FROM packets p JOIN txs t ON p.tx_id = t.id WHERE p.id > ? ORDER BY p.id LIMIT ?"
+            ))
+            .bind(cursor)
+            .bind(batch_size as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+            let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+                break;
+            };
+
+            let (first, last) = (first.id, last.id);
+
+            match destination {
+                Destination::BigQuery(bigquery) => self.insert_bigquery(bigquery, &rows).await?,
+                Destination::ObjectStore(store) => {
+                    let key = object_key(&store.prefix, Utc::now(), first, last);
+                    self.put_object(store, &key, &rows).await?
+                }
+            }
+
+            save_cursor(&self.pool, destination.name(), last).await?;
+            exported += rows.len();
+
+            if rows.len() < batch_size {
+                break;
+            }
+        }
+
+        Ok(exported)
+    }
+
+    /// Stream the rows into the BigQuery table, each with an insert ID sparing the duplicates
+    /// of a retried batch
+    async fn insert_bigquery(
+        &mut self,
+        bigquery: &BigQuery,
+        rows: &[PacketRow],
+    ) -> crate::Result<()> {
+        let token = self.token(bigquery).await?;
+        self.ensure_table(bigquery, &token).await?;
+
+        let url = format!("{}/{}/insertAll", tables_url(bigquery), bigquery.table);
+
+        for chunk in rows.chunks(BIGQUERY_INSERT_ROWS) {
+            let rows: Vec<Value> = chunk
+                .iter()
+                .map(|row| json!({ "insertId": format!("packet-{}", row.id), "json": row }))
+                .collect();
+
+            let response: Value = self
+                .client
+                .post(&url)
+                .bearer_auth(&token)
+                .json(&json!({ "rows": rows }))
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            if let Some(errors) = response["insertErrors"]
+                .as_array()
+                .filter(|errors| !errors.is_empty())
+            {
+                return Err(
+                    format!("BigQuery rejected {} rows: {}", errors.len(), errors[0]).into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the BigQuery table, partitioned by day, unless it exists
+    async fn ensure_table(&mut self, bigquery: &BigQuery, token: &str) -> crate::Result<()> {
+        let url = format!("{}/{}", tables_url(bigquery), bigquery.table);
+
+        if self.tables.contains(&url) {
+            return Ok(());
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(token)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            let table = json!({
+                "tableReference": {
+                    "projectId": bigquery.project,
+                    "datasetId": bigquery.dataset,
+                    "tableId": bigquery.table,
+                },
+                "schema": { "fields": bigquery_schema() },
+                "timePartitioning": { "type": "DAY", "field": "created_at" },
+            });
+
+            self.client
+                .post(tables_url(bigquery))
+                .bearer_auth(token)
+                .json(&table)
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            info!(
+                "Created the BigQuery table {}.{}.{}",
+                bigquery.project, bigquery.dataset, bigquery.table
+            );
+        } else {
+            response.error_for_status()?;
+        }
+
+        self.tables.insert(url);
+
+        Ok(())
+    }
+
+    /// Access token of BigQuery, renewed a minute before it expires
+    async fn token(&mut self, bigquery: &BigQuery) -> crate::Result<String> {
+        if let Some((token, expires)) = &self.token {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct Token {
+            access_token: String,
+            expires_in: u64,
+        }
+
+        let request = match &bigquery.credentials_file {
+            Some(path) => {
+                let key: ServiceAccountKey = serde_json::from_str(&secret::read_file(path)?)
+                    .map_err(|e| format!("invalid service account key {}: {e}", path.display()))?;
+                let assertion = jwt(&key, Utc::now())?;
+
+                self.client.post(&key.token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            }
+            None => self
+                .client
+                .get(METADATA_TOKEN_URL)
+                .query(&[("scopes", BIGQUERY_SCOPE)])
+                .header("Metadata-Flavor", "Google"),
+        };
+
+        let token: Token = request
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let expires = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        self.token = Some((token.access_token.clone(), expires));
+
+        Ok(token.access_token)
+    }
+
+    /// Write the rows as JSON lines to an object of the bucket, signed with AWS Signature
+    /// Version 4
+    async fn put_object(
+        &self,
+        store: &ObjectStore,
+        key: &str,
+        rows: &[PacketRow],
+    ) -> crate::Result<()> {
+        let mut body = Vec::new();
+
+        for row in rows {
+            serde_json::to_writer(&mut body, row)?;
+            body.push(b'\n');
+        }
+
+        let endpoint = url::Url::parse(&store.endpoint)?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("no host in {}", store.endpoint).into()),
+        };
+
+        // Path-style requests, which every S3 compatible store serves
+        let path = format!(
+            "{}/{}/{key}",
+            endpoint.path().trim_end_matches('/'),
+            store.bucket
+        );
+
+        let credentials = Credentials::from_env()?;
+        let now = Utc::now();
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", secret::hex(&Sha256::digest(&body))),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+
+        let authorization = secret::sign(
+            &credentials,
+            &store.region,
+            "s3",
+            "PUT",
+            &path,
+            &mut headers,
+            &body,
+            now,
+        );
+
+        let mut request = self
+            .client
+            .put(format!("{}{path}", endpoint.origin().ascii_serialization()))
+            .header("authorization", authorization)
+            .header("content-type", "application/x-ndjson")
+            .timeout(REQUEST_TIMEOUT)
+            .body(body);
+
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Fields of the Google Cloud service account keys used to get an access token
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// Token signed by the service account, exchanged for an access token of BigQuery
+fn jwt(key: &ServiceAccountKey, now: DateTime<Utc>) -> crate::Result<String> {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(
+        json!({
+            "iss": key.client_email,
+            "scope": BIGQUERY_SCOPE,
+            "aud": key.token_uri,
+            "iat": now.timestamp(),
+            "exp": now.timestamp() + 3600,
+        })
+        .to_string(),
+    );
+    let message = format!("{header}.{claims}");
+
+    // PKCS #8 key in PEM, the base64 of the DER between the BEGIN and END lines
+    let der: String = key
+        .private_key
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = STANDARD
+        .decode(der.trim())
+        .map_err(|e| format!("invalid private key: {e}"))?;
+
+    let pair = RsaKeyPair::from_pkcs8(&der).map_err(|e| format!("invalid private key: {e}"))?;
+    let mut signature = vec![0; pair.public().modulus_len()];
+
+    pair.sign(
+        &RSA_PKCS1_SHA256,
+        &SystemRandom::new(),
+        message.as_bytes(),
+        &mut signature,
+    )
+    .map_err(|_| "failed to sign the token of the service account")?;
+
+    Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+}
+
+fn tables_url(bigquery: &BigQuery) -> String {
+    format!(
+        "{}/bigquery/v2/projects/{}/datasets/{}/tables",
+        bigquery.endpoint.trim_end_matches('/'),
+        bigquery.project,
+        bigquery.dataset
+    )
+}
+
+/// Schema of the BigQuery table, matching the rows of `chainpulse export`
+fn bigquery_schema() -> Value {
+    let field =
+        |name: &str, kind: &str, mode: &str| json!({ "name": name, "type": kind, "mode": mode });
+
+    json!([
+        field("id", "INT64", "REQUIRED"),
+        field("chain", "STRING", "REQUIRED"),
+        field("height", "INT64", "REQUIRED"),
+        field("tx_hash", "STRING", "REQUIRED"),
+        field("msg_type_url", "STRING", "REQUIRED"),
+        field("sequence", "INT64", "REQUIRED"),
+        field("src_channel", "STRING", "REQUIRED"),
+        field("src_port", "STRING", "REQUIRED"),
+        field("dst_channel", "STRING", "REQUIRED"),
+        field("dst_port", "STRING", "REQUIRED"),
+        field("signer", "STRING", "NULLABLE"),
+        field("effected", "BOOL", "REQUIRED"),
+        field("effected_signer", "STRING", "NULLABLE"),
+        field("sender", "STRING", "NULLABLE"),
+        field("receiver", "STRING", "NULLABLE"),
+        field("denom", "STRING", "NULLABLE"),
+        field("amount", "STRING", "NULLABLE"),
+        field("timeout_timestamp", "INT64", "NULLABLE"),
+        field("ack_error", "STRING", "NULLABLE"),
+        field("memo", "STRING", "REQUIRED"),
+        field("created_at", "TIMESTAMP", "REQUIRED"),
+    ])
+}
+
+/// Key of the object holding a batch of packets, under a `dt=` partition of the day it was
+/// exported, such as `chainpulse/packets/dt=2024-05-01/000000000001-000000005000.jsonl`
+fn object_key(prefix: &str, now: DateTime<Utc>, first: i64, last: i64) -> String {
+    format!(
+        "{prefix}packets/dt={}/{first:012}-{last:012}.jsonl",
+        now.format("%Y-%m-%d")
+    )
+}
+
+/// ID of the last packet exported to a destination
+async fn cursor(pool: &SqlitePool, destination: &str) -> sqlx::Result<i64> {
+    let cursor = sqlx::query_scalar("SELECT last_id FROM warehouse_cursors WHERE destination = ?")
+        .bind(destination)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(cursor.unwrap_or(0))
+}
+
+async fn save_cursor(pool: &SqlitePool, destination: &str, last_id: i64) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO warehouse_cursors (destination, last_id, updated_at)
+        VALUES (?, ?, datetime('now'))
+        ON CONFLICT (destination) DO UPDATE
+        SET last_id = excluded.last_id, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(destination)
+    .bind(last_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_key() {
+        let now = DateTime::from_timestamp(1_714_564_800, 0).unwrap();
+
+        assert_eq!(
+            object_key("chainpulse/", now, 1, 5000),
+            "chainpulse/packets/dt=2024-05-01/000000000001-000000005000.jsonl"
+        );
+        assert_eq!(
+            object_key("", now, 5001, 5002),
+            "packets/dt=2024-05-01/000000005001-000000005002.jsonl"
+        );
+    }
+}
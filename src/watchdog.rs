@@ -0,0 +1,152 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fs, io,
+    os::unix::{ffi::OsStrExt, net::UnixDatagram},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sqlx::SqlitePool;
+use tokio::time;
+use tracing::{debug, info, warn};
+
+/// How often the health file is written
+pub const HEALTH_FILE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time given to the database to answer the liveness check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tell systemd that chainpulse started, when running as a `Type=notify` service
+pub fn ready() {
+    notify("READY=1");
+}
+
+/// Tell systemd that chainpulse is shutting down
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Signal that chainpulse is alive to the systemd watchdog, when `WatchdogSec` is set, and by
+/// writing the current UNIX time to the health file, when given
+///
+/// Heartbeats are skipped while the database does not answer, so that a wedged instance is
+/// restarted by the service manager or reported by the container healthcheck.
+pub async fn run(pool: SqlitePool, health_file: Option<PathBuf>) {
+    let watchdog = watchdog_interval();
+
+    let period = match (watchdog, &health_file) {
+        (Some(watchdog), Some(_)) => watchdog.min(HEALTH_FILE_INTERVAL),
+        (Some(watchdog), None) => watchdog,
+        (None, Some(_)) => HEALTH_FILE_INTERVAL,
+        (None, None) => return,
+    };
+
+    if let Some(watchdog) = watchdog {
+        info!("Notifying the systemd watchdog every {watchdog:?}");
+    }
+
+    let mut interval = time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = check(&pool).await {
+            warn!("Skipping the heartbeat, the database is not responding: {e}");
+            continue;
+        }
+
+        if watchdog.is_some() {
+            notify("WATCHDOG=1");
+        }
+
+        if let Some(path) = &health_file {
+            if let Err(e) = write_health_file(path) {
+                warn!("Failed to write the health file {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Remove the health file on shutdown, so that it does not look fresh to a healthcheck
+pub fn remove_health_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!("Failed to remove the health file {}: {e}", path.display());
+        }
+    }
+}
+
+async fn check(pool: &SqlitePool) -> crate::Result<()> {
+    time::timeout(CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(pool))
+        .await
+        .map_err(|_| format!("no answer after {CHECK_TIMEOUT:?}"))??;
+
+    Ok(())
+}
+
+/// Write the file next to the health file, then rename it, so that it is never read half written
+fn write_health_file(path: &Path) -> io::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    fs::write(&tmp, format!("{now}\n"))?;
+    fs::rename(&tmp, path)
+}
+
+/// Half the watchdog timeout set by systemd for this process, as recommended by `sd_notify(3)`
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid != std::process::id().to_string() {
+            return None;
+        }
+    }
+
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Send a state to the service manager through the socket in `NOTIFY_SOCKET`, if any
+fn notify(state: &str) {
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    match send(&socket, state) {
+        Ok(()) => debug!("Notified systemd: {state}"),
+        Err(e) => warn!("Failed to notify systemd ({state}): {e}"),
+    }
+}
+
+fn send(socket: &OsStr, state: &str) -> io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+
+    // Sockets starting with `@` are in the abstract namespace of Linux
+    match socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            let addr = SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract sockets are only supported on Linux",
+            ))
+        }
+        None => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1000 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use tokio::{sync::broadcast, time};
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::{self, Config, Notifier},
+    discord::Discord,
+    email::Email,
+    grafana::Grafana,
+    silence::Silences,
+    slack::Slack,
+    telegram::Telegram,
+};
+
+/// Number of delivery attempts per event before giving up
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled after every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Timeout of a single delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header carrying the hex encoded HMAC-SHA256 of the body, when the webhook has a secret
+pub const SIGNATURE_HEADER: &str = "X-Chainpulse-Signature";
+
+/// Header carrying the event kind
+pub const EVENT_HEADER: &str = "X-Chainpulse-Event";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    PacketStuck,
+    PacketNearTimeout,
+    WatchedNearTimeout,
+    PacketCleared,
+    StuckResolved,
+    ChainHalted,
+    ClientExpiring,
+    NoActiveRelayer,
+    CollectorUnhealthy,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PacketStuck => "packet_stuck",
+            Self::PacketNearTimeout => "packet_near_timeout",
+            Self::WatchedNearTimeout => "watched_near_timeout",
+            Self::PacketCleared => "packet_cleared",
+            Self::StuckResolved => "stuck_resolved",
+            Self::ChainHalted => "chain_halted",
+            Self::ClientExpiring => "client_expiring",
+            Self::NoActiveRelayer => "no_active_relayer",
+            Self::CollectorUnhealthy => "collector_unhealthy",
+        }
+    }
+
+    /// Heading of chat notifications for this event
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::PacketStuck => "Packets stuck",
+            Self::PacketNearTimeout => "Packets nearing timeout",
+            Self::WatchedNearTimeout => "Watched packets nearing timeout",
+            Self::PacketCleared => "Packets cleared",
+            Self::StuckResolved => "Stuck packets resolved",
+            Self::ChainHalted => "Chain halted",
+            Self::ClientExpiring => "Light client expiring",
+            Self::NoActiveRelayer => "No active relayer",
+            Self::CollectorUnhealthy => "Collector unhealthy",
+        }
+    }
+
+    /// API route listing the packets concerned by this event, if any
+    pub fn api_path(&self) -> Option<&'static str> {
+        match self {
+            Self::PacketStuck => Some("/api/v1/packets/stuck"),
+            Self::PacketNearTimeout | Self::WatchedNearTimeout => Some("/api/v1/packets/expiring"),
+            Self::PacketCleared
+            | Self::StuckResolved
+            | Self::ChainHalted
+            | Self::ClientExpiring
+            | Self::NoActiveRelayer
+            | Self::CollectorUnhealthy => None,
+        }
+    }
+
+    /// Severity of this event unless overridden by its alert rule
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::WatchedNearTimeout | Self::ChainHalted => Severity::Critical,
+            Self::PacketCleared | Self::StuckResolved => Severity::Info,
+            Self::PacketStuck
+            | Self::PacketNearTimeout
+            | Self::ClientExpiring
+            | Self::NoActiveRelayer
+            | Self::CollectorUnhealthy => Severity::Warning,
+        }
+    }
+}
+
+/// Severity of an event, used to route it to notifiers
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Payload POSTed to webhooks
+#[derive(Clone, Debug, Serialize)]
+pub struct PacketEvent {
+    pub event: EventKind,
+    pub severity: Severity,
+    pub timestamp: String,
+    pub packet: PacketSnapshot,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<AlertContext>,
+}
+
+impl PacketEvent {
+    pub fn new(event: EventKind, packet: PacketSnapshot) -> Self {
+        Self {
+            event,
+            severity: event.severity(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            packet,
+            alert: None,
+        }
+    }
+
+    pub fn with_alert(self, alert: AlertContext) -> Self {
+        Self {
+            alert: Some(alert),
+            ..self
+        }
+    }
+}
+
+/// State of the alert on the channel of the packet at the time of an event
+#[derive(Clone, Debug, Serialize)]
+pub struct AlertContext {
+    /// Packets of the channel currently in the condition of the alert
+    pub packets: usize,
+    /// Time since the alert fired
+    pub firing_seconds: i64,
+}
+
+/// Event published to the subscribers of an embedded instance, once its severity is set and
+/// unless it is silenced, whether or not a notifier is configured
+#[derive(Clone, Debug)]
+pub enum Event {
+    Packet(PacketEvent),
+    Chain(ChainEvent),
+}
+
+/// Payload POSTed to webhooks when a condition of a chain or channel is met or cleared
+#[derive(Clone, Debug, Serialize)]
+pub struct ChainEvent {
+    pub event: EventKind,
+    pub severity: Severity,
+    pub timestamp: String,
+    pub chain_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Whether the condition cleared
+    pub resolved: bool,
+    /// Human readable description of the condition
+    pub summary: String,
+    /// Time the condition lasted, once resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firing_seconds: Option<i64>,
+    /// Measurements specific to the event
+    pub details: serde_json::Value,
+}
+
+impl ChainEvent {
+    pub fn new(
+        event: EventKind,
+        chain_id: &str,
+        channel: Option<&str>,
+        summary: String,
+        details: serde_json::Value,
+    ) -> Self {
+        Self {
+            event,
+            severity: event.severity(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            chain_id: chain_id.to_string(),
+            channel: channel.map(str::to_string),
+            resolved: false,
+            summary,
+            firing_seconds: None,
+            details,
+        }
+    }
+
+    /// Mark the event as notifying that the condition cleared after `firing_seconds`
+    pub fn resolved(self, firing_seconds: i64) -> Self {
+        Self {
+            resolved: true,
+            firing_seconds: Some(firing_seconds),
+            ..self
+        }
+    }
+
+    /// `Chain halted on osmosis-1`, prefixed with `Resolved: ` once the condition cleared
+    pub fn heading(&self) -> String {
+        let on = match &self.channel {
+            Some(channel) => format!("{} {channel}", self.chain_id),
+            None => self.chain_id.clone(),
+        };
+
+        match self.resolved {
+            true => format!("Resolved: {} on {on}", self.event.title()),
+            false => format!("{} on {on}", self.event.title()),
+        }
+    }
+
+    /// Whether the event concerns the chain and channel, when set
+    ///
+    /// Events about a whole chain are not filtered by channel.
+    pub fn in_scope(&self, chain: &Option<String>, channel: &Option<String>) -> bool {
+        chain.as_ref().is_none_or(|chain| *chain == self.chain_id)
+            && match (channel, &self.channel) {
+                (Some(channel), Some(event_channel)) => channel == event_channel,
+                _ => true,
+            }
+    }
+}
+
+/// Events delivered to webhooks
+trait Payload: Clone + Serialize + Send + 'static {
+    fn kind(&self) -> EventKind;
+
+    fn severity(&self) -> Severity;
+
+    fn set_severity(&mut self, severity: Severity);
+
+    /// Chain of the event and the channels it concerns, none for a whole chain
+    fn scope(&self) -> (&str, Vec<&str>);
+}
+
+impl Payload for PacketEvent {
+    fn kind(&self) -> EventKind {
+        self.event
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn set_severity(&mut self, severity: Severity) {
+        self.severity = severity;
+    }
+
+    fn scope(&self) -> (&str, Vec<&str>) {
+        let packet = &self.packet;
+        (
+            &packet.chain_id,
+            vec![&packet.src_channel, &packet.dst_channel],
+        )
+    }
+}
+
+impl Payload for ChainEvent {
+    fn kind(&self) -> EventKind {
+        self.event
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn set_severity(&mut self, severity: Severity) {
+        self.severity = severity;
+    }
+
+    fn scope(&self) -> (&str, Vec<&str>) {
+        (
+            &self.chain_id,
+            self.channel.iter().map(String::as_str).collect(),
+        )
+    }
+}
+
+/// State of a pending packet at the time of an event
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct PacketSnapshot {
+    pub chain_id: String,
+    pub src_channel: String,
+    pub dst_channel: String,
+    pub sequence: i64,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub amount: Option<String>,
+    pub denom: Option<String>,
+    pub age_seconds: i64,
+    pub seconds_until_timeout: Option<i64>,
+}
+
+/// Whether a webhook subscribed to this event
+pub fn matches(webhook: &config::Webhook, event: &PacketEvent) -> bool {
+    (webhook.events.is_empty() || webhook.events.contains(&event.event))
+        && in_scope(&webhook.chain, &webhook.channel, &event.packet)
+}
+
+/// Whether a packet was sent from the chain and travels on the channel, when set
+pub fn in_scope(chain: &Option<String>, channel: &Option<String>, packet: &PacketSnapshot) -> bool {
+    chain.as_ref().is_none_or(|chain| *chain == packet.chain_id)
+        && channel
+            .as_ref()
+            .is_none_or(|channel| *channel == packet.src_channel || *channel == packet.dst_channel)
+}
+
+/// Group events of the same kind on the same channel, ordered by kind and channel
+pub fn group_by_channel<'a>(events: &[&'a PacketEvent]) -> Vec<Vec<&'a PacketEvent>> {
+    let mut groups: BTreeMap<_, Vec<&PacketEvent>> = BTreeMap::new();
+
+    for event in events {
+        let packet = &event.packet;
+        let key = (
+            event.event.as_str(),
+            packet.chain_id.as_str(),
+            packet.src_channel.as_str(),
+            packet.dst_channel.as_str(),
+        );
+
+        groups.entry(key).or_default().push(event);
+    }
+
+    groups.into_values().collect()
+}
+
+/// Number of packets concerned by events of the same kind on a channel,
+/// counting those already notified when the events belong to an alert
+pub fn packet_count(events: &[&PacketEvent]) -> usize {
+    events
+        .iter()
+        .filter_map(|event| event.alert.as_ref().map(|alert| alert.packets))
+        .max()
+        .unwrap_or(events.len())
+}
+
+/// Link to the API route listing the packets of an event on its channel
+pub fn api_link(api_url: &str, event: &PacketEvent) -> Option<String> {
+    event.event.api_path().map(|path| {
+        format!(
+            "{}{path}?chain={}&channel={}",
+            api_url.trim_end_matches('/'),
+            event.packet.chain_id,
+            event.packet.src_channel
+        )
+    })
+}
+
+/// A webhook registered through the API
+#[derive(Clone, Debug, Serialize)]
+pub struct RegisteredWebhook {
+    pub id: i64,
+    #[serde(flatten)]
+    pub webhook: config::Webhook,
+    pub created_at: String,
+}
+
+/// Webhooks from the configuration file and from the API, and the chat notifiers
+///
+/// The notifiers are rebuilt when the configuration is reloaded, events being delivered by
+/// the notifiers current when they are dispatched.
+#[derive(Clone)]
+pub struct Webhooks {
+    notifiers: Arc<RwLock<Arc<Notifiers>>>,
+    subscribers: broadcast::Sender<Event>,
+}
+
+impl Webhooks {
+    /// Notifiers of the configuration, also publishing the events to `subscribers`
+    pub fn new(config: &Config, pool: SqlitePool, subscribers: broadcast::Sender<Event>) -> Self {
+        let notifiers = Notifiers::new(config, pool, None);
+
+        Self {
+            notifiers: Arc::new(RwLock::new(Arc::new(notifiers))),
+            subscribers,
+        }
+    }
+
+    /// Rebuild the notifiers from a reloaded configuration
+    ///
+    /// The email notifier is kept when its settings did not change, along with its pending digests.
+    pub fn reload(&self, config: &Config) {
+        let current = self.current();
+        let email = current
+            .email
+            .as_ref()
+            .filter(|email| {
+                config
+                    .email
+                    .as_ref()
+                    .is_some_and(|config| email.uses(config))
+            })
+            .cloned();
+
+        let notifiers = Notifiers::new(config, current.pool.clone(), email);
+        *self.notifiers.write().unwrap() = Arc::new(notifiers);
+    }
+
+    /// Deliver the events to every matching webhook in the background
+    pub async fn dispatch(&self, events: Vec<PacketEvent>) {
+        self.current().dispatch(events, &self.subscribers).await
+    }
+
+    /// Deliver events about chains and channels to every matching webhook in the background
+    pub async fn announce(&self, events: Vec<ChainEvent>) {
+        self.current().announce(events, &self.subscribers).await
+    }
+
+    /// Send the pending email digests, on shutdown
+    pub async fn flush(&self) {
+        if let Some(email) = &self.current().email {
+            email.flush().await;
+        }
+    }
+
+    fn current(&self) -> Arc<Notifiers> {
+        self.notifiers.read().unwrap().clone()
+    }
+}
+
+/// Notifiers built from the configuration
+struct Notifiers {
+    configured: Vec<config::Webhook>,
+    slack: Option<Slack>,
+    discord: Option<Discord>,
+    telegram: Option<Telegram>,
+    email: Option<Email>,
+    grafana: Option<Grafana>,
+    alerts: config::Alerts,
+    silences: Silences,
+    pool: SqlitePool,
+    client: reqwest::Client,
+}
+
+impl Notifiers {
+    fn new(config: &Config, pool: SqlitePool, email: Option<Email>) -> Self {
+        Self {
+            configured: config.webhooks.clone(),
+            slack: config
+                .slack
+                .clone()
+                .map(|slack| Slack::new(slack, config.stuck_packets.clone())),
+            discord: config.discord.clone().map(Discord::new),
+            telegram: config.telegram.clone().map(Telegram::new),
+            email: email.or_else(|| config.email.clone().map(Email::new)),
+            grafana: config.grafana.clone().map(Grafana::new),
+            alerts: config.alerts.clone(),
+            silences: Silences::new(config.alerts.silences.clone(), pool.clone()),
+            pool,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn dispatch(&self, mut events: Vec<PacketEvent>, subscribers: &broadcast::Sender<Event>) {
+        self.set_severities(&mut events);
+        self.silence(&mut events).await;
+
+        // Sending only fails without subscribers
+        for event in &events {
+            let _ = subscribers.send(Event::Packet(event.clone()));
+        }
+
+        // Digests are sent on schedule, whether or not the scan found events
+        if let Some(email) = &self.email {
+            email.notify(&self.routed(Notifier::Email, &events), &[]);
+        }
+
+        if events.is_empty() {
+            return;
+        }
+
+        if let Some(slack) = &self.slack {
+            for (template, events) in self.slack_routes(&events) {
+                let slack = match template {
+                    Some(template) => slack.with_template(template),
+                    None => slack.clone(),
+                };
+
+                tokio::spawn(slack.notify(self.pool.clone(), events));
+            }
+        }
+
+        if let Some(discord) = &self.discord {
+            tokio::spawn(
+                discord
+                    .clone()
+                    .notify(self.routed(Notifier::Discord, &events)),
+            );
+        }
+
+        if let Some(telegram) = &self.telegram {
+            tokio::spawn(
+                telegram
+                    .clone()
+                    .notify(self.routed(Notifier::Telegram, &events)),
+            );
+        }
+
+        if let Some(grafana) = &self.grafana {
+            tokio::spawn(
+                grafana
+                    .clone()
+                    .notify(self.routed(Notifier::Grafana, &events)),
+            );
+        }
+
+        let routed = self.routed(Notifier::Webhooks, &events);
+
+        for webhook in self.webhooks().await {
+            let events: Vec<_> = routed
+                .iter()
+                .filter(|event| matches(&webhook, event))
+                .cloned()
+                .collect();
+
+            if !events.is_empty() {
+                tokio::spawn(deliver(self.client.clone(), webhook, events));
+            }
+        }
+
+        self.deliver_to_routes(&events);
+    }
+
+    async fn announce(&self, mut events: Vec<ChainEvent>, subscribers: &broadcast::Sender<Event>) {
+        self.set_severities(&mut events);
+        self.silence(&mut events).await;
+
+        for event in &events {
+            let _ = subscribers.send(Event::Chain(event.clone()));
+        }
+
+        if let Some(email) = &self.email {
+            email.notify(&[], &self.routed(Notifier::Email, &events));
+        }
+
+        if events.is_empty() {
+            return;
+        }
+
+        if let Some(slack) = &self.slack {
+            tokio::spawn(
+                slack
+                    .clone()
+                    .announce(self.routed(Notifier::Slack, &events)),
+            );
+        }
+
+        if let Some(discord) = &self.discord {
+            tokio::spawn(
+                discord
+                    .clone()
+                    .announce(self.routed(Notifier::Discord, &events)),
+            );
+        }
+
+        if let Some(telegram) = &self.telegram {
+            tokio::spawn(
+                telegram
+                    .clone()
+                    .announce(self.routed(Notifier::Telegram, &events)),
+            );
+        }
+
+        if let Some(grafana) = &self.grafana {
+            tokio::spawn(
+                grafana
+                    .clone()
+                    .announce(self.routed(Notifier::Grafana, &events)),
+            );
+        }
+
+        let routed = self.routed(Notifier::Webhooks, &events);
+
+        for webhook in self.webhooks().await {
+            let events: Vec<_> = routed
+                .iter()
+                .filter(|event| {
+                    (webhook.events.is_empty() || webhook.events.contains(&event.event))
+                        && event.in_scope(&webhook.chain, &webhook.channel)
+                })
+                .cloned()
+                .collect();
+
+            if !events.is_empty() {
+                tokio::spawn(deliver(self.client.clone(), webhook, events));
+            }
+        }
+
+        self.deliver_to_routes(&events);
+    }
+
+    fn set_severities<E: Payload>(&self, events: &mut [E]) {
+        for event in events {
+            event.set_severity(self.alerts.severity(event.kind()));
+        }
+    }
+
+    /// Drop the events covered by a silence, which are not notified later on
+    async fn silence<E: Payload>(&self, events: &mut Vec<E>) {
+        if events.is_empty() {
+            return;
+        }
+
+        let active = match self.silences.active().await {
+            Ok(active) => active,
+            Err(e) => {
+                error!("Failed to load the silences: {e}");
+                Vec::new()
+            }
+        };
+
+        let count = events.len();
+
+        events.retain(|event| {
+            let (chain, channels) = event.scope();
+            !self
+                .silences
+                .covers(&active, event.kind(), chain, &channels)
+        });
+
+        if events.len() < count {
+            debug!("Silenced {} events", count - events.len());
+        }
+    }
+
+    /// Events to send to a notifier: those of the routes to it, or all of them when none is
+    fn routed<E: Payload>(&self, notifier: Notifier, events: &[E]) -> Vec<E> {
+        let routes: Vec<_> = self
+            .alerts
+            .routes
+            .iter()
+            .filter(|route| route.to.contains(&notifier))
+            .collect();
+
+        events
+            .iter()
+            .filter(|event| {
+                routes.is_empty()
+                    || routes
+                        .iter()
+                        .any(|route| route.matches(event.kind(), event.severity()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Events to post to Slack, grouped by the template of the first route sending each to it
+    fn slack_routes(&self, events: &[PacketEvent]) -> Vec<(Option<&str>, Vec<PacketEvent>)> {
+        let mut groups: Vec<(Option<&str>, Vec<PacketEvent>)> = Vec::new();
+
+        for event in self.routed(Notifier::Slack, events) {
+            let template = self
+                .alerts
+                .routes
+                .iter()
+                .find(|route| {
+                    route.to.contains(&Notifier::Slack)
+                        && route.matches(event.event, event.severity)
+                })
+                .and_then(|route| route.slack_template.as_deref());
+
+            match groups.iter_mut().find(|(t, _)| *t == template) {
+                Some((_, events)) => events.push(event),
+                None => groups.push((template, vec![event])),
+            }
+        }
+
+        groups
+    }
+
+    /// POST the events to the URL of each route they match, in the background
+    fn deliver_to_routes<E: Payload>(&self, events: &[E]) {
+        for route in &self.alerts.routes {
+            let Some(webhook) = route.webhook() else {
+                continue;
+            };
+
+            let events: Vec<_> = events
+                .iter()
+                .filter(|event| route.matches(event.kind(), event.severity()))
+                .cloned()
+                .collect();
+
+            if !events.is_empty() {
+                tokio::spawn(deliver(self.client.clone(), webhook, events));
+            }
+        }
+    }
+
+    /// Webhooks from the configuration file followed by the registered ones
+    async fn webhooks(&self) -> Vec<config::Webhook> {
+        let mut webhooks = self.configured.clone();
+
+        match list(&self.pool).await {
+            Ok(registered) => webhooks.extend(registered.into_iter().map(|r| r.webhook)),
+            Err(e) => warn!("Failed to load registered webhooks: {e}"),
+        }
+
+        webhooks
+    }
+}
+
+/// POST each event to the webhook, retrying with exponential backoff
+async fn deliver<E: Payload>(client: reqwest::Client, webhook: config::Webhook, events: Vec<E>) {
+    for event in events {
+        let body = match &webhook.template {
+            Some(template) => render(template, &event),
+            None => serde_json::to_vec(&event),
+        };
+
+        let body = match body {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(url = %webhook.url, "Failed to build webhook payload: {e}");
+                continue;
+            }
+        };
+
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = client
+                .post(&webhook.url)
+                .timeout(DELIVERY_TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.kind().as_str());
+
+            if let Some(secret) = &webhook.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+            }
+
+            let result = request
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match result {
+                Ok(_) => {
+                    debug!(url = %webhook.url, "Delivered {} webhook", event.kind().as_str());
+                    break;
+                }
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    debug!(url = %webhook.url, "Webhook delivery attempt {attempt} failed: {e}");
+                    time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    warn!(url = %webhook.url, "Giving up on webhook delivery after {attempt} attempts: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Render a JSON body template for an event
+///
+/// Placeholders are named after the fields of the event and of its nested objects, such as the
+/// packet, or `{packet}` for the whole packet. A placeholder which is a whole JSON string, such as `"{sequence}"`, is replaced
+/// by the JSON value of the field, any other by its text escaped for use in a JSON string.
+pub fn render(template: &str, event: &impl Serialize) -> serde_json::Result<Vec<u8>> {
+    let mut values = HashMap::new();
+
+    if let serde_json::Value::Object(fields) = serde_json::to_value(event)? {
+        for (name, value) in fields {
+            if let serde_json::Value::Object(packet) = &value {
+                values.extend(packet.clone());
+            }
+
+            values.insert(name, value);
+        }
+    }
+
+    // Substituted in a single pass, so that values are never taken for placeholders
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+
+        let Some(value) = values.get(&rest[start + 1..start + len]) else {
+            body.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        let (before, after) = (&rest[..start], &rest[start + len + 1..]);
+
+        if before.ends_with('"') && after.starts_with('"') {
+            body.push_str(&before[..before.len() - 1]);
+            body.push_str(&value.to_string());
+            rest = &after[1..];
+        } else {
+            let text = match value {
+                serde_json::Value::String(text) => text.clone(),
+                serde_json::Value::Null => String::new(),
+                value => value.to_string(),
+            };
+
+            // Escaped as the content of a JSON string, without the surrounding quotes
+            let escaped = serde_json::to_string(&text)?;
+
+            body.push_str(before);
+            body.push_str(&escaped[1..escaped.len() - 1]);
+            rest = after;
+        }
+    }
+
+    body.push_str(rest);
+
+    // Only deliver well-formed JSON
+    serde_json::from_str::<serde_json::Value>(&body)?;
+
+    Ok(body.into_bytes())
+}
+
+/// Check that a template renders to valid JSON
+pub fn validate_template(template: &str) -> serde_json::Result<()> {
+    let sample = PacketEvent::new(
+        EventKind::PacketStuck,
+        PacketSnapshot {
+            chain_id: "osmosis-1".to_string(),
+            src_channel: "channel-0".to_string(),
+            dst_channel: "channel-141".to_string(),
+            sequence: 1,
+            sender: Some("osmo1sender".to_string()),
+            receiver: Some("cosmos1receiver".to_string()),
+            amount: Some("1000000".to_string()),
+            denom: Some("uosmo".to_string()),
+            age_seconds: 960,
+            seconds_until_timeout: Some(2640),
+        },
+    );
+
+    render(template, &sample).map(|_| ())
+}
+
+/// `sha256=<hex>` HMAC of the body keyed with the webhook secret
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    let signature = subtle_encoding::hex::encode(mac.finalize().into_bytes());
+    format!("sha256={}", String::from_utf8_lossy(&signature))
+}
+
+/// Webhooks registered through the API
+pub async fn list(pool: &SqlitePool) -> sqlx::Result<Vec<RegisteredWebhook>> {
+    let rows = sqlx::query_as::<
+        _,
+        (
+            i64,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+        ),
+    >(
+        "SELECT id, url, secret, events, chain, channel, template, created_at FROM webhooks ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, url, secret, events, chain, channel, template, created_at)| RegisteredWebhook {
+                id,
+                webhook: config::Webhook {
+                    url,
+                    secret,
+                    events: serde_json::from_str(&events).unwrap_or_default(),
+                    chain,
+                    channel,
+                    template,
+                },
+                created_at,
+            },
+        )
+        .collect())
+}
+
+/// Register a webhook
+pub async fn register(
+    pool: &SqlitePool,
+    webhook: config::Webhook,
+) -> sqlx::Result<RegisteredWebhook> {
+    let query = r#"
+        INSERT INTO webhooks (url, secret, events, chain, channel, template, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+        RETURNING id, created_at
+    "#;
+
+    let (id, created_at) = sqlx::query_as::<_, (i64, String)>(query)
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(serde_json::to_string(&webhook.events).unwrap_or_default())
+        .bind(&webhook.chain)
+        .bind(&webhook.channel)
+        .bind(&webhook.template)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(RegisteredWebhook {
+        id,
+        webhook,
+        created_at,
+    })
+}
+
+/// Remove a registered webhook, returning whether it existed
+pub async fn remove(pool: &SqlitePool, id: i64) -> sqlx::Result<bool> {
+    let result = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let event = PacketEvent {
+            event: EventKind::PacketNearTimeout,
+            severity: Severity::Warning,
+            timestamp: "2025-01-15T10:30:00+00:00".to_string(),
+            packet: PacketSnapshot {
+                chain_id: "osmosis-1".to_string(),
+                src_channel: "channel-0".to_string(),
+                dst_channel: "channel-141".to_string(),
+                sequence: 7,
+                sender: Some("osmo1\"quoted\"".to_string()),
+                receiver: None,
+                amount: None,
+                denom: None,
+                age_seconds: 60,
+                seconds_until_timeout: Some(30),
+            },
+            alert: None,
+        };
+
+        let template = r#"{
+            "summary": "{event}: #{sequence} on {chain_id} from {sender}{receiver}",
+            "sequence": "{sequence}",
+            "receiver": "{receiver}",
+            "packet": "{packet}"
+        }"#;
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&render(template, &event).unwrap()).unwrap();
+
+        assert_eq!(
+            body["summary"],
+            r#"packet_near_timeout: #7 on osmosis-1 from osmo1"quoted""#
+        );
+        assert_eq!(body["sequence"], 7);
+        assert_eq!(body["receiver"], serde_json::Value::Null);
+        assert_eq!(body["packet"]["seconds_until_timeout"], 30);
+
+        assert!(validate_template(r#"{"text": "{sequence}"}"#).is_ok());
+        assert!(validate_template(r#"{"text": {sender}}"#).is_err());
+    }
+}